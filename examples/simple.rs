@@ -30,7 +30,7 @@ async fn main() {
 async fn handler(value: JsonRpcExtractor) -> JrpcResult {
     let answer_id = value.get_answer_id();
     println!("{:?}", value);
-    match value.method.as_str() {
+    match value.method() {
         "add" => {
             let request: Test = value.parse_params()?;
             let result = request.a + request.b;
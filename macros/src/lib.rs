@@ -0,0 +1,56 @@
+#![warn(
+    clippy::all,
+    clippy::dbg_macro,
+    clippy::todo,
+    clippy::str_to_string,
+    rust_2018_idioms,
+    future_incompatible,
+    nonstandard_style
+)]
+
+use proc_macro::TokenStream;
+
+mod method;
+mod openrpc;
+mod params;
+mod rpc;
+
+/// Generates a server dispatcher and a typed client for a JSON-RPC api trait.
+///
+/// See `axum_jrpc::rpc` for details.
+#[proc_macro_attribute]
+pub fn rpc(attr: TokenStream, item: TokenStream) -> TokenStream {
+    rpc::expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Turns an async function into a JSON-RPC method registered with `JsonRpcRouter::register`.
+///
+/// See `axum_jrpc::jrpc_method` for details.
+#[proc_macro_attribute]
+pub fn jrpc_method(attr: TokenStream, item: TokenStream) -> TokenStream {
+    method::expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates the types and the api trait of an OpenRPC document, for spec-first servers.
+///
+/// See `axum_jrpc::openrpc_server` for details.
+#[proc_macro_attribute]
+pub fn openrpc_server(attr: TokenStream, item: TokenStream) -> TokenStream {
+    openrpc::expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `Deserialize` for params passed either by position or by name.
+///
+/// See `axum_jrpc::JsonRpcParams` for details.
+#[proc_macro_derive(JsonRpcParams, attributes(serde))]
+pub fn json_rpc_params(item: TokenStream) -> TokenStream {
+    params::expand(item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,83 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{FnArg, ItemFn, LitStr, Pat, ReturnType};
+
+use crate::params;
+
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let name = if attr.is_empty() {
+        None
+    } else {
+        Some(syn::parse2::<LitStr>(attr)?.value())
+    };
+    let item: ItemFn = syn::parse2(item)?;
+    let sig = &item.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new(sig.span(), "methods must be async"));
+    }
+    if let ReturnType::Default = sig.output {
+        return Err(syn::Error::new(
+            sig.span(),
+            "methods must return a `Result`",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            sig.generics.span(),
+            "methods can't be generic",
+        ));
+    }
+
+    let mut args = Vec::new();
+    for input in &sig.inputs {
+        let FnArg::Typed(arg) = input else {
+            return Err(syn::Error::new(input.span(), "methods can't take `self`"));
+        };
+        let Pat::Ident(pat) = &*arg.pat else {
+            return Err(syn::Error::new(arg.pat.span(), "expected an identifier"));
+        };
+        args.push((pat.ident.clone(), (*arg.ty).clone()));
+    }
+    let arg_idents: Vec<_> = args.iter().map(|(ident, _)| ident).collect();
+    // the same rule as `#[rpc]`: each argument is a param
+    let parse = params::parse_args(&args);
+
+    let vis = &item.vis;
+    let ident = &sig.ident;
+    let name = name.unwrap_or_else(|| ident.to_string());
+    let doc = format!("Registers [`{ident}()`] as `{name}`, see `axum_jrpc::jrpc_method`.");
+
+    Ok(quote! {
+        #item
+
+        #[doc = #doc]
+        #[allow(non_camel_case_types, dead_code)]
+        #vis struct #ident {}
+
+        impl ::axum_jrpc::JsonRpcMethod for #ident {
+            const NAME: &'static str = #name;
+
+            fn call(
+                request: ::axum_jrpc::JsonRpcExtractor,
+            ) -> ::core::pin::Pin<::std::boxed::Box<
+                dyn ::core::future::Future<Output = ::axum_jrpc::JrpcResult> + ::core::marker::Send,
+            >> {
+                ::std::boxed::Box::pin(async move {
+                    let id = request.get_answer_id();
+                    #parse
+                    match #ident(#(#arg_idents),*).await {
+                        ::core::result::Result::Ok(result) => {
+                            ::core::result::Result::Ok(::axum_jrpc::JsonRpcResponse::success(id, result))
+                        }
+                        ::core::result::Result::Err(error) => ::core::result::Result::Err(
+                            ::axum_jrpc::JsonRpcResponse::error(id, ::core::convert::Into::into(error)),
+                        ),
+                    }
+                })
+            }
+        }
+
+        ::axum_jrpc::__submit_method!(#ident);
+    })
+}
@@ -0,0 +1,312 @@
+use std::path::Path;
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use serde_json::{Map, Value};
+use syn::{ItemTrait, LitStr, TraitItem};
+
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let path: LitStr = syn::parse2(attr)?;
+    let mut item: ItemTrait = syn::parse2(item)?;
+    if !item.items.is_empty() {
+        return Err(syn::Error::new(
+            item.ident.span(),
+            "the methods are generated from the document, leave the trait empty",
+        ));
+    }
+
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let file = Path::new(&root).join(path.value());
+    let error = |e: &dyn std::fmt::Display| {
+        syn::Error::new(path.span(), format!("can't read {}: {e}", file.display()))
+    };
+    let text = std::fs::read_to_string(&file).map_err(|e| error(&e))?;
+    let document: Value = serde_json::from_str(&text).map_err(|e| error(&e))?;
+
+    let empty = Map::new();
+    let schemas = document
+        .pointer("/components/schemas")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let types = schemas
+        .iter()
+        .map(|(name, schema)| definition(&type_ident(name), schema));
+
+    let methods = document.get("methods").and_then(Value::as_array);
+    for method in methods.into_iter().flatten() {
+        let Some(name) = method.get("name").and_then(Value::as_str) else {
+            return Err(error(&"a method has no name"));
+        };
+        let ident = to_ident(&snake_case(name));
+        let docs = doc_attrs(method);
+        let params = method.get("params").and_then(Value::as_array);
+        let args = params.into_iter().flatten().map(|param| {
+            let name = param.get("name").and_then(Value::as_str).unwrap_or("param");
+            let required = param.get("required").and_then(Value::as_bool) == Some(true);
+            let ident = to_ident(name);
+            let ty = field_ty(param.get("schema").unwrap_or(&Value::Bool(true)), required);
+            quote! { #ident: #ty }
+        });
+        let result = method
+            .pointer("/result/schema")
+            .map_or_else(|| quote!(::axum_jrpc::Value), ty);
+        item.items.push(TraitItem::Fn(syn::parse_quote! {
+            #(#docs)*
+            #[method(name = #name)]
+            async fn #ident(&self, #(#args),*)
+                -> ::core::result::Result<#result, ::axum_jrpc::error::JsonRpcError>;
+        }));
+    }
+
+    // rebuilds when the document changes
+    let file = file.display().to_string();
+    Ok(quote! {
+        #(#types)*
+
+        #[::axum_jrpc::rpc(server)]
+        #item
+
+        const _: &str = ::core::include_str!(#file);
+    })
+}
+
+/// A struct, an enum or an alias for the named `schema`
+fn definition(ident: &Ident, schema: &Value) -> TokenStream {
+    let docs = doc_attrs(schema);
+    let derive = quote! {
+        #[derive(
+            ::core::fmt::Debug,
+            ::core::clone::Clone,
+            ::core::cmp::PartialEq,
+            ::axum_jrpc::__private::serde::Serialize,
+            ::axum_jrpc::__private::serde::Deserialize,
+        )]
+        #[serde(crate = "::axum_jrpc::__private::serde")]
+    };
+
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        let required = required(schema);
+        let fields = properties.iter().map(|(name, property)| {
+            let field = to_ident(name);
+            let rename = (field.to_string().trim_start_matches("r#") != name)
+                .then(|| quote!(#[serde(rename = #name)]));
+            let required = required.contains(&name.as_str());
+            let skip = (!required || nullable(property)).then(|| {
+                quote!(#[serde(default, skip_serializing_if = "::core::option::Option::is_none")])
+            });
+            let ty = field_ty(property, required);
+            let docs = doc_attrs(property);
+            quote! { #(#docs)* #rename #skip pub #field: #ty, }
+        });
+        return quote! {
+            #(#docs)*
+            #derive
+            pub struct #ident { #(#fields)* }
+        };
+    }
+
+    let variants: Option<Vec<&str>> = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|values| values.iter().map(Value::as_str).collect());
+    if let Some(variants) = variants.filter(|variants| !variants.is_empty()) {
+        let variants = variants.iter().map(|name| {
+            let variant = type_ident(name);
+            quote! { #[serde(rename = #name)] #variant, }
+        });
+        return quote! {
+            #(#docs)*
+            #derive
+            pub enum #ident { #(#variants)* }
+        };
+    }
+
+    let ty = ty(schema);
+    quote! {
+        #(#docs)*
+        pub type #ident = #ty;
+    }
+}
+
+/// Type of a field or an argument, optional unless it is `required`
+fn field_ty(schema: &Value, required: bool) -> TokenStream {
+    let ty = ty(schema);
+    if required || nullable(schema) {
+        ty
+    } else {
+        quote!(::core::option::Option<#ty>)
+    }
+}
+
+/// Rust type of `schema`, anything it can't express is a `Value`
+fn ty(schema: &Value) -> TokenStream {
+    let value = quote!(::axum_jrpc::Value);
+    let Some(map) = schema.as_object() else {
+        return value;
+    };
+    if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+        let ident = type_ident(reference.rsplit('/').next().unwrap_or(reference));
+        return quote!(#ident);
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(schemas) = map.get(keyword).and_then(Value::as_array) {
+            let (nulls, others): (Vec<_>, Vec<_>) = schemas.iter().partition(|s| is_null(s));
+            return match (&*nulls, &*others) {
+                ([_], [schema]) => {
+                    let ty = ty(schema);
+                    quote!(::core::option::Option<#ty>)
+                }
+                _ => value,
+            };
+        }
+    }
+    match map.get("type") {
+        Some(Value::String(name)) => typed(name, map),
+        Some(Value::Array(names)) => {
+            let names: Vec<_> = names.iter().filter_map(Value::as_str).collect();
+            match &*names {
+                [name] => typed(name, map),
+                ["null", name] | [name, "null"] => {
+                    let ty = typed(name, map);
+                    quote!(::core::option::Option<#ty>)
+                }
+                _ => value,
+            }
+        }
+        _ => value,
+    }
+}
+
+fn typed(name: &str, schema: &Map<String, Value>) -> TokenStream {
+    match name {
+        "boolean" => quote!(bool),
+        "string" => quote!(::std::string::String),
+        "number" => match schema.get("format").and_then(Value::as_str) {
+            Some("float") => quote!(f32),
+            _ => quote!(f64),
+        },
+        "integer" => match schema.get("format").and_then(Value::as_str) {
+            Some("int8") => quote!(i8),
+            Some("int16") => quote!(i16),
+            Some("int32") => quote!(i32),
+            Some("uint8") => quote!(u8),
+            Some("uint16") => quote!(u16),
+            Some("uint32") => quote!(u32),
+            Some("uint64" | "uint") => quote!(u64),
+            _ => quote!(i64),
+        },
+        "null" => quote!(()),
+        "array" => match schema.get("prefixItems").and_then(Value::as_array) {
+            Some(items) => {
+                let items = items.iter().map(ty);
+                quote!((#(#items,)*))
+            }
+            None => {
+                let item = schema
+                    .get("items")
+                    .map_or_else(|| quote!(::axum_jrpc::Value), ty);
+                quote!(::std::vec::Vec<#item>)
+            }
+        },
+        "object" if !schema.contains_key("properties") => {
+            let value = match schema.get("additionalProperties") {
+                Some(schema @ Value::Object(_)) => ty(schema),
+                _ => quote!(::axum_jrpc::Value),
+            };
+            quote!(::std::collections::HashMap<::std::string::String, #value>)
+        }
+        _ => quote!(::axum_jrpc::Value),
+    }
+}
+
+fn is_null(schema: &Value) -> bool {
+    schema.get("type").and_then(Value::as_str) == Some("null")
+}
+
+/// Whether `schema` accepts `null`, its type is then already an `Option`
+fn nullable(schema: &Value) -> bool {
+    let types = schema.get("type").and_then(Value::as_array);
+    let variants = ["anyOf", "oneOf"]
+        .iter()
+        .filter_map(|keyword| schema.get(*keyword).and_then(Value::as_array));
+    types.is_some_and(|types| types.len() == 2 && types.iter().any(|t| t == "null"))
+        || variants
+            .into_iter()
+            .any(|schemas| schemas.len() == 2 && schemas.iter().filter(|s| is_null(s)).count() == 1)
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// `#[doc]` attributes of the summary and description of `schema`
+fn doc_attrs(schema: &Value) -> Vec<TokenStream> {
+    let texts: Vec<_> = ["summary", "description"]
+        .iter()
+        .filter_map(|key| schema.get(*key).and_then(Value::as_str))
+        .collect();
+    if texts.is_empty() {
+        return Vec::new();
+    }
+    texts
+        .join("\n\n")
+        .lines()
+        .map(|line| {
+            let line = format!(" {line}");
+            quote!(#[doc = #line])
+        })
+        .collect()
+}
+
+/// Identifier of a field, an argument or a method, raw if it is a keyword
+fn to_ident(name: &str) -> Ident {
+    let mut name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    match name.as_str() {
+        "_" | "self" | "Self" | "super" | "crate" => format_ident!("{name}_"),
+        _ if syn::parse_str::<Ident>(&name).is_err() => Ident::new_raw(&name, Span::call_site()),
+        _ => Ident::new(&name, Span::call_site()),
+    }
+}
+
+/// Identifier of a type or a variant, in upper camel case
+fn type_ident(name: &str) -> Ident {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            camel.push(if upper { c.to_ascii_uppercase() } else { c });
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    to_ident(&camel)
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len());
+    let mut lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if lower {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+            lower = false;
+        } else {
+            snake.push(if c.is_ascii_alphanumeric() { c } else { '_' });
+            lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+    }
+    snake
+}
@@ -0,0 +1,176 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, Ident, Type};
+
+/// Parses the params of `request` into `args`, each a param passed by position or by name.
+/// Trailing `Option` params may be left out, as with `JsonRpcParams`
+pub(crate) fn parse_args(args: &[(Ident, Type)]) -> TokenStream {
+    if args.is_empty() {
+        return quote! {};
+    }
+    let idents: Vec<_> = args.iter().map(|(ident, _)| ident).collect();
+    let types = args.iter().map(|(_, ty)| ty);
+    let item = quote! {
+        struct __Params { #(#idents: #types,)* }
+    };
+    let deserialize = expand(item.clone()).unwrap_or_else(syn::Error::into_compile_error);
+    quote! {
+        #item
+        #deserialize
+
+        let __Params { #(#idents,)* } = request.parse_params::<__Params>()?;
+    }
+}
+
+pub(crate) fn expand(item: TokenStream) -> syn::Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(item)?;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "params can't be generic",
+        ));
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(input.span(), "params must be a struct"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            data.fields.span(),
+            "params must have named fields",
+        ));
+    };
+
+    let ident = &input.ident;
+    let expecting = format!("the params of {ident} as an array or an object");
+    let container_attrs = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"));
+    let field_attrs = fields.named.iter().map(|field| {
+        field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("serde"))
+            .collect::<Vec<_>>()
+    });
+    let idents: Vec<_> = fields.named.iter().map(|field| &field.ident).collect();
+    let types: Vec<_> = fields.named.iter().map(|field| &field.ty).collect();
+    let count = idents.len();
+    let positions = fields.named.iter().enumerate().map(|(i, field)| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        // trailing optional params may be left out
+        let missing = match default(field) {
+            Some(default) => default,
+            None if is_option(ty) => quote!(::core::option::Option::None),
+            None => quote!(return ::core::result::Result::Err(
+                ::axum_jrpc::__private::serde::de::Error::invalid_length(#i, &self)
+            )),
+        };
+        quote! {
+            let #ident = match seq.next_element::<#ty>()? {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => #missing,
+            };
+        }
+    });
+
+    Ok(quote! {
+        impl<'de> ::axum_jrpc::__private::serde::Deserialize<'de> for #ident {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: ::axum_jrpc::__private::serde::Deserializer<'de>,
+            {
+                use ::axum_jrpc::__private::serde::de;
+
+                #[derive(::axum_jrpc::__private::serde::Deserialize)]
+                #[serde(crate = "::axum_jrpc::__private::serde")]
+                #(#container_attrs)*
+                struct Named {
+                    #(#(#field_attrs)* #idents: #types,)*
+                }
+
+                struct Visitor;
+
+                impl<'de> de::Visitor<'de> for Visitor {
+                    type Value = #ident;
+
+                    fn expecting(
+                        &self,
+                        f: &mut ::core::fmt::Formatter<'_>,
+                    ) -> ::core::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_seq<A: de::SeqAccess<'de>>(
+                        self,
+                        mut seq: A,
+                    ) -> ::core::result::Result<#ident, A::Error> {
+                        #(#positions)*
+                        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+                            return ::core::result::Result::Err(de::Error::invalid_length(
+                                #count + 1,
+                                &self,
+                            ));
+                        }
+                        ::core::result::Result::Ok(#ident { #(#idents,)* })
+                    }
+
+                    fn visit_map<A: de::MapAccess<'de>>(
+                        self,
+                        map: A,
+                    ) -> ::core::result::Result<#ident, A::Error> {
+                        let Named { #(#idents,)* } = <Named as de::Deserialize>::deserialize(
+                            de::value::MapAccessDeserializer::new(map),
+                        )?;
+                        ::core::result::Result::Ok(#ident { #(#idents,)* })
+                    }
+                }
+
+                deserializer.deserialize_any(Visitor)
+            }
+        }
+    })
+}
+
+fn is_option(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    path.qself.is_none()
+        && path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option")
+}
+
+/// Value of the field when `#[serde(default)]` lets it be left out
+fn default(field: &syn::Field) -> Option<TokenStream> {
+    let mut default = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        // other keys are left to serde
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                default = Some(match meta.input.peek(syn::Token![=]) {
+                    true => {
+                        let path: syn::ExprPath = meta.value()?.parse::<syn::LitStr>()?.parse()?;
+                        quote!(#path())
+                    }
+                    false => quote!(::core::default::Default::default()),
+                });
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|_| Ok(()))?;
+            }
+            Ok(())
+        });
+    }
+    default
+}
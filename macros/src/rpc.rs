@@ -0,0 +1,216 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    FnArg, Ident, ItemTrait, LitStr, Meta, Pat, ReturnType, Token, TraitItem, TraitItemFn, Type,
+};
+
+use crate::params;
+
+struct Method {
+    name: String,
+    ident: Ident,
+    args: Vec<(Ident, Type)>,
+}
+
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let (server, client) = parse_attr(attr)?;
+    let mut item: ItemTrait = syn::parse2(item)?;
+
+    let mut methods = Vec::new();
+    for trait_item in &mut item.items {
+        if let TraitItem::Fn(method) = trait_item {
+            methods.push(parse_method(method)?);
+        }
+    }
+
+    item.supertraits
+        .push(syn::parse_quote!(::core::marker::Send));
+    item.supertraits
+        .push(syn::parse_quote!(::core::marker::Sync));
+
+    let server = server.then(|| expand_server(&item, &methods));
+    let client = client.then(|| expand_client(&item, &methods));
+
+    Ok(quote! {
+        #[::axum_jrpc::__private::async_trait::async_trait]
+        #item
+        #server
+        #client
+    })
+}
+
+fn parse_attr(attr: TokenStream) -> syn::Result<(bool, bool)> {
+    let idents = Punctuated::<Ident, Token![,]>::parse_terminated.parse2(attr)?;
+    if idents.is_empty() {
+        return Ok((true, true));
+    }
+
+    let (mut server, mut client) = (false, false);
+    for ident in idents {
+        match ident.to_string().as_str() {
+            "server" => server = true,
+            "client" => client = true,
+            _ => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected `server` or `client`",
+                ))
+            }
+        }
+    }
+    Ok((server, client))
+}
+
+fn parse_method(method: &mut TraitItemFn) -> syn::Result<Method> {
+    let sig = &method.sig;
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new(sig.span(), "rpc methods must be async"));
+    }
+    if let ReturnType::Default = sig.output {
+        return Err(syn::Error::new(
+            sig.span(),
+            "rpc methods must return a `Result`",
+        ));
+    }
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => return Err(syn::Error::new(sig.span(), "rpc methods must take `&self`")),
+    }
+
+    let mut args = Vec::new();
+    for input in inputs {
+        let FnArg::Typed(arg) = input else {
+            unreachable!("receiver is always the first argument")
+        };
+        let Pat::Ident(pat) = &*arg.pat else {
+            return Err(syn::Error::new(arg.pat.span(), "expected an identifier"));
+        };
+        args.push((pat.ident.clone(), (*arg.ty).clone()));
+    }
+
+    let mut name = sig.ident.to_string();
+    let mut error = None;
+    method.attrs.retain(|attr| {
+        if !attr.path().is_ident("method") {
+            return true;
+        }
+        if let Err(e) = parse_method_attr(&attr.meta, &mut name) {
+            error = Some(e);
+        }
+        false
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    Ok(Method {
+        name,
+        ident: sig.ident.clone(),
+        args,
+    })
+}
+
+/// Parses `#[method(name = "...")]`
+fn parse_method_attr(meta: &Meta, name: &mut String) -> syn::Result<()> {
+    meta.require_list()?.parse_nested_meta(|meta| {
+        if meta.path.is_ident("name") {
+            *name = meta.value()?.parse::<LitStr>()?.value();
+            Ok(())
+        } else {
+            Err(meta.error("expected `name`"))
+        }
+    })
+}
+
+fn expand_server(item: &ItemTrait, methods: &[Method]) -> TokenStream {
+    let vis = &item.vis;
+    let trait_ident = &item.ident;
+    let server_ident = format_ident!("{}Server", trait_ident);
+    let doc = format!("Dispatches JSON-RPC requests to a [`{trait_ident}`] implementation.");
+
+    let arms = methods.iter().map(|method| {
+        let name = &method.name;
+        let ident = &method.ident;
+        let arg_idents: Vec<_> = method.args.iter().map(|(ident, _)| ident).collect();
+        let parse = params::parse_args(&method.args);
+
+        quote! {
+            #name => {
+                #parse
+                match #trait_ident::#ident(self, #(#arg_idents),*).await {
+                    ::core::result::Result::Ok(result) => {
+                        ::core::result::Result::Ok(::axum_jrpc::JsonRpcResponse::success(id, result))
+                    }
+                    ::core::result::Result::Err(error) => ::core::result::Result::Err(
+                        ::axum_jrpc::JsonRpcResponse::error(id, ::core::convert::Into::into(error)),
+                    ),
+                }
+            }
+        }
+    });
+
+    quote! {
+        #[doc = #doc]
+        #[::axum_jrpc::__private::async_trait::async_trait]
+        #vis trait #server_ident: #trait_ident {
+            /// Calls the method named in `request`, positional and named params are accepted
+            async fn dispatch(&self, request: ::axum_jrpc::JsonRpcExtractor) -> ::axum_jrpc::JrpcResult {
+                let id = request.get_answer_id();
+                match &*request.method {
+                    #(#arms)*
+                    method => ::core::result::Result::Ok(request.method_not_found(method)),
+                }
+            }
+        }
+
+        impl<T: #trait_ident + ?::core::marker::Sized> #server_ident for T {}
+    }
+}
+
+fn expand_client(item: &ItemTrait, methods: &[Method]) -> TokenStream {
+    let vis = &item.vis;
+    let trait_ident = &item.ident;
+    let client_ident = format_ident!("{}Client", trait_ident);
+    let doc = format!("[`{trait_ident}`] implementation calling a remote server.");
+
+    let fns = item.items.iter().filter_map(|item| match item {
+        TraitItem::Fn(f) => Some(&f.sig),
+        _ => None,
+    });
+    let impls = fns.zip(methods).map(|(sig, method)| {
+        let name = &method.name;
+        let arg_idents = method.args.iter().map(|(ident, _)| ident);
+        let params = if method.args.is_empty() {
+            // null, which requests are sent without
+            quote! { () }
+        } else {
+            quote! { (#(#arg_idents,)*) }
+        };
+
+        quote! {
+            #sig {
+                ::axum_jrpc::client::JsonRpcClient::call(&self.0, #name, #params)
+                    .await
+                    .map_err(::core::convert::Into::into)
+            }
+        }
+    });
+
+    let client = Ident::new("C", Span::call_site());
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug)]
+        #vis struct #client_ident<#client>(pub #client);
+
+        #[::axum_jrpc::__private::async_trait::async_trait]
+        impl<#client: ::axum_jrpc::client::JsonRpcClient> #trait_ident for #client_ident<#client> {
+            #(#impls)*
+        }
+    }
+}
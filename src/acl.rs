@@ -0,0 +1,286 @@
+//! Network access control by IP address, for the `acl` feature.
+//!
+//! [`AclLayer`] wraps the axum route of an endpoint and checks the peer of every request
+//! against an [`AccessList`] before its body is even read. Peers in a denied range, or outside
+//! all allowed ranges if there are any, are answered with a [`FORBIDDEN`] error without an id,
+//! or with an empty `403 Forbidden` response once [`AclLayer::silent`] is set, which tells
+//! them nothing about the service.
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::acl::{AccessList, AclLayer};
+//! use axum_jrpc::{router, JsonRpcRouter};
+//!
+//! let list = AccessList::new()
+//!     .allow("10.0.0.0/8".parse().unwrap())
+//!     .deny("10.66.0.0/16".parse().unwrap());
+//! let app: Router = Router::new()
+//!     .route("/", router::post(JsonRpcRouter::new()))
+//!     .layer(AclLayer::new(list.clone()));
+//!
+//! // later, e.g. when the configuration file changes
+//! list.reload(AccessList::new().allow("10.0.0.0/8".parse().unwrap()));
+//! ```
+//! Peers are known from axum's [`ConnectInfo`], so the app must be served with
+//! [`into_make_service_with_connect_info`](axum::Router::into_make_service_with_connect_info).
+//! Requests without it are blocked, whatever the ranges are. IPv4 peers of dual-stack listeners
+//! are matched by their IPv4 address rather than the IPv4-mapped IPv6 one.
+
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+use axum::extract::{ConnectInfo, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+pub use ipnet::IpNet;
+use tower::{Layer, Service};
+
+use crate::auth::FORBIDDEN;
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{Id, JsonRpcResponse, Value};
+
+#[derive(Debug, Clone, Default)]
+struct Rules {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl Rules {
+    fn permits(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        let contains = |nets: &[IpNet]| nets.iter().any(|n| n.contains(&ip));
+        !contains(&self.deny) && (self.allow.is_empty() || contains(&self.allow))
+    }
+}
+
+/// Allowed and denied ranges of addresses, shared by its clones so it can be reloaded while
+/// serving.
+///
+/// Denied ranges take precedence, and if no range is allowed, every address not denied is
+/// allowed.
+#[derive(Debug, Clone, Default)]
+pub struct AccessList {
+    rules: Arc<RwLock<Arc<Rules>>>,
+}
+
+impl AccessList {
+    /// A list permitting every address
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the addresses of `net`, and only the addresses of allowed ranges
+    pub fn allow(self, net: IpNet) -> Self {
+        self.update(|rules| rules.allow.push(net));
+        self
+    }
+
+    /// Denies the addresses of `net`
+    pub fn deny(self, net: IpNet) -> Self {
+        self.update(|rules| rules.deny.push(net));
+        self
+    }
+
+    /// Replaces the ranges with those of `other`, for the requests received from now on
+    pub fn reload(&self, other: AccessList) {
+        let rules = other.current();
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+    }
+
+    /// Whether requests from `ip` are let through
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        self.current().permits(ip)
+    }
+
+    fn current(&self) -> Arc<Rules> {
+        self.rules.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Rules)) {
+        let mut rules = self.rules.write().unwrap_or_else(|e| e.into_inner());
+        f(Arc::make_mut(&mut rules));
+    }
+}
+
+/// Wraps axum services in [`Acl`], see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct AclLayer {
+    list: AccessList,
+    silent: bool,
+}
+
+impl AclLayer {
+    pub fn new(list: AccessList) -> Self {
+        Self {
+            list,
+            silent: false,
+        }
+    }
+
+    /// Answers blocked peers with an empty `403 Forbidden` response instead of a JSON-RPC
+    /// error
+    pub fn silent(mut self, silent: bool) -> Self {
+        self.silent = silent;
+        self
+    }
+
+    /// The response to `request`, if its peer is blocked
+    fn check(&self, request: &Request) -> Option<Response> {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| peer.ip());
+        // without connect info every peer would look alike, so none is trusted
+        if peer.is_some_and(|peer| self.list.current().permits(peer)) {
+            return None;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?peer, "blocked by the access list");
+        if self.silent {
+            return Some(StatusCode::FORBIDDEN.into_response());
+        }
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::ServerError(FORBIDDEN),
+            "Forbidden".to_owned(),
+            Value::default(),
+        );
+        Some(JsonRpcResponse::error(Id::None(()), error).into_response())
+    }
+}
+
+impl<S> Layer<S> for AclLayer {
+    type Service = Acl<S>;
+
+    fn layer(&self, inner: S) -> Acl<S> {
+        Acl {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Blocks the peers not permitted by an [`AccessList`], see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Acl<S> {
+    inner: S,
+    layer: AclLayer,
+}
+
+impl<S> Service<Request> for Acl<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        match self.layer.check(&request) {
+            Some(response) => Box::pin(async move { Ok(response) }),
+            None => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::net::SocketAddr;
+
+    use axum::extract::ConnectInfo;
+    use axum::http::StatusCode;
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+
+    use super::{AccessList, AclLayer};
+    use crate::auth::FORBIDDEN;
+    use crate::{router, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn ping(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "pong"))
+    }
+
+    #[tokio::test]
+    async fn access_list() {
+        let list = AccessList::new()
+            .allow("10.0.0.0/8".parse().unwrap())
+            .deny("10.66.0.0/16".parse().unwrap());
+        assert!(list.permits("10.1.2.3".parse().unwrap()));
+        assert!(!list.permits("10.66.2.3".parse().unwrap()));
+        assert!(!list.permits("192.168.1.1".parse().unwrap()));
+        assert!(list.permits("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!list.permits("::ffff:10.66.2.3".parse().unwrap()));
+
+        let server = |layer: AclLayer, peer: &str| {
+            let peer: SocketAddr = peer.parse().unwrap();
+            let app = Router::new()
+                .route("/", router::post(JsonRpcRouter::new().method("ping", ping)))
+                .layer(layer)
+                .layer(axum::Extension(ConnectInfo(peer)));
+            TestServer::new(app).unwrap()
+        };
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+
+        let res: Value = server(AclLayer::new(list.clone()), "10.1.2.3:1000")
+            .post("/")
+            .json(&request)
+            .await
+            .json();
+        assert_eq!(res["result"], "pong");
+        let res: Value = server(AclLayer::new(list.clone()), "10.66.2.3:1000")
+            .post("/")
+            .json(&request)
+            .await
+            .json();
+        assert_eq!(res["error"]["code"], FORBIDDEN);
+        assert_eq!(res["id"], Value::Null);
+        let res = server(AclLayer::new(list.clone()).silent(true), "10.66.2.3:1000")
+            .post("/")
+            .json(&request)
+            .await;
+        res.assert_status(StatusCode::FORBIDDEN);
+        assert!(res.as_bytes().is_empty());
+
+        // ipv4 peers of dual-stack listeners
+        let res: Value = server(AclLayer::new(list.clone()), "[::ffff:10.1.2.3]:1000")
+            .post("/")
+            .json(&request)
+            .await
+            .json();
+        assert_eq!(res["result"], "pong");
+        let res: Value = server(AclLayer::new(list.clone()), "[::ffff:10.66.2.3]:1000")
+            .post("/")
+            .json(&request)
+            .await
+            .json();
+        assert_eq!(res["error"]["code"], FORBIDDEN);
+
+        // requests without connect info are blocked, even by a deny-only list
+        let app = Router::new()
+            .route("/", router::post(JsonRpcRouter::new().method("ping", ping)))
+            .layer(AclLayer::new(
+                AccessList::new().deny("10.0.0.0/8".parse().unwrap()),
+            ));
+        let res: Value = TestServer::new(app)
+            .unwrap()
+            .post("/")
+            .json(&request)
+            .await
+            .json();
+        assert_eq!(res["error"]["code"], FORBIDDEN);
+
+        // reloading applies to the layers already built
+        let server = server(AclLayer::new(list.clone()), "192.168.1.1:1000");
+        list.reload(AccessList::new().deny("10.0.0.0/8".parse().unwrap()));
+        let res: Value = server.post("/").json(&request).await.json();
+        assert_eq!(res["result"], "pong");
+    }
+}
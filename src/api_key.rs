@@ -0,0 +1,178 @@
+//! Authentication with API keys.
+//!
+//! [`ApiKeyAuth`] is an [`Authenticator`] reading the key from the `X-API-Key` header, or
+//! another one, and looking it up in a [`KeyStore`]: a [`HashMap`] of the known keys, a
+//! closure, or any other store implementing the trait, e.g. a database. The identity the key
+//! belongs to becomes the principal of the call.
+//! ```rust
+//! use std::collections::HashMap;
+//!
+//! use axum_jrpc::api_key::{ApiKey, ApiKeyAuth};
+//! use axum_jrpc::auth::AuthLayer;
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let keys = HashMap::from([(
+//!     "k3y".to_owned(),
+//!     ApiKey {
+//!         name: "billing".to_owned(),
+//!         scopes: vec!["invoices:read".to_owned()],
+//!         rate_limit: Some(10),
+//!     },
+//! )]);
+//! let rpc = AuthLayer::new(ApiKeyAuth::new(keys)).layer(JsonRpcRouter::new());
+//! ```
+
+use std::collections::HashMap;
+
+use axum::http::HeaderName;
+
+use crate::auth::{Authenticator, Principal, Unauthorized};
+use crate::router::RequestHeaders;
+use crate::JsonRpcExtractor;
+
+/// Identity and limits of a key, for stores without their own type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKey {
+    /// Who the key was issued to
+    pub name: String,
+    /// Checked by [`AuthorizeLayer`](crate::auth::AuthorizeLayer)
+    pub scopes: Vec<String>,
+    /// Calls per second the key may make, if limited
+    pub rate_limit: Option<u32>,
+}
+
+impl Principal for ApiKey {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+/// Where keys are looked up.
+#[async_trait::async_trait]
+pub trait KeyStore: Send + Sync + 'static {
+    /// Identity of the owner of a key
+    type Identity: Clone + Send + Sync + 'static;
+
+    /// The owner of `key`, `None` if it is unknown or revoked
+    async fn lookup(&self, key: &str) -> Result<Option<Self::Identity>, Unauthorized>;
+}
+
+#[async_trait::async_trait]
+impl<I: Clone + Send + Sync + 'static> KeyStore for HashMap<String, I> {
+    type Identity = I;
+
+    async fn lookup(&self, key: &str) -> Result<Option<I>, Unauthorized> {
+        Ok(self.get(key).cloned())
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, I> KeyStore for F
+where
+    F: Fn(&str) -> Option<I> + Send + Sync + 'static,
+    I: Clone + Send + Sync + 'static,
+{
+    type Identity = I;
+
+    async fn lookup(&self, key: &str) -> Result<Option<I>, Unauthorized> {
+        Ok(self(key))
+    }
+}
+
+/// Authenticates calls by their API key, see the [module docs](self).
+#[derive(Debug)]
+pub struct ApiKeyAuth<K> {
+    store: K,
+    header: HeaderName,
+}
+
+impl<K: KeyStore> ApiKeyAuth<K> {
+    /// Header carrying the key unless [`header`](Self::header) sets another one
+    pub const HEADER: &'static str = "x-api-key";
+
+    pub fn new(store: K) -> Self {
+        Self {
+            store,
+            header: HeaderName::from_static(Self::HEADER),
+        }
+    }
+
+    /// Reads the key from `header`
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<K: KeyStore> Authenticator for ApiKeyAuth<K> {
+    type Principal = K::Identity;
+
+    async fn authenticate(&self, request: &JsonRpcExtractor) -> Result<K::Identity, Unauthorized> {
+        let key = request
+            .extension::<RequestHeaders>()
+            .and_then(|headers| headers.0.get(&self.header)?.to_str().ok())
+            .ok_or_else(|| Unauthorized::new("Missing API key"))?;
+        self.store
+            .lookup(key)
+            .await?
+            .ok_or_else(|| Unauthorized::new("Invalid API key"))
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::collections::HashMap;
+
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{ApiKey, ApiKeyAuth};
+    use crate::auth::{AuthLayer, UNAUTHORIZED};
+    use crate::router;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn whoami(req: JsonRpcExtractor) -> JrpcResult {
+        let key = req.extension::<ApiKey>().cloned().unwrap();
+        Ok(JsonRpcResponse::success(
+            req.get_answer_id(),
+            (key.name, key.rate_limit),
+        ))
+    }
+
+    #[tokio::test]
+    async fn api_keys() {
+        let key = ApiKey {
+            name: "billing".to_owned(),
+            scopes: vec![],
+            rate_limit: Some(10),
+        };
+        let keys = HashMap::from([("k3y".to_owned(), key)]);
+        let rpc = AuthLayer::new(ApiKeyAuth::new(keys))
+            .layer(JsonRpcRouter::new().method("whoami", whoami));
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "whoami"});
+
+        let res: Value = server.post("/").json(&request).await.json();
+        assert_eq!(res["error"]["code"], UNAUTHORIZED);
+        assert_eq!(res["error"]["message"], "Missing API key");
+        let res: Value = server
+            .post("/")
+            .add_header("x-api-key", "wrong")
+            .json(&request)
+            .await
+            .json();
+        assert_eq!(res["error"]["message"], "Invalid API key");
+        let res: Value = server
+            .post("/")
+            .add_header("x-api-key", "k3y")
+            .json(&request)
+            .await
+            .json();
+        assert_eq!(res["result"], json!(["billing", 10]));
+    }
+}
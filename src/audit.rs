@@ -0,0 +1,283 @@
+//! Audit log of every call and its outcome, for environments that must retain RPC history.
+//!
+//! [`AuditLayer`] wraps a service and hands an [`AuditRecord`] of each call to an
+//! [`AuditSink`]. Records are collected by a background task and written in batches, so
+//! sinks can be slow without delaying responses. [`JsonLinesSink`] appends records to a
+//! file, one json object per line, and with the `tracing` feature [`TracingSink`] logs them.
+//! ```rust,no_run
+//! use axum_jrpc::audit::{AuditLayer, JsonLinesSink};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let sink = JsonLinesSink::open("audit.jsonl").await?;
+//! let rpc = AuditLayer::new(sink).layer(JsonRpcRouter::new());
+//! # Ok(())
+//! # }
+//! ```
+//! Records are only lost if the sink fails or falls behind by more than
+//! [`AuditLayer::CAPACITY`] records, both cases are logged with `tracing`.
+
+use std::convert::Infallible;
+use std::path::Path;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, Mutex};
+use tower::{Layer, Service};
+
+use crate::router::BoxFuture;
+use crate::{to_vec, Id, JsonRpcAnswer, JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// A call and its outcome, immutable once recorded.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    /// Time the call was received, in milliseconds since the unix epoch
+    pub timestamp_ms: u64,
+    pub method: String,
+    pub id: Id,
+    pub params: Value,
+    pub outcome: AuditOutcome,
+    pub duration_ms: u64,
+}
+
+/// How a call ended.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Success,
+    Error { code: i32, message: String },
+}
+
+/// Destination of audit records.
+#[async_trait::async_trait]
+pub trait AuditSink: Send + Sync + 'static {
+    /// Persists a batch of records, in the order the calls completed
+    async fn write(&self, records: &[AuditRecord]) -> std::io::Result<()>;
+}
+
+/// Appends records to a file as json lines.
+#[derive(Debug)]
+pub struct JsonLinesSink {
+    file: Mutex<tokio::fs::File>,
+}
+
+impl JsonLinesSink {
+    /// Opens `path` for appending, creating it if needed
+    pub async fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditSink for JsonLinesSink {
+    async fn write(&self, records: &[AuditRecord]) -> std::io::Result<()> {
+        let mut lines = Vec::new();
+        for record in records {
+            lines.extend(to_vec(record).map_err(std::io::Error::other)?);
+            lines.push(b'\n');
+        }
+        let mut file = self.file.lock().await;
+        file.write_all(&lines).await?;
+        file.flush().await
+    }
+}
+
+/// Logs records as `INFO` events with the `jrpc_audit` target.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+#[cfg(feature = "tracing")]
+#[async_trait::async_trait]
+impl AuditSink for TracingSink {
+    async fn write(&self, records: &[AuditRecord]) -> std::io::Result<()> {
+        for record in records {
+            let record = to_vec(record).map_err(std::io::Error::other)?;
+            tracing::info!(target: "jrpc_audit", record = %String::from_utf8_lossy(&record));
+        }
+        Ok(())
+    }
+}
+
+/// Wraps services in [`Audit`], see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct AuditLayer {
+    records: mpsc::Sender<AuditRecord>,
+}
+
+impl AuditLayer {
+    /// Pending records, further ones are dropped until the sink catches up
+    pub const CAPACITY: usize = 8192;
+
+    /// Records per batch at most
+    pub const BATCH_SIZE: usize = 256;
+
+    /// Longest a record waits for its batch to fill up
+    pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Starts the task writing to `sink`, must be called within a Tokio runtime
+    pub fn new(sink: impl AuditSink) -> Self {
+        Self::with_capacity(sink, Self::CAPACITY)
+    }
+
+    /// Like [`new`](Self::new), with room for `capacity` pending records
+    pub fn with_capacity(sink: impl AuditSink, capacity: usize) -> Self {
+        let (records, receiver) = mpsc::channel(capacity);
+        tokio::spawn(write_batches(sink, receiver));
+        Self { records }
+    }
+
+    /// Number of records not handed to the sink yet
+    pub fn pending(&self) -> usize {
+        self.records.max_capacity() - self.records.capacity()
+    }
+}
+
+async fn write_batches(sink: impl AuditSink, mut receiver: mpsc::Receiver<AuditRecord>) {
+    let mut batch = Vec::with_capacity(AuditLayer::BATCH_SIZE);
+    loop {
+        let Some(record) = receiver.recv().await else {
+            return;
+        };
+        batch.push(record);
+        let deadline = tokio::time::sleep(AuditLayer::FLUSH_INTERVAL);
+        tokio::pin!(deadline);
+        while batch.len() < AuditLayer::BATCH_SIZE {
+            tokio::select! {
+                record = receiver.recv() => match record {
+                    Some(record) => batch.push(record),
+                    None => break,
+                },
+                _ = &mut deadline => break,
+            }
+        }
+        if let Err(_e) = sink.write(&batch).await {
+            #[cfg(feature = "tracing")]
+            tracing::error!(error = %_e, records = batch.len(), "failed to write audit records");
+        }
+        batch.clear();
+    }
+}
+
+impl<S> Layer<S> for AuditLayer {
+    type Service = Audit<S>;
+
+    fn layer(&self, inner: S) -> Audit<S> {
+        Audit {
+            inner,
+            records: self.records.clone(),
+        }
+    }
+}
+
+/// Records every call, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Audit<S> {
+    inner: S,
+    records: mpsc::Sender<AuditRecord>,
+}
+
+impl<S> Service<JsonRpcExtractor> for Audit<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let (method, params) = (request.method.to_string(), request.parsed.clone());
+        let started = Instant::now();
+        let records = self.records.clone();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let Ok(response) = response.await;
+            let outcome = match &response.result {
+                JsonRpcAnswer::Result(_) => AuditOutcome::Success,
+                JsonRpcAnswer::Error(error) => AuditOutcome::Error {
+                    code: error.code(),
+                    message: error.message().to_owned(),
+                },
+            };
+            let record = AuditRecord {
+                timestamp_ms,
+                method,
+                id: response.id.clone(),
+                params,
+                outcome,
+                duration_ms: started.elapsed().as_millis() as u64,
+            };
+            if let Err(_e) = records.try_send(record) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_e, "audit record dropped");
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{AuditLayer, JsonLinesSink};
+    use crate::router::JsonRpcService;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i32; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    #[tokio::test]
+    async fn json_lines() {
+        let path = std::env::temp_dir().join(format!("jrpc-audit-{}.jsonl", std::process::id()));
+        let sink = JsonLinesSink::open(&path).await.unwrap();
+        let rpc = AuditLayer::new(sink).layer(JsonRpcRouter::new().method("add", add));
+
+        for (id, params) in [(1, json!([1, 2])), (2, json!([1]))] {
+            let request = JsonRpcRequest {
+                id: id.into(),
+                method: "add".to_owned(),
+                params,
+            };
+            rpc.dispatch(request.into()).await;
+        }
+        tokio::time::sleep(AuditLayer::FLUSH_INTERVAL * 2).await;
+
+        let log = tokio::fs::read_to_string(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["method"], "add");
+        assert_eq!(records[0]["params"], json!([1, 2]));
+        assert_eq!(records[0]["outcome"], json!({"status": "success"}));
+        assert_eq!(records[1]["id"], 2);
+        assert_eq!(records[1]["outcome"]["code"], -32602);
+    }
+}
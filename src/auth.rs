@@ -0,0 +1,487 @@
+//! Authentication of callers, answered with JSON-RPC errors instead of bare HTTP statuses.
+//!
+//! [`AuthLayer`] wraps a service and asks an [`Authenticator`] about every call before it is
+//! dispatched. Calls of unauthenticated callers are answered with an [`UNAUTHORIZED`] error
+//! carrying their id, which JSON-RPC clients can parse, and those of authenticated ones get
+//! the returned principal as an extension.
+//!
+//! Credentials are usually read from the [`RequestHeaders`] extension of calls received over
+//! HTTP. [`AuthorizeLayer`] then restricts methods to principals with the scopes or roles
+//! they require.
+//! ```rust
+//! use axum::http::header;
+//! use axum_jrpc::auth::{AuthLayer, Unauthorized};
+//! use axum_jrpc::router::RequestHeaders;
+//! use axum_jrpc::{JsonRpcExtractor, JsonRpcRouter};
+//! use tower::Layer;
+//!
+//! #[derive(Clone)]
+//! struct User(String);
+//!
+//! let auth = AuthLayer::new(|req: &JsonRpcExtractor| {
+//!     let token = req
+//!         .extension::<RequestHeaders>()
+//!         .and_then(|headers| headers.0.get(header::AUTHORIZATION)?.to_str().ok())
+//!         .and_then(|value| value.strip_prefix("Bearer "));
+//!     match token {
+//!         Some("secret") => Ok(User("alice".to_owned())),
+//!         _ => Err(Unauthorized::new("Invalid token")),
+//!     }
+//! })
+//! .public("health");
+//! let rpc = auth.layer(JsonRpcRouter::new());
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tower::{Layer, Service};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+#[cfg(doc)]
+use crate::router::RequestHeaders;
+use crate::{JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Error code of calls without valid credentials
+pub const UNAUTHORIZED: i32 = -32001;
+
+/// Error code of calls whose principal lacks a scope required by the method
+pub const FORBIDDEN: i32 = -32003;
+
+/// Why a caller was not authenticated, the message is sent to the caller.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{0}")]
+pub struct Unauthorized(pub String);
+
+impl Unauthorized {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl Default for Unauthorized {
+    fn default() -> Self {
+        Self::new("Unauthorized")
+    }
+}
+
+impl From<Unauthorized> for JsonRpcError {
+    fn from(error: Unauthorized) -> Self {
+        JsonRpcError::new(
+            JsonRpcErrorReason::ServerError(UNAUTHORIZED),
+            error.0,
+            Value::default(),
+        )
+    }
+}
+
+/// Checks the credentials of a call.
+#[async_trait::async_trait]
+pub trait Authenticator: Send + Sync + 'static {
+    /// Who made the call, inserted into its extensions
+    type Principal: Clone + Send + Sync + 'static;
+
+    async fn authenticate(
+        &self,
+        request: &JsonRpcExtractor,
+    ) -> Result<Self::Principal, Unauthorized>;
+}
+
+#[async_trait::async_trait]
+impl<F, P> Authenticator for F
+where
+    F: Fn(&JsonRpcExtractor) -> Result<P, Unauthorized> + Send + Sync + 'static,
+    P: Clone + Send + Sync + 'static,
+{
+    type Principal = P;
+
+    async fn authenticate(&self, request: &JsonRpcExtractor) -> Result<P, Unauthorized> {
+        self(request)
+    }
+}
+
+/// Wraps services in [`Auth`], see the [module docs](self).
+pub struct AuthLayer<A> {
+    authenticator: Arc<A>,
+    public: Arc<HashSet<String>>,
+}
+
+impl<A> Clone for AuthLayer<A> {
+    fn clone(&self) -> Self {
+        Self {
+            authenticator: self.authenticator.clone(),
+            public: self.public.clone(),
+        }
+    }
+}
+
+impl<A> std::fmt::Debug for AuthLayer<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthLayer")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A: Authenticator> AuthLayer<A> {
+    pub fn new(authenticator: A) -> Self {
+        Self {
+            authenticator: Arc::new(authenticator),
+            public: Default::default(),
+        }
+    }
+
+    /// Lets anyone call `method`, without a principal
+    pub fn public(mut self, method: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.public).insert(method.into());
+        self
+    }
+}
+
+impl<A, S> Layer<S> for AuthLayer<A> {
+    type Service = Auth<A, S>;
+
+    fn layer(&self, inner: S) -> Auth<A, S> {
+        Auth {
+            inner,
+            auth: self.clone(),
+        }
+    }
+}
+
+/// Authenticates every call, see the [module docs](self).
+pub struct Auth<A, S> {
+    inner: S,
+    auth: AuthLayer<A>,
+}
+
+impl<A, S: Clone> Clone for Auth<A, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            auth: self.auth.clone(),
+        }
+    }
+}
+
+impl<A, S: std::fmt::Debug> std::fmt::Debug for Auth<A, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Auth")
+            .field("inner", &self.inner)
+            .field("auth", &self.auth)
+            .finish()
+    }
+}
+
+impl<A, S> Service<JsonRpcExtractor> for Auth<A, S>
+where
+    A: Authenticator,
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: JsonRpcExtractor) -> Self::Future {
+        if self.auth.public.contains(request.method()) {
+            return Box::pin(self.inner.call(request));
+        }
+        // the ready service is taken, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authenticator = self.auth.authenticator.clone();
+
+        Box::pin(async move {
+            match authenticator.authenticate(&request).await {
+                Ok(principal) => {
+                    request.extensions.insert(principal);
+                    inner.call(request).await
+                }
+                Err(error) => Ok(JsonRpcResponse::error(request.id, error.into())),
+            }
+        })
+    }
+}
+
+/// Scopes or roles of a principal, checked by [`AuthorizeLayer`].
+pub trait Principal: Send + Sync + 'static {
+    fn has_scope(&self, scope: &str) -> bool;
+}
+
+/// Wraps services in [`Authorize`], which enforces the scopes required by each method.
+///
+/// Calls of methods requiring scopes are answered with an [`UNAUTHORIZED`] error if they
+/// have no `P` extension, usually inserted by an [`AuthLayer`] wrapping this one, and with a
+/// [`FORBIDDEN`] error if it lacks any of the scopes. Other methods are left alone.
+/// ```rust
+/// use axum_jrpc::auth::{AuthLayer, AuthorizeLayer, Principal, Unauthorized};
+/// use axum_jrpc::{JsonRpcExtractor, JsonRpcRouter};
+/// use tower::Layer;
+///
+/// #[derive(Clone)]
+/// struct User {
+///     roles: Vec<String>,
+/// }
+///
+/// impl Principal for User {
+///     fn has_scope(&self, scope: &str) -> bool {
+///         self.roles.iter().any(|role| role == scope)
+///     }
+/// }
+///
+/// # let authenticate = |_: &JsonRpcExtractor| Err::<User, _>(Unauthorized::default());
+/// let authorize = AuthorizeLayer::<User>::new().require("user_delete", ["admin"]);
+/// let rpc = AuthLayer::new(authenticate).layer(authorize.layer(JsonRpcRouter::new()));
+/// ```
+pub struct AuthorizeLayer<P> {
+    required: Arc<HashMap<String, Vec<String>>>,
+    principal: PhantomData<fn() -> P>,
+}
+
+impl<P> Clone for AuthorizeLayer<P> {
+    fn clone(&self) -> Self {
+        Self {
+            required: self.required.clone(),
+            principal: PhantomData,
+        }
+    }
+}
+
+impl<P> std::fmt::Debug for AuthorizeLayer<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthorizeLayer")
+            .field("required", &self.required)
+            .finish()
+    }
+}
+
+impl<P> Default for AuthorizeLayer<P> {
+    fn default() -> Self {
+        Self {
+            required: Default::default(),
+            principal: PhantomData,
+        }
+    }
+}
+
+impl<P: Principal> AuthorizeLayer<P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only lets principals with all of `scopes` call `method`
+    pub fn require<I>(mut self, method: impl Into<String>, scopes: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Arc::make_mut(&mut self.required)
+            .entry(method.into())
+            .or_default()
+            .extend(scopes.into_iter().map(Into::into));
+        self
+    }
+
+    /// The error answering a call of `method` by `principal`, if it may not make it
+    fn check(&self, method: &str, principal: Option<&P>) -> Option<JsonRpcError> {
+        let required = self.required.get(method)?;
+        let Some(principal) = principal else {
+            return Some(Unauthorized::default().into());
+        };
+        let missing = required.iter().find(|scope| !principal.has_scope(scope))?;
+        Some(JsonRpcError::new(
+            JsonRpcErrorReason::ServerError(FORBIDDEN),
+            format!("Missing scope `{missing}`"),
+            Value::default(),
+        ))
+    }
+}
+
+impl<P, S> Layer<S> for AuthorizeLayer<P> {
+    type Service = Authorize<P, S>;
+
+    fn layer(&self, inner: S) -> Authorize<P, S> {
+        Authorize {
+            inner,
+            policy: self.clone(),
+        }
+    }
+}
+
+/// Enforces the scopes required by each method, see [`AuthorizeLayer`].
+pub struct Authorize<P, S> {
+    inner: S,
+    policy: AuthorizeLayer<P>,
+}
+
+impl<P, S: Clone> Clone for Authorize<P, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl<P, S: std::fmt::Debug> std::fmt::Debug for Authorize<P, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Authorize")
+            .field("inner", &self.inner)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<P, S> Service<JsonRpcExtractor> for Authorize<P, S>
+where
+    P: Principal,
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        match self
+            .policy
+            .check(request.method(), request.extension::<P>())
+        {
+            Some(error) => {
+                let response = JsonRpcResponse::error(request.id, error);
+                Box::pin(async move { Ok(response) })
+            }
+            None => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::http::header;
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{AuthLayer, AuthorizeLayer, Principal, Unauthorized, FORBIDDEN, UNAUTHORIZED};
+    use crate::router::{self, RequestHeaders};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    #[derive(Clone)]
+    struct User(&'static str);
+
+    impl Principal for User {
+        fn has_scope(&self, scope: &str) -> bool {
+            self.0 == "alice" && scope == "admin"
+        }
+    }
+
+    fn authenticate(req: &JsonRpcExtractor) -> Result<User, Unauthorized> {
+        let token = req
+            .extension::<RequestHeaders>()
+            .and_then(|headers| headers.0.get(header::AUTHORIZATION)?.to_str().ok());
+        match token {
+            Some("Bearer secret") => Ok(User("alice")),
+            Some(_) => Err(Unauthorized::new("Invalid token")),
+            None => Err(Unauthorized::default()),
+        }
+    }
+
+    async fn whoami(req: JsonRpcExtractor) -> JrpcResult {
+        let user = req.extension::<User>().map(|user| user.0);
+        Ok(JsonRpcResponse::success(req.get_answer_id(), user))
+    }
+
+    #[tokio::test]
+    async fn unauthorized() {
+        let rpc = JsonRpcRouter::new()
+            .method("whoami", whoami)
+            .method("health", whoami);
+        let rpc = AuthLayer::new(authenticate).public("health").layer(rpc);
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        let call = |method: &str| json!({"jsonrpc": "2.0", "id": "abc", "method": method});
+
+        let res = server.post("/").json(&call("whoami")).await;
+        res.assert_status_ok();
+        let res: Value = res.json();
+        assert_eq!(res["id"], "abc");
+        assert_eq!(res["error"]["code"], UNAUTHORIZED);
+        assert_eq!(res["error"]["message"], "Unauthorized");
+
+        let res = server
+            .post("/")
+            .authorization_bearer("wrong")
+            .json(&call("whoami"))
+            .await;
+        assert_eq!(res.json::<Value>()["error"]["message"], "Invalid token");
+
+        let res = server
+            .post("/")
+            .authorization_bearer("secret")
+            .json(&call("whoami"))
+            .await;
+        assert_eq!(res.json::<Value>()["result"], "alice");
+
+        let res = server.post("/").json(&call("health")).await;
+        assert_eq!(res.json::<Value>()["result"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn forbidden() {
+        let rpc = JsonRpcRouter::new()
+            .method("whoami", whoami)
+            .method("reset", whoami);
+        let rpc = AuthorizeLayer::<User>::new()
+            .require("reset", ["admin"])
+            .layer(rpc);
+        let rpc = AuthLayer::new(|req: &JsonRpcExtractor| match authenticate(req) {
+            Ok(user) => Ok(user),
+            Err(_) => Ok(User("bob")),
+        })
+        .layer(rpc);
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        let call = |method: &str| json!({"jsonrpc": "2.0", "id": 1, "method": method});
+
+        let res = server.post("/").json(&call("reset")).await;
+        let res: Value = res.json();
+        assert_eq!(res["id"], 1);
+        assert_eq!(res["error"]["code"], FORBIDDEN);
+        assert_eq!(res["error"]["message"], "Missing scope `admin`");
+
+        let res = server.post("/").json(&call("whoami")).await;
+        assert_eq!(res.json::<Value>()["result"], "bob");
+
+        let res = server
+            .post("/")
+            .authorization_bearer("secret")
+            .json(&call("reset"))
+            .await;
+        assert_eq!(res.json::<Value>()["result"], "alice");
+
+        // without authentication there is no principal to check
+        let rpc = AuthorizeLayer::<User>::new()
+            .require("reset", ["admin"])
+            .layer(JsonRpcRouter::new().method("reset", whoami));
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        let res = server.post("/").json(&call("reset")).await;
+        assert_eq!(res.json::<Value>()["error"]["code"], UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,303 @@
+//! Incremental handling of batches, dispatching requests while the rest of the body is still
+//! being received.
+//!
+//! [`BatchSplitter`] cuts the top level array of a batch into its elements without parsing
+//! them, so only the element currently being received is buffered. [`stream_batch`] feeds it
+//! with the body, handles each element as soon as it is complete and writes the responses
+//! one per line as they finish.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::task::Poll;
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, Extensions, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use bytes::{Buf, BytesMut};
+use futures_util::stream::{self, FuturesUnordered, Stream, StreamExt};
+
+use crate::error::JsonRpcErrorReason;
+use crate::malformed::Malformed;
+use crate::router::{
+    error_response, exceeds_limit, handle_request, BatchIndex, BoxFuture, JsonRpcService,
+    JSON_LINES,
+};
+use crate::{from_slice, to_vec, JsonRpcResponse};
+
+/// Longest element of a batch, longer ones end the batch with a parse error
+const MAX_ELEMENT_SIZE: usize = 16 * 1024 * 1024;
+
+const ELEMENT_TOO_LONG: &str = "Batch element too long";
+
+/// Requests of a batch, or messages of a WebSocket, stream or NATS subscription, handled at the
+/// same time. The body or connection is not read further until one of them completes
+pub(crate) const MAX_IN_FLIGHT: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the opening bracket
+    Start,
+    /// Before an element, `after_comma` is set unless it is the first one
+    Separator { after_comma: bool },
+    /// Inside an element, at nesting `depth`
+    Element {
+        depth: usize,
+        string: bool,
+        escaped: bool,
+    },
+    /// After the closing bracket
+    End,
+}
+
+/// Splits a json array received in chunks into its elements.
+#[derive(Debug)]
+pub(crate) struct BatchSplitter {
+    buffer: BytesMut,
+    /// Bytes of the current element already scanned
+    scanned: usize,
+    state: State,
+    elements: usize,
+}
+
+impl BatchSplitter {
+    pub(crate) fn new() -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            scanned: 0,
+            state: State::Start,
+            elements: 0,
+        }
+    }
+
+    /// Number of elements split so far
+    pub(crate) fn elements(&self) -> usize {
+        self.elements
+    }
+
+    /// Appends `chunk` and returns the elements it completes
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Result<Vec<BytesMut>, &'static str> {
+        self.buffer.extend_from_slice(chunk);
+        let mut elements = Vec::new();
+        while self.scanned < self.buffer.len() {
+            let byte = self.buffer[self.scanned];
+            match &mut self.state {
+                State::Start | State::End | State::Separator { .. }
+                    if byte.is_ascii_whitespace() =>
+                {
+                    self.buffer.advance(1);
+                }
+                State::Start if byte == b'[' => {
+                    self.buffer.advance(1);
+                    self.state = State::Separator { after_comma: false };
+                }
+                State::Start => return Err("Expected a batch"),
+                State::End => return Err("Trailing characters after the batch"),
+                State::Separator { after_comma: false } if byte == b']' => {
+                    self.buffer.advance(1);
+                    self.state = State::End;
+                }
+                State::Separator { .. } if byte == b',' || byte == b']' => {
+                    return Err("Missing batch element")
+                }
+                State::Separator { .. } => {
+                    self.state = State::Element {
+                        depth: 0,
+                        string: false,
+                        escaped: false,
+                    };
+                }
+                State::Element {
+                    string: string @ true,
+                    escaped,
+                    ..
+                } => {
+                    match byte {
+                        _ if *escaped => *escaped = false,
+                        b'\\' => *escaped = true,
+                        b'"' => *string = false,
+                        _ => {}
+                    }
+                    self.scanned += 1;
+                }
+                State::Element { depth, string, .. } => {
+                    match byte {
+                        b'"' => *string = true,
+                        b'{' | b'[' => *depth += 1,
+                        b'}' | b']' if *depth > 0 => *depth -= 1,
+                        b',' | b']' if *depth == 0 => {
+                            elements.push(self.buffer.split_to(self.scanned));
+                            self.buffer.advance(1);
+                            self.scanned = 0;
+                            self.elements += 1;
+                            self.state = match byte {
+                                b',' => State::Separator { after_comma: true },
+                                _ => State::End,
+                            };
+                            continue;
+                        }
+                        b'}' => return Err("Unbalanced brackets"),
+                        _ => {}
+                    }
+                    self.scanned += 1;
+                }
+            }
+        }
+        if self.scanned > MAX_ELEMENT_SIZE {
+            return Err(ELEMENT_TOO_LONG);
+        }
+        Ok(elements)
+    }
+
+    /// Checks the batch was complete once the body has ended
+    pub(crate) fn finish(&self) -> Result<(), &'static str> {
+        match self.state {
+            State::End => Ok(()),
+            _ => Err("Unexpected end of batch"),
+        }
+    }
+}
+
+/// Handles the batch in `body`, whose beginning was already read into `prefix`, writing the
+/// responses one per line in the order they complete.
+///
+/// A malformed element is answered with a parse error and the rest of the batch handled as
+/// usual. A malformed batch, e.g. a truncated one, is answered with a parse error after the
+/// responses to the elements before the defect, which have already been dispatched.
+///
+/// The status is sent before any request is handled, so a batch of notifications gets an
+/// empty `200 OK` instead of `204 No Content`
+pub(crate) fn stream_batch<S, B>(
+    service: S,
+    prefix: Bytes,
+    body: B,
+    extensions: Extensions,
+) -> Response
+where
+    S: JsonRpcService,
+    B: Stream<Item = Result<Bytes, axum::Error>> + Send + Unpin + 'static,
+{
+    let mut body = stream::iter([Ok(prefix)]).chain(body);
+    let mut splitter = BatchSplitter::new();
+    let mut in_flight = FuturesUnordered::<BoxFuture<Option<JsonRpcResponse>>>::new();
+    let mut errors = VecDeque::new();
+    let mut done = false;
+    let mut index = 0;
+
+    let responses = stream::poll_fn(move |cx| loop {
+        if let Some(error) = errors.pop_front() {
+            return Poll::Ready(Some(error));
+        }
+        match in_flight.poll_next_unpin(cx) {
+            Poll::Ready(Some(Some(response))) => return Poll::Ready(Some(response)),
+            // a notification
+            Poll::Ready(Some(None)) => continue,
+            Poll::Ready(None) | Poll::Pending => {}
+        }
+        if done || in_flight.len() >= MAX_IN_FLIGHT {
+            return match done && in_flight.is_empty() {
+                true => Poll::Ready(None),
+                false => Poll::Pending,
+            };
+        }
+
+        let chunk = match body.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(chunk))) => chunk,
+            Poll::Ready(Some(Err(e))) => {
+                done = true;
+                if exceeds_limit(&e) {
+                    Malformed::Oversized.report(None, &e.to_string(), &extensions);
+                }
+                errors.push_back(error_response(
+                    JsonRpcErrorReason::ParseError,
+                    e.to_string(),
+                ));
+                continue;
+            }
+            Poll::Ready(None) => {
+                done = true;
+                let end = match splitter.finish() {
+                    Ok(()) if splitter.elements() == 0 => {
+                        Err((JsonRpcErrorReason::InvalidRequest, "Empty batch"))
+                    }
+                    Ok(()) => Ok(()),
+                    Err(e) => {
+                        Malformed::Syntax.report(None, e, &extensions);
+                        Err((JsonRpcErrorReason::ParseError, e))
+                    }
+                };
+                if let Err((reason, message)) = end {
+                    errors.push_back(error_response(reason, message));
+                }
+                continue;
+            }
+            Poll::Pending => return Poll::Pending,
+        };
+        match splitter.push(&chunk) {
+            Ok(elements) => in_flight.extend(elements.into_iter().map(|mut element| {
+                let service = service.clone();
+                let mut extensions = extensions.clone();
+                extensions.insert(BatchIndex(index));
+                index += 1;
+                Box::pin(async move {
+                    match from_slice(&mut element) {
+                        Ok(value) => handle_request(&service, value, extensions).await,
+                        Err(e) => {
+                            Malformed::Syntax.report(None, &e, &extensions);
+                            Some(error_response(JsonRpcErrorReason::ParseError, e))
+                        }
+                    }
+                }) as BoxFuture<_>
+            })),
+            Err(e) => {
+                done = true;
+                let malformed = match e {
+                    ELEMENT_TOO_LONG => Malformed::Oversized,
+                    _ => Malformed::Syntax,
+                };
+                malformed.report(None, e, &extensions);
+                errors.push_back(error_response(JsonRpcErrorReason::ParseError, e));
+            }
+        }
+    });
+    let lines = responses.filter_map(|response| async move {
+        let mut line = to_vec(&response).ok()?;
+        line.push(b'\n');
+        Some(Ok::<_, Infallible>(Bytes::from(line)))
+    });
+
+    (
+        [(header::CONTENT_TYPE, HeaderValue::from_static(JSON_LINES))],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod test {
+    use super::BatchSplitter;
+
+    fn split(chunks: &[&str]) -> Result<Vec<String>, &'static str> {
+        let mut splitter = BatchSplitter::new();
+        let mut elements = Vec::new();
+        for chunk in chunks {
+            for element in splitter.push(chunk.as_bytes())? {
+                elements.push(String::from_utf8(element.to_vec()).unwrap());
+            }
+        }
+        splitter.finish()?;
+        Ok(elements)
+    }
+
+    #[test]
+    fn splitter() {
+        assert_eq!(
+            split(&[" [{\"a\": [1, \"],\\\"\"]}", " , 2,\"x\" ", "]\n"]).unwrap(),
+            ["{\"a\": [1, \"],\\\"\"]} ", "2", "\"x\" "]
+        );
+        assert_eq!(split(&["[", "]"]).unwrap(), Vec::<String>::new());
+        assert!(split(&["[1,]"]).is_err());
+        assert!(split(&["[1", ",2"]).is_err());
+        assert!(split(&["[1] 2"]).is_err());
+        assert!(split(&["{}"]).is_err());
+    }
+}
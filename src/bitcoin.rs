@@ -0,0 +1,316 @@
+//! Compatibility with bitcoind-style clients, for the `bitcoin` feature.
+//!
+//! Bitcoin Core and the wallets modelled on it speak JSON-RPC 1.0: requests may lack
+//! `"jsonrpc"` or set it to `"1.0"`, and responses carry both `result` and `error`, one of
+//! them `null`. [`post`] serves such requests next to 2.0 ones, answering each in its own
+//! dialect and, for 1.0 errors, with the HTTP status bitcoind uses.
+//!
+//! [`BasicAuth`] checks HTTP basic credentials against `rpcuser`/`rpcpassword` pairs, and
+//! the cookie file which bitcoind writes for local clients.
+//! ```rust,no_run
+//! use axum::Router;
+//! use axum_jrpc::auth::AuthLayer;
+//! use axum_jrpc::bitcoin::{self, BasicAuth};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! # fn run() -> std::io::Result<()> {
+//! let auth = BasicAuth::new().user("alice", "hunter2").cookie("/var/lib/node/.cookie")?;
+//! let rpc = AuthLayer::new(auth).layer(JsonRpcRouter::new());
+//! let app: Router = Router::new().route("/", bitcoin::post(rpc));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::MethodRouter;
+use base64::Engine;
+use serde_json::{Map, Value};
+
+use crate::auth::{Authenticator, Unauthorized, UNAUTHORIZED};
+use crate::error::METHOD_NOT_FOUND;
+use crate::router::{JsonRpcService, RequestHeaders};
+use crate::JsonRpcExtractor;
+
+/// User name of the credentials in a cookie file
+pub const COOKIE_USER: &str = "__cookie__";
+
+/// Stands in for the missing or null id of a 1.0 request, which bitcoind answers with a null
+/// id while 2.0 would take it for a notification
+const NULL_ID: &str = "\u{0}null";
+
+/// Serves `service` like [`router::post`](crate::router::post), also accepting JSON-RPC 1.0
+/// requests, see the [module docs](self).
+///
+/// A message is answered in the 1.0 dialect when it, or any request of its batch, has no
+/// version or claims version 1.0. Other versions than these and 2.0 are invalid requests. 1.0
+/// requests without an id are answered with a null id, as bitcoind does. Single 1.0 requests
+/// failing get a `401 Unauthorized` status when their credentials are rejected, `404 Not
+/// Found` for unknown methods and `500 Internal Server Error` for other errors
+pub fn post<S: JsonRpcService>(service: S) -> MethodRouter {
+    axum::routing::post(move |request: Request| handle_post(service, request))
+}
+
+async fn handle_post<S: JsonRpcService>(service: S, request: Request) -> Response {
+    let (mut parts, body) = request.into_parts();
+    let bytes = match Bytes::from_request(Request::from_parts(parts.clone(), body), &()).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let legacy = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|mut message| upgrade(&mut message).then(|| serde_json::to_vec(&message)))
+        .and_then(Result::ok);
+    let Some(body) = legacy else {
+        let request = Request::from_parts(parts, Body::from(bytes));
+        return crate::router::handle_post(service, request, None).await;
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let request = Request::from_parts(parts, Body::from(body));
+    let response = crate::router::handle_post(service, request, None).await;
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(mut reply) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    match &mut reply {
+        Value::Array(responses) => responses.iter_mut().for_each(downgrade),
+        response => {
+            downgrade(response);
+            parts.status = match response["error"]["code"].as_i64() {
+                None => parts.status,
+                Some(code) if code == i64::from(UNAUTHORIZED) => {
+                    let challenge = HeaderValue::from_static(r#"Basic realm="jsonrpc""#);
+                    parts.headers.insert(header::WWW_AUTHENTICATE, challenge);
+                    StatusCode::UNAUTHORIZED
+                }
+                Some(code) if code == i64::from(METHOD_NOT_FOUND) => StatusCode::NOT_FOUND,
+                Some(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+        }
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let body = serde_json::to_vec(&reply).unwrap_or_default();
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Marks the 1.0 requests of `message` as 2.0, `true` if there were any. Requests claiming
+/// other versions are left for the router to reject
+fn upgrade(message: &mut Value) -> bool {
+    let upgrade = |request: &mut Map<String, Value>| {
+        let legacy = request
+            .get("jsonrpc")
+            .is_none_or(|version| *version == "1.0");
+        if legacy {
+            request.insert("jsonrpc".to_owned(), Value::from("2.0"));
+            let id = request.entry("id").or_insert(Value::Null);
+            if id.is_null() {
+                *id = Value::from(NULL_ID);
+            }
+        }
+        legacy
+    };
+    match message {
+        Value::Object(request) => upgrade(request),
+        Value::Array(requests) => requests
+            .iter_mut()
+            .filter_map(Value::as_object_mut)
+            .fold(false, |legacy, request| upgrade(request) | legacy),
+        _ => false,
+    }
+}
+
+/// Rewrites a response in the 1.0 dialect
+fn downgrade(response: &mut Value) {
+    if let Value::Object(response) = response {
+        response.remove("jsonrpc");
+        if response.get("id").and_then(Value::as_str) == Some(NULL_ID) {
+            response.insert("id".to_owned(), Value::Null);
+        }
+        response.entry("result").or_insert(Value::Null);
+        response.entry("error").or_insert(Value::Null);
+    }
+}
+
+/// Who made a call authenticated by [`BasicAuth`], [`COOKIE_USER`] for the cookie file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcUser(pub String);
+
+/// Authenticates calls by their HTTP basic credentials, see the [module docs](self).
+#[derive(Clone, Default)]
+pub struct BasicAuth {
+    credentials: Vec<(String, String)>,
+    cookie: Option<PathBuf>,
+}
+
+impl std::fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let users: Vec<_> = self.credentials.iter().map(|(user, _)| user).collect();
+        f.debug_struct("BasicAuth")
+            .field("users", &users)
+            .field("cookie", &self.cookie)
+            .finish()
+    }
+}
+
+impl BasicAuth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts `user` with `password`, as `rpcuser` and `rpcpassword` do
+    pub fn user(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials.push((user.into(), password.into()));
+        self
+    }
+
+    /// Accepts [`COOKIE_USER`] with a random password, which is written to `path` as
+    /// `__cookie__:<password>` for local clients to read. On unix the file is only readable
+    /// by its owner. Like bitcoind, call [`remove_cookie`](Self::remove_cookie) on shutdown
+    pub fn cookie(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut secret = [0; 32];
+        getrandom::getrandom(&mut secret).map_err(io::Error::other)?;
+        let password = hex::encode(secret);
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path.as_ref())?;
+        io::Write::write_all(&mut file, format!("{COOKIE_USER}:{password}").as_bytes())?;
+
+        self.cookie = Some(path.as_ref().to_owned());
+        Ok(self.user(COOKIE_USER, password))
+    }
+
+    /// Deletes the cookie file, if any
+    pub fn remove_cookie(&self) -> io::Result<()> {
+        match &self.cookie {
+            Some(path) => std::fs::remove_file(path),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Authenticator for BasicAuth {
+    type Principal = RpcUser;
+
+    async fn authenticate(&self, request: &JsonRpcExtractor) -> Result<RpcUser, Unauthorized> {
+        let credentials = request
+            .extension::<RequestHeaders>()
+            .and_then(|headers| headers.0.get(header::AUTHORIZATION)?.to_str().ok())
+            .and_then(|value| value.strip_prefix("Basic "))
+            .and_then(|value| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(value.trim())
+                    .ok()
+            })
+            .ok_or_else(|| Unauthorized::new("Missing credentials"))?;
+        let (user, password) = credentials
+            .iter()
+            .position(|&byte| byte == b':')
+            .map(|colon| (&credentials[..colon], &credentials[colon + 1..]))
+            .ok_or_else(|| Unauthorized::new("Invalid credentials"))?;
+
+        // every pair is compared, in constant time, so timing reveals nothing
+        let mut found = None;
+        for (known_user, known_password) in &self.credentials {
+            let matches = constant_time_eq(user, known_user.as_bytes())
+                & constant_time_eq(password, known_password.as_bytes());
+            if matches {
+                found = Some(known_user);
+            }
+        }
+        found
+            .map(|user| RpcUser(user.clone()))
+            .ok_or_else(|| Unauthorized::new("Incorrect rpcuser or rpcpassword"))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use axum::http::header;
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{BasicAuth, RpcUser};
+    use crate::auth::AuthLayer;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn whoami(req: JsonRpcExtractor) -> JrpcResult {
+        let user = req.extension::<RpcUser>().cloned().unwrap();
+        Ok(JsonRpcResponse::success(req.get_answer_id(), user.0))
+    }
+
+    #[tokio::test]
+    async fn serves_bitcoind_clients() {
+        let path = std::env::temp_dir().join(format!("axum-jrpc-{}.cookie", std::process::id()));
+        let auth = BasicAuth::new()
+            .user("alice", "hunter2")
+            .cookie(&path)
+            .unwrap();
+        let cookie = std::fs::read_to_string(&path).unwrap();
+        auth.remove_cookie().unwrap();
+        assert!(cookie.starts_with("__cookie__:"));
+
+        let rpc = AuthLayer::new(auth).layer(JsonRpcRouter::new().method("whoami", whoami));
+        let server = TestServer::new(Router::new().route("/", super::post(rpc))).unwrap();
+        let call = |credentials: &str, body: Value| {
+            let credentials =
+                base64::Engine::encode(&base64::engine::general_purpose::STANDARD, credentials);
+            server
+                .post("/")
+                .add_header(header::AUTHORIZATION, format!("Basic {credentials}"))
+                .json(&body)
+        };
+
+        let legacy = json!({"jsonrpc": "1.0", "id": "curl", "method": "whoami", "params": []});
+        let response = call(&cookie, legacy).await;
+        response.assert_status_ok();
+        response.assert_json(&json!({"result": "__cookie__", "error": null, "id": "curl"}));
+
+        let response = call("alice:hunter2", json!({"id": 1, "method": "getblock"})).await;
+        response.assert_status_not_found();
+        let response: Value = response.json();
+        assert_eq!(response["result"], Value::Null);
+        assert_eq!(response["error"]["code"], -32601);
+
+        let response = call("alice:hunter3", json!({"id": 1, "method": "whoami"})).await;
+        response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.header(header::WWW_AUTHENTICATE),
+            r#"Basic realm="jsonrpc""#
+        );
+
+        // bitcoind answers requests without an id too
+        let response = call("alice:hunter2", json!({"method": "whoami"})).await;
+        response.assert_json(&json!({"result": "alice", "error": null, "id": null}));
+        let response = call("alice:hunter2", json!({"id": null, "method": "whoami"})).await;
+        response.assert_json(&json!({"result": "alice", "error": null, "id": null}));
+
+        // unknown versions are not taken for 1.0
+        let future = json!({"jsonrpc": "3.0", "id": 1, "method": "whoami"});
+        let response: Value = call("alice:hunter2", future).await.json();
+        assert_eq!(response["error"]["code"], -32600);
+
+        let modern = json!([{"jsonrpc": "2.0", "id": 1, "method": "whoami"}]);
+        let response = call("alice:hunter2", modern).await;
+        response.assert_json(&json!([{"jsonrpc": "2.0", "result": "alice", "id": 1}]));
+    }
+}
@@ -0,0 +1,271 @@
+//! Fan-out of subscription notifications across replicas.
+//!
+//! With several replicas behind a load balancer, an event observed by one of them has to
+//! reach the subscribers connected to all the others. A [`Broadcast`] backend delivers
+//! everything published on a topic to every subscriber of that topic, wherever it runs.
+//! [`LocalBroadcast`] covers a single process, with the `redis` feature [`RedisBroadcast`]
+//! spans all processes connected to the same Redis server.
+//! ```rust
+//! use std::sync::Arc;
+//! use axum_jrpc::broadcast::{Broadcast, LocalBroadcast};
+//! use axum_jrpc::pubsub::PendingSubscription;
+//! use axum_jrpc::{JsonRpcExtractor, JsonRpcRouter};
+//!
+//! let backend: Arc<dyn Broadcast> = Arc::new(LocalBroadcast::new());
+//! let rpc = JsonRpcRouter::new().subscription("subscribe_blocks", "block", "unsubscribe_blocks", {
+//!     let backend = backend.clone();
+//!     move |_: JsonRpcExtractor, pending: PendingSubscription| {
+//!         let backend = backend.clone();
+//!         async move {
+//!             match backend.subscribe("blocks").await {
+//!                 Ok(blocks) => pending.accept().pipe(blocks).await,
+//!                 Err(e) => pending.reject(e.into()),
+//!             }
+//!         }
+//!     }
+//! });
+//!
+//! // anywhere else, on any replica
+//! # async fn publish(backend: Arc<dyn Broadcast>) {
+//! backend.publish("blocks", 42.into()).await.unwrap();
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{stream, Stream};
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::Value;
+
+/// Messages published on a topic.
+pub type TopicStream = Pin<Box<dyn Stream<Item = Value> + Send>>;
+
+/// Errors returned by [`Broadcast`] backends.
+#[derive(Debug, Error)]
+pub enum BroadcastError {
+    /// The backend could not be reached.
+    #[error("Broadcast backend error: {0}")]
+    Backend(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// A message could not be (de)serialized.
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
+
+impl From<BroadcastError> for JsonRpcError {
+    fn from(error: BroadcastError) -> Self {
+        JsonRpcError::new(
+            JsonRpcErrorReason::InternalError,
+            error.to_string(),
+            Value::default(),
+        )
+    }
+}
+
+/// Delivers messages published on a topic to all its subscribers.
+///
+/// Delivery is best effort: slow subscribers skip the messages they couldn't keep up with.
+#[async_trait::async_trait]
+pub trait Broadcast: Send + Sync + 'static {
+    /// Sends `message` to every current subscriber of `topic`
+    async fn publish(&self, topic: &str, message: Value) -> Result<(), BroadcastError>;
+
+    /// Returns the messages published on `topic` from now on
+    async fn subscribe(&self, topic: &str) -> Result<TopicStream, BroadcastError>;
+}
+
+/// In-process [`Broadcast`], also used by other backends to fan out received messages.
+#[derive(Debug, Clone)]
+pub struct LocalBroadcast {
+    topics: Arc<Mutex<HashMap<String, broadcast::Sender<Value>>>>,
+    capacity: usize,
+}
+
+impl Default for LocalBroadcast {
+    fn default() -> Self {
+        Self {
+            topics: Arc::default(),
+            capacity: 1024,
+        }
+    }
+}
+
+impl LocalBroadcast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of messages a subscriber may lag behind before skipping some, 1024 by default
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    fn send(&self, topic: &str, message: Value) {
+        let mut topics = self.topics.lock().unwrap();
+        if let Some(sender) = topics.get(topic) {
+            if sender.send(message).is_err() {
+                // the last subscriber is gone
+                topics.remove(topic);
+            }
+        }
+    }
+
+    fn receive(&self, topic: &str) -> TopicStream {
+        let mut topics = self.topics.lock().unwrap();
+        let receiver = match topics.get(topic) {
+            Some(sender) => sender.subscribe(),
+            None => {
+                let (sender, receiver) = broadcast::channel(self.capacity);
+                topics.insert(topic.to_owned(), sender);
+                receiver
+            }
+        };
+
+        let stream = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => return Some((message, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Box::pin(stream)
+    }
+}
+
+#[async_trait::async_trait]
+impl Broadcast for LocalBroadcast {
+    async fn publish(&self, topic: &str, message: Value) -> Result<(), BroadcastError> {
+        self.send(topic, message);
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<TopicStream, BroadcastError> {
+        Ok(self.receive(topic))
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_backend::RedisBroadcast;
+
+#[cfg(feature = "redis")]
+mod redis_backend {
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::StreamExt;
+    use redis::aio::{MultiplexedConnection, PubSubSink};
+    use redis::AsyncCommands;
+
+    use super::{Broadcast, BroadcastError, LocalBroadcast, TopicStream};
+    use crate::{from_slice, to_vec, Value};
+
+    impl From<redis::RedisError> for BroadcastError {
+        fn from(error: redis::RedisError) -> Self {
+            Self::Backend(Box::new(error))
+        }
+    }
+
+    /// [`Broadcast`] over Redis pub/sub, topics are Redis channels carrying JSON.
+    ///
+    /// A single subscriber connection is shared by all topics, received messages are fanned
+    /// out to local subscribers. Channels stay subscribed once used.
+    #[derive(Clone)]
+    pub struct RedisBroadcast {
+        publisher: MultiplexedConnection,
+        subscriber: PubSubSink,
+        subscribed: Arc<Mutex<HashSet<String>>>,
+        local: LocalBroadcast,
+    }
+
+    impl std::fmt::Debug for RedisBroadcast {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RedisBroadcast")
+                .field("subscribed", &self.subscribed)
+                .finish()
+        }
+    }
+
+    impl RedisBroadcast {
+        /// Opens the publisher and subscriber connections, spawning a task receiving messages
+        pub async fn connect(client: &redis::Client) -> Result<Self, BroadcastError> {
+            let publisher = client.get_multiplexed_async_connection().await?;
+            let (subscriber, mut messages) = client.get_async_pubsub().await?.split();
+
+            let local = LocalBroadcast::new();
+            tokio::spawn({
+                let local = local.clone();
+                async move {
+                    while let Some(message) = messages.next().await {
+                        let mut payload = message.get_payload_bytes().to_vec();
+                        if let Ok(value) = from_slice::<Value>(&mut payload) {
+                            local.send(message.get_channel_name(), value);
+                        }
+                    }
+                }
+            });
+
+            Ok(Self {
+                publisher,
+                subscriber,
+                subscribed: Arc::default(),
+                local,
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Broadcast for RedisBroadcast {
+        async fn publish(&self, topic: &str, message: Value) -> Result<(), BroadcastError> {
+            let payload = to_vec(&message).map_err(BroadcastError::Serialization)?;
+            self.publisher
+                .clone()
+                .publish::<_, _, ()>(topic, payload)
+                .await?;
+            Ok(())
+        }
+
+        async fn subscribe(&self, topic: &str) -> Result<TopicStream, BroadcastError> {
+            let stream = self.local.receive(topic);
+            let new = self.subscribed.lock().unwrap().insert(topic.to_owned());
+            if new {
+                if let Err(e) = self.subscriber.clone().subscribe(topic).await {
+                    self.subscribed.lock().unwrap().remove(topic);
+                    return Err(e.into());
+                }
+            }
+            Ok(stream)
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use futures_util::StreamExt;
+    use serde_json::json;
+
+    use super::{Broadcast, LocalBroadcast};
+
+    #[tokio::test]
+    async fn local() {
+        let backend = LocalBroadcast::new();
+        backend.publish("blocks", json!(0)).await.unwrap();
+
+        let mut first = backend.subscribe("blocks").await.unwrap();
+        let mut second = backend.subscribe("blocks").await.unwrap();
+        let mut other = backend.subscribe("txs").await.unwrap();
+        backend.publish("blocks", json!(1)).await.unwrap();
+        backend.publish("txs", json!("tx")).await.unwrap();
+
+        assert_eq!(first.next().await, Some(json!(1)));
+        assert_eq!(second.next().await, Some(json!(1)));
+        assert_eq!(other.next().await, Some(json!("tx")));
+    }
+}
@@ -0,0 +1,79 @@
+//! Response serialization into a per-thread buffer.
+//!
+//! Every response is written at the end of a thread-local [`BytesMut`] and split off as
+//! [`Bytes`] sharing its allocation. Once the responses written into a chunk are sent and
+//! dropped, reserving space reclaims the chunk instead of allocating a new one, so steady
+//! traffic runs without allocating per response.
+
+use std::cell::RefCell;
+
+use axum::body::Bytes;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bytes::BytesMut;
+use serde::Serialize;
+
+use crate::codec::{Codec, JsonBackend};
+
+/// Size of the chunks responses are written into
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Below this much free space a fresh or reclaimed chunk is reserved before writing
+const MIN_FREE: usize = 4 * 1024;
+
+thread_local! {
+    static BUFFER: RefCell<BytesMut> = RefCell::new(BytesMut::new());
+}
+
+/// Serializes `value` as json with the enabled backend
+pub(crate) fn to_bytes<T: Serialize>(value: &T) -> Result<Bytes, String> {
+    BUFFER.with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        if buffer.capacity() < MIN_FREE {
+            buffer.reserve(CHUNK_SIZE);
+        }
+        match JsonBackend::encode_into(value, &mut buffer) {
+            Ok(()) => Ok(buffer.split().freeze()),
+            Err(e) => {
+                buffer.clear();
+                Err(e)
+            }
+        }
+    })
+}
+
+/// Answers with `value` as json, like [`axum::Json`] does
+pub(crate) fn json_response<T: Serialize>(value: &T) -> Response {
+    match to_bytes(value) {
+        Ok(bytes) => (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(JsonBackend::CONTENT_TYPE),
+            )],
+            bytes,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::json;
+
+    use super::to_bytes;
+
+    #[test]
+    fn reuse() {
+        let first = to_bytes(&json!({"id": 1})).unwrap();
+        let second = to_bytes(&json!([1, 2, 3])).unwrap();
+        assert_eq!(&first[..], br#"{"id":1}"#);
+        assert_eq!(&second[..], b"[1,2,3]");
+
+        // a failed serialization leaves nothing behind
+        let map = std::collections::HashMap::from([((1, 2), 3)]);
+        assert!(to_bytes(&map).is_err());
+        assert_eq!(&to_bytes(&json!(null)).unwrap()[..], b"null");
+    }
+}
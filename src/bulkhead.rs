@@ -0,0 +1,143 @@
+//! Concurrency limits per method, see
+//! [`JsonRpcRouter::bulkhead`](crate::JsonRpcRouter::bulkhead), for the `bulkhead` feature.
+//!
+//! A [`Bulkhead`] caps the calls of a method running at once, so an expensive method can't
+//! take up all the workers and database connections the cheap ones need. Calls over the cap
+//! wait for a free slot, up to the length of the queue, and the others are answered with a
+//! [`SERVER_OVERLOADED`] error.
+//! ```rust
+//! use axum_jrpc::bulkhead::Bulkhead;
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//!
+//! async fn report(req: JsonRpcExtractor) -> JrpcResult {
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), "..."))
+//! }
+//!
+//! let rpc = JsonRpcRouter::new()
+//!     .method("report_generate", report)
+//!     .bulkhead("report_generate", Bulkhead::new(2).queue(8));
+//! ```
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::{BoxFuture, BoxHandler};
+pub use crate::shed::SERVER_OVERLOADED;
+use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Calls of a method allowed to run at once, and to wait for their turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bulkhead {
+    max_concurrent: usize,
+    queue: usize,
+}
+
+impl Bulkhead {
+    /// Lets `max_concurrent` calls run at once, and turns the others away
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            max_concurrent,
+            queue: 0,
+        }
+    }
+
+    /// Lets up to `queue` calls wait for a slot instead of being turned away
+    pub fn queue(mut self, queue: usize) -> Self {
+        self.queue = queue;
+        self
+    }
+}
+
+/// Counts a call out of the queue once it got a slot or was cancelled
+struct Waiting(Arc<AtomicUsize>);
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+pub(crate) fn bulkhead(
+    bulkhead: Bulkhead,
+    handler: BoxHandler,
+) -> impl Fn(JsonRpcExtractor) -> BoxFuture<JrpcResult> + Send + Sync + 'static {
+    let semaphore = Arc::new(Semaphore::new(bulkhead.max_concurrent));
+    let waiting = Arc::new(AtomicUsize::new(0));
+    move |request: JsonRpcExtractor| {
+        let permit = semaphore.clone().try_acquire_owned().ok();
+        let queued = permit.is_none().then(|| {
+            waiting
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                    (n < bulkhead.queue).then_some(n + 1)
+                })
+                .ok()
+                .map(|_| Waiting(waiting.clone()))
+        });
+        if let Some(None) = queued {
+            let error = JsonRpcError::new(
+                JsonRpcErrorReason::ServerError(SERVER_OVERLOADED),
+                "Method busy".to_owned(),
+                Value::default(),
+            );
+            let response = JsonRpcResponse::error(request.id, error);
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let semaphore = semaphore.clone();
+        let handler = handler.clone();
+        Box::pin(async move {
+            let _permit = match permit {
+                Some(permit) => permit,
+                None => {
+                    let permit = semaphore.acquire_owned().await;
+                    drop(queued);
+                    permit.expect("the semaphore is never closed")
+                }
+            };
+            handler(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+    use serde_json::json;
+
+    use super::{Bulkhead, SERVER_OVERLOADED};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    async fn slow(req: JsonRpcExtractor) -> JrpcResult {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "done"))
+    }
+
+    #[tokio::test]
+    async fn bulkheads() {
+        let rpc = JsonRpcRouter::new()
+            .method("slow", slow)
+            .method("cheap", slow)
+            .bulkhead("slow", Bulkhead::new(1).queue(1));
+        let call = |method: &str| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": method}).to_string();
+            rpc.dispatch_bytes(request.into())
+        };
+
+        let replies = join_all([call("slow"), call("slow"), call("slow"), call("cheap")]).await;
+        let replies: Vec<serde_json::Value> = replies
+            .into_iter()
+            .map(|reply| serde_json::from_slice(&reply.unwrap()).unwrap())
+            .collect();
+        assert_eq!(replies[0]["result"], "done");
+        // the second call waited in the queue, the third found it full
+        assert_eq!(replies[1]["result"], "done");
+        assert_eq!(replies[2]["error"]["code"], SERVER_OVERLOADED);
+        assert_eq!(replies[3]["result"], "done");
+    }
+}
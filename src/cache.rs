@@ -0,0 +1,450 @@
+//! Stores of cached answers, for the results of
+//! [`JsonRpcRouter::cached_method`](crate::JsonRpcRouter::cached_method) and the answers
+//! replayed by [`idempotency`](crate::idempotency).
+//!
+//! Both keep answers in a [`CacheStore`]. [`MemoryStore`] keeps them in the process, which
+//! suits single instances. With the `moka` feature `MokaStore` is a bounded concurrent
+//! in-memory cache, and with the `redis` feature `RedisStore` shares answers between the
+//! replicas of a deployment, so a call is answered the same whichever replica gets it.
+//! ```rust
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! use axum_jrpc::cache::MemoryStore;
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//!
+//! async fn block(req: JsonRpcExtractor) -> JrpcResult {
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), 1))
+//! }
+//!
+//! let store = Arc::new(MemoryStore::default());
+//! let ttl = Duration::from_secs(12);
+//! let rpc = JsonRpcRouter::new()
+//!     .cached_method_in("block", ttl, store.clone(), block)
+//!     .cached_method_in("blockByHash", ttl, store, block);
+//! ```
+//! Only results are cached by methods, errors always reach the handler again.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use web_time::SystemTime;
+
+use crate::router::{BoxFuture, BoxHandler};
+use crate::{to_vec, JrpcResult, JsonRpcAnswer, JsonRpcExtractor, JsonRpcResponse};
+
+/// Where cached answers are kept.
+#[async_trait::async_trait]
+pub trait CacheStore: Send + Sync + 'static {
+    /// The answer stored under `key`, unless it expired
+    async fn get(&self, key: &str) -> Option<JsonRpcAnswer>;
+
+    /// Stores `answer` under `key`, it may be forgotten at `expires`
+    async fn put(&self, key: &str, answer: JsonRpcAnswer, expires: SystemTime);
+
+    /// Whether an answer [`put`](Self::put) under `key` now would be kept, stores that never
+    /// drop new keys keep the default
+    async fn admits(&self, _key: &str) -> bool {
+        true
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: CacheStore + ?Sized> CacheStore for Arc<T> {
+    async fn get(&self, key: &str) -> Option<JsonRpcAnswer> {
+        (**self).get(key).await
+    }
+
+    async fn put(&self, key: &str, answer: JsonRpcAnswer, expires: SystemTime) {
+        (**self).put(key, answer, expires).await
+    }
+
+    async fn admits(&self, key: &str) -> bool {
+        (**self).admits(key).await
+    }
+}
+
+/// Keeps answers in memory until they expire.
+#[derive(Debug)]
+pub struct MemoryStore {
+    answers: Mutex<HashMap<String, (JsonRpcAnswer, SystemTime)>>,
+    capacity: usize,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::with_capacity(Self::CAPACITY)
+    }
+}
+
+impl MemoryStore {
+    /// Answers kept by default, and per method by
+    /// [`JsonRpcRouter::cached_method`](crate::JsonRpcRouter::cached_method)
+    pub const CAPACITY: usize = 1024;
+
+    /// Keeps at most `capacity` answers, new keys are dropped while none has expired
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            answers: Mutex::default(),
+            capacity,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheStore for MemoryStore {
+    async fn get(&self, key: &str) -> Option<JsonRpcAnswer> {
+        let answers = self.answers.lock().unwrap_or_else(|e| e.into_inner());
+        let (answer, expires) = answers.get(key)?;
+        (*expires > SystemTime::now()).then(|| answer.clone())
+    }
+
+    async fn put(&self, key: &str, answer: JsonRpcAnswer, expires: SystemTime) {
+        let mut answers = self.answers.lock().unwrap_or_else(|e| e.into_inner());
+        let len = answers.len();
+        if len >= self.capacity || (len >= 1024 && len.is_power_of_two()) {
+            let now = SystemTime::now();
+            answers.retain(|_, (_, expires)| *expires > now);
+        }
+        if answers.len() < self.capacity || answers.contains_key(key) {
+            answers.insert(key.to_owned(), (answer, expires));
+        }
+    }
+
+    async fn admits(&self, key: &str) -> bool {
+        let mut answers = self.answers.lock().unwrap_or_else(|e| e.into_inner());
+        if answers.len() >= self.capacity && !answers.contains_key(key) {
+            let now = SystemTime::now();
+            answers.retain(|_, (_, expires)| *expires > now);
+        }
+        answers.len() < self.capacity || answers.contains_key(key)
+    }
+}
+
+#[cfg(feature = "moka")]
+pub use moka_store::MokaStore;
+
+#[cfg(feature = "moka")]
+mod moka_store {
+    use std::time::{Duration, Instant};
+
+    use moka::future::Cache;
+    use moka::Expiry;
+    use web_time::SystemTime;
+
+    use super::CacheStore;
+    use crate::JsonRpcAnswer;
+
+    type Entry = (JsonRpcAnswer, SystemTime);
+
+    /// Keeps answers in a [moka](https://docs.rs/moka) cache, which evicts the least used
+    /// ones once it is full.
+    #[derive(Clone)]
+    pub struct MokaStore {
+        cache: Cache<String, Entry>,
+    }
+
+    impl std::fmt::Debug for MokaStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("MokaStore")
+                .field("entries", &self.cache.entry_count())
+                .finish()
+        }
+    }
+
+    impl MokaStore {
+        /// Keeps at most `capacity` answers
+        pub fn new(capacity: u64) -> Self {
+            let cache = Cache::builder()
+                .max_capacity(capacity)
+                .expire_after(UntilExpires)
+                .build();
+            Self { cache }
+        }
+    }
+
+    /// Expires entries at the time they were put with
+    struct UntilExpires;
+
+    impl Expiry<String, Entry> for UntilExpires {
+        fn expire_after_create(&self, _: &String, entry: &Entry, _: Instant) -> Option<Duration> {
+            let (_, expires) = entry;
+            Some(
+                expires
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default(),
+            )
+        }
+
+        fn expire_after_update(
+            &self,
+            key: &String,
+            entry: &Entry,
+            created_at: Instant,
+            _: Option<Duration>,
+        ) -> Option<Duration> {
+            self.expire_after_create(key, entry, created_at)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CacheStore for MokaStore {
+        async fn get(&self, key: &str) -> Option<JsonRpcAnswer> {
+            let (answer, _) = self.cache.get(key).await?;
+            Some(answer)
+        }
+
+        async fn put(&self, key: &str, answer: JsonRpcAnswer, expires: SystemTime) {
+            self.cache.insert(key.to_owned(), (answer, expires)).await;
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_store::RedisStore;
+
+#[cfg(feature = "redis")]
+mod redis_store {
+    use redis::aio::MultiplexedConnection;
+    use redis::AsyncCommands;
+    use web_time::SystemTime;
+
+    use super::CacheStore;
+    use crate::{from_slice, to_vec, JsonRpcAnswer};
+
+    /// Keeps answers in Redis as JSON, expiring with them, so replicas sharing the server
+    /// share the answers.
+    ///
+    /// Redis errors are treated as misses: the call reaches the handler, and its answer isn't
+    /// cached.
+    /// ```rust,no_run
+    /// use axum_jrpc::cache::RedisStore;
+    /// use axum_jrpc::idempotency::IdempotencyLayer;
+    /// use axum_jrpc::JsonRpcRouter;
+    /// use tower::Layer;
+    ///
+    /// # async fn run() -> redis::RedisResult<()> {
+    /// let client = redis::Client::open("redis://127.0.0.1/")?;
+    /// let store = RedisStore::connect(&client).await?.prefix("payments:");
+    /// let rpc = IdempotencyLayer::new(store)
+    ///     .method("transfer")
+    ///     .layer(JsonRpcRouter::new());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Clone)]
+    pub struct RedisStore {
+        connection: MultiplexedConnection,
+        prefix: String,
+    }
+
+    impl std::fmt::Debug for RedisStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RedisStore")
+                .field("prefix", &self.prefix)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl RedisStore {
+        /// Prefix of the keys unless [`prefix`](Self::prefix) sets another one
+        pub const PREFIX: &'static str = "axum-jrpc:";
+
+        pub fn new(connection: MultiplexedConnection) -> Self {
+            Self {
+                connection,
+                prefix: Self::PREFIX.to_owned(),
+            }
+        }
+
+        /// Opens a connection to `client`
+        pub async fn connect(client: &redis::Client) -> redis::RedisResult<Self> {
+            Ok(Self::new(client.get_multiplexed_async_connection().await?))
+        }
+
+        /// Prefixes keys with `prefix`, e.g. to keep apart services sharing a server
+        pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+            self.prefix = prefix.into();
+            self
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CacheStore for RedisStore {
+        async fn get(&self, key: &str) -> Option<JsonRpcAnswer> {
+            let key = format!("{}{key}", self.prefix);
+            let bytes: Option<Vec<u8>> = match self.connection.clone().get(&key).await {
+                Ok(bytes) => bytes,
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(key, error = %_e, "failed to read a cached answer");
+                    return None;
+                }
+            };
+            from_slice(&mut bytes?).ok()
+        }
+
+        async fn put(&self, key: &str, answer: JsonRpcAnswer, expires: SystemTime) {
+            let Ok(ttl) = expires.duration_since(SystemTime::now()) else {
+                return;
+            };
+            let Ok(value) = to_vec(&answer) else {
+                return;
+            };
+            let key = format!("{}{key}", self.prefix);
+            let millis = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX).max(1);
+            let mut connection = self.connection.clone();
+            let stored = connection.pset_ex::<_, _, ()>(&key, value, millis);
+            if let Err(_e) = stored.await {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(key, error = %_e, "failed to cache an answer");
+            }
+        }
+    }
+}
+
+/// Wraps `handler` of `method` so its results are reused for `ttl` by requests with the same
+/// params, as long as `store` keeps them
+pub(crate) fn cached(
+    method: &str,
+    ttl: Duration,
+    store: Option<Arc<dyn CacheStore>>,
+    handler: BoxHandler,
+) -> impl Fn(JsonRpcExtractor) -> BoxFuture<JrpcResult> + Send + Sync + 'static {
+    let store = store.unwrap_or_else(|| Arc::new(MemoryStore::default()));
+    let prefix = format!("{method}:");
+    move |request: JsonRpcExtractor| {
+        let key = to_vec(&request.parsed)
+            .ok()
+            .and_then(|params| String::from_utf8(params).ok())
+            .map(|params| prefix.clone() + &params);
+        let store = store.clone();
+        let handler = handler.clone();
+        Box::pin(async move {
+            let Some(key) = key else {
+                return handler(request).await;
+            };
+            if let Some(result) = store.get(&key).await {
+                return Ok(JsonRpcResponse {
+                    id: request.id,
+                    result,
+                });
+            }
+            let response = handler(request).await;
+            if let Ok(JsonRpcResponse {
+                result: result @ JsonRpcAnswer::Result(_),
+                ..
+            }) = &response
+            {
+                store
+                    .put(&key, result.clone(), SystemTime::now() + ttl)
+                    .await;
+            }
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::{CacheStore, MemoryStore};
+    use crate::router::JsonRpcService;
+    use crate::{JsonRpcAnswer, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    #[tokio::test]
+    async fn cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let rpc = JsonRpcRouter::new().cached_method(
+            "block",
+            Duration::from_millis(200),
+            move |req: JsonRpcExtractor| {
+                let calls = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                async move { Ok(JsonRpcResponse::success(req.get_answer_id(), calls)) }
+            },
+        );
+        let call = |id: i64, params| {
+            let rpc = rpc.clone();
+            async move {
+                let request = JsonRpcRequest {
+                    id: id.into(),
+                    method: "block".to_owned(),
+                    params,
+                };
+                let response = rpc.dispatch(request.into()).await;
+                assert_eq!(response.id, id.into());
+                match response.result {
+                    JsonRpcAnswer::Result(value) => value,
+                    JsonRpcAnswer::Error(error) => panic!("{error}"),
+                }
+            }
+        };
+
+        assert_eq!(call(1, json!([1])).await, 1);
+        assert_eq!(call(2, json!([1])).await, 1);
+        assert_eq!(call(3, json!([2])).await, 2);
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(call(4, json!([1])).await, 3);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn bounded_by_default() {
+        let store = MemoryStore::default();
+        let answer = JsonRpcAnswer::Result(json!(1));
+        let expires = web_time::SystemTime::now() + Duration::from_secs(60);
+        for key in 0..=MemoryStore::CAPACITY {
+            store.put(&key.to_string(), answer.clone(), expires).await;
+        }
+        assert_eq!(store.get("0").await, Some(answer.clone()));
+        assert_eq!(store.get(&MemoryStore::CAPACITY.to_string()).await, None);
+        // stored keys are still updated
+        let other = JsonRpcAnswer::Result(json!(2));
+        store.put("0", other.clone(), expires).await;
+        assert_eq!(store.get("0").await, Some(other));
+    }
+
+    #[tokio::test]
+    async fn shared_store() {
+        let stores: Vec<Arc<dyn CacheStore>> = vec![
+            Arc::new(MemoryStore::default()),
+            #[cfg(feature = "moka")]
+            Arc::new(super::MokaStore::new(16)),
+        ];
+        for store in stores {
+            let calls = Arc::new(AtomicUsize::new(0));
+            // two replicas, each with its own handler
+            let replica = || {
+                let calls = calls.clone();
+                let handler = move |req: JsonRpcExtractor| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    async move { Ok(JsonRpcResponse::success(req.get_answer_id(), "block")) }
+                };
+                let ttl = Duration::from_secs(60);
+                JsonRpcRouter::new().cached_method_in("block", ttl, store.clone(), handler)
+            };
+            let (first, second) = (replica(), replica());
+            let request = || {
+                let request = JsonRpcRequest::builder("block")
+                    .params([7])
+                    .unwrap()
+                    .id(1)
+                    .build();
+                request.into()
+            };
+
+            let expected = JsonRpcResponse::success(1, "block");
+            assert_eq!(first.dispatch(request()).await, expected);
+            assert_eq!(second.dispatch(request()).await, expected);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use futures_util::lock::Mutex;
+use reqwest::RequestBuilder;
+
+use super::{ClientError, MaybeSend};
+
+/// Supplies tokens for [`Auth::Provider`].
+///
+/// [`TokenProvider::invalidate`] is called with the token the server rejected with
+/// `401 Unauthorized`, the call is then retried once with a fresh token.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait TokenProvider: Send + Sync {
+    /// Returns the bearer token to use for the next call
+    async fn token(&self) -> Result<String, ClientError>;
+
+    /// Marks `rejected` as expired, unless the current token was already replaced
+    fn invalidate(&self, _rejected: &str) {}
+}
+
+/// Authentication applied to every outgoing request.
+#[derive(Clone)]
+pub enum Auth {
+    /// `Authorization: Bearer <token>`
+    Bearer(String),
+    /// `Authorization: Basic <credentials>`
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    /// Bearer token obtained from a [`TokenProvider`]
+    Provider(Arc<dyn TokenProvider>),
+}
+
+impl Auth {
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self::Bearer(token.into())
+    }
+
+    pub fn basic(username: impl Into<String>, password: Option<impl Into<String>>) -> Self {
+        Self::Basic {
+            username: username.into(),
+            password: password.map(Into::into),
+        }
+    }
+
+    pub fn provider(provider: impl TokenProvider + 'static) -> Self {
+        Self::Provider(Arc::new(provider))
+    }
+
+    /// Bearer token fetched by `refresh` on first use and every time the server rejects it.
+    /// Concurrent calls wait for a single refresh
+    pub fn refreshing<F, Fut>(refresh: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, ClientError>> + MaybeSend + 'static,
+    {
+        Self::provider(RefreshingToken {
+            refresh,
+            token: RwLock::new(None),
+            refreshing: Mutex::new(()),
+        })
+    }
+
+    /// Adds the credentials to `builder`, along with the token of a provider to invalidate
+    /// if the server rejects it
+    pub(super) async fn apply(
+        &self,
+        builder: RequestBuilder,
+    ) -> Result<(RequestBuilder, Option<String>), ClientError> {
+        Ok(match self {
+            Auth::Bearer(token) => (builder.bearer_auth(token), None),
+            Auth::Basic { username, password } => {
+                (builder.basic_auth(username, password.as_ref()), None)
+            }
+            Auth::Provider(provider) => {
+                let token = provider.token().await?;
+                (builder.bearer_auth(&token), Some(token))
+            }
+        })
+    }
+
+    pub(super) fn invalidate(&self, rejected: &str) {
+        if let Auth::Provider(provider) = self {
+            provider.invalidate(rejected);
+        }
+    }
+}
+
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::Bearer(_) => f.write_str("Bearer(..)"),
+            Auth::Basic { username, .. } => {
+                f.debug_struct("Basic").field("username", username).finish()
+            }
+            Auth::Provider(_) => f.write_str("Provider(..)"),
+        }
+    }
+}
+
+struct RefreshingToken<F> {
+    refresh: F,
+    token: RwLock<Option<String>>,
+    // held while refreshing, so callers arriving meanwhile get the same token
+    refreshing: Mutex<()>,
+}
+
+impl<F> RefreshingToken<F> {
+    fn current(&self) -> Option<String> {
+        self.token.read().unwrap().clone()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl<F, Fut> TokenProvider for RefreshingToken<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, ClientError>> + MaybeSend,
+{
+    async fn token(&self) -> Result<String, ClientError> {
+        if let Some(token) = self.current() {
+            return Ok(token);
+        }
+
+        let _refreshing = self.refreshing.lock().await;
+        // refreshed by another call while this one waited
+        if let Some(token) = self.current() {
+            return Ok(token);
+        }
+        let token = (self.refresh)().await?;
+        *self.token.write().unwrap() = Some(token.clone());
+        Ok(token)
+    }
+
+    fn invalidate(&self, rejected: &str) {
+        let mut token = self.token.write().unwrap();
+        if token.as_deref() == Some(rejected) {
+            *token = None;
+        }
+    }
+}
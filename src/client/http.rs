@@ -0,0 +1,478 @@
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+
+use super::{Auth, CallOptions, ClientError, JsonRpcClient};
+use crate::{Id, JsonRpcRequest, JsonRpcResponse};
+
+/// JSON-RPC client over HTTP.
+#[derive(Debug)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    url: String,
+    timeout: Option<Duration>,
+    headers: HeaderMap,
+    auth: Option<Auth>,
+    next_id: AtomicI64,
+}
+
+impl HttpClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), url)
+    }
+
+    /// Uses a preconfigured [`reqwest::Client`]
+    pub fn with_client(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            timeout: None,
+            headers: HeaderMap::new(),
+            auth: None,
+            next_id: AtomicI64::new(0),
+        }
+    }
+
+    /// Configures the connection pool and keep-alive of the underlying [`reqwest::Client`]
+    /// ```rust
+    /// use std::time::Duration;
+    /// use axum_jrpc::client::HttpClient;
+    ///
+    /// let client = HttpClient::builder("http://127.0.0.1:8080")
+    ///     .pool_max_idle_per_host(64)
+    ///     .pool_idle_timeout(Some(Duration::from_secs(90)))
+    ///     .tcp_keepalive(Some(Duration::from_secs(30)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn builder(url: impl Into<String>) -> HttpClientBuilder {
+        HttpClientBuilder {
+            builder: reqwest::Client::builder(),
+            url: url.into(),
+        }
+    }
+
+    /// Connects through the Unix domain socket at `path` instead of TCP.
+    /// `url` still provides the request path and the `Host` header, e.g. `http://localhost/rpc`
+    #[cfg(unix)]
+    pub fn unix(path: impl AsRef<Path>, url: impl Into<String>) -> Result<Self, ClientError> {
+        Self::builder(url).unix_socket(path).build()
+    }
+
+    /// Authenticates every call with `auth`
+    pub fn with_auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Shorthand for `with_auth(Auth::bearer(token))`
+    pub fn with_bearer_token(self, token: impl Into<String>) -> Self {
+        self.with_auth(Auth::bearer(token))
+    }
+
+    /// Shorthand for `with_auth(Auth::basic(username, password))`
+    pub fn with_basic_auth(
+        self,
+        username: impl Into<String>,
+        password: Option<impl Into<String>>,
+    ) -> Self {
+        self.with_auth(Auth::basic(username, password))
+    }
+
+    /// Adds a header sent with every call
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Sets the client-wide timeout, applied to every call without its own timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Builder for [`HttpClient`], see [`HttpClient::builder`].
+///
+/// Connections are pooled per host, so a client talking to several endpoints keeps a separate
+/// pool for each of them.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct HttpClientBuilder {
+    builder: reqwest::ClientBuilder,
+    url: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpClientBuilder {
+    /// Maximum number of idle connections kept per host
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.builder = self.builder.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// How long idle connections are kept, `None` keeps them forever
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.builder = self.builder.pool_idle_timeout(timeout);
+        self
+    }
+
+    /// Interval of TCP keep-alive probes, `None` disables them
+    pub fn tcp_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.builder = self.builder.tcp_keepalive(interval);
+        self
+    }
+
+    /// Timeout for establishing new connections
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.connect_timeout(timeout);
+        self
+    }
+
+    /// Talks HTTP/2 without negotiation, multiplexing all calls over a single connection
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.builder = self.builder.http2_prior_knowledge();
+        self
+    }
+
+    /// Interval of HTTP/2 PING frames keeping the connection alive, `None` disables them
+    pub fn http2_keep_alive_interval(mut self, interval: Option<Duration>) -> Self {
+        self.builder = self
+            .builder
+            .http2_keep_alive_interval(interval)
+            .http2_keep_alive_while_idle(interval.is_some());
+        self
+    }
+
+    /// Connects through the Unix domain socket at `path` instead of TCP
+    #[cfg(unix)]
+    pub fn unix_socket(mut self, path: impl AsRef<Path>) -> Self {
+        self.builder = self.builder.unix_socket(path.as_ref());
+        self
+    }
+
+    pub fn build(self) -> Result<HttpClient, ClientError> {
+        Ok(HttpClient::with_client(self.builder.build()?, self.url))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl JsonRpcClient for HttpClient {
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    fn next_id(&self) -> Id {
+        Id::Num(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    async fn send(
+        &self,
+        request: JsonRpcRequest,
+        options: CallOptions,
+    ) -> Result<JsonRpcResponse, ClientError> {
+        let mut headers = self.headers.clone();
+        headers.extend(options.headers);
+        #[cfg(feature = "opentelemetry")]
+        crate::otel::inject_context(&mut headers);
+
+        let timeout = options.timeout.or(self.timeout);
+        let map_err = |e: reqwest::Error| match timeout {
+            Some(timeout) if e.is_timeout() => ClientError::Timeout(timeout),
+            _ => ClientError::from(e),
+        };
+
+        let mut retried = false;
+        loop {
+            let mut builder = self
+                .client
+                .post(&self.url)
+                .headers(headers.clone())
+                .json(&request);
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            let mut token = None;
+            if let Some(auth) = &self.auth {
+                (builder, token) = auth.apply(builder).await?;
+            }
+
+            let response = builder.send().await.map_err(map_err)?;
+            if response.status() == StatusCode::UNAUTHORIZED && !retried {
+                if let (Some(auth), Some(token)) = (&self.auth, token) {
+                    auth.invalidate(&token);
+                    retried = true;
+                    continue;
+                }
+            }
+
+            return response.json().await.map_err(map_err);
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "serde_json", feature = "server"))]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use axum::Router;
+
+    use super::HttpClient;
+    use crate::client::{Auth, CallOptions, ClientError, JsonRpcClient};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse};
+
+    async fn handler(headers: HeaderMap, value: JsonRpcExtractor) -> JrpcResult {
+        let answer_id = value.get_answer_id();
+        match value.method() {
+            "sleep" => {
+                let millis: u64 = value.parse_params()?;
+                tokio::time::sleep(Duration::from_millis(millis)).await;
+                Ok(JsonRpcResponse::success(answer_id, millis))
+            }
+            "header" => {
+                let name: String = value.parse_params()?;
+                let header = headers
+                    .get(name.as_str())
+                    .and_then(|v| v.to_str().ok())
+                    .map(ToOwned::to_owned);
+                Ok(JsonRpcResponse::success(answer_id, header))
+            }
+            method => Ok(value.method_not_found(method)),
+        }
+    }
+
+    async fn auth_handler(headers: HeaderMap, value: JsonRpcExtractor) -> impl IntoResponse {
+        match headers.get(header::AUTHORIZATION) {
+            Some(token) if token == "Bearer fresh" => {
+                Ok(JsonRpcResponse::success(value.get_answer_id(), true))
+            }
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+
+    async fn serve() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                Router::new()
+                    .route("/", post(handler))
+                    .route("/auth", post(auth_handler)),
+            )
+            .await
+            .unwrap();
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn per_call_options() {
+        let client = HttpClient::new(serve().await).with_timeout(Duration::from_millis(50));
+
+        let res: Result<u64, _> = client.call("sleep", 200).await;
+        assert!(matches!(res, Err(ClientError::Timeout(t)) if t == Duration::from_millis(50)));
+
+        let options = CallOptions {
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let res: u64 = client.call_with("sleep", 200, options).await.unwrap();
+        assert_eq!(res, 200);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-custom", HeaderValue::from_static("value"));
+        let options = CallOptions {
+            headers,
+            ..Default::default()
+        };
+        let res: Option<String> = client
+            .call_with("header", "x-custom", options)
+            .await
+            .unwrap();
+        assert_eq!(res.as_deref(), Some("value"));
+
+        let res: Result<(), _> = client.call("missing", ()).await;
+        assert!(matches!(res, Err(ClientError::Rpc(_))));
+    }
+
+    #[tokio::test]
+    async fn refreshing_token() {
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let auth = Auth::refreshing({
+            let refreshes = refreshes.clone();
+            move || {
+                let token = match refreshes.fetch_add(1, Ordering::Relaxed) {
+                    0 => "stale",
+                    _ => "fresh",
+                };
+                async move { Ok(token.to_owned()) }
+            }
+        });
+        let client = HttpClient::new(format!("{}/auth", serve().await)).with_auth(auth);
+
+        let res: bool = client.call("any", ()).await.unwrap();
+        assert!(res);
+        let res: bool = client.call("any", ()).await.unwrap();
+        assert!(res);
+        assert_eq!(refreshes.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_calls_refresh_once() {
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let auth = Auth::refreshing({
+            let refreshes = refreshes.clone();
+            move || {
+                let token = match refreshes.fetch_add(1, Ordering::Relaxed) {
+                    0 => "stale",
+                    _ => "fresh",
+                };
+                async move {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok(token.to_owned())
+                }
+            }
+        });
+        let client = HttpClient::new(format!("{}/auth", serve().await)).with_auth(auth);
+
+        // all of them are rejected with the stale token, one refresh serves their retries
+        let calls = (0..8).map(|_| client.call::<_, bool>("any", ()));
+        for res in futures_util::future::join_all(calls).await {
+            assert!(res.unwrap());
+        }
+        assert_eq!(refreshes.load(Ordering::Relaxed), 2);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn call_spans() {
+        use std::sync::Mutex;
+
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        #[derive(Clone, Default)]
+        struct Logs(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Logs {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let client = HttpClient::new(serve().await);
+        let res: u64 = client.call("sleep", 0).await.unwrap();
+        assert_eq!(res, 0);
+        let res: Result<(), _> = client.call("missing", ()).await;
+        assert!(res.is_err());
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let closed: Vec<_> = logs
+            .lines()
+            .filter(|line| line.contains("jrpc_call{") && line.contains(" close "))
+            .collect();
+        assert_eq!(closed.len(), 2, "{logs}");
+        assert!(closed[0].contains("method=sleep"), "{logs}");
+        assert!(!closed[0].contains("code="), "{logs}");
+        assert!(closed[1].contains("method=missing"), "{logs}");
+        assert!(closed[1].contains("code=-32601"), "{logs}");
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    #[tokio::test]
+    async fn propagates_trace_context() {
+        use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+        use opentelemetry::trace::{
+            SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+        };
+        use opentelemetry::Context;
+        use tracing::Instrument;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        /// Writes the `traceparent` header of the W3C trace context
+        #[derive(Debug)]
+        struct TraceParent;
+
+        impl TextMapPropagator for TraceParent {
+            fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+                let span = cx.span();
+                let context = span.span_context();
+                if context.is_valid() {
+                    let flags = context.trace_flags().to_u8();
+                    let value = format!(
+                        "00-{}-{}-{flags:02x}",
+                        context.trace_id(),
+                        context.span_id()
+                    );
+                    injector.set("traceparent", value);
+                }
+            }
+
+            fn extract_with_context(&self, cx: &Context, _: &dyn Extractor) -> Context {
+                cx.clone()
+            }
+
+            fn fields(&self) -> opentelemetry::propagation::text_map_propagator::FieldIter<'_> {
+                opentelemetry::propagation::text_map_propagator::FieldIter::new(&[])
+            }
+        }
+
+        opentelemetry::global::set_text_map_propagator(TraceParent);
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+        let span_id = SpanId::from_hex("00f067aa0ba902b7").unwrap();
+        let remote = SpanContext::new(
+            trace_id,
+            span_id,
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let root = tracing::info_span!("root");
+        root.set_parent(Context::new().with_remote_span_context(remote))
+            .unwrap();
+
+        let client = HttpClient::new(serve().await);
+        let header: Option<String> = client
+            .call("header", "traceparent")
+            .instrument(root)
+            .await
+            .unwrap();
+        let header = header.unwrap();
+        assert!(header.contains(&trace_id.to_string()), "{header}");
+    }
+}
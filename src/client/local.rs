@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{CallOptions, ClientError, JsonRpcClient};
+use crate::router::{JsonRpcService, RequestHeaders};
+use crate::{Id, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse};
+
+/// JSON-RPC client calling a [`JsonRpcService`] living in the same process, e.g. a
+/// [`JsonRpcRouter`](crate::JsonRpcRouter) wrapped in tower middleware.
+///
+/// Requests are handed to the service as they are, without being serialized. The headers of
+/// [`CallOptions`] reach handlers as the [`RequestHeaders`] extension.
+/// ```rust
+/// use axum_jrpc::client::{JsonRpcClient, LocalClient};
+/// use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+///
+/// async fn add(req: JsonRpcExtractor) -> JrpcResult {
+///     let id = req.get_answer_id();
+///     let params: [i32; 2] = req.parse_params()?;
+///     Ok(JsonRpcResponse::success(id, params[0] + params[1]))
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let client = LocalClient::new(JsonRpcRouter::new().method("add", add));
+/// let sum: i32 = client.call("add", [1, 2]).await.unwrap();
+/// assert_eq!(sum, 3);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LocalClient<S> {
+    service: S,
+    timeout: Option<Duration>,
+    next_id: AtomicI64,
+}
+
+impl<S> LocalClient<S> {
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            timeout: None,
+            next_id: AtomicI64::new(0),
+        }
+    }
+
+    /// Sets the client-wide timeout, applied to every call without its own timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: JsonRpcService> JsonRpcClient for LocalClient<S> {
+    fn endpoint(&self) -> &str {
+        "local"
+    }
+
+    fn next_id(&self) -> Id {
+        Id::Num(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    async fn send(
+        &self,
+        request: JsonRpcRequest,
+        options: CallOptions,
+    ) -> Result<JsonRpcResponse, ClientError> {
+        let mut request = JsonRpcExtractor::from(request);
+        request
+            .extensions
+            .insert(RequestHeaders(Arc::new(options.headers)));
+
+        let call = self.service.dispatch(request);
+        match options.timeout.or(self.timeout) {
+            Some(timeout) => tokio::time::timeout(timeout, call)
+                .await
+                .map_err(|_| ClientError::Timeout(timeout)),
+            None => Ok(call.await),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::Duration;
+
+    use axum::http::{HeaderMap, HeaderValue};
+
+    use super::LocalClient;
+    use crate::client::{CallOptions, ClientError, JsonRpcClient};
+    use crate::router::RequestHeaders;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn sleep(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let millis: u64 = req.parse_params()?;
+        tokio::time::sleep(Duration::from_millis(millis)).await;
+        Ok(JsonRpcResponse::success(id, millis))
+    }
+
+    async fn header(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let name: String = req.parse_params_ref()?;
+        let value = req
+            .extension::<RequestHeaders>()
+            .and_then(|RequestHeaders(headers)| headers.get(name.as_str()))
+            .and_then(|v| v.to_str().ok())
+            .map(ToOwned::to_owned);
+        Ok(JsonRpcResponse::success(id, value))
+    }
+
+    fn client() -> LocalClient<JsonRpcRouter> {
+        LocalClient::new(
+            JsonRpcRouter::new()
+                .method("sleep", sleep)
+                .method("header", header),
+        )
+    }
+
+    #[tokio::test]
+    async fn calls() {
+        let client = client();
+        let res: u64 = client.call("sleep", 0).await.unwrap();
+        assert_eq!(res, 0);
+
+        let res: Result<(), _> = client.call("missing", ()).await;
+        assert!(matches!(res, Err(ClientError::Rpc(e)) if e.code() == -32601));
+    }
+
+    #[tokio::test]
+    async fn passes_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-custom", HeaderValue::from_static("value"));
+        let options = CallOptions {
+            headers,
+            ..Default::default()
+        };
+        let res: Option<String> = client()
+            .call_with("header", "x-custom", options)
+            .await
+            .unwrap();
+        assert_eq!(res.as_deref(), Some("value"));
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let client = client().with_timeout(Duration::from_millis(50));
+        let res: Result<u64, _> = client.call("sleep", 200).await;
+        assert!(matches!(res, Err(ClientError::Timeout(t)) if t == Duration::from_millis(50)));
+
+        let options = CallOptions {
+            timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let res: u64 = client.call_with("sleep", 200, options).await.unwrap();
+        assert_eq!(res, 200);
+    }
+}
@@ -0,0 +1,224 @@
+//! JSON-RPC client for calling remote services.
+//!
+//! Enabled with the `client` feature. The client builds for `wasm32-unknown-unknown` too,
+//! where requests go through the browser `fetch` API. Disable the default `server` feature
+//! to drop the axum dependency:
+//! ```toml
+//! axum-jrpc = { version = "*", default-features = false, features = ["serde_json", "client"] }
+//! ```
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use axum_jrpc::client::{CallOptions, HttpClient, JsonRpcClient};
+//!
+//! # async fn run() -> Result<(), axum_jrpc::client::ClientError> {
+//! let client = HttpClient::new("http://127.0.0.1:8080").with_timeout(Duration::from_secs(10));
+//! let sum: i32 = client.call("add", [1, 2]).await?;
+//!
+//! let options = CallOptions {
+//!     timeout: Some(Duration::from_secs(1)),
+//!     ..Default::default()
+//! };
+//! let diff: i32 = client.call_with("sub", [3, 2], options).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every transport implements [`JsonRpcClient`], so calling code doesn't depend on how
+//! requests are delivered. With the `ws-client` feature [`WsClient`] multiplexes calls over
+//! a single WebSocket connection and exposes server notifications as a stream. Local daemons
+//! listening on a Unix domain socket are reachable with [`HttpClient::unix`]. With the
+//! `local-client` feature [`LocalClient`] calls a service in the same process directly.
+//!
+//! Authentication is configured once per client with [`HttpClient::with_auth`], see [`Auth`].
+//!
+//! With the `tracing` feature every call runs inside a `jrpc_call` span carrying the method,
+//! id, endpoint, duration and error code. The `opentelemetry` feature additionally injects
+//! the current trace context into the request headers using the globally configured
+//! propagator, so server spans can be linked to the caller.
+
+use std::time::Duration;
+
+use cfg_if::cfg_if;
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{from_value, to_value, Id, JsonRpcAnswer, JsonRpcRequest, JsonRpcResponse, Value};
+
+pub use auth::{Auth, TokenProvider};
+pub use http::HttpClient;
+#[cfg(not(target_arch = "wasm32"))]
+pub use http::HttpClientBuilder;
+#[cfg(feature = "local-client")]
+pub use local::LocalClient;
+#[cfg(feature = "ws-client")]
+pub use ws::{Notifications, WsClient};
+
+mod auth;
+mod http;
+#[cfg(feature = "local-client")]
+mod local;
+#[cfg(feature = "ws-client")]
+mod ws;
+
+cfg_if! {
+    if #[cfg(target_arch = "wasm32")] {
+        /// `Send` everywhere except wasm, where futures are bound to the browser event loop
+        pub trait MaybeSend {}
+        impl<T> MaybeSend for T {}
+    } else {
+        /// `Send` everywhere except wasm, where futures are bound to the browser event loop
+        pub trait MaybeSend: Send {}
+        impl<T: Send> MaybeSend for T {}
+    }
+}
+
+/// Errors returned by the client.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The call did not complete within the configured timeout.
+    #[error("Request timed out after {0:?}")]
+    Timeout(Duration),
+    /// The request could not be delivered or the response could not be read.
+    #[error("Transport error: {0}")]
+    Transport(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The connection was closed before the response arrived.
+    #[error("Connection closed")]
+    ConnectionClosed,
+    /// Params or result could not be (de)serialized.
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    /// The server answered with a different id than the one sent.
+    #[error("Response id {0:?} doesn't match request id")]
+    IdMismatch(Id),
+    /// The server answered with a JSON-RPC error object.
+    #[error(transparent)]
+    Rpc(#[from] JsonRpcError),
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Transport(Box::new(error))
+    }
+}
+
+impl From<ClientError> for JsonRpcError {
+    fn from(error: ClientError) -> Self {
+        match error {
+            ClientError::Rpc(error) => error,
+            error => JsonRpcError::new(
+                JsonRpcErrorReason::InternalError,
+                error.to_string(),
+                Value::default(),
+            ),
+        }
+    }
+}
+
+/// Per-call overrides for [`JsonRpcClient::call_with`].
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    /// Overrides the client-wide timeout for this call.
+    pub timeout: Option<Duration>,
+    /// Extra headers sent with this call only, ignored by transports without headers.
+    pub headers: HeaderMap,
+}
+
+/// Common interface of all client transports.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait JsonRpcClient: Send + Sync {
+    /// Human readable address of the remote side
+    fn endpoint(&self) -> &str;
+
+    /// Returns a fresh id for the next call
+    fn next_id(&self) -> Id;
+
+    /// Delivers `request` and waits for the matching response
+    async fn send(
+        &self,
+        request: JsonRpcRequest,
+        options: CallOptions,
+    ) -> Result<JsonRpcResponse, ClientError>;
+
+    /// Calls `method` with `params`, using the client-wide settings
+    async fn call<P, R>(&self, method: &str, params: P) -> Result<R, ClientError>
+    where
+        P: Serialize + MaybeSend,
+        R: DeserializeOwned,
+    {
+        self.call_with(method, params, CallOptions::default()).await
+    }
+
+    /// Calls `method` with `params`, overriding the client-wide settings with `options`
+    async fn call_with<P, R>(
+        &self,
+        method: &str,
+        params: P,
+        options: CallOptions,
+    ) -> Result<R, ClientError>
+    where
+        P: Serialize + MaybeSend,
+        R: DeserializeOwned,
+    {
+        let request = JsonRpcRequest {
+            id: self.next_id(),
+            method: method.to_owned(),
+            params: to_value(params).map_err(ClientError::Serialization)?,
+        };
+
+        cfg_if! {
+            if #[cfg(feature = "tracing")] {
+                use tracing::Instrument;
+
+                let span = tracing::debug_span!(
+                    "jrpc_call",
+                    method = %request.method,
+                    id = ?request.id,
+                    endpoint = %self.endpoint(),
+                    code = tracing::field::Empty,
+                    duration_ms = tracing::field::Empty,
+                );
+                let started = web_time::Instant::now();
+
+                let result = execute(self, request, options).instrument(span.clone()).await;
+
+                span.record("duration_ms", started.elapsed().as_millis() as u64);
+                match &result {
+                    Ok(_) => tracing::debug!(parent: &span, "call succeeded"),
+                    Err(ClientError::Rpc(e)) => {
+                        span.record("code", e.code());
+                        tracing::debug!(parent: &span, error = %e, "call failed");
+                    }
+                    Err(e) => tracing::debug!(parent: &span, error = %e, "call failed"),
+                }
+                result
+            } else {
+                execute(self, request, options).await
+            }
+        }
+    }
+}
+
+async fn execute<C, R>(
+    client: &C,
+    request: JsonRpcRequest,
+    options: CallOptions,
+) -> Result<R, ClientError>
+where
+    C: JsonRpcClient + ?Sized,
+    R: DeserializeOwned,
+{
+    let id = request.id.clone();
+    let response = client.send(request, options).await?;
+    if response.id != id {
+        return Err(ClientError::IdMismatch(response.id));
+    }
+
+    match response.result {
+        JsonRpcAnswer::Result(value) => from_value(value).map_err(ClientError::Serialization),
+        JsonRpcAnswer::Error(error) => Err(ClientError::Rpc(error)),
+    }
+}
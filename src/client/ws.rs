@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::{SinkExt, Stream, StreamExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{CallOptions, ClientError, JsonRpcClient};
+use crate::{from_slice, to_vec, Id, JsonRpcMessage, JsonRpcRequest, JsonRpcResponse};
+
+type Pending = Arc<Mutex<HashMap<Id, oneshot::Sender<JsonRpcResponse>>>>;
+
+impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::Transport(Box::new(error))
+    }
+}
+
+/// JSON-RPC client multiplexing calls over a single WebSocket connection.
+///
+/// The connection is driven by a background task which stops once the client is dropped.
+/// ```rust,no_run
+/// use axum_jrpc::client::{JsonRpcClient, WsClient};
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> Result<(), axum_jrpc::client::ClientError> {
+/// let (client, mut notifications) = WsClient::connect("ws://127.0.0.1:8080/ws").await?;
+/// let _: u64 = client.call("subscribe", ["blocks"]).await?;
+/// while let Some(notification) = notifications.next().await {
+///     println!("{}: {:?}", notification.method, notification.params);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WsClient {
+    url: String,
+    sender: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+    timeout: Option<Duration>,
+    next_id: AtomicI64,
+}
+
+/// Requests sent by the server, usually notifications.
+///
+/// Requests carrying an id are calls made by the server, answer them with [`WsClient::respond`].
+#[derive(Debug)]
+pub struct Notifications(mpsc::UnboundedReceiver<JsonRpcRequest>);
+
+impl Stream for Notifications {
+    type Item = JsonRpcRequest;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+impl WsClient {
+    /// Connects to `url` and spawns the connection task on the current tokio runtime
+    pub async fn connect(url: impl Into<String>) -> Result<(Self, Notifications), ClientError> {
+        let url = url.into();
+        let (stream, _) = tokio_tungstenite::connect_async(url.as_str()).await?;
+
+        let (sender, outgoing) = mpsc::unbounded_channel();
+        let (notifications, incoming) = mpsc::unbounded_channel();
+        let pending = Pending::default();
+        tokio::spawn(run(stream, outgoing, notifications, pending.clone()));
+
+        let client = Self {
+            url,
+            sender,
+            pending,
+            timeout: None,
+            next_id: AtomicI64::new(0),
+        };
+        Ok((client, Notifications(incoming)))
+    }
+
+    /// Sets the client-wide timeout, applied to every call without its own timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Answers a call made by the server
+    pub fn respond(&self, response: JsonRpcResponse) -> Result<(), ClientError> {
+        let message = to_vec(&response).map_err(ClientError::Serialization)?;
+        let message =
+            String::from_utf8(message).map_err(|e| ClientError::Serialization(e.to_string()))?;
+        self.sender
+            .send(Message::Text(message))
+            .map_err(|_| ClientError::ConnectionClosed)
+    }
+}
+
+async fn run<S>(
+    stream: S,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    notifications: mpsc::UnboundedSender<JsonRpcRequest>,
+    pending: Pending,
+) where
+    S: Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
+        + futures_util::Sink<Message, Error = tokio_tungstenite::tungstenite::Error>,
+{
+    let (mut sink, mut stream) = stream.split();
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => match message {
+                Some(message) => {
+                    if sink.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                None => {
+                    let _ = sink.close().await;
+                    break;
+                }
+            },
+            message = stream.next() => {
+                let mut bytes = match message {
+                    Some(Ok(Message::Text(text))) => text.into_bytes(),
+                    Some(Ok(Message::Binary(bytes))) => bytes,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                match from_slice(&mut bytes) {
+                    Ok(JsonRpcMessage::Response(response)) => {
+                        let sender = pending.lock().unwrap().remove(&response.id);
+                        if let Some(sender) = sender {
+                            let _ = sender.send(response);
+                        }
+                    }
+                    Ok(JsonRpcMessage::Request(request)) => {
+                        let _ = notifications.send(request);
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+
+    // Dropping the senders wakes up all callers still waiting for a response
+    pending.lock().unwrap().clear();
+}
+
+#[async_trait::async_trait]
+impl JsonRpcClient for WsClient {
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    fn next_id(&self) -> Id {
+        Id::Num(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    async fn send(
+        &self,
+        request: JsonRpcRequest,
+        options: CallOptions,
+    ) -> Result<JsonRpcResponse, ClientError> {
+        let message = to_vec(&request).map_err(ClientError::Serialization)?;
+        let message =
+            String::from_utf8(message).map_err(|e| ClientError::Serialization(e.to_string()))?;
+
+        let id = request.id;
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), tx);
+        if self.sender.send(Message::Text(message)).is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(ClientError::ConnectionClosed);
+        }
+
+        let response = match options.timeout.or(self.timeout) {
+            Some(timeout) => match tokio::time::timeout(timeout, rx).await {
+                Ok(response) => response,
+                Err(_) => {
+                    self.pending.lock().unwrap().remove(&id);
+                    return Err(ClientError::Timeout(timeout));
+                }
+            },
+            None => rx.await,
+        };
+        response.map_err(|_| ClientError::ConnectionClosed)
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "serde_json", feature = "server"))]
+mod test {
+    use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+    use axum::response::Response;
+    use axum::routing::get;
+    use axum::Router;
+    use futures_util::StreamExt;
+
+    use super::WsClient;
+    use crate::client::JsonRpcClient;
+    use crate::{JsonRpcRequest, JsonRpcResponse};
+
+    async fn handler(ws: WebSocketUpgrade) -> Response {
+        ws.on_upgrade(|mut socket: WebSocket| async move {
+            while let Some(Ok(Message::Text(text))) = socket.recv().await {
+                let request: JsonRpcRequest = serde_json::from_str(&text).unwrap();
+                let notification = r#"{"jsonrpc":"2.0","method":"tick","params":[1]}"#;
+                socket
+                    .send(Message::Text(notification.to_owned()))
+                    .await
+                    .unwrap();
+                let response = JsonRpcResponse::success(request.id, request.method);
+                let response = serde_json::to_string(&response).unwrap();
+                socket.send(Message::Text(response)).await.unwrap();
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn calls_and_notifications() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().route("/ws", get(handler)))
+                .await
+                .unwrap();
+        });
+
+        let (client, mut notifications) =
+            WsClient::connect(format!("ws://{addr}/ws")).await.unwrap();
+        let (a, b) = tokio::join!(
+            client.call::<_, String>("first", ()),
+            client.call::<_, String>("second", ())
+        );
+        assert_eq!(a.unwrap(), "first");
+        assert_eq!(b.unwrap(), "second");
+
+        let notification = notifications.next().await.unwrap();
+        assert_eq!(notification.method, "tick");
+        assert_eq!(notification.params, serde_json::json!([1]));
+    }
+}
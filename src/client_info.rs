@@ -0,0 +1,328 @@
+//! Client metadata parsed from typed headers, for the `typed-headers` feature.
+//!
+//! [`ClientInfoLayer`] wraps a service and parses the headers of the HTTP request each call
+//! was received in into a [`ClientInfo`] extension, so handlers don't have to pick apart
+//! [`RequestHeaders`](crate::router::RequestHeaders) themselves:
+//! * [`AcceptLanguage`], the languages of `Accept-Language` by preference
+//! * [`ClientVersion`], from `X-Client-Version`
+//! * [`Timezone`], an IANA name such as `Europe/Berlin` from `X-Timezone`
+//!
+//! Missing or malformed headers are left out. Each type is an
+//! [axum-extra typed header](axum_extra::TypedHeader), so plain axum routes can extract them
+//! too.
+//! ```rust
+//! use axum_jrpc::client_info::{ClientInfo, ClientInfoLayer};
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//! use tower::Layer;
+//!
+//! async fn greet(req: JsonRpcExtractor) -> JrpcResult {
+//!     let info = req.extension::<ClientInfo>().cloned().unwrap_or_default();
+//!     let greeting = match info.language.and_then(|l| l.best_match(&["de", "en"])) {
+//!         Some("de") => "Hallo",
+//!         _ => "Hello",
+//!     };
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), greeting))
+//! }
+//!
+//! let rpc = ClientInfoLayer::new().layer(JsonRpcRouter::new().method("greet", greet));
+//! ```
+
+use std::convert::Infallible;
+use std::task::{Context, Poll};
+
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use axum_extra::headers::{self, Header, HeaderMapExt};
+use tower::{Layer, Service};
+
+use crate::router::RequestHeaders;
+use crate::{JsonRpcExtractor, JsonRpcResponse};
+
+/// Name of the [`ClientVersion`] header
+pub static X_CLIENT_VERSION: HeaderName = HeaderName::from_static("x-client-version");
+/// Name of the [`Timezone`] header
+pub static X_TIMEZONE: HeaderName = HeaderName::from_static("x-timezone");
+
+/// Metadata of the client making a call, an extension of calls, see the
+/// [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub language: Option<AcceptLanguage>,
+    pub version: Option<ClientVersion>,
+    pub timezone: Option<Timezone>,
+}
+
+impl ClientInfo {
+    /// Parses the headers of `headers`, leaving out malformed ones
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            language: headers.typed_get(),
+            version: headers.typed_get(),
+            timezone: headers.typed_get(),
+        }
+    }
+}
+
+/// `Accept-Language`, with the languages sorted by their weight.
+///
+/// Languages weighted `q=0` are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcceptLanguage {
+    /// Tags and weights in thousandths, heaviest first
+    languages: Vec<(String, u16)>,
+}
+
+impl AcceptLanguage {
+    /// Tags of the accepted languages, preferred ones first, e.g. `de-CH` or `*`
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.languages.iter().map(|(tag, _)| tag.as_str())
+    }
+
+    /// The most preferred of `available`, matching tags ignoring case and by their primary
+    /// language, so `de-CH` is served `de` and `de` is served `de-DE`
+    pub fn best_match<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        let primary = |tag: &str| {
+            tag.split('-')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase()
+        };
+        self.languages().find_map(|tag| {
+            if tag == "*" {
+                return available.first().copied();
+            }
+            let exact = available.iter().find(|a| a.eq_ignore_ascii_case(tag));
+            exact
+                .or_else(|| available.iter().find(|a| primary(a) == primary(tag)))
+                .copied()
+        })
+    }
+}
+
+impl Header for AcceptLanguage {
+    fn name() -> &'static HeaderName {
+        &header::ACCEPT_LANGUAGE
+    }
+
+    fn decode<'i, I: Iterator<Item = &'i HeaderValue>>(
+        values: &mut I,
+    ) -> Result<Self, headers::Error> {
+        let mut languages = Vec::new();
+        for value in values {
+            let value = value.to_str().map_err(|_| headers::Error::invalid())?;
+            for item in value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+            {
+                let mut parts = item.split(';').map(str::trim);
+                let tag = parts.next().unwrap_or_default();
+                let valid = |c: char| c.is_ascii_alphanumeric() || c == '-';
+                if tag.is_empty() || !(tag == "*" || tag.chars().all(valid)) {
+                    return Err(headers::Error::invalid());
+                }
+                let weight = match parts.find_map(|param| param.strip_prefix("q=")) {
+                    Some(q) => quality(q).ok_or_else(headers::Error::invalid)?,
+                    None => 1000,
+                };
+                if weight > 0 {
+                    languages.push((tag.to_owned(), weight));
+                }
+            }
+        }
+        if languages.is_empty() {
+            return Err(headers::Error::invalid());
+        }
+        // stable, so languages of equal weight keep their order
+        languages.sort_by(|(_, a), (_, b)| b.cmp(a));
+        Ok(Self { languages })
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        let value = self
+            .languages
+            .iter()
+            .map(|(tag, weight)| match weight {
+                1000 => tag.clone(),
+                weight => format!("{tag};q={}", f64::from(*weight) / 1000.0),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        values.extend(HeaderValue::from_str(&value).ok());
+    }
+}
+
+/// Weight of a `q=` parameter in thousandths
+fn quality(q: &str) -> Option<u16> {
+    let q: f64 = q.parse().ok()?;
+    (0.0..=1.0)
+        .contains(&q)
+        .then(|| (q * 1000.0).round() as u16)
+}
+
+/// Version of the client application, from the `X-Client-Version` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientVersion(pub String);
+
+impl Header for ClientVersion {
+    fn name() -> &'static HeaderName {
+        &X_CLIENT_VERSION
+    }
+
+    fn decode<'i, I: Iterator<Item = &'i HeaderValue>>(
+        values: &mut I,
+    ) -> Result<Self, headers::Error> {
+        let value = single(values)?.trim();
+        if value.is_empty() {
+            return Err(headers::Error::invalid());
+        }
+        Ok(Self(value.to_owned()))
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        values.extend(HeaderValue::from_str(&self.0).ok());
+    }
+}
+
+/// IANA time zone of the client, e.g. `America/New_York`, from the `X-Timezone` header.
+///
+/// Only the shape of the name is checked, not that the zone exists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timezone(pub String);
+
+impl Header for Timezone {
+    fn name() -> &'static HeaderName {
+        &X_TIMEZONE
+    }
+
+    fn decode<'i, I: Iterator<Item = &'i HeaderValue>>(
+        values: &mut I,
+    ) -> Result<Self, headers::Error> {
+        let value = single(values)?.trim();
+        let valid = |c: char| c.is_ascii_alphanumeric() || "/_+-".contains(c);
+        if value.is_empty() || value.len() > 64 || !value.chars().all(valid) {
+            return Err(headers::Error::invalid());
+        }
+        Ok(Self(value.to_owned()))
+    }
+
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        values.extend(HeaderValue::from_str(&self.0).ok());
+    }
+}
+
+fn single<'i>(
+    values: &mut impl Iterator<Item = &'i HeaderValue>,
+) -> Result<&'i str, headers::Error> {
+    let value = values.next().ok_or_else(headers::Error::invalid)?;
+    if values.next().is_some() {
+        return Err(headers::Error::invalid());
+    }
+    value.to_str().map_err(|_| headers::Error::invalid())
+}
+
+/// Adds [`ClientInfo`] to calls received over HTTP, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfoLayer {
+    _priv: (),
+}
+
+impl ClientInfoLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for ClientInfoLayer {
+    type Service = WithClientInfo<S>;
+
+    fn layer(&self, inner: S) -> WithClientInfo<S> {
+        WithClientInfo { inner }
+    }
+}
+
+/// Adds [`ClientInfo`] to calls, see [`ClientInfoLayer`]
+#[derive(Debug, Clone)]
+pub struct WithClientInfo<S> {
+    inner: S,
+}
+
+impl<S> Service<JsonRpcExtractor> for WithClientInfo<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: JsonRpcExtractor) -> Self::Future {
+        if let Some(RequestHeaders(headers)) = request.extension::<RequestHeaders>() {
+            let info = ClientInfo::from_headers(headers);
+            request.extensions.insert(info);
+        }
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::http::header;
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{ClientInfo, ClientInfoLayer};
+    use crate::router;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn info(req: JsonRpcExtractor) -> JrpcResult {
+        let info = req.extension::<ClientInfo>().cloned().unwrap();
+        let language = info.language.as_ref();
+        let result = json!({
+            "languages": language.map(|l| l.languages().collect::<Vec<_>>()),
+            "language": language.and_then(|l| l.best_match(&["en-US", "de"])),
+            "version": info.version.map(|v| v.0),
+            "timezone": info.timezone.map(|t| t.0),
+        });
+        Ok(JsonRpcResponse::success(req.get_answer_id(), result))
+    }
+
+    #[tokio::test]
+    async fn parses_headers() {
+        let rpc = ClientInfoLayer::new().layer(JsonRpcRouter::new().method("info", info));
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "info"});
+
+        let res: Value = server
+            .post("/")
+            .add_header(header::ACCEPT_LANGUAGE, "fr;q=0, en;q=0.5, de-CH, *;q=0.1")
+            .add_header("x-client-version", "wallet/2.4.1")
+            .add_header("x-timezone", "Europe/Zurich")
+            .json(&request)
+            .await
+            .json();
+        let expected = json!({
+            "languages": ["de-CH", "en", "*"],
+            "language": "de",
+            "version": "wallet/2.4.1",
+            "timezone": "Europe/Zurich",
+        });
+        assert_eq!(res["result"], expected);
+
+        let res: Value = server
+            .post("/")
+            .add_header(header::ACCEPT_LANGUAGE, "en;q=2")
+            .add_header("x-timezone", "Europe/Zurich; rm -rf")
+            .json(&request)
+            .await
+            .json();
+        let expected =
+            json!({"languages": null, "language": null, "version": null, "timezone": null});
+        assert_eq!(res["result"], expected);
+    }
+}
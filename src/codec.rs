@@ -0,0 +1,283 @@
+//! Encodings of JSON-RPC messages.
+//!
+//! Every encoding is a [`Codec`]. The json backend picked by the `serde_json`, `sonic-rs` or
+//! `simd` feature is additionally a [`JsonCodec`] and available as [`JsonBackend`]. Transports
+//! select a codec per message, e.g. by content type, and call it statically.
+
+use cfg_if::cfg_if;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::Value;
+
+/// Turns messages into bytes and back.
+pub(crate) trait Codec {
+    /// Media type of encoded messages
+    #[cfg_attr(not(any(feature = "msgpack", feature = "cbor")), allow(dead_code))]
+    const CONTENT_TYPE: &'static str;
+
+    /// Deserializes `T` from `bytes`, which may be used as scratch space
+    fn decode<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T, String>;
+
+    /// Deserializes `T` from shared `bytes`, copying them only if the codec needs scratch space
+    fn decode_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+        Self::decode(&mut bytes.to_vec())
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String>;
+
+    /// Length of the encoded `value`, without keeping the encoding around
+    #[cfg_attr(not(all(feature = "tracing", feature = "server")), allow(dead_code))]
+    fn encoded_len<T: Serialize>(value: &T) -> Result<usize, String> {
+        Self::encode(value).map(|bytes| bytes.len())
+    }
+
+    /// Appends the encoded `value` to `buffer`
+    #[cfg(feature = "server")]
+    fn encode_into<T: Serialize>(value: &T, buffer: &mut bytes::BytesMut) -> Result<(), String> {
+        buffer.extend_from_slice(&Self::encode(value)?);
+        Ok(())
+    }
+}
+
+/// A json backend, which also owns the representation of [`Value`].
+pub(crate) trait JsonCodec: Codec {
+    fn to_value<T: Serialize>(value: T) -> Result<Value, String>;
+
+    fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, String>;
+}
+
+cfg_if! {
+    if #[cfg(feature = "simd")] {
+        /// The enabled json backend
+        pub(crate) type JsonBackend = SimdJson;
+    } else if #[cfg(feature = "sonic-rs")] {
+        /// The enabled json backend
+        pub(crate) type JsonBackend = SonicRs;
+    } else if #[cfg(feature = "serde_json")] {
+        /// The enabled json backend
+        pub(crate) type JsonBackend = SerdeJson;
+    }
+}
+
+/// Counts the bytes written to it
+#[cfg(any(feature = "serde_json", feature = "simd"))]
+struct Counter(usize);
+
+#[cfg(any(feature = "serde_json", feature = "simd"))]
+impl std::io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde_json")]
+#[derive(Debug)]
+pub(crate) struct SerdeJson;
+
+#[cfg(feature = "serde_json")]
+impl Codec for SerdeJson {
+    const CONTENT_TYPE: &'static str = "application/json";
+
+    fn decode<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T, String> {
+        Self::decode_slice(bytes)
+    }
+
+    fn decode_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(value).map_err(|e| e.to_string())
+    }
+
+    fn encoded_len<T: Serialize>(value: &T) -> Result<usize, String> {
+        let mut counter = Counter(0);
+        serde_json::to_writer(&mut counter, value).map_err(|e| e.to_string())?;
+        Ok(counter.0)
+    }
+
+    #[cfg(feature = "server")]
+    fn encode_into<T: Serialize>(value: &T, buffer: &mut bytes::BytesMut) -> Result<(), String> {
+        use bytes::BufMut;
+        serde_json::to_writer(buffer.writer(), value).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl JsonCodec for SerdeJson {
+    fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+        serde_json::to_value(value).map_err(|e| e.to_string())
+    }
+
+    fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, String> {
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "simd")]
+#[derive(Debug)]
+pub(crate) struct SimdJson;
+
+#[cfg(feature = "simd")]
+impl Codec for SimdJson {
+    const CONTENT_TYPE: &'static str = "application/json";
+
+    fn decode<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T, String> {
+        simd_json::from_slice(bytes).map_err(|e| e.to_string())
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+        simd_json::to_vec(value).map_err(|e| e.to_string())
+    }
+
+    fn encoded_len<T: Serialize>(value: &T) -> Result<usize, String> {
+        let mut counter = Counter(0);
+        simd_json::to_writer(&mut counter, value).map_err(|e| e.to_string())?;
+        Ok(counter.0)
+    }
+
+    #[cfg(feature = "server")]
+    fn encode_into<T: Serialize>(value: &T, buffer: &mut bytes::BytesMut) -> Result<(), String> {
+        use bytes::BufMut;
+        simd_json::to_writer(buffer.writer(), value).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "simd")]
+impl JsonCodec for SimdJson {
+    fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+        simd_json::serde::to_owned_value(value).map_err(|e| e.to_string())
+    }
+
+    fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, String> {
+        simd_json::serde::from_owned_value(value).map_err(|e| e.to_string())
+    }
+}
+
+/// [sonic-rs](https://docs.rs/sonic-rs), keeping [`serde_json::Value`] as [`Value`]
+#[cfg(feature = "sonic-rs")]
+#[derive(Debug)]
+pub(crate) struct SonicRs;
+
+#[cfg(feature = "sonic-rs")]
+impl Codec for SonicRs {
+    const CONTENT_TYPE: &'static str = "application/json";
+
+    fn decode<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T, String> {
+        Self::decode_slice(bytes)
+    }
+
+    fn decode_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+        check_depth(bytes)?;
+        sonic_rs::from_slice(bytes).map_err(|e| e.to_string())
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+        sonic_rs::to_vec(value).map_err(|e| e.to_string())
+    }
+
+    #[cfg(feature = "server")]
+    fn encode_into<T: Serialize>(value: &T, buffer: &mut bytes::BytesMut) -> Result<(), String> {
+        use bytes::BufMut;
+        sonic_rs::to_writer(buffer.writer(), value).map_err(|e| e.to_string())
+    }
+}
+
+/// Nesting depth at which serde_json gives up
+#[cfg(feature = "sonic-rs")]
+const RECURSION_LIMIT: usize = 128;
+
+/// Rejects json nested as deep as [`RECURSION_LIMIT`]. sonic-rs has no limit of its own and
+/// recurses until the stack overflows
+#[cfg(feature = "sonic-rs")]
+fn check_depth(bytes: &[u8]) -> Result<(), String> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (offset, &byte) in bytes.iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth >= RECURSION_LIMIT {
+                    return Err(format!("recursion limit exceeded at offset {offset}"));
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sonic-rs")]
+impl JsonCodec for SonicRs {
+    fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+        SerdeJson::to_value(value)
+    }
+
+    fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, String> {
+        SerdeJson::from_value(value)
+    }
+}
+
+/// [MessagePack](https://msgpack.org/), structs are encoded as maps
+#[cfg(feature = "msgpack")]
+#[derive(Debug)]
+pub(crate) struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePack {
+    const CONTENT_TYPE: &'static str = "application/msgpack";
+
+    fn decode<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T, String> {
+        Self::decode_slice(bytes)
+    }
+
+    fn decode_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+        rmp_serde::from_slice(bytes).map_err(|e| e.to_string())
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec_named(value).map_err(|e| e.to_string())
+    }
+}
+
+/// [CBOR](https://cbor.io/)
+#[cfg(feature = "cbor")]
+#[derive(Debug)]
+pub(crate) struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    const CONTENT_TYPE: &'static str = "application/cbor";
+
+    fn decode<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T, String> {
+        Self::decode_slice(bytes)
+    }
+
+    fn decode_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+        ciborium::from_reader(bytes).map_err(|e| e.to_string())
+    }
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(|e| e.to_string())?;
+        Ok(bytes)
+    }
+}
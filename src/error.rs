@@ -117,4 +117,12 @@ impl JsonRpcError {
     pub fn code(&self) -> i32 {
         self.code
     }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn data(&self) -> &Value {
+        &self.data
+    }
 }
@@ -0,0 +1,255 @@
+//! Helpers for Ethereum-compatible JSON-RPC APIs, for the `eth` feature.
+//!
+//! * [`quantity`] and [`data`] serialize numbers and bytes as the `0x`-prefixed hex strings
+//!   of the [Ethereum JSON-RPC spec](https://ethereum.org/en/developers/docs/apis/json-rpc/#hex-encoding)
+//! * the error codes of [EIP-1474](https://eips.ethereum.org/EIPS/eip-1474), and
+//!   [`execution_reverted`] for failed calls
+//! * [`JsonRpcRouter::namespace`] registers methods under a prefix, e.g. `eth_blockNumber`
+//! ```rust
+//! use axum_jrpc::{eth, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Block {
+//!     #[serde(with = "eth::quantity")]
+//!     number: u64,
+//!     #[serde(with = "eth::data")]
+//!     hash: [u8; 32],
+//! }
+//!
+//! async fn block_number(req: JsonRpcExtractor) -> JrpcResult {
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), eth::Quantity(19_000_000u64)))
+//! }
+//!
+//! let rpc = JsonRpcRouter::new()
+//!     .namespace("eth", |eth| eth.method("blockNumber", block_number))
+//!     .namespace("net", |net| net);
+//! assert_eq!(rpc.methods().collect::<Vec<_>>(), ["eth_blockNumber"]);
+//! ```
+
+use std::future::Future;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{to_value, JrpcResult, JsonRpcExtractor, JsonRpcRouter, Value};
+
+/// Missing or invalid parameters
+pub const INVALID_INPUT: i32 = -32000;
+/// Requested resource not found
+pub const RESOURCE_NOT_FOUND: i32 = -32001;
+/// Requested resource not available
+pub const RESOURCE_UNAVAILABLE: i32 = -32002;
+/// Transaction creation failed
+pub const TRANSACTION_REJECTED: i32 = -32003;
+/// Method is not implemented
+pub const METHOD_NOT_SUPPORTED: i32 = -32004;
+/// Request exceeds defined limit
+pub const LIMIT_EXCEEDED: i32 = -32005;
+/// Version of JSON-RPC protocol is not supported
+pub const JSON_RPC_VERSION_NOT_SUPPORTED: i32 = -32006;
+/// A call or gas estimation reverted, used by geth and most other nodes
+pub const EXECUTION_REVERTED: i32 = 3;
+
+/// An error with one of the codes of this module, or any other
+pub fn error(code: i32, message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError::new(
+        JsonRpcErrorReason::ApplicationError(code),
+        message.into(),
+        Value::default(),
+    )
+}
+
+/// The error of a reverted call, with the revert data as its `data`
+pub fn execution_reverted(revert: &[u8]) -> JsonRpcError {
+    JsonRpcError::new(
+        JsonRpcErrorReason::ApplicationError(EXECUTION_REVERTED),
+        "execution reverted".to_owned(),
+        to_value(format!("0x{}", hex::encode(revert))).unwrap_or_default(),
+    )
+}
+
+/// A number serialized as a [`quantity`], for params and results which aren't fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Quantity<T>(pub T);
+
+impl<T: Copy + Into<u128>> Serialize for Quantity<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        quantity::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: TryFrom<u128>> Deserialize<'de> for Quantity<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        quantity::deserialize(deserializer).map(Quantity)
+    }
+}
+
+/// Serde adapter for unsigned integers as quantities: hex without leading zeros, e.g. `0x0`
+/// or `0x41`
+pub mod quantity {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<u128>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("{:#x}", (*value).into()))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<u128>,
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        let digits = super::strip(&text).map_err(Error::custom)?;
+        if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+            return Err(Error::custom(format!("invalid quantity `{text}`")));
+        }
+        let value = u128::from_str_radix(digits, 16)
+            .map_err(|_| Error::custom(format!("invalid quantity `{text}`")))?;
+        T::try_from(value).map_err(|_| Error::custom(format!("quantity `{text}` is out of range")))
+    }
+}
+
+/// Serde adapter for bytes as unformatted data: two hex digits per byte, e.g. `0x` or
+/// `0x004200`. Works with `Vec<u8>` and byte arrays, whose length is checked
+pub mod data {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("0x{}", hex::encode(value)))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        let bytes = hex::decode(super::strip(&text).map_err(Error::custom)?)
+            .map_err(|e| Error::custom(format!("invalid data `{text}`: {e}")))?;
+        let len = bytes.len();
+        T::try_from(bytes).map_err(|_| Error::custom(format!("unexpected length {len} of data")))
+    }
+}
+
+fn strip(text: &str) -> Result<&str, String> {
+    text.strip_prefix("0x")
+        .ok_or_else(|| format!("`{text}` is missing the 0x prefix"))
+}
+
+/// Methods of a namespace, see [`JsonRpcRouter::namespace`]
+#[derive(Debug)]
+pub struct Namespace {
+    router: JsonRpcRouter,
+    prefix: String,
+}
+
+impl Namespace {
+    /// Registers `handler` for `method` of the namespace, e.g. `blockNumber` for
+    /// `eth_blockNumber`
+    pub fn method<H, F>(mut self, method: &str, handler: H) -> Self
+    where
+        H: Fn(JsonRpcExtractor) -> F + Send + Sync + 'static,
+        F: Future<Output = JrpcResult> + Send + 'static,
+    {
+        self.router = self
+            .router
+            .method(format!("{}_{method}", self.prefix), handler);
+        self
+    }
+}
+
+impl JsonRpcRouter {
+    /// Registers the methods added by `methods` with `namespace` and an underscore as
+    /// their prefix, as Ethereum nodes name them
+    pub fn namespace(self, namespace: &str, methods: impl FnOnce(Namespace) -> Namespace) -> Self {
+        let namespace = Namespace {
+            router: self,
+            prefix: namespace.to_owned(),
+        };
+        methods(namespace).router
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::Quantity;
+    use crate::router::JsonRpcService;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Transaction {
+        #[serde(with = "super::quantity")]
+        nonce: u64,
+        #[serde(with = "super::quantity")]
+        value: u128,
+        #[serde(with = "super::data")]
+        to: [u8; 4],
+        #[serde(with = "super::data")]
+        input: Vec<u8>,
+    }
+
+    async fn block_number(req: JsonRpcExtractor) -> JrpcResult {
+        let [Quantity(offset)]: [Quantity<u64>; 1] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(1, Quantity(0x41 + offset)))
+    }
+
+    #[tokio::test]
+    async fn encodes_hex() {
+        let json = json!({"nonce": "0x0", "value": "0x400", "to": "0x0042abcd", "input": "0x"});
+        let transaction: Transaction = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            transaction,
+            Transaction {
+                nonce: 0,
+                value: 1024,
+                to: [0, 0x42, 0xab, 0xcd],
+                input: Vec::new(),
+            }
+        );
+        assert_eq!(serde_json::to_value(&transaction).unwrap(), json);
+
+        for quantity in ["0x", "0x0400", "400", "0xfg", "0x10000000000000000"] {
+            assert!(
+                serde_json::from_value::<Quantity<u64>>(json!(quantity)).is_err(),
+                "{quantity}"
+            );
+        }
+        for data in [
+            json!({"nonce": "0x0", "value": "0x0", "to": "0x00", "input": "0x"}),
+            json!({"nonce": "0x0", "value": "0x0", "to": "0x00000000", "input": "0x1"}),
+        ] {
+            assert!(serde_json::from_value::<Transaction>(data).is_err());
+        }
+
+        let error = serde_json::to_value(super::execution_reverted(&[8, 0xc3])).unwrap();
+        assert_eq!(
+            error,
+            json!({"code": 3, "message": "execution reverted", "data": "0x08c3"})
+        );
+
+        let rpc =
+            JsonRpcRouter::new().namespace("eth", |eth| eth.method("blockNumber", block_number));
+        let request = JsonRpcRequest::builder("eth_blockNumber")
+            .params(["0x1"])
+            .unwrap()
+            .id(1)
+            .build();
+        let response = rpc.dispatch(request.into()).await;
+        assert_eq!(response, JsonRpcResponse::success(1, "0x42"));
+    }
+}
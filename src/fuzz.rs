@@ -0,0 +1,236 @@
+//! Random [`Id`], [`JsonRpcRequest`], [`JsonRpcResponse`] and [`JsonRpcError`] values for
+//! fuzzing and property-based tests. The `arbitrary` feature implements
+//! [`arbitrary::Arbitrary`](::arbitrary::Arbitrary), the `proptest` feature
+//! [`proptest::arbitrary::Arbitrary`](::proptest::arbitrary::Arbitrary), so the types work
+//! with `cargo fuzz` targets and `any::<T>()`:
+//! ```rust
+//! use axum_jrpc::JsonRpcResponse;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(response in any::<JsonRpcResponse>())| {
+//!     let json = serde_json::to_string(&response).unwrap();
+//!     prop_assert_eq!(serde_json::from_str::<JsonRpcResponse>(&json).unwrap(), response);
+//! });
+//! ```
+//!
+//! Params, results and error data are JSON values nested up to [`DEPTH`] levels deep, with
+//! floats that survive a round trip through their text. Error codes range over the whole
+//! `i32` space, not only the reserved ones.
+
+use cfg_if::cfg_if;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{to_value, Id, JsonRpcAnswer, JsonRpcRequest, JsonRpcResponse, Value};
+
+/// How deep generated JSON values nest
+pub(crate) const DEPTH: u32 = 3;
+
+fn error(code: i32, message: String, data: serde_json::Value) -> JsonRpcError {
+    JsonRpcError::new(
+        JsonRpcErrorReason::ApplicationError(code),
+        message,
+        value(data),
+    )
+}
+
+/// A fraction surviving a round trip through its decimal representation
+fn float(n: i16) -> serde_json::Number {
+    serde_json::Number::from_f64(f64::from(n) / 16.0).unwrap_or_else(|| 0.into())
+}
+
+fn value(json: serde_json::Value) -> Value {
+    to_value(json).unwrap_or_default()
+}
+
+cfg_if! {
+    if #[cfg(feature = "arbitrary")] {
+        use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+        impl<'a> Arbitrary<'a> for Id {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                Ok(match u.choose_index(3)? {
+                    0 => Id::Num(u.arbitrary()?),
+                    1 => Id::Str(u.arbitrary()?),
+                    _ => Id::None(()),
+                })
+            }
+        }
+
+        impl<'a> Arbitrary<'a> for JsonRpcError {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                Ok(error(u.arbitrary()?, u.arbitrary()?, json(u, DEPTH)?))
+            }
+        }
+
+        impl<'a> Arbitrary<'a> for JsonRpcRequest {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                let params = match u.choose_index(3)? {
+                    0 => serde_json::Value::Null,
+                    1 => serde_json::Value::Array(array(u, DEPTH)?),
+                    _ => serde_json::Value::Object(object(u, DEPTH)?),
+                };
+                Ok(JsonRpcRequest {
+                    id: u.arbitrary()?,
+                    method: u.arbitrary()?,
+                    params: value(params),
+                })
+            }
+        }
+
+        impl<'a> Arbitrary<'a> for JsonRpcResponse {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                let result = match u.arbitrary()? {
+                    true => JsonRpcAnswer::Result(value(json(u, DEPTH)?)),
+                    false => JsonRpcAnswer::Error(u.arbitrary()?),
+                };
+                Ok(JsonRpcResponse { id: u.arbitrary()?, result })
+            }
+        }
+
+        fn json(u: &mut Unstructured<'_>, depth: u32) -> Result<serde_json::Value> {
+            use serde_json::Value;
+
+            let kinds = if depth == 0 { 5 } else { 7 };
+            Ok(match u.choose_index(kinds)? {
+                0 => Value::Null,
+                1 => Value::Bool(u.arbitrary()?),
+                2 => Value::Number(u.arbitrary::<i64>()?.into()),
+                3 => Value::Number(float(u.arbitrary()?)),
+                4 => Value::String(u.arbitrary()?),
+                5 => Value::Array(array(u, depth - 1)?),
+                _ => Value::Object(object(u, depth - 1)?),
+            })
+        }
+
+        fn array(u: &mut Unstructured<'_>, depth: u32) -> Result<Vec<serde_json::Value>> {
+            let len = u.arbitrary_len::<u64>()?.min(8);
+            (0..len).map(|_| json(u, depth)).collect()
+        }
+
+        fn object(
+            u: &mut Unstructured<'_>,
+            depth: u32,
+        ) -> Result<serde_json::Map<String, serde_json::Value>> {
+            let mut object = serde_json::Map::new();
+            for _ in 0..u.arbitrary_len::<u64>()?.min(8) {
+                object.insert(u.arbitrary()?, json(u, depth)?);
+            }
+            Ok(object)
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "proptest")] {
+        use ::proptest::prelude::*;
+        use ::proptest::{collection, option};
+
+        impl ::proptest::arbitrary::Arbitrary for Id {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with((): ()) -> Self::Strategy {
+                prop_oneof![
+                    any::<i64>().prop_map(Id::Num),
+                    any::<String>().prop_map(Id::Str),
+                    Just(Id::None(())),
+                ]
+                .boxed()
+            }
+        }
+
+        impl ::proptest::arbitrary::Arbitrary for JsonRpcError {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with((): ()) -> Self::Strategy {
+                (any::<i32>(), any::<String>(), json_strategy())
+                    .prop_map(|(code, message, data)| error(code, message, data))
+                    .boxed()
+            }
+        }
+
+        impl ::proptest::arbitrary::Arbitrary for JsonRpcRequest {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with((): ()) -> Self::Strategy {
+                let params = option::of(json_strategy().prop_filter("structured", |params| {
+                    params.is_array() || params.is_object()
+                }));
+                (any::<Id>(), any::<String>(), params)
+                    .prop_map(|(id, method, params)| JsonRpcRequest {
+                        id,
+                        method,
+                        params: value(params.unwrap_or_default()),
+                    })
+                    .boxed()
+            }
+        }
+
+        impl ::proptest::arbitrary::Arbitrary for JsonRpcResponse {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with((): ()) -> Self::Strategy {
+                let result = prop_oneof![
+                    json_strategy().prop_map(|result| JsonRpcAnswer::Result(value(result))),
+                    any::<JsonRpcError>().prop_map(JsonRpcAnswer::Error),
+                ];
+                (any::<Id>(), result)
+                    .prop_map(|(id, result)| JsonRpcResponse { id, result })
+                    .boxed()
+            }
+        }
+
+        fn json_strategy() -> impl Strategy<Value = serde_json::Value> {
+            use serde_json::Value;
+
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::Bool),
+                any::<i64>().prop_map(|n| Value::Number(n.into())),
+                any::<i16>().prop_map(|n| Value::Number(float(n))),
+                any::<String>().prop_map(Value::String),
+            ];
+            leaf.prop_recursive(DEPTH, 64, 8, |inner| {
+                prop_oneof![
+                    collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+                    collection::btree_map(any::<String>(), inner, 0..8)
+                        .prop_map(|object| Value::Object(object.into_iter().collect())),
+                ]
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "serde_json", feature = "arbitrary", feature = "proptest"))]
+mod test {
+    use ::arbitrary::{Arbitrary, Unstructured};
+    use proptest::prelude::*;
+
+    use crate::{JsonRpcRequest, JsonRpcResponse};
+
+    proptest! {
+        #[test]
+        fn round_trips(request in any::<JsonRpcRequest>(), response in any::<JsonRpcResponse>()) {
+            let json = serde_json::to_string(&request).unwrap();
+            let parsed: JsonRpcRequest = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(parsed.id, request.id);
+            prop_assert_eq!(parsed.method, request.method);
+            prop_assert_eq!(parsed.params, request.params);
+
+            let json = serde_json::to_string(&response).unwrap();
+            prop_assert_eq!(serde_json::from_str::<JsonRpcResponse>(&json).unwrap(), response);
+        }
+
+        #[test]
+        fn arbitrary_round_trips(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let mut u = Unstructured::new(&bytes);
+            let response = JsonRpcResponse::arbitrary(&mut u).unwrap();
+            let json = serde_json::to_string(&response).unwrap();
+            prop_assert_eq!(serde_json::from_str::<JsonRpcResponse>(&json).unwrap(), response);
+        }
+    }
+}
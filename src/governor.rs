@@ -0,0 +1,264 @@
+//! [tower_governor](https://docs.rs/tower_governor) rate limits answered with JSON-RPC errors,
+//! for the `tower-governor` feature.
+//!
+//! tower_governor limits HTTP requests, rejecting them with a bare `429 Too Many Requests`.
+//! [`GovernorLayer`] applies a [`GovernorConfig`] to each call instead, and answers calls over
+//! the limit with a [`RATE_LIMITED`] error whose data holds `retry_after_ms`, as
+//! [`RateLimitLayer`](crate::rate_limit::RateLimitLayer) does.
+//!
+//! Key extractors are handed a request with the headers and extensions of the HTTP request
+//! the call was received in, and the [`CallMethod`]. [`MethodKeyExtractor`] uses it to limit
+//! each method separately:
+//! ```rust
+//! use axum_jrpc::governor::{GovernorLayer, MethodKeyExtractor};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//! use tower_governor::governor::GovernorConfigBuilder;
+//! use tower_governor::key_extractor::PeerIpKeyExtractor;
+//!
+//! let config = GovernorConfigBuilder::default()
+//!     .per_second(2)
+//!     .burst_size(10)
+//!     .key_extractor(MethodKeyExtractor(PeerIpKeyExtractor))
+//!     .finish()
+//!     .unwrap();
+//! let rpc = GovernorLayer::new(&config).layer(JsonRpcRouter::new());
+//! ```
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use ::governor::clock::{Clock, DefaultClock, QuantaInstant};
+use ::governor::middleware::RateLimitingMiddleware;
+use ::governor::NotUntil;
+use axum::extract::ConnectInfo;
+use http::Request;
+use tower::{Layer, Service};
+pub use tower_governor;
+use tower_governor::governor::{Governor, GovernorConfig, SharedRateLimiter};
+use tower_governor::key_extractor::KeyExtractor;
+use tower_governor::GovernorError;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+#[cfg(doc)]
+use crate::rate_limit::RATE_LIMITED;
+use crate::router::{BoxFuture, RequestHeaders};
+use crate::{JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Method of a call, an extension of the requests handed to key extractors
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallMethod(pub Arc<str>);
+
+/// Keys calls by the key of `K` and their method, so each method has its own limit
+#[derive(Debug, Clone)]
+pub struct MethodKeyExtractor<K>(pub K);
+
+impl<K: KeyExtractor> KeyExtractor for MethodKeyExtractor<K> {
+    type Key = (K::Key, Arc<str>);
+
+    fn extract<T>(&self, req: &Request<T>) -> Result<Self::Key, GovernorError> {
+        let CallMethod(method) = req
+            .extensions()
+            .get::<CallMethod>()
+            .ok_or(GovernorError::UnableToExtractKey)?;
+        Ok((self.0.extract(req)?, method.clone()))
+    }
+}
+
+/// Limits calls with a tower_governor config, see the [module docs](self).
+pub struct GovernorLayer<K: KeyExtractor, M: RateLimitingMiddleware<QuantaInstant>> {
+    key_extractor: K,
+    limiter: SharedRateLimiter<K::Key, M>,
+}
+
+impl<K, M> Clone for GovernorLayer<K, M>
+where
+    K: KeyExtractor,
+    M: RateLimitingMiddleware<QuantaInstant>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            key_extractor: self.key_extractor.clone(),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+impl<K, M> std::fmt::Debug for GovernorLayer<K, M>
+where
+    K: KeyExtractor + std::fmt::Debug,
+    M: RateLimitingMiddleware<QuantaInstant>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GovernorLayer")
+            .field("key_extractor", &self.key_extractor)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, M> GovernorLayer<K, M>
+where
+    K: KeyExtractor,
+    M: RateLimitingMiddleware<QuantaInstant>,
+{
+    /// Limits calls as `config` limits HTTP requests, sharing its limiter. The HTTP methods
+    /// of the config are ignored
+    pub fn new(config: &GovernorConfig<K, M>) -> Self {
+        // the config only hands out its key extractor to the middleware
+        let governor = Governor::<K, M, (), ()>::new((), config);
+        Self {
+            key_extractor: governor.key_extractor,
+            limiter: governor.limiter,
+        }
+    }
+}
+
+impl<K, M, S> Layer<S> for GovernorLayer<K, M>
+where
+    K: KeyExtractor,
+    M: RateLimitingMiddleware<QuantaInstant>,
+{
+    type Service = GovernorLimit<K, M, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GovernorLimit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Limits calls, see [`GovernorLayer`]
+pub struct GovernorLimit<K: KeyExtractor, M: RateLimitingMiddleware<QuantaInstant>, S> {
+    inner: S,
+    layer: GovernorLayer<K, M>,
+}
+
+impl<K, M, S: Clone> Clone for GovernorLimit<K, M, S>
+where
+    K: KeyExtractor,
+    M: RateLimitingMiddleware<QuantaInstant>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<K, M, S: std::fmt::Debug> std::fmt::Debug for GovernorLimit<K, M, S>
+where
+    K: KeyExtractor + std::fmt::Debug,
+    M: RateLimitingMiddleware<QuantaInstant>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GovernorLimit")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<K, M, S> Service<JsonRpcExtractor> for GovernorLimit<K, M, S>
+where
+    K: KeyExtractor,
+    M: RateLimitingMiddleware<QuantaInstant, NegativeOutcome = NotUntil<QuantaInstant>>,
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let key = self.layer.key_extractor.extract(&http_request(&request));
+        let response = match key.map(|key| self.layer.limiter.check_key(&key)) {
+            Ok(Ok(_)) => return Box::pin(self.inner.call(request)),
+            Ok(Err(negative)) => {
+                let wait = negative.wait_time_from(DefaultClock::default().now());
+                crate::rate_limit::limited(request.id, wait)
+            }
+            Err(e) => {
+                let error = JsonRpcError::new(
+                    JsonRpcErrorReason::InternalError,
+                    e.to_string(),
+                    Value::default(),
+                );
+                JsonRpcResponse::error(request.id, error)
+            }
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// The request key extractors see for a call
+fn http_request(request: &JsonRpcExtractor) -> Request<()> {
+    let mut http = Request::new(());
+    *http.extensions_mut() = request.extensions.clone();
+    if let Some(RequestHeaders(headers)) = request.extension::<RequestHeaders>() {
+        *http.headers_mut() = (**headers).clone();
+    }
+    // tower_governor looks for the peer address in its own axum version's `ConnectInfo`
+    if let Some(ConnectInfo(addr)) = request.extension::<ConnectInfo<SocketAddr>>() {
+        http.extensions_mut().insert(*addr);
+    }
+    http.extensions_mut()
+        .insert(CallMethod(request.method.clone()));
+    http
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use tower::Layer;
+    use tower_governor::governor::GovernorConfigBuilder;
+    use tower_governor::key_extractor::GlobalKeyExtractor;
+
+    use super::{GovernorLayer, MethodKeyExtractor};
+    use crate::rate_limit::RATE_LIMITED;
+    use crate::router::JsonRpcService;
+    use crate::{
+        JrpcResult, JsonRpcAnswer, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter,
+    };
+
+    async fn ping(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "pong"))
+    }
+
+    #[tokio::test]
+    async fn limits_methods() {
+        let config = GovernorConfigBuilder::default()
+            .per_second(60)
+            .burst_size(2)
+            .key_extractor(MethodKeyExtractor(GlobalKeyExtractor))
+            .finish()
+            .unwrap();
+        let rpc = GovernorLayer::new(&config).layer(
+            JsonRpcRouter::new()
+                .method("ping", ping)
+                .method("status", ping),
+        );
+        let call = |method: &str| {
+            let request = JsonRpcRequest::builder(method).id(1).build();
+            rpc.dispatch(request.into())
+        };
+
+        for _ in 0..2 {
+            assert_eq!(call("ping").await, JsonRpcResponse::success(1, "pong"));
+        }
+        let JsonRpcAnswer::Error(error) = call("ping").await.result else {
+            panic!("not limited");
+        };
+        assert_eq!(error.code(), RATE_LIMITED);
+        let retry_after = error.data()["retry_after_ms"].as_u64().unwrap();
+        assert!(retry_after > 0 && retry_after <= 60_000, "{retry_after}");
+        assert_eq!(call("status").await, JsonRpcResponse::success(1, "pong"));
+    }
+}
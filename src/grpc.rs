@@ -0,0 +1,228 @@
+//! Bridging between tonic gRPC services and JSON-RPC, so one implementation serves both kinds
+//! of consumers.
+//!
+//! [`unary`] turns a unary gRPC method into a JSON-RPC handler, params are deserialized into
+//! the request message and the response message is serialized as the result. Messages only
+//! need serde support, e.g. generated with `prost-build`'s `type_attribute`:
+//! ```rust
+//! use std::sync::Arc;
+//! use axum_jrpc::{grpc, JsonRpcRouter};
+//! use serde::{Deserialize, Serialize};
+//! use tonic::{Request, Response, Status};
+//!
+//! #[derive(Deserialize)]
+//! struct HelloRequest { name: String }
+//! #[derive(Serialize)]
+//! struct HelloReply { message: String }
+//!
+//! struct Greeter;
+//!
+//! impl Greeter {
+//!     // as generated by tonic-build
+//!     async fn say_hello(&self, req: Request<HelloRequest>) -> Result<Response<HelloReply>, Status> {
+//!         let message = format!("Hello {}", req.into_inner().name);
+//!         Ok(Response::new(HelloReply { message }))
+//!     }
+//! }
+//!
+//! let greeter = Arc::new(Greeter);
+//! let rpc = JsonRpcRouter::new().method("say_hello", grpc::unary(move |req| {
+//!     let greeter = greeter.clone();
+//!     async move { greeter.say_hello(req).await }
+//! }));
+//! ```
+//! The other way around, [`forward`] implements a gRPC method by calling a JSON-RPC one.
+//!
+//! Errors are translated in both directions: invalid params become `INVALID_ARGUMENT`,
+//! unknown methods `UNIMPLEMENTED`, and other gRPC codes map to the server error range as
+//! `-32000 - code`, e.g. `NOT_FOUND` becomes `-32005`.
+
+use std::future::Future;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Request, Response, Status};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::{BoxFuture, JsonRpcService};
+use crate::{
+    from_slice, from_value, to_value, to_vec, Id, JrpcResult, JsonRpcAnswer, JsonRpcExtractor,
+    JsonRpcResponse, Value,
+};
+
+/// Start of the server error range gRPC codes are mapped to
+const GRPC_CODE_BASE: i32 = -32000;
+
+impl From<Status> for JsonRpcError {
+    fn from(status: Status) -> Self {
+        let reason = match status.code() {
+            Code::InvalidArgument | Code::OutOfRange => JsonRpcErrorReason::InvalidParams,
+            Code::Unimplemented => JsonRpcErrorReason::MethodNotFound,
+            Code::Internal | Code::Unknown | Code::DataLoss => JsonRpcErrorReason::InternalError,
+            code => JsonRpcErrorReason::ServerError(GRPC_CODE_BASE - code as i32),
+        };
+        let mut details = status.details().to_vec();
+        let data = match details.is_empty() {
+            true => Value::default(),
+            false => from_slice(&mut details).unwrap_or_default(),
+        };
+        JsonRpcError::new(reason, status.message().to_owned(), data)
+    }
+}
+
+impl From<JsonRpcError> for Status {
+    fn from(error: JsonRpcError) -> Self {
+        let code = match error.error_reason() {
+            JsonRpcErrorReason::ParseError
+            | JsonRpcErrorReason::InvalidRequest
+            | JsonRpcErrorReason::InvalidParams => Code::InvalidArgument,
+            JsonRpcErrorReason::MethodNotFound => Code::Unimplemented,
+            JsonRpcErrorReason::InternalError => Code::Internal,
+            JsonRpcErrorReason::ServerError(code) => match GRPC_CODE_BASE - code {
+                code @ 1..=16 => Code::from_i32(code),
+                _ => Code::Unknown,
+            },
+            JsonRpcErrorReason::ApplicationError(_) => Code::Unknown,
+        };
+        let details = match error.data() {
+            data if *data == Value::default() => Vec::new(),
+            data => to_vec(data).unwrap_or_default(),
+        };
+        Status::with_details(code, error.message(), details.into())
+    }
+}
+
+/// Exposes a unary gRPC method as a JSON-RPC handler, for [`JsonRpcRouter::method`].
+///
+/// The extensions of the JSON-RPC request are passed on to the gRPC request, its metadata
+/// is empty
+///
+/// [`JsonRpcRouter::method`]: crate::JsonRpcRouter::method
+pub fn unary<H, F, Req, Res>(
+    handler: H,
+) -> impl Fn(JsonRpcExtractor) -> BoxFuture<JrpcResult> + Clone + Send + Sync + 'static
+where
+    H: Fn(Request<Req>) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Result<Response<Res>, Status>> + Send + 'static,
+    Req: DeserializeOwned + Send + 'static,
+    Res: Serialize,
+{
+    move |mut req: JsonRpcExtractor| {
+        let handler = handler.clone();
+        Box::pin(async move {
+            let id = req.get_answer_id();
+            let extensions = std::mem::take(&mut req.extensions);
+            let message: Req = req.parse_params()?;
+
+            let request = Request::from_parts(MetadataMap::new(), extensions, message);
+            let response = handler(request)
+                .await
+                .map_err(|status| JsonRpcResponse::error(id.clone(), status.into()))?;
+            let result = to_value(response.into_inner()).map_err(|e| {
+                let error =
+                    JsonRpcError::new(JsonRpcErrorReason::InternalError, e, Value::default());
+                JsonRpcResponse::error(id.clone(), error)
+            })?;
+            Ok(JsonRpcResponse::success(id, result))
+        })
+    }
+}
+
+/// Implements a unary gRPC method by calling `method` on a JSON-RPC service.
+///
+/// The request message is serialized as params, the result deserialized into the response
+/// message. The extensions of the gRPC request are passed on, its metadata is dropped
+pub async fn forward<S, Req, Res>(
+    service: &S,
+    method: &str,
+    request: Request<Req>,
+) -> Result<Response<Res>, Status>
+where
+    S: JsonRpcService,
+    Req: Serialize,
+    Res: DeserializeOwned,
+{
+    let (_, extensions, message) = request.into_parts();
+    let params = to_value(message).map_err(Status::invalid_argument)?;
+    let mut request = JsonRpcExtractor::from(crate::JsonRpcRequest {
+        id: Id::Num(0),
+        method: method.to_owned(),
+        params,
+    });
+    request.extensions = extensions;
+
+    match service.dispatch(request).await.result {
+        JsonRpcAnswer::Result(value) => from_value(value)
+            .map(Response::new)
+            .map_err(Status::internal),
+        JsonRpcAnswer::Error(error) => Err(error.into()),
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use tonic::{Code, Request, Response, Status};
+
+    use crate::error::JsonRpcError;
+    use crate::router::JsonRpcService;
+    use crate::{JsonRpcAnswer, JsonRpcExtractor, JsonRpcRequest, JsonRpcRouter};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Account {
+        id: u32,
+    }
+
+    async fn get_account(req: Request<Account>) -> Result<Response<Account>, Status> {
+        match req.into_inner() {
+            Account { id: 0 } => Err(Status::not_found("no such account")),
+            account => Ok(Response::new(account)),
+        }
+    }
+
+    #[tokio::test]
+    async fn bridge() {
+        let rpc = JsonRpcRouter::new().method("get_account", super::unary(get_account));
+
+        let request = |id| {
+            JsonRpcExtractor::from(JsonRpcRequest {
+                id: 1.into(),
+                method: "get_account".to_owned(),
+                params: json!({ "id": id }),
+            })
+        };
+        match rpc.dispatch(request(1)).await.result {
+            JsonRpcAnswer::Result(value) => assert_eq!(value, json!({"id": 1})),
+            JsonRpcAnswer::Error(error) => panic!("{error}"),
+        }
+        match rpc.dispatch(request(0)).await.result {
+            JsonRpcAnswer::Error(error) => assert_eq!(error.code(), -32005),
+            JsonRpcAnswer::Result(value) => panic!("{value}"),
+        }
+
+        let account: Response<Account> =
+            super::forward(&rpc, "get_account", Request::new(Account { id: 2 }))
+                .await
+                .unwrap();
+        assert_eq!(account.into_inner(), Account { id: 2 });
+
+        let status =
+            super::forward::<_, _, Account>(&rpc, "get_account", Request::new(Account { id: 0 }))
+                .await
+                .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "no such account");
+
+        let status =
+            super::forward::<_, _, Account>(&rpc, "missing", Request::new(Account { id: 2 }))
+                .await
+                .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+
+        let error = JsonRpcError::from(Status::invalid_argument("bad id"));
+        assert_eq!(error.code(), -32602);
+    }
+}
@@ -0,0 +1,561 @@
+//! Idempotency keys, so a retried call replays the answer of the first one instead of taking
+//! effect twice.
+//!
+//! [`IdempotencyLayer`] wraps a service and looks for a key on the calls of the methods opted
+//! in with [`IdempotencyLayer::method`], either in the `Idempotency-Key` header or in a
+//! `_meta` object of the params:
+//! ```json
+//! {"jsonrpc": "2.0", "id": 7, "method": "transfer",
+//!     "params": {"to": "bob", "amount": 5, "_meta": {"idempotency_key": "f81d4fae"}}}
+//! ```
+//! The answer to the first call with a key is kept by a [`CacheStore`] for
+//! [`IdempotencyLayer::ttl`], and later calls of the same method with the same key get it
+//! back, with their own id, without reaching the handler. Errors are kept like results, so a
+//! retry can't take effect after the first call failed halfway. [`MemoryResultStore`] keeps
+//! up to [`CAPACITY`](MemoryResultStore::CAPACITY) answers in memory by default, which suits
+//! single instances, replicas can share the stores of [`cache`](crate::cache) instead. While
+//! the store can't take another answer, calls with a new key are answered with an
+//! [`IDEMPOTENCY_STORE_FULL`] error without reaching the handler, since their retries could
+//! not be replayed. Calls without a key are handled as usual.
+//!
+//! Keys are scoped by the caller, told apart by their IP address if axum provides
+//! [`ConnectInfo`](axum::extract::ConnectInfo), or by the key returned from
+//! [`IdempotencyLayer::key_by`], e.g. the subject of their token. Calls whose caller is
+//! unknown are handled as usual, since replaying them could leak another caller's answer. A
+//! key reused with other params is answered with an [`IDEMPOTENCY_KEY_REUSED`] error.
+//! ```rust
+//! use axum_jrpc::idempotency::{IdempotencyLayer, MemoryResultStore};
+//! use axum_jrpc::router::RequestHeaders;
+//! use axum_jrpc::{JsonRpcExtractor, JsonRpcRouter};
+//! use tower::Layer;
+//!
+//! let idempotency = IdempotencyLayer::new(MemoryResultStore::default())
+//!     .method("transfer")
+//!     .key_by(|req: &JsonRpcExtractor| {
+//!         let headers = &req.extension::<RequestHeaders>()?.0;
+//!         Some(headers.get("x-account")?.to_str().ok()?.to_owned())
+//!     });
+//! let rpc = idempotency.layer(JsonRpcRouter::new());
+//! ```
+//! A retry arriving while the first call is still running is not held back, so clients
+//! should wait for an answer, or its timeout, before retrying.
+
+use std::collections::HashSet;
+use std::convert::Infallible;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use axum::extract::ConnectInfo;
+use serde::{Deserialize, Serialize};
+use tower::{Layer, Service};
+use web_time::SystemTime;
+
+use crate::cache::CacheStore;
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::{BatchIndex, BoxFuture, RequestHeaders};
+use crate::{
+    from_value, to_value, to_vec, JsonRpcAnswer, JsonRpcExtractor, JsonRpcResponse, Value,
+};
+
+/// Error code of calls reusing the idempotency key of a call with other params
+pub const IDEMPOTENCY_KEY_REUSED: i32 = -32009;
+/// Error code of calls with a key whose answer the store has no room for
+pub const IDEMPOTENCY_STORE_FULL: i32 = -32010;
+
+/// Where the answers of calls with a key are kept, any [`CacheStore`]
+pub use crate::cache::CacheStore as ResultStore;
+/// Keeps answers in memory until they expire
+pub use crate::cache::MemoryStore as MemoryResultStore;
+
+type KeyFn = dyn Fn(&JsonRpcExtractor) -> Option<String> + Send + Sync;
+
+/// Wraps services in [`Idempotency`], see the [module docs](self).
+pub struct IdempotencyLayer<R> {
+    store: Arc<R>,
+    methods: Arc<HashSet<String>>,
+    ttl: Duration,
+    caller: Option<Arc<KeyFn>>,
+}
+
+impl<R> Clone for IdempotencyLayer<R> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            methods: self.methods.clone(),
+            ttl: self.ttl,
+            caller: self.caller.clone(),
+        }
+    }
+}
+
+impl<R> std::fmt::Debug for IdempotencyLayer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdempotencyLayer")
+            .field("methods", &self.methods)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: CacheStore> IdempotencyLayer<R> {
+    /// Time answers are kept unless [`ttl`](Self::ttl) sets another one
+    pub const TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+    pub fn new(store: R) -> Self {
+        Self {
+            store: Arc::new(store),
+            methods: Default::default(),
+            ttl: Self::TTL,
+            caller: None,
+        }
+    }
+
+    /// Accepts idempotency keys on the calls of `method`
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into());
+        self
+    }
+
+    /// Keeps answers for `ttl`
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Identifies the caller of a call with `key` instead of its IP address
+    pub fn key_by<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&JsonRpcExtractor) -> Option<String> + Send + Sync + 'static,
+    {
+        self.caller = Some(Arc::new(key));
+        self
+    }
+
+    /// Key the answer to `request` is stored under, if it carries one and its caller is known
+    fn key(&self, request: &JsonRpcExtractor) -> Option<String> {
+        if !self.methods.contains(request.method()) {
+            return None;
+        }
+        let key = match from_header(request) {
+            // the calls of a batch share its headers
+            Some(key) => match request.extension() {
+                Some(BatchIndex(index)) if *index > 0 => format!("{key}#{index}"),
+                _ => key,
+            },
+            None => from_meta(request)?,
+        };
+        let caller = match &self.caller {
+            Some(caller) => caller(request),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => request
+                .extension::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(peer)| peer.ip().to_string()),
+            // axum has no connection info without tokio
+            #[cfg(target_arch = "wasm32")]
+            None => None,
+        }?;
+        // the length keeps callers containing `:` apart
+        Some(format!(
+            "{}:{}:{caller}:{key}",
+            request.method(),
+            caller.len()
+        ))
+    }
+}
+
+fn from_header(request: &JsonRpcExtractor) -> Option<String> {
+    let headers = &request.extension::<RequestHeaders>()?.0;
+    Some(headers.get("idempotency-key")?.to_str().ok()?.to_owned())
+}
+
+fn from_meta(request: &JsonRpcExtractor) -> Option<String> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "serde_json")] {
+            let key = request.parsed.get("_meta")?.get("idempotency_key")?.as_str()?;
+        } else {
+            use simd_json::prelude::*;
+            let key = request.parsed.get("_meta")?.get_str("idempotency_key")?;
+        }
+    }
+    Some(key.to_owned())
+}
+
+/// What is stored for a key, the answer and the params it was given for
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    params: String,
+    answer: JsonRpcAnswer,
+}
+
+impl Entry {
+    fn from_answer(answer: JsonRpcAnswer) -> Option<Self> {
+        match answer {
+            JsonRpcAnswer::Result(value) => from_value(value).ok(),
+            JsonRpcAnswer::Error(_) => None,
+        }
+    }
+
+    fn into_answer(self) -> Option<JsonRpcAnswer> {
+        to_value(self).ok().map(JsonRpcAnswer::Result)
+    }
+}
+
+/// FNV-1a hash of the serialized params, stable across processes so replicas sharing a store
+/// agree on it
+fn fingerprint(params: &Value) -> String {
+    let bytes = to_vec(params).unwrap_or_default();
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+impl<R, S> Layer<S> for IdempotencyLayer<R> {
+    type Service = Idempotency<R, S>;
+
+    fn layer(&self, inner: S) -> Idempotency<R, S> {
+        Idempotency {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Replays the answers of calls with a known key, see the [module docs](self).
+pub struct Idempotency<R, S> {
+    inner: S,
+    layer: IdempotencyLayer<R>,
+}
+
+impl<R, S: Clone> Clone for Idempotency<R, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<R, S: std::fmt::Debug> std::fmt::Debug for Idempotency<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Idempotency")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<R, S> Service<JsonRpcExtractor> for Idempotency<R, S>
+where
+    R: CacheStore,
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let Some(key) = self.layer.key(&request) else {
+            return Box::pin(self.inner.call(request));
+        };
+        // the ready service is taken, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            let params = fingerprint(&request.parsed);
+            if let Some(entry) = layer.store.get(&key).await.and_then(Entry::from_answer) {
+                let result = if entry.params == params {
+                    entry.answer
+                } else {
+                    JsonRpcAnswer::Error(JsonRpcError::new(
+                        JsonRpcErrorReason::ServerError(IDEMPOTENCY_KEY_REUSED),
+                        "Idempotency key reused with other params".to_owned(),
+                        Value::default(),
+                    ))
+                };
+                return Ok(JsonRpcResponse {
+                    id: request.id,
+                    result,
+                });
+            }
+            if !layer.store.admits(&key).await {
+                let error = JsonRpcError::new(
+                    JsonRpcErrorReason::ServerError(IDEMPOTENCY_STORE_FULL),
+                    "Too many idempotency keys in use".to_owned(),
+                    Value::default(),
+                );
+                return Ok(JsonRpcResponse::error(request.id, error));
+            }
+            let response = match inner.call(request).await {
+                Ok(response) => response,
+                Err(infallible) => match infallible {},
+            };
+            let entry = Entry {
+                params,
+                answer: response.result.clone(),
+            };
+            if let Some(entry) = entry.into_answer() {
+                let expires = SystemTime::now() + layer.ttl;
+                layer.store.put(&key, entry, expires).await;
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum::Router;
+    use axum_test::{TestRequest, TestServer};
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{
+        IdempotencyLayer, MemoryResultStore, IDEMPOTENCY_KEY_REUSED, IDEMPOTENCY_STORE_FULL,
+    };
+    use crate::error::{JsonRpcError, JsonRpcErrorReason};
+    use crate::router::{self, RequestHeaders};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    /// Serves `transfer` and `other`, which answer with the number of calls they handled, and
+    /// `fail`, which answers with an error holding it. Callers are named by `x-user`
+    fn server(ttl: Duration) -> (TestServer, Arc<AtomicU64>) {
+        server_in(MemoryResultStore::default(), ttl)
+    }
+
+    /// [`server`] keeping its answers in `store`
+    fn server_in(store: MemoryResultStore, ttl: Duration) -> (TestServer, Arc<AtomicU64>) {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = calls.clone();
+        let count = move |req: JsonRpcExtractor| {
+            let n = counter.fetch_add(1, Ordering::Relaxed) + 1;
+            async move {
+                // long enough for concurrent retries to overlap
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                JrpcResult::Ok(JsonRpcResponse::success(req.get_answer_id(), n))
+            }
+        };
+        let counter = calls.clone();
+        let fail = move |req: JsonRpcExtractor| {
+            let n = counter.fetch_add(1, Ordering::Relaxed) + 1;
+            let error = JsonRpcError::new(JsonRpcErrorReason::InternalError, "no".into(), n.into());
+            async move { JrpcResult::Err(JsonRpcResponse::error(req.get_answer_id(), error)) }
+        };
+        let rpc = IdempotencyLayer::new(store)
+            .method("transfer")
+            .method("fail")
+            .ttl(ttl)
+            .key_by(|req: &JsonRpcExtractor| {
+                let headers = &req.extension::<RequestHeaders>()?.0;
+                Some(headers.get("x-user")?.to_str().ok()?.to_owned())
+            })
+            .layer(
+                JsonRpcRouter::new()
+                    .method("transfer", count.clone())
+                    .method("other", count)
+                    .method("fail", fail),
+            );
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        (server, calls)
+    }
+
+    /// A call by `alice` with `key` in the `_meta` of `params`
+    fn call(server: &TestServer, method: &str, id: u64, key: &str, params: Value) -> TestRequest {
+        let mut params = params;
+        params["_meta"] = json!({"idempotency_key": key});
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        server
+            .post("/")
+            .add_header("x-user", "alice")
+            .json(&request)
+    }
+
+    #[tokio::test]
+    async fn replays_answers() {
+        let (server, calls) = server(IdempotencyLayer::<MemoryResultStore>::TTL);
+
+        let res: Value = call(&server, "transfer", 1, "a", json!({})).await.json();
+        assert_eq!(res["result"], 1);
+        let res: Value = call(&server, "transfer", 2, "a", json!({})).await.json();
+        assert_eq!((&res["id"], &res["result"]), (&json!(2), &json!(1)));
+        let res: Value = call(&server, "transfer", 3, "b", json!({})).await.json();
+        assert_eq!(res["result"], 2);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn ignores_other_methods() {
+        let (server, _) = server(IdempotencyLayer::<MemoryResultStore>::TTL);
+
+        let res: Value = call(&server, "other", 1, "a", json!({})).await.json();
+        assert_eq!(res["result"], 1);
+        let res: Value = call(&server, "other", 2, "a", json!({})).await.json();
+        assert_eq!(res["result"], 2);
+    }
+
+    #[tokio::test]
+    async fn header_keys() {
+        let (server, _) = server(IdempotencyLayer::<MemoryResultStore>::TTL);
+        let send = |request: Value| {
+            server
+                .post("/")
+                .add_header("x-user", "alice")
+                .add_header("idempotency-key", "c")
+                .json(&request)
+        };
+
+        let single = json!({"jsonrpc": "2.0", "id": 1, "method": "transfer"});
+        let res: Value = send(single.clone()).await.json();
+        assert_eq!(res["result"], 1);
+        let res: Value = send(single).await.json();
+        assert_eq!(res["result"], 1);
+
+        // each call of a batch has its own key, the first one shares the header's
+        let batch = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "transfer"},
+            {"jsonrpc": "2.0", "id": 2, "method": "transfer", "params": {"amount": 5}},
+        ]);
+        let res: Value = send(batch.clone()).await.json();
+        assert_eq!(
+            (&res[0]["result"], &res[1]["result"]),
+            (&json!(1), &json!(2))
+        );
+        let res: Value = send(batch).await.json();
+        assert_eq!(
+            (&res[0]["result"], &res[1]["result"]),
+            (&json!(1), &json!(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn scopes_keys_by_caller() {
+        let (server, _) = server(IdempotencyLayer::<MemoryResultStore>::TTL);
+        let request = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "transfer",
+            "params": {"_meta": {"idempotency_key": "a"}},
+        });
+        let send = |user: Option<&str>| {
+            let request = server.post("/").json(&request);
+            match user {
+                Some(user) => request.add_header("x-user", user),
+                None => request,
+            }
+        };
+
+        let res: Value = send(Some("alice")).await.json();
+        assert_eq!(res["result"], 1);
+        // the same key of another caller is another call
+        let res: Value = send(Some("bob")).await.json();
+        assert_eq!(res["result"], 2);
+        let res: Value = send(Some("bob")).await.json();
+        assert_eq!(res["result"], 2);
+        // calls of unknown callers are never replayed
+        let res: Value = send(None).await.json();
+        assert_eq!(res["result"], 3);
+        let res: Value = send(None).await.json();
+        assert_eq!(res["result"], 4);
+    }
+
+    #[tokio::test]
+    async fn rejects_other_params() {
+        let (server, calls) = server(IdempotencyLayer::<MemoryResultStore>::TTL);
+
+        let res: Value = call(&server, "transfer", 1, "a", json!({"amount": 5}))
+            .await
+            .json();
+        assert_eq!(res["result"], 1);
+        let res: Value = call(&server, "transfer", 2, "a", json!({"amount": 50}))
+            .await
+            .json();
+        assert_eq!(res["id"], 2);
+        assert_eq!(res["error"]["code"], IDEMPOTENCY_KEY_REUSED);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        // the first answer is kept
+        let res: Value = call(&server, "transfer", 3, "a", json!({"amount": 5}))
+            .await
+            .json();
+        assert_eq!(res["result"], 1);
+    }
+
+    #[tokio::test]
+    async fn replays_errors() {
+        let (server, calls) = server(IdempotencyLayer::<MemoryResultStore>::TTL);
+
+        let res: Value = call(&server, "fail", 1, "a", json!({})).await.json();
+        assert_eq!(res["error"]["data"], 1);
+        let res: Value = call(&server, "fail", 2, "a", json!({})).await.json();
+        assert_eq!((&res["id"], &res["error"]["data"]), (&json!(2), &json!(1)));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn rejects_keys_once_full() {
+        let store = MemoryResultStore::with_capacity(2);
+        let (server, calls) = server_in(store, Duration::from_millis(50));
+
+        for key in ["a", "b"] {
+            let res: Value = call(&server, "transfer", 1, key, json!({})).await.json();
+            assert!(res["result"].is_u64());
+        }
+        let res: Value = call(&server, "transfer", 3, "c", json!({})).await.json();
+        assert_eq!(res["id"], 3);
+        assert_eq!(res["error"]["code"], IDEMPOTENCY_STORE_FULL);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        // the stored keys are still replayed
+        let res: Value = call(&server, "transfer", 4, "a", json!({})).await.json();
+        assert_eq!(res["result"], 1);
+
+        // expired answers make room
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let res: Value = call(&server, "transfer", 5, "c", json!({})).await.json();
+        assert_eq!(res["result"], 3);
+    }
+
+    #[tokio::test]
+    async fn expires_answers() {
+        let (server, _) = server(Duration::from_millis(50));
+
+        let res: Value = call(&server, "transfer", 1, "a", json!({})).await.json();
+        assert_eq!(res["result"], 1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let res: Value = call(&server, "transfer", 2, "a", json!({})).await.json();
+        assert_eq!(res["result"], 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_retries_are_not_held_back() {
+        let (server, calls) = server(IdempotencyLayer::<MemoryResultStore>::TTL);
+
+        let (first, second) = tokio::join!(
+            call(&server, "transfer", 1, "a", json!({})),
+            call(&server, "transfer", 2, "a", json!({})),
+        );
+        let (first, second): (Value, Value) = (first.json(), second.json());
+        // neither had an answer to replay
+        assert_ne!(first["result"], second["result"]);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        // later ones replay the answer stored last
+        let res: Value = call(&server, "transfer", 3, "a", json!({})).await.json();
+        assert!(res["result"] == first["result"] || res["result"] == second["result"]);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,150 @@
+//! [metrics](https://docs.rs/metrics) recorded by the dispatcher, for the `metrics` feature.
+//!
+//! - `jrpc_requests_total` counter, by `method`
+//! - `jrpc_errors_total` counter, by `method` and `code`
+//! - `jrpc_request_duration_seconds` histogram, by `method`
+//! - `jrpc_requests_in_flight` gauge, by `method`
+//! - `jrpc_message_bytes` histogram of message sizes, by `direction`, `request` or `response`
+//! - `jrpc_malformed_requests_total` counter of rejected messages, by `kind` and `method`,
+//!   see [`malformed`](crate::malformed)
+//!
+//! Methods not registered with any router are labeled `unknown`, so arbitrary requests can't
+//! create new series.
+
+use std::sync::Arc;
+
+use metrics::SharedString;
+use web_time::Instant;
+
+use crate::intern;
+use crate::{JsonRpcAnswer, JsonRpcResponse};
+
+/// Tracks a single call from dispatch to response
+pub(crate) struct Call {
+    method: SharedString,
+    started: Instant,
+}
+
+impl Call {
+    pub(crate) fn start(method: &Arc<str>) -> Self {
+        let method = match intern::lookup(method) {
+            Some(method) => SharedString::from(method),
+            None => SharedString::const_str("unknown"),
+        };
+        metrics::counter!("jrpc_requests_total", "method" => method.clone()).increment(1);
+        metrics::gauge!("jrpc_requests_in_flight", "method" => method.clone()).increment(1);
+        Self {
+            method,
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(self, response: &JsonRpcResponse) {
+        if let JsonRpcAnswer::Error(error) = &response.result {
+            metrics::counter!(
+                "jrpc_errors_total",
+                "method" => self.method.clone(),
+                "code" => error.code().to_string(),
+            )
+            .increment(1);
+        }
+        metrics::histogram!("jrpc_request_duration_seconds", "method" => self.method.clone())
+            .record(self.started.elapsed().as_secs_f64());
+    }
+}
+
+impl Drop for Call {
+    // also runs for cancelled calls, e.g. when the connection was closed
+    fn drop(&mut self) {
+        metrics::gauge!("jrpc_requests_in_flight", "method" => self.method.clone()).decrement(1);
+    }
+}
+
+/// Records the size of a message received or sent
+pub(crate) fn message_size(direction: &'static str, bytes: usize) {
+    metrics::histogram!("jrpc_message_bytes", "direction" => direction).record(bytes as f64);
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::body::Bytes;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    async fn ping(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "pong"))
+    }
+
+    #[tokio::test]
+    async fn recorded() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        let rpc = JsonRpcRouter::new().method("metrics_ping", ping);
+        let message = r#"[
+            {"jsonrpc": "2.0", "id": 1, "method": "metrics_ping"},
+            {"jsonrpc": "2.0", "id": 2, "method": "metrics_missing"},
+            {"jsonrpc": "1.0", "id": 3, "method": "metrics_ping"}
+        ]"#;
+        rpc.dispatch_bytes(Bytes::from(message)).await.unwrap();
+        rpc.dispatch_bytes(Bytes::from("[{")).await.unwrap();
+
+        let metrics: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels: Vec<_> = key
+                    .key()
+                    .labels()
+                    .map(|label| format!("{}={}", label.key(), label.value()))
+                    .collect();
+                (key.key().name().to_owned(), labels.join(","), value)
+            })
+            .collect();
+        let get = |name: &str, labels: &str| {
+            metrics
+                .iter()
+                .find(|(n, l, _)| n == name && l == labels)
+                .map(|(_, _, value)| value)
+        };
+
+        assert_eq!(
+            get("jrpc_requests_total", "method=metrics_ping"),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(
+            get("jrpc_requests_total", "method=unknown"),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(
+            get("jrpc_errors_total", "method=unknown,code=-32601"),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(
+            get(
+                "jrpc_malformed_requests_total",
+                "kind=version,method=metrics_ping"
+            ),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(
+            get(
+                "jrpc_malformed_requests_total",
+                "kind=syntax,method=unknown"
+            ),
+            Some(&DebugValue::Counter(1))
+        );
+        assert!(matches!(
+            get("jrpc_request_duration_seconds", "method=metrics_ping"),
+            Some(DebugValue::Histogram(values)) if values.len() == 1
+        ));
+        assert!(matches!(
+            get("jrpc_message_bytes", "direction=request"),
+            Some(DebugValue::Histogram(values)) if values.len() == 2
+        ));
+    }
+}
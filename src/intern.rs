@@ -0,0 +1,87 @@
+//! Interned method names.
+//!
+//! Routers register the names of their methods here, requests for one of them then share
+//! the registered [`Arc<str>`] instead of allocating their own copy. Names of unknown
+//! methods are not registered, so arbitrary requests can't grow the table.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+
+fn names() -> &'static RwLock<HashSet<Arc<str>>> {
+    static NAMES: OnceLock<RwLock<HashSet<Arc<str>>>> = OnceLock::new();
+    NAMES.get_or_init(Default::default)
+}
+
+/// Registers `name` and returns the shared copy
+pub(crate) fn intern(name: &str) -> Arc<str> {
+    if let Some(name) = lookup(name) {
+        return name;
+    }
+    let mut names = names().write().unwrap_or_else(|e| e.into_inner());
+    match names.get(name) {
+        Some(name) => name.clone(),
+        None => {
+            let name: Arc<str> = Arc::from(name);
+            names.insert(name.clone());
+            name
+        }
+    }
+}
+
+/// The shared copy of `name`, if it was registered
+pub(crate) fn lookup(name: &str) -> Option<Arc<str>> {
+    let names = names().read().unwrap_or_else(|e| e.into_inner());
+    names.get(name).cloned()
+}
+
+/// The shared copy of `name` if it was registered, otherwise a new one
+pub(crate) fn name(name: &str) -> Arc<str> {
+    lookup(name).unwrap_or_else(|| Arc::from(name))
+}
+
+/// A method name deserialized without allocating if it was registered
+#[derive(Debug)]
+pub(crate) struct Interned(pub(crate) Arc<str>);
+
+impl<'de> Deserialize<'de> for Interned {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NameVisitor;
+
+        impl Visitor<'_> for NameVisitor {
+            type Value = Interned;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a method name")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Interned, E> {
+                Ok(Interned(name(v)))
+            }
+        }
+
+        deserializer.deserialize_str(NameVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{intern, lookup, name};
+
+    #[test]
+    fn shared() {
+        let registered = intern("intern_test");
+        assert!(Arc::ptr_eq(&registered, &name("intern_test")));
+        assert!(Arc::ptr_eq(&registered, &intern("intern_test")));
+        assert!(lookup("intern_test_unknown").is_none());
+        name("intern_test_unknown");
+        assert!(lookup("intern_test_unknown").is_none());
+    }
+}
@@ -0,0 +1,235 @@
+//! Adapter serving a [jsonrpc-core](https://docs.rs/jsonrpc-core) `IoHandler` through this
+//! crate, for the `jsonrpc-core` feature.
+//!
+//! [`IoHandlerService`] is a [`JsonRpcService`](crate::router::JsonRpcService), so an
+//! existing paritytech-style server can move to axum before its handlers are rewritten:
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::io_handler::jsonrpc_core::{IoHandler, Params, Value};
+//! use axum_jrpc::io_handler::IoHandlerService;
+//!
+//! let mut io = IoHandler::new();
+//! io.add_sync_method("say_hello", |_: Params| Ok(Value::String("hello".into())));
+//! let app: Router = Router::new().route("/", axum_jrpc::router::post(IoHandlerService::new(io)));
+//! ```
+//! Handlers can then be moved one at a time to a [`JsonRpcRouter`](crate::JsonRpcRouter),
+//! falling back to the legacy ones:
+//! ```rust
+//! # use axum_jrpc::io_handler::jsonrpc_core::IoHandler;
+//! # use axum_jrpc::io_handler::IoHandlerService;
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//!
+//! async fn say_hello(req: JsonRpcExtractor) -> JrpcResult {
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), "hello"))
+//! }
+//!
+//! let legacy = IoHandlerService::new(IoHandler::new());
+//! let rpc = JsonRpcRouter::new()
+//!     .method("say_hello", say_hello)
+//!     .fallback(move |req| legacy.handle(req));
+//! ```
+//! Responses keep the id of the request. jsonrpc-core ids are unsigned, negative ones are
+//! passed to handlers and middlewares as `null`.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+pub use jsonrpc_core;
+use jsonrpc_core::middleware::Noop;
+use jsonrpc_core::{Call, ErrorCode, MetaIoHandler, Metadata, MethodCall, Middleware, Output};
+use tower::Service;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{to_value, Id, JrpcResult, JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Serves the methods of a jsonrpc-core handler, see the [module docs](self).
+pub struct IoHandlerService<M: Metadata = (), S: Middleware<M> = Noop> {
+    io: Arc<MetaIoHandler<M, S>>,
+    metadata: Arc<dyn Fn(&JsonRpcExtractor) -> M + Send + Sync>,
+}
+
+impl<M: Metadata, S: Middleware<M>> Clone for IoHandlerService<M, S> {
+    fn clone(&self) -> Self {
+        Self {
+            io: self.io.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+impl<M: Metadata, S: Middleware<M>> std::fmt::Debug for IoHandlerService<M, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoHandlerService").finish_non_exhaustive()
+    }
+}
+
+impl<M: Metadata + Default, S: Middleware<M>> IoHandlerService<M, S> {
+    /// Serves `io`, with default metadata for every call
+    pub fn new(io: impl Into<MetaIoHandler<M, S>>) -> Self {
+        Self::with_metadata(io, |_| M::default())
+    }
+}
+
+impl<M: Metadata, S: Middleware<M>> IoHandlerService<M, S> {
+    /// Serves `io`, with the metadata of a call built from its request, e.g. from the
+    /// extensions set by the transport
+    pub fn with_metadata<F>(io: impl Into<MetaIoHandler<M, S>>, metadata: F) -> Self
+    where
+        F: Fn(&JsonRpcExtractor) -> M + Send + Sync + 'static,
+    {
+        Self {
+            io: Arc::new(io.into()),
+            metadata: Arc::new(metadata),
+        }
+    }
+
+    /// Handles `request` with the legacy handler, as a [`JsonRpcRouter`](crate::JsonRpcRouter)
+    /// fallback would
+    pub fn handle(&self, request: JsonRpcExtractor) -> BoxFuture<JrpcResult> {
+        let answer_id = request.get_answer_id();
+        let meta = (self.metadata)(&request);
+        let call = match call(request) {
+            Ok(call) => call,
+            Err(error) => {
+                return Box::pin(async move { Err(JsonRpcResponse::error(answer_id, error)) })
+            }
+        };
+        let output = self.io.handle_call(call, meta);
+        Box::pin(async move {
+            match output.await {
+                Some(Output::Success(success)) => match to_value(success.result) {
+                    Ok(result) => Ok(JsonRpcResponse::success(answer_id, result)),
+                    Err(e) => Err(JsonRpcResponse::error(
+                        answer_id,
+                        JsonRpcError::new(JsonRpcErrorReason::InternalError, e, Value::default()),
+                    )),
+                },
+                Some(Output::Failure(failure)) => {
+                    Err(JsonRpcResponse::error(answer_id, error(failure.error)))
+                }
+                // notifications get no output
+                None => Ok(JsonRpcResponse::success(answer_id, Value::default())),
+            }
+        })
+    }
+}
+
+impl<M: Metadata, S: Middleware<M>> Service<JsonRpcExtractor> for IoHandlerService<M, S> {
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let response = self.handle(request);
+        Box::pin(async move {
+            match response.await {
+                Ok(response) | Err(response) => Ok(response),
+            }
+        })
+    }
+}
+
+fn call(request: JsonRpcExtractor) -> Result<Call, JsonRpcError> {
+    let params = match request.parsed {
+        params if params == Value::default() => jsonrpc_core::Params::None,
+        params => serde_json::to_value(params)
+            .and_then(serde_json::from_value)
+            .map_err(|e| {
+                JsonRpcError::new(
+                    JsonRpcErrorReason::InvalidParams,
+                    e.to_string(),
+                    Value::default(),
+                )
+            })?,
+    };
+    let method = request.method.to_string();
+    let id = match request.id {
+        Id::None(()) => {
+            return Ok(Call::Notification(jsonrpc_core::Notification {
+                jsonrpc: Some(jsonrpc_core::Version::V2),
+                method,
+                params,
+            }))
+        }
+        Id::Num(id) => u64::try_from(id).map_or(jsonrpc_core::Id::Null, jsonrpc_core::Id::Num),
+        Id::Str(id) => jsonrpc_core::Id::Str(id),
+    };
+    Ok(Call::MethodCall(MethodCall {
+        jsonrpc: Some(jsonrpc_core::Version::V2),
+        method,
+        params,
+        id,
+    }))
+}
+
+fn error(error: jsonrpc_core::Error) -> JsonRpcError {
+    let reason = match error.code {
+        ErrorCode::ParseError => JsonRpcErrorReason::ParseError,
+        ErrorCode::InvalidRequest => JsonRpcErrorReason::InvalidRequest,
+        ErrorCode::MethodNotFound => JsonRpcErrorReason::MethodNotFound,
+        ErrorCode::InvalidParams => JsonRpcErrorReason::InvalidParams,
+        ErrorCode::InternalError => JsonRpcErrorReason::InternalError,
+        ErrorCode::ServerError(code) => JsonRpcErrorReason::ApplicationError(code as i32),
+    };
+    let data = error.data.and_then(|data| to_value(data).ok());
+    JsonRpcError::new(reason, error.message, data.unwrap_or_default())
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use jsonrpc_core::{IoHandler, Params, Value};
+    use serde_json::json;
+
+    use super::IoHandlerService;
+    use crate::router::JsonRpcService;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    async fn rewritten(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "new"))
+    }
+
+    #[tokio::test]
+    async fn serves_legacy_methods() {
+        let mut io = IoHandler::new();
+        io.add_sync_method("add", |params: Params| {
+            let [a, b]: [i64; 2] = params.parse()?;
+            Ok(Value::from(a + b))
+        });
+        io.add_sync_method("rewritten", |_| Ok(Value::from("old")));
+        let legacy = IoHandlerService::new(io);
+        let rpc = JsonRpcRouter::new()
+            .method("rewritten", rewritten)
+            .fallback(move |req| legacy.handle(req));
+
+        let call = |method: &str, params| {
+            let request = JsonRpcRequest::builder(method)
+                .params(params)
+                .unwrap()
+                .id(-5)
+                .build();
+            rpc.dispatch(request.into())
+        };
+        assert_eq!(
+            call("add", json!([1, 2])).await,
+            JsonRpcResponse::success(-5, 3)
+        );
+        assert_eq!(
+            call("rewritten", json!([])).await,
+            JsonRpcResponse::success(-5, "new")
+        );
+
+        let response = serde_json::to_value(call("add", json!({"a": 1})).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        let response = serde_json::to_value(call("missing", json!(1)).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        let response = serde_json::to_value(call("missing", json!([])).await).unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+    }
+}
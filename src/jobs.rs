@@ -0,0 +1,445 @@
+//! Long running calls as jobs, for methods outlasting HTTP timeouts and the `jobs` feature.
+//!
+//! A handler registered with [`Jobs::submit`] is spawned in the background, and its call is
+//! answered at once with the id of the job, `{"job_id": "..."}`. Clients then follow it with
+//! the `rpc.job.*` methods, all taking the id as `job_id`:
+//! * [`JOB_STATUS_METHOD`] answers with the state of the job, its latest progress, and its
+//!   result or error once it has finished
+//! * [`JOB_WAIT_METHOD`] answers the same once the job has finished, or after `timeout_ms`
+//! * [`JOB_CANCEL_METHOD`] aborts the job, answering whether it was still running
+//! ```rust
+//! use axum_jrpc::jobs::{JobProgress, Jobs};
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//!
+//! async fn export(req: JsonRpcExtractor, progress: JobProgress) -> JrpcResult {
+//!     for page in 0..10 {
+//!         // ...
+//!         progress.report(page * 10).await;
+//!     }
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), "s3://exports/1.csv"))
+//! }
+//!
+//! let jobs = Jobs::new();
+//! let rpc = jobs.register(JsonRpcRouter::new().method("export", jobs.submit(export)));
+//! ```
+//! A status looks like
+//! ```json
+//! {"job_id": "9f2c41d07ab3e655", "state": "done", "progress": 90, "result": "s3://exports/1.csv"}
+//! ```
+//! Jobs are kept by a [`JobStore`] until an hour after they finished, [`MemoryJobStore`] keeps
+//! them in memory. With a shared store any instance answers about any job, but only the one
+//! running a job can cancel it. Knowing the id of a job is all it takes to follow or cancel
+//! it, so the `rpc.job.*` methods need the same authorization as the methods submitting jobs.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tokio::task::AbortHandle;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{
+    random_id, to_value, JrpcResult, JsonRpcAnswer, JsonRpcExtractor, JsonRpcResponse, Value,
+};
+
+/// Method answering with the status of a job
+pub const JOB_STATUS_METHOD: &str = "rpc.job.status";
+
+/// Method answering with the status of a job once it has finished
+pub const JOB_WAIT_METHOD: &str = "rpc.job.wait";
+
+/// Method cancelling a job
+pub const JOB_CANCEL_METHOD: &str = "rpc.job.cancel";
+
+/// Time finished jobs are kept for
+const TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Longest [`JOB_WAIT_METHOD`] waits, and the default when no `timeout_ms` is given
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// A job as kept by a [`JobStore`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub state: JobState,
+    /// Latest value passed to [`JobProgress::report`], `null` until then
+    #[serde(default)]
+    pub progress: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+/// Where jobs are kept.
+#[async_trait::async_trait]
+pub trait JobStore: Send + Sync + 'static {
+    /// Stores `job` under `id`, it may be forgotten at `expires`
+    async fn put(&self, id: &str, job: Job, expires: SystemTime) -> Result<(), String>;
+
+    /// The job stored under `id`, unless it expired
+    async fn get(&self, id: &str) -> Result<Option<Job>, String>;
+}
+
+/// Keeps jobs in memory until they expire.
+#[derive(Debug, Default)]
+pub struct MemoryJobStore {
+    jobs: Mutex<HashMap<String, (Job, SystemTime)>>,
+}
+
+#[async_trait::async_trait]
+impl JobStore for MemoryJobStore {
+    async fn put(&self, id: &str, job: Job, expires: SystemTime) -> Result<(), String> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if jobs.len() >= 1024 && jobs.len().is_power_of_two() {
+            let now = SystemTime::now();
+            jobs.retain(|_, (_, expires)| *expires > now);
+        }
+        jobs.insert(id.to_owned(), (job, expires));
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Job>, String> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(jobs
+            .get(id)
+            .filter(|(_, expires)| *expires > SystemTime::now())
+            .map(|(job, _)| job.clone()))
+    }
+}
+
+/// Jobs submitted to this instance, and the store they are kept in.
+#[derive(Clone)]
+pub struct Jobs {
+    store: Arc<dyn JobStore>,
+    running: Arc<Mutex<HashMap<String, AbortHandle>>>,
+}
+
+impl std::fmt::Debug for Jobs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("Jobs")
+            .field("running", &running.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Jobs {
+    /// Jobs kept in a [`MemoryJobStore`]
+    pub fn new() -> Self {
+        Self::with_store(MemoryJobStore::default())
+    }
+
+    pub fn with_store(store: impl JobStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            running: Default::default(),
+        }
+    }
+
+    /// Registers the `rpc.job.*` methods on `router`
+    pub fn register(&self, router: crate::JsonRpcRouter) -> crate::JsonRpcRouter {
+        let jobs = self.clone();
+        let status = move |req: JsonRpcExtractor| jobs.clone().status(req, Duration::ZERO);
+        let jobs = self.clone();
+        let wait = move |req: JsonRpcExtractor| jobs.clone().status(req, MAX_WAIT);
+        let jobs = self.clone();
+        let cancel = move |req: JsonRpcExtractor| jobs.clone().cancel(req);
+        router
+            .method(JOB_STATUS_METHOD, status)
+            .method(JOB_WAIT_METHOD, wait)
+            .method(JOB_CANCEL_METHOD, cancel)
+    }
+
+    /// A handler running `handler` as a job, for
+    /// [`JsonRpcRouter::method`](crate::JsonRpcRouter::method)
+    pub fn submit<H, F>(
+        &self,
+        handler: H,
+    ) -> impl Fn(JsonRpcExtractor) -> BoxFuture<JrpcResult> + Clone + Send + Sync + 'static
+    where
+        H: Fn(JsonRpcExtractor, JobProgress) -> F + Send + Sync + 'static,
+        F: Future<Output = JrpcResult> + Send + 'static,
+    {
+        let jobs = self.clone();
+        let handler = Arc::new(handler);
+        move |req: JsonRpcExtractor| {
+            let jobs = jobs.clone();
+            let handler = handler.clone();
+            Box::pin(async move {
+                let id = random_id();
+                let running = Job {
+                    state: JobState::Running,
+                    progress: Value::default(),
+                    result: None,
+                    error: None,
+                };
+                if let Err(e) = jobs.store.put(&id, running, far_future()).await {
+                    let error = JsonRpcError::new(
+                        JsonRpcErrorReason::InternalError,
+                        format!("Failed to store the job: {e}"),
+                        Value::default(),
+                    );
+                    return Err(JsonRpcResponse::error(req.get_answer_id(), error));
+                }
+
+                let answer_id = req.get_answer_id();
+                let progress = JobProgress {
+                    id: id.clone(),
+                    store: jobs.store.clone(),
+                };
+                // the handle is registered before the job can finish and remove it
+                let mut running = jobs.running.lock().unwrap_or_else(|e| e.into_inner());
+                let task = tokio::spawn(run(jobs.clone(), id.clone(), handler(req, progress)));
+                running.insert(id.clone(), task.abort_handle());
+                drop(running);
+
+                #[derive(Serialize)]
+                struct Submitted {
+                    job_id: String,
+                }
+                Ok(JsonRpcResponse::success(
+                    answer_id,
+                    Submitted { job_id: id },
+                ))
+            })
+        }
+    }
+
+    async fn status(self, req: JsonRpcExtractor, max_wait: Duration) -> JrpcResult {
+        #[derive(Deserialize)]
+        struct Params {
+            job_id: String,
+            timeout_ms: Option<u64>,
+        }
+
+        let id = req.get_answer_id();
+        let params: Params = req.parse_params()?;
+        let wait = params
+            .timeout_ms
+            .map_or(max_wait, Duration::from_millis)
+            .min(max_wait);
+        let deadline = tokio::time::Instant::now() + wait;
+        let mut interval = Duration::from_millis(50);
+        loop {
+            let job = match self.store.get(&params.job_id).await {
+                Ok(Some(job)) => job,
+                Ok(None) => return Err(unknown_job(id)),
+                Err(e) => return Err(store_failed(id, e)),
+            };
+            let now = tokio::time::Instant::now();
+            if job.state != JobState::Running || now >= deadline {
+                #[derive(Serialize)]
+                struct Status {
+                    job_id: String,
+                    #[serde(flatten)]
+                    job: Job,
+                }
+                let status = Status {
+                    job_id: params.job_id,
+                    job,
+                };
+                return Ok(JsonRpcResponse::success(id, status));
+            }
+            // the store may be shared, so it is polled rather than waiting for a local job
+            tokio::time::sleep_until(deadline.min(now + interval)).await;
+            interval = (interval * 2).min(Duration::from_secs(1));
+        }
+    }
+
+    async fn cancel(self, req: JsonRpcExtractor) -> JrpcResult {
+        #[derive(Deserialize)]
+        struct Params {
+            job_id: String,
+        }
+
+        let id = req.get_answer_id();
+        let params: Params = req.parse_params()?;
+        let task = {
+            let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+            running.remove(&params.job_id)
+        };
+        let Some(task) = task else {
+            return match self.store.get(&params.job_id).await {
+                Ok(Some(_)) => Ok(JsonRpcResponse::success(id, false)),
+                Ok(None) => Err(unknown_job(id)),
+                Err(e) => Err(store_failed(id, e)),
+            };
+        };
+        task.abort();
+        let job = Job {
+            state: JobState::Cancelled,
+            progress: Value::default(),
+            result: None,
+            error: None,
+        };
+        let expires = SystemTime::now() + TTL;
+        if let Err(e) = self.store.put(&params.job_id, job, expires).await {
+            return Err(store_failed(id, e));
+        }
+        Ok(JsonRpcResponse::success(id, true))
+    }
+}
+
+/// Runs a job and stores its answer
+async fn run(jobs: Jobs, id: String, job: impl Future<Output = JrpcResult>) {
+    let response = job.await.unwrap_or_else(|e| e);
+    jobs.running
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&id);
+    let progress = match jobs.store.get(&id).await {
+        Ok(Some(job)) => job.progress,
+        _ => Value::default(),
+    };
+    let job = match response.result {
+        JsonRpcAnswer::Result(result) => Job {
+            state: JobState::Done,
+            progress,
+            result: Some(result),
+            error: None,
+        },
+        JsonRpcAnswer::Error(error) => Job {
+            state: JobState::Failed,
+            progress,
+            result: None,
+            error: Some(error),
+        },
+    };
+    if let Err(_e) = jobs.store.put(&id, job, SystemTime::now() + TTL).await {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(job = id, error = %_e, "failed to store the result of a job");
+    }
+}
+
+/// Reports the progress of a job, see [`Jobs::submit`].
+#[derive(Clone)]
+pub struct JobProgress {
+    id: String,
+    store: Arc<dyn JobStore>,
+}
+
+impl std::fmt::Debug for JobProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobProgress")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JobProgress {
+    /// Id of the job
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Sets the progress clients see, e.g. a percentage or the items processed so far
+    pub async fn report(&self, progress: impl Serialize) {
+        let job = Job {
+            state: JobState::Running,
+            progress: to_value(progress).unwrap_or_default(),
+            result: None,
+            error: None,
+        };
+        if let Err(_e) = self.store.put(&self.id, job, far_future()).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(job = self.id, error = %_e, "failed to store the progress of a job");
+        }
+    }
+}
+
+/// Expiry of running jobs, which are kept until they finish
+fn far_future() -> SystemTime {
+    SystemTime::now() + 24 * TTL
+}
+
+fn unknown_job(id: crate::Id) -> JsonRpcResponse {
+    let error = JsonRpcError::new(
+        JsonRpcErrorReason::InvalidParams,
+        "Unknown job".to_owned(),
+        Value::default(),
+    );
+    JsonRpcResponse::error(id, error)
+}
+
+fn store_failed(id: crate::Id, e: String) -> JsonRpcResponse {
+    let error = JsonRpcError::new(
+        JsonRpcErrorReason::InternalError,
+        format!("Failed to read the job: {e}"),
+        Value::default(),
+    );
+    JsonRpcResponse::error(id, error)
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use super::{JobProgress, Jobs};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    async fn export(req: JsonRpcExtractor, progress: JobProgress) -> JrpcResult {
+        let pages: u64 = req.clone().parse_params()?;
+        for page in 1..=pages {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            progress.report(page).await;
+        }
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "exported"))
+    }
+
+    #[tokio::test]
+    async fn jobs() {
+        let jobs = Jobs::new();
+        let rpc = jobs.register(JsonRpcRouter::new().method("export", jobs.submit(export)));
+        let call = |method: &str, params: Value| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+            let rpc = rpc.clone();
+            async move {
+                let reply = rpc.dispatch_bytes(request.to_string().into()).await;
+                serde_json::from_slice::<Value>(&reply.unwrap()).unwrap()
+            }
+        };
+
+        let res = call("export", json!(3)).await;
+        let job_id = res["result"]["job_id"].as_str().unwrap().to_owned();
+        let res = call("rpc.job.status", json!({"job_id": job_id})).await;
+        assert_eq!(res["result"]["state"], "running");
+        let res = call("rpc.job.wait", json!({"job_id": job_id})).await;
+        assert_eq!(
+            res["result"],
+            json!({"job_id": job_id, "state": "done", "progress": 3, "result": "exported"})
+        );
+        let res = call("rpc.job.cancel", json!({"job_id": job_id})).await;
+        assert_eq!(res["result"], false);
+
+        let res = call("export", json!(1000)).await;
+        let job_id = res["result"]["job_id"].as_str().unwrap().to_owned();
+        let res = call("rpc.job.cancel", json!({"job_id": job_id})).await;
+        assert_eq!(res["result"], true);
+        let res = call("rpc.job.status", json!({"job_id": job_id})).await;
+        assert_eq!(res["result"]["state"], "cancelled");
+
+        let res = call("rpc.job.status", json!({"job_id": "nope"})).await;
+        assert_eq!(res["error"]["message"], "Unknown job");
+    }
+}
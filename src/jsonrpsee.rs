@@ -0,0 +1,224 @@
+//! Conversions between the types of this crate and those of
+//! [jsonrpsee](https://docs.rs/jsonrpsee-types), for the `jsonrpsee` feature.
+//!
+//! They let a service migrate from jsonrpsee one handler or middleware at a time:
+//! * [`Id`] and [`jsonrpsee_types::Id`], with [`TryFrom`] as jsonrpsee ids are unsigned
+//! * [`JsonRpcRequest`] and [`jsonrpsee_types::Request`]
+//! * [`JsonRpcResponse`] and [`jsonrpsee_types::Response`]
+//! * [`JsonRpcError`] and [`jsonrpsee_types::ErrorObject`]
+//!
+//! ```rust
+//! use axum_jrpc::jsonrpsee::jsonrpsee_types::{ErrorObjectOwned, Request};
+//! use axum_jrpc::JsonRpcRequest;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let request: Request = serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}"#)?;
+//! let request = JsonRpcRequest::try_from(request)?;
+//! assert_eq!(request.method, "add");
+//!
+//! let error: axum_jrpc::error::JsonRpcError = ErrorObjectOwned::owned(-32000, "busy", None::<()>).into();
+//! assert_eq!(error.code(), -32000);
+//! # Ok(())
+//! # }
+//! ```
+//! A notification, with [`Id::None`], maps to a request with a `null` id, as jsonrpsee
+//! requests always have one.
+
+use std::borrow::Cow;
+
+pub use jsonrpsee_types;
+use jsonrpsee_types::{ErrorObject, ErrorObjectOwned, ResponsePayload, TwoPointZero};
+use serde_json::value::RawValue;
+use thiserror::Error;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{from_bytes, Id, JsonRpcAnswer, JsonRpcRequest, JsonRpcResponse, Value};
+
+/// A value without an equivalent on the other side
+#[derive(Debug, Error)]
+pub enum ConversionError {
+    #[error("id {0} is out of range")]
+    IdOutOfRange(String),
+    #[error("invalid json: {0}")]
+    Json(String),
+}
+
+impl TryFrom<jsonrpsee_types::Id<'_>> for Id {
+    type Error = ConversionError;
+
+    fn try_from(id: jsonrpsee_types::Id<'_>) -> Result<Self, ConversionError> {
+        Ok(match id {
+            jsonrpsee_types::Id::Null => Id::None(()),
+            jsonrpsee_types::Id::Number(id) => match i64::try_from(id) {
+                Ok(id) => Id::Num(id),
+                Err(_) => return Err(ConversionError::IdOutOfRange(id.to_string())),
+            },
+            jsonrpsee_types::Id::Str(id) => Id::Str(id.into_owned()),
+        })
+    }
+}
+
+impl TryFrom<Id> for jsonrpsee_types::Id<'static> {
+    type Error = ConversionError;
+
+    fn try_from(id: Id) -> Result<Self, ConversionError> {
+        Ok(match id {
+            Id::None(()) => jsonrpsee_types::Id::Null,
+            Id::Num(id) => match u64::try_from(id) {
+                Ok(id) => jsonrpsee_types::Id::Number(id),
+                Err(_) => return Err(ConversionError::IdOutOfRange(id.to_string())),
+            },
+            Id::Str(id) => jsonrpsee_types::Id::Str(Cow::Owned(id)),
+        })
+    }
+}
+
+impl TryFrom<jsonrpsee_types::Request<'_>> for JsonRpcRequest {
+    type Error = ConversionError;
+
+    fn try_from(request: jsonrpsee_types::Request<'_>) -> Result<Self, ConversionError> {
+        Ok(JsonRpcRequest {
+            id: request.id.try_into()?,
+            method: request.method.into_owned(),
+            params: request
+                .params
+                .as_deref()
+                .map(value)
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl TryFrom<JsonRpcRequest> for jsonrpsee_types::Request<'static> {
+    type Error = ConversionError;
+
+    fn try_from(request: JsonRpcRequest) -> Result<Self, ConversionError> {
+        let params = match request.params {
+            params if params == Value::default() => None,
+            params => Some(Cow::Owned(raw(&params)?)),
+        };
+        Ok(jsonrpsee_types::Request {
+            jsonrpc: TwoPointZero,
+            id: request.id.try_into()?,
+            method: Cow::Owned(request.method),
+            params,
+            extensions: Default::default(),
+        })
+    }
+}
+
+impl From<ErrorObject<'_>> for JsonRpcError {
+    fn from(error: ErrorObject<'_>) -> Self {
+        // unparsable data can't come from jsonrpsee, which only holds valid json
+        let data = error.data().and_then(|data| value(data).ok());
+        JsonRpcError::new(
+            JsonRpcErrorReason::ApplicationError(error.code()),
+            error.message().to_owned(),
+            data.unwrap_or_default(),
+        )
+    }
+}
+
+impl From<JsonRpcError> for ErrorObjectOwned {
+    fn from(error: JsonRpcError) -> Self {
+        let data = match error.data() {
+            data if *data == Value::default() => None,
+            data => Some(data),
+        };
+        ErrorObject::owned(error.code(), error.message(), data)
+    }
+}
+
+impl<T: serde::Serialize + Clone> TryFrom<jsonrpsee_types::Response<'_, T>> for JsonRpcResponse {
+    type Error = ConversionError;
+
+    fn try_from(response: jsonrpsee_types::Response<'_, T>) -> Result<Self, ConversionError> {
+        let result = match response.payload {
+            ResponsePayload::Success(result) => {
+                JsonRpcAnswer::Result(crate::to_value(&*result).map_err(ConversionError::Json)?)
+            }
+            ResponsePayload::Error(error) => JsonRpcAnswer::Error(error.into()),
+        };
+        Ok(JsonRpcResponse {
+            id: response.id.try_into()?,
+            result,
+        })
+    }
+}
+
+impl TryFrom<JsonRpcResponse> for jsonrpsee_types::Response<'static, Value> {
+    type Error = ConversionError;
+
+    fn try_from(response: JsonRpcResponse) -> Result<Self, ConversionError> {
+        let payload = match response.result {
+            JsonRpcAnswer::Result(result) => ResponsePayload::success(result),
+            JsonRpcAnswer::Error(error) => ResponsePayload::error(error),
+        };
+        Ok(jsonrpsee_types::Response::new(
+            payload,
+            response.id.try_into()?,
+        ))
+    }
+}
+
+fn value(raw: &RawValue) -> Result<Value, ConversionError> {
+    from_bytes(raw.get().as_bytes()).map_err(ConversionError::Json)
+}
+
+fn raw(value: &Value) -> Result<Box<RawValue>, ConversionError> {
+    serde_json::value::to_raw_value(value).map_err(|e| ConversionError::Json(e.to_string()))
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use jsonrpsee_types::{ErrorObjectOwned, Request, Response, ResponsePayload};
+    use serde_json::{json, Value};
+
+    use crate::error::{JsonRpcError, JsonRpcErrorReason};
+    use crate::{Id, JsonRpcRequest, JsonRpcResponse};
+
+    #[test]
+    fn converts() {
+        let json = r#"{"jsonrpc":"2.0","id":"a","method":"add","params":{"a":1}}"#;
+        let request: Request = serde_json::from_str(json).unwrap();
+        let request = JsonRpcRequest::try_from(request).unwrap();
+        assert_eq!(request.id, Id::Str("a".to_owned()));
+        assert_eq!(request.params, json!({"a": 1}));
+        let request = Request::try_from(request).unwrap();
+        assert_eq!(serde_json::to_string(&request).unwrap(), json);
+
+        let notification = JsonRpcRequest::builder("ping").notification();
+        let request = Request::try_from(notification).unwrap();
+        assert_eq!(request.id, jsonrpsee_types::Id::Null);
+        assert!(request.params.is_none());
+        assert!(Id::try_from(jsonrpsee_types::Id::Number(u64::MAX)).is_err());
+        assert!(jsonrpsee_types::Id::try_from(Id::Num(-1)).is_err());
+
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::InvalidParams,
+            "bad".to_owned(),
+            json!([1]),
+        );
+        let response = Response::try_from(JsonRpcResponse::error(7, error.clone())).unwrap();
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"jsonrpc": "2.0", "error": {"code": -32602, "message": "bad", "data": [1]}, "id": 7})
+        );
+        let response = Response::new(
+            ResponsePayload::<Value>::error(ErrorObjectOwned::from(error.clone())),
+            jsonrpsee_types::Id::Number(7),
+        );
+        assert_eq!(
+            JsonRpcResponse::try_from(response).unwrap(),
+            JsonRpcResponse::error(7, error)
+        );
+
+        let response = Response::new(ResponsePayload::success(3), jsonrpsee_types::Id::Number(1));
+        assert_eq!(
+            JsonRpcResponse::try_from(response).unwrap(),
+            JsonRpcResponse::success(1, 3)
+        );
+    }
+}
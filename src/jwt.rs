@@ -0,0 +1,401 @@
+//! Bearer token authentication with [JWTs](https://jwt.io/), for the `jwt` feature.
+//!
+//! [`JwtAuth`] is an [`Authenticator`] verifying the token of the `Authorization: Bearer`
+//! header with a fixed key, or with the keys of a [JWKS](https://datatracker.ietf.org/doc/html/rfc7517)
+//! endpoint. Any algorithm supported by [`jsonwebtoken`] can be used, the accepted ones,
+//! audiences and issuers are set on the [`Validation`]. The validated claims become the
+//! principal of the call, handlers read them with `req.extension::<Claims>()` and
+//! [`AuthorizeLayer`](crate::auth::AuthorizeLayer) checks their scopes.
+//! ```rust
+//! use axum_jrpc::auth::{AuthLayer, AuthorizeLayer};
+//! use axum_jrpc::jwt::{Algorithm, Claims, JwtAuth, Validation};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let mut validation = Validation::new(Algorithm::RS256);
+//! validation.set_audience(&["wallet-api"]);
+//! validation.set_issuer(&["https://auth.example.com/"]);
+//! let jwt = JwtAuth::<Claims>::jwks("https://auth.example.com/.well-known/jwks.json", validation);
+//!
+//! let rpc = AuthorizeLayer::<Claims>::new()
+//!     .require("wallet_send", ["wallet:write"])
+//!     .layer(JsonRpcRouter::new());
+//! let rpc = AuthLayer::new(jwt).layer(rpc);
+//! ```
+//! Keys of a JWKS endpoint are fetched on first use and again after
+//! [`JwtAuth::REFRESH_INTERVAL`], or when a token names an unknown key id, at most once per
+//! [`JwtAuth::MIN_REFRESH_INTERVAL`]. One call fetches them while the others needing them
+//! wait for it, calls with a cached key aren't held up, and a fetch taking longer than
+//! [`JwtAuth::FETCH_TIMEOUT`] fails.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use axum::http::header;
+use jsonwebtoken::jwk::JwkSet;
+pub use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::auth::{Authenticator, Principal, Unauthorized};
+use crate::router::RequestHeaders;
+use crate::{JsonRpcExtractor, Value};
+
+/// Registered claims, the scopes and roles checked by [`Principal::has_scope`], and all the
+/// others in `extra`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Claims {
+    pub sub: Option<String>,
+    pub iss: Option<String>,
+    pub exp: Option<u64>,
+    /// Space separated scopes, as issued by OAuth 2.0 servers
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Principal for Claims {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split(' ').any(|s| s == scope) || self.roles.iter().any(|role| role == scope)
+    }
+}
+
+enum Keys {
+    Static(DecodingKey),
+    Jwks {
+        url: String,
+        client: reqwest::Client,
+        timeout: Duration,
+        cache: RwLock<KeyCache>,
+        /// Held while fetching, so one call fetches for all
+        refresh: Mutex<()>,
+    },
+}
+
+#[derive(Default)]
+struct KeyCache {
+    keys: HashMap<String, DecodingKey>,
+    fetched: Option<Instant>,
+    /// Last fetch, successful or not
+    attempted: Option<Instant>,
+}
+
+/// Verifies bearer tokens, see the [module docs](self).
+pub struct JwtAuth<C = Claims> {
+    keys: Keys,
+    validation: Validation,
+    claims: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<C> std::fmt::Debug for JwtAuth<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("JwtAuth");
+        if let Keys::Jwks { url, .. } = &self.keys {
+            debug.field("jwks", url);
+        }
+        debug
+            .field("validation", &self.validation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C> JwtAuth<C> {
+    /// Longest the keys of a JWKS endpoint are used before fetching them again
+    pub const REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+
+    /// Shortest time between two fetches of a JWKS endpoint
+    pub const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Longest a fetch of a JWKS endpoint may take unless [`timeout`](Self::timeout) sets
+    /// another limit
+    pub const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Verifies tokens signed with `key`
+    pub fn new(key: DecodingKey, validation: Validation) -> Self {
+        Self {
+            keys: Keys::Static(key),
+            validation,
+            claims: std::marker::PhantomData,
+        }
+    }
+
+    /// Verifies tokens signed with a key of the JWKS served at `url`, selected by key id
+    pub fn jwks(url: impl Into<String>, validation: Validation) -> Self {
+        Self {
+            keys: Keys::Jwks {
+                url: url.into(),
+                client: reqwest::Client::new(),
+                timeout: Self::FETCH_TIMEOUT,
+                cache: Default::default(),
+                refresh: Mutex::new(()),
+            },
+            validation,
+            claims: std::marker::PhantomData,
+        }
+    }
+
+    /// Gives up fetching the JWKS after `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        if let Keys::Jwks { timeout: t, .. } = &mut self.keys {
+            *t = timeout;
+        }
+        self
+    }
+
+    async fn key(&self, kid: Option<&str>) -> Result<DecodingKey, Unauthorized> {
+        let (url, client, timeout, cache, refresh) = match &self.keys {
+            Keys::Static(key) => return Ok(key.clone()),
+            Keys::Jwks {
+                url,
+                client,
+                timeout,
+                cache,
+                refresh,
+            } => (url, client, *timeout, cache, refresh),
+        };
+        let kid = kid.ok_or_else(|| Unauthorized::new("Missing key id"))?;
+        let within = |at: Option<Instant>, interval| at.is_some_and(|at| at.elapsed() < interval);
+        let cached = || {
+            let cache = cache.read().unwrap_or_else(|e| e.into_inner());
+            let key = cache.keys.get(kid).cloned();
+            (key, cache.fetched, cache.attempted)
+        };
+
+        if let (Some(key), fetched, _) = cached() {
+            if within(fetched, Self::REFRESH_INTERVAL) {
+                return Ok(key);
+            }
+        }
+        let _refresh = refresh.lock().await;
+        // another call may have fetched the keys, or failed to, while this one waited
+        let (key, _, attempted) = cached();
+        if within(attempted, Self::MIN_REFRESH_INTERVAL) {
+            return key.ok_or_else(|| Unauthorized::new("Unknown key id"));
+        }
+        let keys = fetch(client, url, timeout).await;
+        let mut cache = cache.write().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        cache.attempted = Some(now);
+        match keys {
+            Ok(keys) => {
+                cache.keys = keys;
+                cache.fetched = Some(now);
+            }
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(url, error = %_e, "failed to fetch JWKS");
+            }
+        }
+        cache
+            .keys
+            .get(kid)
+            .cloned()
+            .ok_or_else(|| Unauthorized::new("Unknown key id"))
+    }
+}
+
+/// Keys of the JWKS at `url` by key id, keys without one or of unsupported types are skipped
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    timeout: Duration,
+) -> Result<HashMap<String, DecodingKey>, String> {
+    let response = client
+        .get(url)
+        .timeout(timeout)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    let jwks: JwkSet = crate::from_bytes(&bytes)?;
+    Ok(jwks
+        .keys
+        .iter()
+        .filter_map(|jwk| {
+            let kid = jwk.common.key_id.clone()?;
+            Some((kid, DecodingKey::from_jwk(jwk).ok()?))
+        })
+        .collect())
+}
+
+#[async_trait::async_trait]
+impl<C> Authenticator for JwtAuth<C>
+where
+    C: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    type Principal = C;
+
+    async fn authenticate(&self, request: &JsonRpcExtractor) -> Result<C, Unauthorized> {
+        let token = request
+            .extension::<RequestHeaders>()
+            .and_then(|headers| headers.0.get(header::AUTHORIZATION)?.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Unauthorized::new("Missing bearer token"))?;
+        let invalid =
+            |e: jsonwebtoken::errors::Error| Unauthorized::new(format!("Invalid token: {e}"));
+
+        let header = jsonwebtoken::decode_header(token).map_err(invalid)?;
+        let key = self.key(header.kid.as_deref()).await?;
+        let data = jsonwebtoken::decode::<C>(token, &key, &self.validation).map_err(invalid)?;
+        Ok(data.claims)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use axum::routing::get;
+    use axum::Router;
+    use axum_test::TestServer;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{Algorithm, Claims, DecodingKey, JwtAuth, Validation};
+    use crate::auth::{AuthLayer, AuthorizeLayer, FORBIDDEN, UNAUTHORIZED};
+    use crate::router;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    const SECRET: &[u8] = b"secret";
+
+    fn token(kid: Option<&str>, claims: Value) -> String {
+        let header = Header {
+            kid: kid.map(str::to_owned),
+            ..Header::new(Algorithm::HS256)
+        };
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    fn claims(scope: &str) -> Value {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        json!({"sub": "alice", "aud": "api", "exp": now.as_secs() + 60, "scope": scope})
+    }
+
+    async fn whoami(req: JsonRpcExtractor) -> JrpcResult {
+        let sub = req
+            .extension::<Claims>()
+            .and_then(|claims| claims.sub.clone());
+        Ok(JsonRpcResponse::success(req.get_answer_id(), sub))
+    }
+
+    fn server(jwt: JwtAuth) -> TestServer {
+        let rpc = AuthorizeLayer::<Claims>::new()
+            .require("admin", ["admin"])
+            .layer(
+                JsonRpcRouter::new()
+                    .method("whoami", whoami)
+                    .method("admin", whoami),
+            );
+        let rpc = AuthLayer::new(jwt).layer(rpc);
+        TestServer::new(Router::new().route("/", router::post(rpc))).unwrap()
+    }
+
+    fn validation() -> Validation {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&["api"]);
+        validation
+    }
+
+    async fn call(server: &TestServer, method: &str, token: &str) -> Value {
+        server
+            .post("/")
+            .authorization_bearer(token)
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method}))
+            .await
+            .json()
+    }
+
+    #[tokio::test]
+    async fn secret() {
+        let server = server(JwtAuth::new(DecodingKey::from_secret(SECRET), validation()));
+
+        let res = call(&server, "whoami", &token(None, claims("read"))).await;
+        assert_eq!(res["result"], "alice");
+        let res = call(&server, "admin", &token(None, claims("read"))).await;
+        assert_eq!(res["error"]["code"], FORBIDDEN);
+        let res = call(&server, "admin", &token(None, claims("read admin"))).await;
+        assert_eq!(res["result"], "alice");
+
+        let mut other = claims("read");
+        other["aud"] = json!("other");
+        let res = call(&server, "whoami", &token(None, other)).await;
+        assert_eq!(res["error"]["code"], UNAUTHORIZED);
+        let res = call(&server, "whoami", "garbage").await;
+        assert_eq!(res["error"]["code"], UNAUTHORIZED);
+    }
+
+    /// Serves a JWKS with the key `k1` at the returned url after `delay`, counting requests
+    async fn jwks_server(delay: Duration) -> (String, Arc<AtomicUsize>) {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let jwks = Router::new().route(
+            "/jwks",
+            get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(delay).await;
+                axum::Json(
+                    json!({"keys": [{"kty": "oct", "kid": "k1", "alg": "HS256", "k": "c2VjcmV0"}]}),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/jwks", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, jwks).await });
+        (url, fetches)
+    }
+
+    #[tokio::test]
+    async fn jwks() {
+        let (url, fetches) = jwks_server(Duration::ZERO).await;
+        let server = server(JwtAuth::jwks(url, validation()));
+
+        let res = call(&server, "whoami", &token(Some("k1"), claims("read"))).await;
+        assert_eq!(res["result"], "alice");
+        let res = call(&server, "whoami", &token(Some("k2"), claims("read"))).await;
+        assert_eq!(res["error"]["message"], "Unknown key id");
+        let res = call(&server, "whoami", &token(None, claims("read"))).await;
+        assert_eq!(res["error"]["message"], "Missing key id");
+        // the unknown key id came too soon after the first fetch to fetch again
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn jwks_fetched_once() {
+        let (url, fetches) = jwks_server(Duration::from_millis(100)).await;
+        let server = server(JwtAuth::jwks(url, validation()));
+        let token = token(Some("k1"), claims("read"));
+
+        let calls = (0..8).map(|_| call(&server, "whoami", &token));
+        for res in futures_util::future::join_all(calls).await {
+            assert_eq!(res["result"], "alice");
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn jwks_timeout() {
+        let (url, fetches) = jwks_server(Duration::from_secs(60)).await;
+        let jwt = JwtAuth::jwks(url, validation()).timeout(Duration::from_millis(100));
+        let server = server(jwt);
+        let token = token(Some("k1"), claims("read"));
+
+        let started = Instant::now();
+        let calls = (0..4).map(|_| call(&server, "whoami", &token));
+        for res in futures_util::future::join_all(calls).await {
+            assert_eq!(res["error"]["message"], "Unknown key id");
+        }
+        // the waiting calls don't fetch again after the first one failed
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+}
@@ -2,7 +2,7 @@
     clippy::all,
     clippy::dbg_macro,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::enum_glob_use,
     clippy::mem_forget,
     clippy::unused_self,
@@ -11,9 +11,7 @@
     clippy::needless_borrow,
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
-    clippy::mismatched_target_os,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -36,16 +34,27 @@
 #![allow(elided_lifetimes_in_paths, clippy::type_complexity)]
 
 use std::borrow::Cow;
+use std::sync::Arc;
 
-use axum::body::Bytes;
-use axum::extract::{FromRequest, Request};
-use axum::http::{header, HeaderMap};
-use axum::response::{IntoResponse, Response};
-use axum::Json;
+#[cfg(feature = "server")]
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
 use cfg_if::cfg_if;
 use serde::de::DeserializeOwned;
+#[cfg(feature = "server")]
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
 
+use crate::codec::{Codec, JsonBackend, JsonCodec};
+#[cfg(feature = "server")]
+use crate::intern::Interned;
+#[cfg(feature = "server")]
+use crate::malformed::Malformed;
+
 cfg_if! {
     if #[cfg(feature = "serde_json")] {
         pub use serde_json::Value;
@@ -62,9 +71,401 @@ cfg_if! {
     }
 }
 
+// Lets generated code refer to `::axum_jrpc` in tests
+#[cfg(all(test, feature = "macros", feature = "server", feature = "serde_json"))]
+extern crate self as axum_jrpc;
+
+#[cfg(feature = "acl")]
+pub mod acl;
+#[cfg(feature = "server")]
+pub mod api_key;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "server")]
+pub mod auth;
+#[cfg(feature = "server")]
+mod batch;
+#[cfg(feature = "bitcoin")]
+pub mod bitcoin;
+#[cfg(feature = "pubsub")]
+pub mod broadcast;
+#[cfg(feature = "server")]
+mod buffer;
+#[cfg(feature = "bulkhead")]
+pub mod bulkhead;
+#[cfg(feature = "server")]
+pub mod cache;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "typed-headers")]
+pub mod client_info;
+mod codec;
+#[cfg(feature = "eth")]
+pub mod eth;
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+mod fuzz;
+#[cfg(feature = "tower-governor")]
+pub mod governor;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "server")]
+pub mod idempotency;
+#[cfg(feature = "metrics")]
+mod instrument;
+#[cfg(feature = "server")]
+mod intern;
+#[cfg(feature = "jsonrpc-core")]
+pub mod io_handler;
+#[cfg(feature = "jobs")]
+pub mod jobs;
+#[cfg(feature = "jsonrpsee")]
+pub mod jsonrpsee;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+#[cfg(feature = "server")]
+pub mod limits;
+#[cfg(feature = "long-poll")]
+pub mod longpoll;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+#[cfg(feature = "server")]
+mod malformed;
+#[cfg(feature = "mtls")]
+pub mod mtls;
+#[cfg(feature = "nats")]
+pub mod nats;
+#[cfg(feature = "utoipa")]
+pub mod openapi;
+#[cfg(feature = "openrpc")]
+pub mod openrpc;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+#[cfg(feature = "playground")]
+pub mod playground;
+#[cfg(feature = "priority")]
+pub mod priority;
+#[cfg(feature = "pubsub")]
+pub mod pubsub;
+#[cfg(feature = "server")]
+pub mod quota;
+#[cfg(feature = "server")]
+pub mod rate_limit;
+#[cfg(all(feature = "server", feature = "serde_json"))]
+pub mod raw;
+#[cfg(feature = "redact")]
+pub mod redact;
+#[cfg(feature = "server")]
+pub mod replay;
+#[cfg(feature = "server")]
+pub mod rest;
+#[cfg(feature = "server")]
+pub mod router;
+#[cfg(feature = "jsonschema")]
+pub mod schema;
+#[cfg(feature = "server")]
+pub mod shed;
+#[cfg(feature = "shutdown")]
+pub mod shutdown;
+#[cfg(feature = "hmac")]
+pub mod signature;
+#[cfg(any(feature = "hmac", feature = "ed25519"))]
+pub mod signing;
+#[cfg(feature = "sse")]
+pub mod sse;
+#[cfg(feature = "server")]
+pub mod stats;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(all(feature = "server", feature = "simd"))]
+pub mod tape;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(all(feature = "server", feature = "tracing"))]
+pub mod trace;
+#[cfg(feature = "typescript")]
+pub mod typescript;
+#[cfg(all(unix, feature = "unix"))]
+pub mod unix;
+#[cfg(feature = "validator")]
+pub mod validate;
+#[cfg(feature = "ws")]
+pub mod ws;
+
+#[cfg(feature = "server")]
+pub use router::{JsonRpcRouter, JsonRpcService};
+
+/// Generates a server dispatcher and a typed client from an api trait.
+///
+/// Every method must be `async`, take `&self` and return a `Result` whose error converts
+/// into [`JsonRpcError`]. The method name defaults to the function name and can be set with
+/// `#[method(name = "...")]`. `#[rpc(server)]` or `#[rpc(client)]` limit what's generated,
+/// the client side needs the `client` feature.
+///
+/// For a trait `Calculator` this generates:
+/// - `CalculatorServer`, implemented for every `Calculator`, whose `dispatch` method handles a
+///   [`JsonRpcExtractor`]. Each argument is a param, passed by position or by name as with
+///   [`JsonRpcParams`], so trailing `Option` arguments may be left out
+/// - `CalculatorClient<C>`, implementing `Calculator` on top of any
+///   [`JsonRpcClient`](client::JsonRpcClient)
+///
+/// ```rust
+/// use axum_jrpc::error::JsonRpcError;
+/// use axum_jrpc::{JrpcResult, JsonRpcExtractor};
+///
+/// #[axum_jrpc::rpc(server)]
+/// pub trait Calculator {
+///     #[method(name = "add")]
+///     async fn add(&self, a: i32, b: i32) -> Result<i32, JsonRpcError>;
+/// }
+///
+/// struct Impl;
+///
+/// #[axum_jrpc::async_trait]
+/// impl Calculator for Impl {
+///     async fn add(&self, a: i32, b: i32) -> Result<i32, JsonRpcError> {
+///         Ok(a + b)
+///     }
+/// }
+///
+/// async fn handler(req: JsonRpcExtractor) -> JrpcResult {
+///     Impl.dispatch(req).await
+/// }
+/// ```
+#[cfg(feature = "macros")]
+pub use axum_jrpc_macros::rpc;
+
+/// Implements `Deserialize` for a struct of params passed either by position or by name.
+///
+/// `[1, 2]` and `{"a": 1, "b": 2}` both deserialize into the struct below. Positional params
+/// follow the order of the fields, trailing `Option` or `#[serde(default)]` fields may be left
+/// out. Field and container `#[serde(...)]` attributes apply to named params.
+/// ```rust
+/// use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcParams, JsonRpcResponse};
+///
+/// #[derive(JsonRpcParams)]
+/// struct AddParams {
+///     a: i64,
+///     b: i64,
+///     #[serde(rename = "roundTo")]
+///     round_to: Option<i64>,
+/// }
+///
+/// async fn add(req: JsonRpcExtractor) -> JrpcResult {
+///     let id = req.get_answer_id();
+///     let params: AddParams = req.parse_params()?;
+///     Ok(JsonRpcResponse::success(id, params.a + params.b))
+/// }
+/// ```
+#[cfg(feature = "macros")]
+pub use axum_jrpc_macros::JsonRpcParams;
+
+/// Re-exported for implementing traits generated by [`rpc`]
+#[cfg(feature = "macros")]
+pub use async_trait::async_trait;
+
+/// Turns an async function into a JSON-RPC method.
+///
+/// The function must be `async` and return a `Result` whose error converts into
+/// [`JsonRpcError`](error::JsonRpcError). Each argument is a param, passed by position or by
+/// name as with [`rpc`], so `scale` below accepts `[3, 2]` and `{"value": 3, "by": 2}`, and a
+/// single struct argument is the param named after it rather than the params as a whole.
+/// Handlers parsing the params as a whole are registered with [`JsonRpcRouter::method`]. The
+/// method name defaults to the function name.
+///
+/// A unit struct named after the function implements [`JsonRpcMethod`] and is registered with
+/// [`JsonRpcRouter::register`]. With the `inventory` feature,
+/// [`JsonRpcRouter::collect`] registers every such method in the binary instead.
+/// ```rust
+/// use axum_jrpc::error::JsonRpcError;
+/// use axum_jrpc::{jrpc_method, JsonRpcRouter};
+///
+/// #[jrpc_method("math.add")]
+/// async fn add(a: i64, b: Option<i64>) -> Result<i64, JsonRpcError> {
+///     Ok(a + b.unwrap_or_default())
+/// }
+///
+/// #[jrpc_method]
+/// async fn scale(value: i64, by: i64) -> Result<i64, JsonRpcError> {
+///     Ok(value * by)
+/// }
+///
+/// let rpc = JsonRpcRouter::new().register::<add>().register::<scale>();
+/// ```
+#[cfg(all(feature = "macros", feature = "server"))]
+pub use axum_jrpc_macros::jrpc_method;
+
+/// Generates the types and the api trait of an [OpenRPC](https://spec.open-rpc.org) document,
+/// for servers written spec first.
+///
+/// The path of the document is relative to the manifest of the crate, the crate is rebuilt
+/// when it changes. Its schemas become structs, string enums or aliases, and the empty trait
+/// is given a method per method of the document, then expanded by [`rpc`] with `server`.
+/// Params which aren't required are `Option`s, anything the types can't express is a
+/// [`Value`].
+/// ```rust
+/// use axum_jrpc::error::JsonRpcError;
+/// use axum_jrpc::{JrpcResult, JsonRpcExtractor};
+///
+/// #[axum_jrpc::openrpc_server("src/testdata/calculator.openrpc.json")]
+/// pub trait Calculator {}
+///
+/// struct Impl;
+///
+/// #[axum_jrpc::async_trait]
+/// impl Calculator for Impl {
+///     async fn math_add(&self, a: i64, b: Option<i64>) -> Result<i64, JsonRpcError> {
+///         Ok(a + b.unwrap_or_default())
+///     }
+///
+///     async fn math_round(&self, value: Rounding) -> Result<Option<i64>, JsonRpcError> {
+///         let rounded = match value.mode {
+///             Mode::Up => value.number.ceil(),
+///             Mode::Down => value.number.floor(),
+///         };
+///         Ok(Some(rounded as i64))
+///     }
+/// }
+///
+/// async fn handler(req: JsonRpcExtractor) -> JrpcResult {
+///     Impl.dispatch(req).await
+/// }
+/// ```
+#[cfg(all(feature = "macros", feature = "server"))]
+pub use axum_jrpc_macros::openrpc_server;
+
+/// A method generated by [`jrpc_method`]
+#[cfg(all(feature = "macros", feature = "server"))]
+pub trait JsonRpcMethod: 'static {
+    /// Name the method is registered as
+    const NAME: &'static str;
+
+    fn call(
+        request: JsonRpcExtractor,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = JrpcResult> + Send>>;
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait;
+    #[cfg(feature = "inventory")]
+    pub use inventory;
+    pub use serde;
+
+    /// Method collected by [`JsonRpcRouter::collect`](crate::JsonRpcRouter::collect)
+    #[cfg(feature = "inventory")]
+    #[derive(Debug)]
+    pub struct MethodEntry {
+        pub name: &'static str,
+        pub handler: fn(
+            crate::JsonRpcExtractor,
+        ) -> std::pin::Pin<
+            Box<dyn std::future::Future<Output = crate::JrpcResult> + Send>,
+        >,
+    }
+
+    #[cfg(feature = "inventory")]
+    impl MethodEntry {
+        pub const fn new<M: crate::JsonRpcMethod>() -> Self {
+            Self {
+                name: M::NAME,
+                handler: M::call,
+            }
+        }
+    }
+
+    #[cfg(feature = "inventory")]
+    inventory::collect!(MethodEntry);
+}
+
+#[doc(hidden)]
+#[cfg(feature = "inventory")]
+#[macro_export]
+macro_rules! __submit_method {
+    ($method:ident) => {
+        $crate::__private::inventory::submit! {
+            $crate::__private::MethodEntry::new::<$method>()
+        }
+    };
+}
+
+#[doc(hidden)]
+#[cfg(not(feature = "inventory"))]
+#[macro_export]
+macro_rules! __submit_method {
+    ($method:ident) => {};
+}
+
+/// Declares method names as types, so a typo in a name is a compile error.
+///
+/// Every `Name = "method"` pair becomes a unit struct whose `NAME` is the method name. It
+/// dereferences to the name and converts into a `String`, so the same constant registers the
+/// method on the server and calls it from a client.
+/// ```rust
+/// use axum_jrpc::{jrpc_methods, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+///
+/// jrpc_methods! {
+///     /// Adds two numbers
+///     pub Add = "math.add",
+///     pub Sub = "math.sub",
+/// }
+///
+/// async fn add(req: JsonRpcExtractor) -> JrpcResult {
+///     let id = req.get_answer_id();
+///     let [a, b]: [i64; 2] = req.parse_params()?;
+///     Ok(JsonRpcResponse::success(id, a + b))
+/// }
+///
+/// let rpc = JsonRpcRouter::new().method(Add, add);
+/// assert_eq!(rpc.methods().collect::<Vec<_>>(), [Add::NAME]);
+/// assert_eq!(&*Sub, "math.sub");
+/// ```
+#[macro_export]
+macro_rules! jrpc_methods {
+    ($($(#[$attr:meta])* $vis:vis $name:ident = $method:literal),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+            $vis struct $name;
+
+            impl $name {
+                /// Name of the method
+                $vis const NAME: &'static str = $method;
+            }
+
+            impl ::core::ops::Deref for $name {
+                type Target = str;
+
+                fn deref(&self) -> &str {
+                    Self::NAME
+                }
+            }
+
+            impl ::core::convert::AsRef<str> for $name {
+                fn as_ref(&self) -> &str {
+                    Self::NAME
+                }
+            }
+
+            impl ::core::fmt::Display for $name {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    f.write_str(Self::NAME)
+                }
+            }
+
+            impl ::core::convert::From<$name> for ::std::string::String {
+                fn from(_: $name) -> Self {
+                    $name::NAME.to_owned()
+                }
+            }
+        )*
+    };
+}
+
 /// Hack until [try_trait_v2](https://github.com/rust-lang/rust/issues/84277) is not stabilized
 pub type JrpcResult = Result<JsonRpcResponse, JsonRpcResponse>;
 
+/// A JSON-RPC request. Requests with [`Id::None`] are notifications and are serialized without id.
+/// Null params are serialized without `params`, which must be an array or an object.
 #[derive(Debug)]
 pub struct JsonRpcRequest {
     pub id: Id,
@@ -72,6 +473,98 @@ pub struct JsonRpcRequest {
     pub params: Value,
 }
 
+impl JsonRpcRequest {
+    /// Starts building a request for `method`
+    /// ```rust
+    /// use axum_jrpc::JsonRpcRequest;
+    ///
+    /// # fn main() -> Result<(), axum_jrpc::error::JsonRpcError> {
+    /// let request = JsonRpcRequest::builder("add").positional((1, 2))?.id(7).build();
+    /// let notification = JsonRpcRequest::builder("ping").notification();
+    /// assert!(notification.is_notification());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(method: impl Into<String>) -> JsonRpcRequestBuilder {
+        JsonRpcRequestBuilder {
+            method: method.into(),
+            params: Value::default(),
+            id: Id::Num(0),
+        }
+    }
+
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+/// Builder for [`JsonRpcRequest`], see [`JsonRpcRequest::builder`].
+#[derive(Debug)]
+pub struct JsonRpcRequestBuilder {
+    method: String,
+    params: Value,
+    id: Id,
+}
+
+impl JsonRpcRequestBuilder {
+    /// Serializes `params` as is
+    pub fn params<T: Serialize>(mut self, params: T) -> Result<Self, JsonRpcError> {
+        self.params = to_value(params).map_err(|e| {
+            JsonRpcError::new(JsonRpcErrorReason::InvalidParams, e, Value::default())
+        })?;
+        Ok(self)
+    }
+
+    /// Serializes `params` which must produce an array, e.g. a tuple
+    pub fn positional<T: Serialize>(self, params: T) -> Result<Self, JsonRpcError> {
+        let this = self.params(params)?;
+        match this.params {
+            Value::Array(_) => Ok(this),
+            _ => Err(JsonRpcError::new(
+                JsonRpcErrorReason::InvalidParams,
+                "Positional params must serialize to an array".to_owned(),
+                Value::default(),
+            )),
+        }
+    }
+
+    /// Serializes `params` which must produce an object, e.g. a struct
+    pub fn named<T: Serialize>(self, params: T) -> Result<Self, JsonRpcError> {
+        let this = self.params(params)?;
+        match this.params {
+            Value::Object(_) => Ok(this),
+            _ => Err(JsonRpcError::new(
+                JsonRpcErrorReason::InvalidParams,
+                "Named params must serialize to an object".to_owned(),
+                Value::default(),
+            )),
+        }
+    }
+
+    /// Sets the request id, `0` by default
+    pub fn id(mut self, id: impl Into<Id>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    pub fn build(self) -> JsonRpcRequest {
+        JsonRpcRequest {
+            id: self.id,
+            method: self.method,
+            params: self.params,
+        }
+    }
+
+    /// Builds a notification, which has no id and gets no response
+    pub fn notification(self) -> JsonRpcRequest {
+        JsonRpcRequest {
+            id: Id::None(()),
+            method: self.method,
+            params: self.params,
+        }
+    }
+}
+
 impl Serialize for JsonRpcRequest {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -80,14 +573,20 @@ impl Serialize for JsonRpcRequest {
         #[derive(Serialize)]
         struct Helper<'a> {
             jsonrpc: &'static str,
-            id: Id,
+            #[serde(skip_serializing_if = "Id::is_none")]
+            id: &'a Id,
             method: &'a str,
+            #[serde(skip_serializing_if = "is_null")]
             params: &'a Value,
         }
 
+        fn is_null(params: &&Value) -> bool {
+            **params == Value::default()
+        }
+
         Helper {
             jsonrpc: JSONRPC,
-            id: self.id.clone(),
+            id: &self.id,
             method: &self.method,
             params: &self.params,
         }
@@ -96,6 +595,28 @@ impl Serialize for JsonRpcRequest {
 }
 
 impl<'de> Deserialize<'de> for JsonRpcRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let fields = RequestFields::<String>::deserialize(deserializer)?;
+        Ok(Self {
+            id: fields.id.unwrap_or_default(),
+            method: fields.method,
+            params: fields.params,
+        })
+    }
+}
+
+/// Fields of a request with a supported jsonrpc version, the method name is an `M`.
+/// The id is `None` when it is absent, a `null` id is still a call to be answered
+pub(crate) struct RequestFields<M> {
+    pub(crate) id: Option<Id>,
+    pub(crate) method: M,
+    pub(crate) params: Value,
+}
+
+impl<'de, M: Deserialize<'de>> Deserialize<'de> for RequestFields<M> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
@@ -103,15 +624,24 @@ impl<'de> Deserialize<'de> for JsonRpcRequest {
         use serde::de::Error;
 
         #[derive(Deserialize)]
-        struct Helper<'a> {
+        struct Helper<'a, M> {
             #[serde(borrow)]
             jsonrpc: Cow<'a, str>,
-            id: Id,
-            method: String,
+            // notifications have no id
+            #[serde(default, deserialize_with = "present")]
+            id: Option<Id>,
+            method: M,
+            #[serde(default)]
             params: Value,
         }
 
-        let helper = Helper::deserialize(deserializer)?;
+        fn present<'de, D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Id>, D::Error> {
+            Id::deserialize(deserializer).map(Some)
+        }
+
+        let helper = Helper::<M>::deserialize(deserializer)?;
         if helper.jsonrpc == JSONRPC {
             Ok(Self {
                 id: helper.id,
@@ -119,11 +649,31 @@ impl<'de> Deserialize<'de> for JsonRpcRequest {
                 params: helper.params,
             })
         } else {
-            Err(D::Error::custom("Unknown jsonrpc version"))
+            Err(D::Error::custom(UNKNOWN_VERSION))
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl RequestFields<Interned> {
+    pub(crate) fn into_extractor(self, mut extensions: http::Extensions) -> JsonRpcExtractor {
+        if self.id.is_none() {
+            extensions.insert(NoId);
+        }
+        JsonRpcExtractor {
+            parsed: self.params,
+            method: self.method.0,
+            id: self.id.unwrap_or_default(),
+            extensions,
         }
     }
 }
 
+/// Marks an extracted request that came without an id, unlike one with a `null` id
+#[cfg(feature = "server")]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct NoId;
+
 #[derive(Clone, Debug)]
 /// Parses a JSON-RPC request, and returns the request ID, the method name, and the parameters.
 /// If the request is invalid, returns an error.
@@ -144,8 +694,11 @@ impl<'de> Deserialize<'de> for JsonRpcRequest {
 /// ```
 pub struct JsonRpcExtractor {
     pub parsed: Value,
-    pub method: String,
+    /// Name of the method, shared with the router if it is registered there
+    pub method: Arc<str>,
     pub id: Id,
+    /// Extensions of the underlying HTTP request, or values set by the transport
+    pub extensions: http::Extensions,
 }
 
 impl JsonRpcExtractor {
@@ -153,41 +706,62 @@ impl JsonRpcExtractor {
         self.id.clone()
     }
 
-    pub fn parse_params<T: DeserializeOwned>(self) -> Result<T, JsonRpcResponse> {
-        cfg_if::cfg_if! {
-           if #[cfg(feature = "simd")] {
-                match simd_json::serde::from_owned_value(self.parsed){
-                    Ok(v) => Ok(v),
-                    Err(e) => {
-                        let error = JsonRpcError::new(
-                            JsonRpcErrorReason::InvalidParams,
-                            e.to_string(),
-                            Value::default(),
-                        );
-                        Err(JsonRpcResponse::error(self.id, error))
-                    }
+    /// Borrows the id, for looking at it without cloning
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
 
-                }
-            } else if #[cfg(feature = "serde_json")] {
-                match serde_json::from_value(self.parsed){
-                    Ok(v) => Ok(v),
-                    Err(e) => {
-                        let error = JsonRpcError::new(
-                            JsonRpcErrorReason::InvalidParams,
-                            e.to_string(),
-                            Value::Null,
-                        );
-                        Err(JsonRpcResponse::error(self.id, error))
-                    }
-                }
+    pub fn parse_params<T: DeserializeOwned>(self) -> Result<T, JsonRpcResponse> {
+        match from_value(self.parsed) {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                let error =
+                    JsonRpcError::new(JsonRpcErrorReason::InvalidParams, e, Value::default());
+                Err(JsonRpcResponse::error(self.id, error))
             }
         }
     }
 
+    /// Like [`parse_params`](Self::parse_params), borrowing the params, so a handler can try
+    /// another shape if they don't fit, or borrow strings from them:
+    /// ```rust
+    /// use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Transfer<'a> {
+    ///     to: &'a str,
+    ///     amount: u64,
+    /// }
+    ///
+    /// async fn transfer(req: JsonRpcExtractor) -> JrpcResult {
+    ///     let Transfer { to, amount } = match req.parse_params_ref::<(&str, u64)>() {
+    ///         Ok((to, amount)) => Transfer { to, amount },
+    ///         Err(_) => req.parse_params_ref()?,
+    ///     };
+    ///     Ok(JsonRpcResponse::success(req.get_answer_id(), format!("{amount} to {to}")))
+    /// }
+    /// ```
+    pub fn parse_params_ref<'a, T: Deserialize<'a>>(&'a self) -> Result<T, JsonRpcResponse> {
+        T::deserialize(&self.parsed).map_err(|e| {
+            let error = JsonRpcError::new(
+                JsonRpcErrorReason::InvalidParams,
+                e.to_string(),
+                Value::default(),
+            );
+            JsonRpcResponse::error(self.id.clone(), error)
+        })
+    }
+
     pub fn method(&self) -> &str {
         &self.method
     }
 
+    /// Returns a value inserted by middleware or the transport
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
     pub fn method_not_found(&self, method: &str) -> JsonRpcResponse {
         let error = JsonRpcError::new(
             JsonRpcErrorReason::MethodNotFound,
@@ -197,8 +771,29 @@ impl JsonRpcExtractor {
 
         JsonRpcResponse::error(self.id.clone(), error)
     }
+
+    /// Splits the call into its id, method and params, dropping the extensions
+    pub fn into_parts(self) -> (Id, String, Value) {
+        (self.id, self.method.to_string(), self.parsed)
+    }
+
+    /// A call with the given id, method and params and no extensions, the inverse of
+    /// [`into_parts`](Self::into_parts)
+    pub fn from_parts(id: Id, method: String, params: Value) -> Self {
+        #[cfg(feature = "server")]
+        let method = intern::name(&method);
+        #[cfg(not(feature = "server"))]
+        let method = Arc::from(method);
+        Self {
+            parsed: params,
+            method,
+            id,
+            extensions: Default::default(),
+        }
+    }
 }
 
+#[cfg(feature = "server")]
 #[async_trait::async_trait]
 impl<S> FromRequest<S> for JsonRpcExtractor
 where
@@ -219,10 +814,16 @@ where
             });
         }
 
-        #[allow(unused_mut)]
-        let mut bytes = match Bytes::from_request(req, state).await {
-            Ok(a) => a.to_vec(),
-            Err(_) => {
+        let mut extensions = req.extensions().clone();
+        extensions.insert(router::RequestHeaders(Arc::new(req.headers().clone())));
+        #[cfg(feature = "opentelemetry")]
+        otel::extract_context(req.headers(), &mut extensions);
+        let bytes = match Bytes::from_request(req, state).await {
+            Ok(a) => a,
+            Err(rejection) => {
+                if rejection.into_response().status() == StatusCode::PAYLOAD_TOO_LARGE {
+                    Malformed::Oversized.report(None, "Length limit exceeded", &extensions);
+                }
                 return Err(JsonRpcResponse {
                     id: Id::None(()),
                     result: JsonRpcAnswer::Error(JsonRpcError::new(
@@ -230,51 +831,36 @@ where
                         "Invalid request".to_owned(),
                         Value::default(),
                     )),
-                })
+                });
             }
         };
 
-        cfg_if!(
-            if #[cfg(feature = "simd")] {
-               let parsed: JsonRpcRequest = match simd_json::from_slice(&mut bytes){
-                    Ok(a) => a,
-                    Err(e) => {
-                        return Err(JsonRpcResponse {
-                            id: Id::None(()),
-                            result: JsonRpcAnswer::Error(JsonRpcError::new(
-                                JsonRpcErrorReason::InvalidRequest,
-                                e.to_string(),
-                                Value::default(),
-                            )),
-                        })
-                    }
-                };
-            } else if #[cfg(feature = "serde_json")] {
-               let parsed: JsonRpcRequest = match serde_json::from_slice(&bytes){
-                    Ok(a) => a,
-                    Err(e) => {
-                        return Err(JsonRpcResponse {
-                            id: Id::None(()),
-                            result: JsonRpcAnswer::Error(JsonRpcError::new(
-                                JsonRpcErrorReason::InvalidRequest,
-                                e.to_string(),
-                                Value::default(),
-                            )),
-                        })
-                    }
+        let parsed: RequestFields<Interned> = match from_bytes(&bytes) {
+            Ok(a) => a,
+            Err(e) => {
+                // only rejected requests are parsed twice, to tell the failures apart
+                let malformed = match from_bytes::<IgnoredAny>(&bytes) {
+                    Ok(_) => Malformed::of_request(&e),
+                    Err(_) => Malformed::Syntax,
                 };
+                malformed.report(None, &e, &extensions);
+                return Err(JsonRpcResponse {
+                    id: Id::None(()),
+                    result: JsonRpcAnswer::Error(JsonRpcError::new(
+                        JsonRpcErrorReason::InvalidRequest,
+                        e,
+                        Value::default(),
+                    )),
+                });
             }
-        );
+        };
 
-        Ok(Self {
-            parsed: parsed.params,
-            method: parsed.method,
-            id: parsed.id,
-        })
+        Ok(parsed.into_extractor(extensions))
     }
 }
 
-fn json_content_type(headers: &HeaderMap) -> bool {
+#[cfg(feature = "server")]
+pub(crate) fn json_content_type(headers: &HeaderMap) -> bool {
     let content_type = if let Some(content_type) = headers.get(header::CONTENT_TYPE) {
         content_type
     } else {
@@ -294,7 +880,7 @@ fn json_content_type(headers: &HeaderMap) -> bool {
     };
 
     let is_json_content_type = mime.type_() == "application"
-        && (mime.subtype() == "json" || mime.suffix().map_or(false, |name| name == "json"));
+        && (mime.subtype() == "json" || mime.suffix().is_some_and(|name| name == "json"));
 
     is_json_content_type
 }
@@ -326,32 +912,12 @@ impl JsonRpcResponse {
         T: Serialize,
         Id: From<ID>,
     {
-        cfg_if::cfg_if! {
-          if #[cfg(feature = "simd")] {
-            match simd_json::serde::to_owned_value(result) {
-                Ok(v) => JsonRpcResponse::new(id, JsonRpcAnswer::Result(v)),
-                Err(e) => {
-                    let err = JsonRpcError::new(
-                        JsonRpcErrorReason::InternalError,
-                        e.to_string(),
-                        Value::default(),
-                    );
-                    JsonRpcResponse::error(id, err)
-                }
-            }
-          } else if #[cfg(feature = "serde_json")] {
-            match serde_json::to_value(result) {
-                Ok(v) => JsonRpcResponse::new(id, JsonRpcAnswer::Result(v)),
-                Err(e) => {
-                    let err = JsonRpcError::new(
-                        JsonRpcErrorReason::InternalError,
-                        e.to_string(),
-                        Value::Null,
-                    );
-                    JsonRpcResponse::error(id, err)
-                }
+        match to_value(result) {
+            Ok(v) => JsonRpcResponse::new(id, JsonRpcAnswer::Result(v)),
+            Err(e) => {
+                let err = JsonRpcError::new(JsonRpcErrorReason::InternalError, e, Value::default());
+                JsonRpcResponse::error(id, err)
             }
-          }
         }
     }
 
@@ -365,6 +931,17 @@ impl JsonRpcResponse {
             id,
         }
     }
+
+    /// Deserializes the result into `T` or returns the error object.
+    /// A result which doesn't match `T` is reported as [`JsonRpcErrorReason::ParseError`]
+    pub fn into_result<T: DeserializeOwned>(self) -> Result<T, JsonRpcError> {
+        match self.result {
+            JsonRpcAnswer::Result(value) => from_value(value).map_err(|e| {
+                JsonRpcError::new(JsonRpcErrorReason::ParseError, e, Value::default())
+            }),
+            JsonRpcAnswer::Error(error) => Err(error),
+        }
+    }
 }
 
 impl Serialize for JsonRpcResponse {
@@ -377,13 +954,13 @@ impl Serialize for JsonRpcResponse {
             jsonrpc: &'static str,
             #[serde(flatten)]
             result: &'a JsonRpcAnswer,
-            id: Id,
+            id: &'a Id,
         }
 
         Helper {
             jsonrpc: JSONRPC,
             result: &self.result,
-            id: self.id.clone(),
+            id: &self.id,
         }
         .serialize(serializer)
     }
@@ -412,17 +989,27 @@ impl<'de> Deserialize<'de> for JsonRpcResponse {
                 id: helper.id,
             })
         } else {
-            Err(D::Error::custom("Unknown jsonrpc version"))
+            Err(D::Error::custom(UNKNOWN_VERSION))
         }
     }
 }
 
+#[cfg(feature = "server")]
 impl IntoResponse for JsonRpcResponse {
     fn into_response(self) -> Response {
-        Json(self).into_response()
+        buffer::json_response(&self)
     }
 }
 
+/// Any message received over a bidirectional transport.
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum JsonRpcMessage {
+    Response(JsonRpcResponse),
+    Request(JsonRpcRequest),
+}
+
 #[derive(Serialize, Clone, Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 /// JsonRpc [response object](https://www.jsonrpc.org/specification#response_object)
@@ -433,6 +1020,49 @@ pub enum JsonRpcAnswer {
 
 const JSONRPC: &str = "2.0";
 
+/// Error of a request object with an unsupported jsonrpc version
+pub(crate) const UNKNOWN_VERSION: &str = "Unknown jsonrpc version";
+
+/// Converts `value` into [`Value`] using the enabled json backend
+pub(crate) fn to_value<T: Serialize>(value: T) -> Result<Value, String> {
+    JsonBackend::to_value(value)
+}
+
+/// Serializes `value` to json bytes using the enabled json backend
+#[allow(dead_code)]
+pub(crate) fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    JsonBackend::encode(value)
+}
+
+/// Deserializes `T` from json bytes using the enabled json backend.
+/// simd-json uses `bytes` as scratch space, so its content is unspecified afterwards
+#[allow(dead_code)]
+pub(crate) fn from_slice<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T, String> {
+    JsonBackend::decode(bytes)
+}
+
+/// Deserializes `T` from shared json bytes using the enabled json backend.
+/// Only simd-json needs to copy them first
+#[allow(dead_code)]
+pub(crate) fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    JsonBackend::decode_slice(bytes)
+}
+
+/// Converts [`Value`] into `T` using the enabled json backend
+pub(crate) fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, String> {
+    JsonBackend::from_value(value)
+}
+
+/// 128 bits from the random source of the operating system, hex encoded, for ids that must
+/// not be guessed
+#[cfg(any(feature = "pubsub", feature = "jobs"))]
+pub(crate) fn random_id() -> String {
+    let mut bytes = [0; 16];
+    // as with the hasher keys of std, there is nothing to fall back to
+    getrandom::getrandom(&mut bytes).expect("no random source");
+    hex::encode(bytes)
+}
+
 /// An identifier established by the Client that MUST contain a String, Number,
 /// or NULL value if included. If it is not included it is assumed to be a notification.
 /// The value SHOULD normally not be Null and Numbers SHOULD NOT contain fractional parts
@@ -444,6 +1074,34 @@ pub enum Id {
     None(()),
 }
 
+impl Id {
+    pub fn is_none(&self) -> bool {
+        matches!(self, Id::None(()))
+    }
+
+    /// Returns the id if it is a number
+    pub fn as_num(&self) -> Option<i64> {
+        match self {
+            Id::Num(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Returns the id if it is a string, without cloning it
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Id::Str(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Id {
+    fn default() -> Self {
+        Id::None(())
+    }
+}
+
 impl From<()> for Id {
     fn from(val: ()) -> Self {
         Id::None(val)
@@ -462,12 +1120,18 @@ impl From<String> for Id {
     }
 }
 
+impl From<&str> for Id {
+    fn from(val: &str) -> Self {
+        Id::Str(val.to_owned())
+    }
+}
+
 #[cfg(test)]
-#[cfg(all(feature = "anyhow_error", feature = "serde_json"))]
+#[cfg(all(feature = "anyhow_error", feature = "serde_json", feature = "server"))]
 mod test {
     use crate::{
-        Deserialize, JrpcResult, JsonRpcAnswer, JsonRpcError, JsonRpcErrorReason, JsonRpcExtractor,
-        JsonRpcRequest, JsonRpcResponse,
+        Deserialize, Id, JrpcResult, JsonRpcAnswer, JsonRpcError, JsonRpcErrorReason,
+        JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse,
     };
     use axum::routing::post;
     use serde::Serialize;
@@ -524,10 +1188,76 @@ mod test {
         );
     }
 
+    #[test]
+    fn id_accessors() {
+        let id = Id::from("abc");
+        assert_eq!(id.as_str(), Some("abc"));
+        assert_eq!(id.as_num(), None);
+        assert_eq!(Id::from(7).as_num(), Some(7));
+
+        let response = JsonRpcResponse::success(id, 1);
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({"jsonrpc": "2.0", "result": 1, "id": "abc"})
+        );
+    }
+
+    #[test]
+    fn request_builder() {
+        let request = JsonRpcRequest::builder("add")
+            .positional((1, 2))
+            .unwrap()
+            .id(7)
+            .build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({"jsonrpc": "2.0", "id": 7, "method": "add", "params": [1, 2]})
+        );
+
+        let notification = JsonRpcRequest::builder("add")
+            .named(Test { a: 1, b: 2 })
+            .unwrap()
+            .notification();
+        assert_eq!(
+            serde_json::to_value(&notification).unwrap(),
+            serde_json::json!({"jsonrpc": "2.0", "method": "add", "params": {"a": 1, "b": 2}})
+        );
+
+        let request = JsonRpcRequest::builder("ping").id(1).build();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "ping"})
+        );
+
+        assert!(JsonRpcRequest::builder("add")
+            .positional(Test { a: 1, b: 2 })
+            .is_err());
+        assert!(JsonRpcRequest::builder("add").named((1, 2)).is_err());
+    }
+
+    #[test]
+    fn response_into_result() {
+        let response = JsonRpcResponse::success(0, Test { a: 1, b: 2 });
+        let test: Test = response.into_result().unwrap();
+        assert_eq!((test.a, test.b), (1, 2));
+
+        let response = JsonRpcResponse::success(0, "not a number");
+        let error = response.into_result::<i32>().unwrap_err();
+        assert_eq!(error.code(), crate::error::PARSE_ERROR);
+
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::InvalidParams,
+            "bad".to_owned(),
+            Value::Null,
+        );
+        let response = JsonRpcResponse::error(0, error.clone());
+        assert_eq!(response.into_result::<i32>().unwrap_err(), error);
+    }
+
     async fn handler(value: JsonRpcExtractor) -> JrpcResult {
         let answer_id = value.get_answer_id();
         println!("{:?}", value);
-        match value.method.as_str() {
+        match value.method() {
             "add" => {
                 let request: Test = value.parse_params()?;
                 let result = request.a + request.b;
@@ -589,3 +1319,347 @@ mod test {
         }
     }
 }
+
+#[cfg(test)]
+#[cfg(all(feature = "macros", feature = "local-client", feature = "serde_json"))]
+mod rpc_test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::client::{JsonRpcClient, LocalClient};
+    use crate::error::{JsonRpcError, JsonRpcErrorReason};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRouter, Value};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[crate::rpc]
+    trait Geometry {
+        async fn ping(&self) -> Result<(), JsonRpcError>;
+
+        #[method(name = "geo_shift")]
+        async fn shift(&self, point: Point, by: i32) -> Result<Point, JsonRpcError>;
+
+        async fn divide(&self, a: i32, b: i32) -> Result<i32, JsonRpcError>;
+    }
+
+    struct Impl;
+
+    #[crate::async_trait]
+    impl Geometry for Impl {
+        async fn ping(&self) -> Result<(), JsonRpcError> {
+            Ok(())
+        }
+
+        async fn shift(&self, point: Point, by: i32) -> Result<Point, JsonRpcError> {
+            Ok(Point {
+                x: point.x + by,
+                y: point.y + by,
+            })
+        }
+
+        async fn divide(&self, a: i32, b: i32) -> Result<i32, JsonRpcError> {
+            a.checked_div(b).ok_or_else(|| {
+                JsonRpcError::new(
+                    JsonRpcErrorReason::ApplicationError(1),
+                    "division by zero".to_owned(),
+                    Value::Null,
+                )
+            })
+        }
+    }
+
+    async fn handler(request: JsonRpcExtractor) -> JrpcResult {
+        Impl.dispatch(request).await
+    }
+
+    #[tokio::test]
+    async fn roundtrip() {
+        let client = GeometryClient(LocalClient::new(JsonRpcRouter::new().fallback(handler)));
+
+        client.ping().await.unwrap();
+        let point = client.shift(Point { x: 1, y: 2 }, 3).await.unwrap();
+        assert_eq!(point, Point { x: 4, y: 5 });
+        assert_eq!(client.divide(6, 3).await.unwrap(), 2);
+        assert_eq!(client.divide(6, 0).await.unwrap_err().code(), 1);
+
+        let request = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "geo_shift",
+            "params": {"point": {"x": 0, "y": 0}, "by": 1}
+        }))
+        .unwrap();
+        let response = client.0.send(request, Default::default()).await.unwrap();
+        assert_eq!(
+            response.result,
+            crate::JsonRpcAnswer::Result(serde_json::json!({"x": 1, "y": 1}))
+        );
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "macros", feature = "server", feature = "serde_json"))]
+mod method_test {
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    use crate::error::{JsonRpcError, JsonRpcErrorReason};
+    use crate::{jrpc_method, JsonRpcRouter, JsonRpcService};
+
+    #[derive(Deserialize)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    #[jrpc_method("math.add")]
+    async fn add(a: i64, b: Option<i64>) -> Result<i64, JsonRpcError> {
+        Ok(a + b.unwrap_or_default())
+    }
+
+    #[jrpc_method]
+    async fn norm(point: Point) -> Result<i64, JsonRpcError> {
+        Ok(point.x.abs() + point.y.abs())
+    }
+
+    #[jrpc_method]
+    async fn divide(a: i64, b: i64) -> Result<i64, JsonRpcError> {
+        a.checked_div(b).ok_or_else(|| {
+            JsonRpcError::new(
+                JsonRpcErrorReason::ApplicationError(1),
+                "division by zero".to_owned(),
+                crate::Value::Null,
+            )
+        })
+    }
+
+    async fn call(rpc: &JsonRpcRouter, method: &str, params: Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let reply = rpc.dispatch_bytes(request.to_string().into()).await;
+        serde_json::from_slice(&reply.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn registers() {
+        let rpc = JsonRpcRouter::new()
+            .register::<add>()
+            .register::<divide>()
+            .register::<norm>();
+
+        let res = call(&rpc, "math.add", json!({"a": 1, "b": 2})).await;
+        assert_eq!(res["result"], 3);
+        let res = call(&rpc, "math.add", json!([1])).await;
+        assert_eq!(res["result"], 1);
+        let res = call(&rpc, "divide", json!([6, 3])).await;
+        assert_eq!(res["result"], 2);
+        let res = call(&rpc, "divide", json!({"a": 6, "b": 0})).await;
+        assert_eq!(res["error"]["code"], 1);
+        let res = call(&rpc, "math.add", json!([])).await;
+        assert_eq!(res["error"]["code"], -32602);
+        // a single argument is a param like any other
+        let res = call(&rpc, "norm", json!([{"x": 1, "y": -2}])).await;
+        assert_eq!(res["result"], 3);
+        let res = call(&rpc, "norm", json!({"point": {"x": 1, "y": -2}})).await;
+        assert_eq!(res["result"], 3);
+        let res = call(&rpc, "norm", json!({"x": 1, "y": -2})).await;
+        assert_eq!(res["error"]["code"], -32602);
+
+        #[cfg(feature = "inventory")]
+        {
+            let rpc = JsonRpcRouter::new().collect();
+            let res = call(&rpc, "divide", json!([6, 2])).await;
+            assert_eq!(res["result"], 3);
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "macros", feature = "server", feature = "serde_json"))]
+mod openrpc_server_test {
+    use serde_json::{json, Value};
+
+    use crate::error::JsonRpcError;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRouter, JsonRpcService};
+
+    #[crate::openrpc_server("src/testdata/calculator.openrpc.json")]
+    trait Calculator {}
+
+    struct Impl;
+
+    #[crate::async_trait]
+    impl Calculator for Impl {
+        async fn math_add(&self, a: i64, b: Option<i64>) -> Result<i64, JsonRpcError> {
+            Ok(a + b.unwrap_or_default())
+        }
+
+        async fn math_round(&self, value: Rounding) -> Result<Option<i64>, JsonRpcError> {
+            let rounded = match value.mode {
+                Mode::Up => value.number.ceil(),
+                Mode::Down => value.number.floor(),
+            };
+            Ok(value.r#type.is_none().then_some(rounded as i64))
+        }
+    }
+
+    async fn handler(req: JsonRpcExtractor) -> JrpcResult {
+        Impl.dispatch(req).await
+    }
+
+    #[tokio::test]
+    async fn serves_document() {
+        let rpc = JsonRpcRouter::new().fallback(handler);
+        let call = |method: &str, params: Value| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+            let rpc = rpc.clone();
+            async move {
+                let reply = rpc.dispatch_bytes(request.to_string().into()).await;
+                serde_json::from_slice::<Value>(&reply.unwrap()).unwrap()
+            }
+        };
+
+        assert_eq!(call("math.add", json!([1, 2])).await["result"], 3);
+        assert_eq!(call("math.add", json!({"a": 1})).await["result"], 1);
+        assert_eq!(call("math.add", json!([1])).await["result"], 1);
+        assert_eq!(call("math.add", json!([])).await["error"]["code"], -32602);
+        let value = json!([{"number": 1.5, "mode": "up"}]);
+        assert_eq!(call("math.round", value).await["result"], 2);
+        let value = json!({"value": {"number": 1.5, "mode": "up"}});
+        assert_eq!(call("math.round", value).await["result"], 2);
+        let value = json!({"value": {"number": 1.5, "mode": "down", "type": "x"}});
+        assert_eq!(call("math.round", value).await["result"], Value::Null);
+        let value = json!({"value": {"number": 1.5, "mode": "sideways"}});
+        assert_eq!(call("math.round", value).await["error"]["code"], -32602);
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "macros", feature = "server", feature = "serde_json"))]
+mod params_test {
+    use serde_json::json;
+
+    use crate::JsonRpcParams;
+
+    fn five() -> u32 {
+        5
+    }
+
+    #[derive(Debug, PartialEq, JsonRpcParams)]
+    #[serde(rename_all = "camelCase")]
+    struct Transfer {
+        from_account: String,
+        amount: u64,
+        memo: Option<String>,
+        #[serde(default = "five")]
+        fee: u32,
+    }
+
+    #[test]
+    fn positional_or_named() {
+        let transfer = Transfer {
+            from_account: "alice".to_owned(),
+            amount: 10,
+            memo: None,
+            fee: 5,
+        };
+        let parse = |params| serde_json::from_value::<Transfer>(params);
+
+        assert_eq!(parse(json!(["alice", 10])).unwrap(), transfer);
+        assert_eq!(
+            parse(json!({"fromAccount": "alice", "amount": 10})).unwrap(),
+            transfer
+        );
+        assert_eq!(
+            parse(json!(["alice", 10, "rent", 1]))
+                .unwrap()
+                .memo
+                .as_deref(),
+            Some("rent")
+        );
+        assert!(parse(json!(["alice"])).is_err());
+        assert!(parse(json!(["alice", 10, null, 1, 2])).is_err());
+        assert!(parse(json!({"from_account": "alice", "amount": 10})).is_err());
+        assert!(parse(json!("alice")).is_err());
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "server", feature = "serde_json"))]
+mod method_names_test {
+    use serde_json::{json, Value};
+
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    jrpc_methods! {
+        Add = "math.add",
+        Neg = "math.neg",
+    }
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i64; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    #[tokio::test]
+    async fn names_methods() {
+        let rpc = JsonRpcRouter::new().method(Add, add);
+        assert_eq!(rpc.methods().collect::<Vec<_>>(), ["math.add"]);
+        assert_eq!(Neg.to_string(), "math.neg");
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": &*Add, "params": [1, 2]});
+        let reply = rpc.dispatch_bytes(request.to_string().into()).await;
+        let reply: Value = serde_json::from_slice(&reply.unwrap()).unwrap();
+        assert_eq!(reply["result"], 3);
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "server", feature = "serde_json"))]
+mod extractor_test {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use crate::{JsonRpcAnswer, JsonRpcExtractor, JsonRpcRequest};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    #[test]
+    fn parse_params_ref() {
+        let request = JsonRpcRequest::builder("move")
+            .params(json!({"x": 1, "y": 2}))
+            .unwrap()
+            .id(7)
+            .build();
+        let req = JsonRpcExtractor::from(request);
+
+        let response = req.parse_params_ref::<[i64; 2]>().unwrap_err();
+        assert_eq!(response.id, 7.into());
+        let JsonRpcAnswer::Error(error) = response.result else {
+            panic!("not an error");
+        };
+        assert_eq!(error.code(), -32602);
+        assert_eq!(
+            req.parse_params_ref::<Point>().unwrap(),
+            Point { x: 1, y: 2 }
+        );
+        assert_eq!(req.parse_params::<Point>().unwrap(), Point { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn into_parts() {
+        let req = JsonRpcExtractor::from_parts(1.into(), "move".to_owned(), json!([1, 2]));
+        assert_eq!(req.method(), "move");
+        let (id, method, params) = req.into_parts();
+        assert_eq!(
+            (id, method, params),
+            (1.into(), "move".to_owned(), json!([1, 2]))
+        );
+    }
+}
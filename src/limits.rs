@@ -0,0 +1,470 @@
+//! Limits on the structure of params, so a single pathological call can't pin a worker.
+//!
+//! [`LimitsLayer`] wraps a service and walks the params of every call before its handler
+//! deserializes them, answering calls nested too deep, with too long arrays or strings, or too
+//! large altogether, with an `Invalid params` error. The walk stops at the first violation,
+//! so its cost is bounded by the limits rather than by the payload.
+//! ```rust
+//! use axum_jrpc::limits::LimitsLayer;
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let limits = LimitsLayer::new().max_depth(8).max_array_len(1000);
+//! let rpc = limits.layer(JsonRpcRouter::new());
+//! ```
+//! That walk only bounds what handlers see, the message has been decoded by then. Applied to
+//! the axum route of an endpoint as well, the layer scans json bodies before they are decoded
+//! and answers the ones nested too deep, or with too long arrays or strings, with a
+//! `Parse error`, so they cost no more than reading them:
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::limits::LimitsLayer;
+//! use axum_jrpc::{router, JsonRpcRouter};
+//! use tower::Layer;
+//!
+//! let limits = LimitsLayer::new();
+//! let app: Router = Router::new()
+//!     .route("/", router::post(limits.layer(JsonRpcRouter::new())))
+//!     .layer(limits);
+//! ```
+//! The scan leaves the size of the params to the body limit of the route. Other transports
+//! only get the walk. Messages nested deeper than the parser's own recursion limit, 128
+//! levels, are always rejected with a `Parse error`.
+
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::task::{Context, Poll};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRequest, Request};
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{json_content_type, Id, JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Wraps services in [`Limits`], see the [module docs](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitsLayer {
+    max_depth: usize,
+    max_size: usize,
+    max_array_len: usize,
+    max_string_len: usize,
+}
+
+impl Default for LimitsLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LimitsLayer {
+    /// Levels of arrays and objects, the params themselves being the first one
+    pub const MAX_DEPTH: usize = 32;
+
+    /// Bytes of the params once encoded as json, escapes aside
+    pub const MAX_SIZE: usize = 1024 * 1024;
+
+    /// Elements of an array, or entries of an object
+    pub const MAX_ARRAY_LEN: usize = 10_000;
+
+    /// Bytes of a string or object key
+    pub const MAX_STRING_LEN: usize = 64 * 1024;
+
+    pub fn new() -> Self {
+        Self {
+            max_depth: Self::MAX_DEPTH,
+            max_size: Self::MAX_SIZE,
+            max_array_len: Self::MAX_ARRAY_LEN,
+            max_string_len: Self::MAX_STRING_LEN,
+        }
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn max_array_len(mut self, max_array_len: usize) -> Self {
+        self.max_array_len = max_array_len;
+        self
+    }
+
+    pub fn max_string_len(mut self, max_string_len: usize) -> Self {
+        self.max_string_len = max_string_len;
+        self
+    }
+
+    /// Describes the first limit `params` exceed
+    fn check(&self, params: &Value) -> Result<(), String> {
+        let mut walk = Walk {
+            limits: self,
+            size: 0,
+        };
+        walk.value(params, 1)
+    }
+
+    /// Describes the first limit the json message in `bytes` exceeds, except for the size.
+    ///
+    /// Escapes count as a single byte of their string, and the messages of a batch are not
+    /// counted as array elements.
+    fn scan(&self, bytes: &[u8]) -> Result<(), String> {
+        // the request object wraps the params, and a batch wraps it
+        let batch = bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[');
+        let max_depth = self.max_depth + 1 + usize::from(batch);
+        // elements seen in every open array or object, as commas
+        let mut open: Vec<usize> = Vec::new();
+        let mut string = None;
+        let mut escaped = false;
+        let mut hex_digits = 0;
+        for &byte in bytes {
+            if let Some(len) = &mut string {
+                match byte {
+                    _ if escaped => {
+                        escaped = false;
+                        if byte == b'u' {
+                            hex_digits = 4;
+                        }
+                        continue;
+                    }
+                    _ if hex_digits > 0 => {
+                        hex_digits -= 1;
+                        continue;
+                    }
+                    b'"' => string = None,
+                    b'\\' => {
+                        escaped = true;
+                        *len += 1;
+                    }
+                    _ => *len += 1,
+                }
+                if string.is_some_and(|len| len > self.max_string_len) {
+                    return Err(format!(
+                        "Params string longer than {} bytes",
+                        self.max_string_len
+                    ));
+                }
+                continue;
+            }
+            match byte {
+                b'"' => string = Some(0usize),
+                b'[' | b'{' => {
+                    if open.len() >= max_depth {
+                        return Err(format!(
+                            "Params nested deeper than {} levels",
+                            self.max_depth
+                        ));
+                    }
+                    open.push(0);
+                }
+                b']' | b'}' => {
+                    open.pop();
+                }
+                b',' if open.len() > 1 => {
+                    let commas = open.last_mut().unwrap();
+                    *commas += 1;
+                    if *commas >= self.max_array_len {
+                        return Err(format!(
+                            "Params array longer than {} elements",
+                            self.max_array_len
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sizes the params as they are walked
+struct Walk<'a> {
+    limits: &'a LimitsLayer,
+    size: usize,
+}
+
+impl Walk<'_> {
+    // simd-json only has a single variant for scalars
+    #[cfg_attr(
+        not(feature = "serde_json"),
+        allow(clippy::match_wildcard_for_single_variants)
+    )]
+    fn value(&mut self, value: &Value, depth: usize) -> Result<(), String> {
+        match value {
+            Value::String(string) => self.string(string)?,
+            Value::Array(values) => {
+                self.container(values.len(), depth)?;
+                for value in values.iter() {
+                    self.value(value, depth + 1)?;
+                }
+            }
+            Value::Object(map) => {
+                self.container(map.len(), depth)?;
+                for (key, value) in map.iter() {
+                    // the colon
+                    self.grow(1)?;
+                    self.string(key)?;
+                    self.value(value, depth + 1)?;
+                }
+            }
+            scalar => {
+                let mut encoded = Count(0);
+                let _ = write!(encoded, "{scalar}");
+                self.grow(encoded.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn container(&mut self, len: usize, depth: usize) -> Result<(), String> {
+        let limits = self.limits;
+        if depth > limits.max_depth {
+            return Err(format!(
+                "Params nested deeper than {} levels",
+                limits.max_depth
+            ));
+        }
+        if len > limits.max_array_len {
+            return Err(format!(
+                "Params array longer than {} elements",
+                limits.max_array_len
+            ));
+        }
+        // brackets and commas
+        self.grow(len.max(1) + 1)
+    }
+
+    fn string(&mut self, string: &str) -> Result<(), String> {
+        if string.len() > self.limits.max_string_len {
+            return Err(format!(
+                "Params string longer than {} bytes",
+                self.limits.max_string_len
+            ));
+        }
+        self.grow(string.len() + 2)
+    }
+
+    fn grow(&mut self, size: usize) -> Result<(), String> {
+        self.size += size;
+        match self.size > self.limits.max_size {
+            true => Err(format!("Params larger than {} bytes", self.limits.max_size)),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Counts the bytes written to it
+struct Count(usize);
+
+impl Write for Count {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for LimitsLayer {
+    type Service = Limits<S>;
+
+    fn layer(&self, inner: S) -> Limits<S> {
+        Limits {
+            inner,
+            limits: *self,
+        }
+    }
+}
+
+/// Rejects calls whose params exceed the limits, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Limits<S> {
+    inner: S,
+    limits: LimitsLayer,
+}
+
+impl<S> Service<JsonRpcExtractor> for Limits<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let Err(message) = self.limits.check(&request.parsed) else {
+            return Box::pin(self.inner.call(request));
+        };
+        let error = JsonRpcError::new(JsonRpcErrorReason::InvalidParams, message, Value::default());
+        let response = JsonRpcResponse::error(request.id, error);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// Scans json bodies before they are decoded, for the axum route of an endpoint
+impl<S> Service<Request> for Limits<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if !json_content_type(request.headers()) {
+            return Box::pin(self.inner.call(request));
+        }
+        // the ready service is taken, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limits = self.limits;
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let request = Request::from_parts(parts.clone(), body);
+            // honors the body limit of the route
+            let bytes = match Bytes::from_request(request, &()).await {
+                Ok(bytes) => bytes,
+                Err(rejection) => return Ok(rejection.into_response()),
+            };
+            if let Err(message) = limits.scan(&bytes) {
+                let error =
+                    JsonRpcError::new(JsonRpcErrorReason::ParseError, message, Value::default());
+                return Ok(JsonRpcResponse::error(Id::None(()), error).into_response());
+            }
+            inner
+                .call(Request::from_parts(parts, Body::from(bytes)))
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::json;
+
+    use super::LimitsLayer;
+    use crate::Value;
+
+    #[test]
+    fn limits() {
+        let limits = LimitsLayer::new()
+            .max_depth(3)
+            .max_size(64)
+            .max_array_len(4)
+            .max_string_len(8);
+        let check = |params: Value| limits.check(&params);
+
+        assert_eq!(check(json!({"a": [1, {"b": null}], "c": "short"})), Ok(()));
+        assert_eq!(
+            check(json!([[[[1]]]])),
+            Err("Params nested deeper than 3 levels".to_owned())
+        );
+        assert_eq!(
+            check(json!([1, 2, 3, 4, 5])),
+            Err("Params array longer than 4 elements".to_owned())
+        );
+        assert_eq!(
+            check(json!({"key": "too long a string"})),
+            Err("Params string longer than 8 bytes".to_owned())
+        );
+        assert_eq!(
+            check(json!({"too long a key": 1})),
+            Err("Params string longer than 8 bytes".to_owned())
+        );
+        assert_eq!(
+            check(json!([
+                ["12345678", "12345678", "12345678"],
+                ["12345678", "12345678", "12345678"]
+            ])),
+            Err("Params larger than 64 bytes".to_owned())
+        );
+        // exactly the size once encoded
+        let params = json!(["1234", 12345, true]);
+        let size = serde_json::to_vec(&params).unwrap().len();
+        assert_eq!(limits.max_size(size).check(&params), Ok(()));
+        assert!(limits.max_size(size - 1).check(&params).is_err());
+    }
+
+    #[test]
+    fn scan() {
+        let limits = LimitsLayer::new()
+            .max_depth(3)
+            .max_array_len(4)
+            .max_string_len(8);
+        let scan = |message: Value| limits.scan(message.to_string().as_bytes());
+        let call =
+            |params: Value| json!({"jsonrpc": "2.0", "id": 1, "method": "m", "params": params});
+
+        assert_eq!(
+            scan(call(json!({"a": [1, {"b": null}], "c": "short"}))),
+            Ok(())
+        );
+        assert_eq!(scan(json!([call(json!([[[1]]])), call(json!([]))])), Ok(()));
+        assert_eq!(
+            scan(call(json!([[[[1]]]]))),
+            Err("Params nested deeper than 3 levels".to_owned())
+        );
+        assert_eq!(
+            scan(call(json!([1, 2, 3, 4, 5]))),
+            Err("Params array longer than 4 elements".to_owned())
+        );
+        assert_eq!(
+            scan(call(json!({"key": "too long a string"}))),
+            Err("Params string longer than 8 bytes".to_owned())
+        );
+        // escapes count once, commas and brackets in strings not at all
+        let message = r#"{"jsonrpc": "2.0", "id": 1, "method": "m", "params": ["\n\u00e9\"[,,,"]}"#;
+        assert_eq!(limits.scan(message.as_bytes()), Ok(()));
+        // the size is left to the body limit
+        let row = json!(["12345678", "12345678", "12345678", "12345678"]);
+        assert_eq!(scan(call(json!([row, row, row, row]))), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn scans_bodies() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use axum::Router;
+        use axum_test::TestServer;
+        use tower::Layer;
+
+        use crate::{router, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        async fn echo(req: JsonRpcExtractor) -> JrpcResult {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(JsonRpcResponse::success(req.get_answer_id(), req.parsed))
+        }
+
+        let limits = LimitsLayer::new().max_depth(2);
+        let rpc = limits.layer(JsonRpcRouter::new().method("echo", echo));
+        let app = Router::new().route("/", router::post(rpc)).layer(limits);
+        let server = TestServer::new(app).unwrap();
+        let call =
+            |params: Value| json!({"jsonrpc": "2.0", "id": 1, "method": "echo", "params": params});
+
+        let res: Value = server.post("/").json(&call(json!([[1]]))).await.json();
+        assert_eq!(res["result"], json!([[1]]));
+        let res: Value = server.post("/").json(&call(json!([[[1]]]))).await.json();
+        assert_eq!(res["error"]["code"], -32700);
+        assert_eq!(res["id"], Value::Null);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+}
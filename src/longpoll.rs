@@ -0,0 +1,244 @@
+//! Long-polling fallback for environments where proxies break WebSockets and event streams.
+//!
+//! Everything goes through plain HTTP `POST`s. Calling `rpc.poll` without the
+//! [`SESSION_HEADER`] opens a session, calls sent with that header belong to it, so
+//! subscriptions made by them queue their notifications there. `rpc.poll` with the header
+//! waits until notifications are queued, or the poll timeout passes, and returns them:
+//! ```json
+//! --> {"jsonrpc": "2.0", "id": 1, "method": "rpc.poll", "params": {"after": 3}}
+//! <-- {"jsonrpc": "2.0", "id": 1, "result": {"session": "...", "cursor": 4, "messages": [...]}}
+//! ```
+//! `after` is the `cursor` of the previous poll, notifications up to it are skipped. Passing
+//! it with every poll makes retries after lost responses safe.
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::longpoll::LongPollTransport;
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! let rpc = JsonRpcRouter::new();
+//! let app: Router = Router::new().route("/rpc", LongPollTransport::new().serve(rpc));
+//! ```
+
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use axum::routing::{self, MethodRouter};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::TryRecvError;
+
+use crate::pubsub::{Event, SessionStore};
+use crate::router::JsonRpcService;
+use crate::{from_slice, JsonRpcExtractor, JsonRpcResponse, Value};
+
+pub use crate::pubsub::SESSION_HEADER;
+
+/// Method polling for notifications
+pub const POLL_METHOD: &str = "rpc.poll";
+
+/// Serves calls and `rpc.poll` on `POST`, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct LongPollTransport {
+    capacity: usize,
+    idle_timeout: Duration,
+    poll_timeout: Duration,
+}
+
+impl Default for LongPollTransport {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            idle_timeout: Duration::from_secs(60),
+            poll_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PollParams {
+    after: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct Poll {
+    session: String,
+    cursor: Option<u64>,
+    messages: Vec<Value>,
+}
+
+impl LongPollTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of notifications kept per session, 1024 by default
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// How long a session survives without being polled, 60 seconds by default. A zero
+    /// timeout closes it as soon as it has no reader
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// How long `rpc.poll` waits for notifications, 30 seconds by default
+    pub fn with_poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    /// Builds the route, for use with [`axum::Router::route`]
+    pub fn serve<S: JsonRpcService>(self, service: S) -> MethodRouter {
+        let store = SessionStore::new(self.capacity, self.idle_timeout);
+        let poll_timeout = self.poll_timeout;
+        routing::post(
+            move |headers: HeaderMap, request: JsonRpcExtractor| async move {
+                if request.method() == POLL_METHOD {
+                    poll(&store, poll_timeout, &headers, request)
+                        .await
+                        .into_response()
+                } else {
+                    store.dispatch(&service, &headers, request).await
+                }
+            },
+        )
+    }
+}
+
+async fn poll(
+    store: &SessionStore,
+    poll_timeout: Duration,
+    headers: &HeaderMap,
+    request: JsonRpcExtractor,
+) -> JsonRpcResponse {
+    let id = request.get_answer_id();
+    let buffered = store.lookup(headers);
+    let PollParams { after } = if request.parsed == Value::default() {
+        PollParams::default()
+    } else {
+        match request.parse_params() {
+            Ok(params) => params,
+            Err(e) => return e,
+        }
+    };
+
+    let Some(buffered) = buffered else {
+        let poll = Poll {
+            session: store.create().id().to_owned(),
+            cursor: None,
+            messages: Vec::new(),
+        };
+        return JsonRpcResponse::success(id, poll);
+    };
+
+    let (mut events, mut live) = buffered.resume(after);
+    let mut last = events.last().map(|(seq, _)| *seq).or(after);
+    let mut push = |event: Event, events: &mut Vec<Event>| {
+        if last.is_none_or(|last| event.0 > last) {
+            last = Some(event.0);
+            events.push(event);
+        }
+    };
+
+    if events.is_empty() {
+        let wait = async {
+            while let Ok(event) = live.recv().await {
+                push(event, &mut events);
+                if !events.is_empty() {
+                    break;
+                }
+            }
+        };
+        let _ = tokio::time::timeout(poll_timeout, wait).await;
+    }
+    loop {
+        match live.try_recv() {
+            Ok(event) => push(event, &mut events),
+            Err(TryRecvError::Lagged(_)) => {}
+            Err(_) => break,
+        }
+    }
+
+    let messages = events
+        .into_iter()
+        .filter_map(|(_, text)| from_slice(&mut text.as_bytes().to_vec()).ok())
+        .collect();
+    let poll = Poll {
+        session: buffered.id().to_owned(),
+        cursor: last,
+        messages,
+    };
+    JsonRpcResponse::success(id, poll)
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "client", feature = "serde_json"))]
+mod test {
+    use std::time::Duration;
+
+    use axum::Router;
+    use serde_json::{json, Value};
+
+    use super::{LongPollTransport, SESSION_HEADER};
+    use crate::pubsub::PendingSubscription;
+    use crate::{JsonRpcExtractor, JsonRpcRouter};
+
+    async fn numbers(_: JsonRpcExtractor, pending: PendingSubscription) {
+        let sink = pending.accept();
+        for number in 0..2 {
+            sink.send(number).await.unwrap();
+        }
+        sink.closed().await;
+    }
+
+    #[tokio::test]
+    async fn poll() {
+        let rpc = JsonRpcRouter::new().subscription("subscribe", "number", "unsubscribe", numbers);
+        let transport = LongPollTransport::new().with_poll_timeout(Duration::from_millis(100));
+        let app = Router::new().route("/rpc", transport.serve(rpc));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/rpc", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        let call = |session: Option<&str>, method: &str, params: Value| {
+            let mut request = client.post(&url).json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }));
+            if let Some(session) = session {
+                request = request.header(SESSION_HEADER, session);
+            }
+            async move {
+                let response: Value = request.send().await.unwrap().json().await.unwrap();
+                response["result"].clone()
+            }
+        };
+
+        let opened = call(None, "rpc.poll", Value::Null).await;
+        let session = opened["session"].as_str().unwrap().to_owned();
+        let subscription = call(Some(&session), "subscribe", json!([])).await;
+
+        let polled = call(Some(&session), "rpc.poll", json!({})).await;
+        let cursor = polled["cursor"].as_u64().unwrap();
+        let messages = polled["messages"].as_array().unwrap();
+        assert!(!messages.is_empty());
+        assert_eq!(messages[0]["method"], "number");
+        assert_eq!(
+            messages[0]["params"],
+            json!({"subscription": subscription, "result": 0})
+        );
+
+        // notifications up to the cursor are not returned again
+        let polled = call(Some(&session), "rpc.poll", json!({ "after": 2 })).await;
+        assert_eq!(polled["messages"], json!([]));
+        assert_eq!(polled["cursor"], 2);
+        assert!(cursor <= 2);
+    }
+}
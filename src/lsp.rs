@@ -0,0 +1,249 @@
+//! The JSON-RPC dialect of the [Language Server Protocol](https://microsoft.github.io/language-server-protocol/),
+//! for the `lsp` feature.
+//!
+//! LSP messages are plain JSON-RPC 2.0 framed with `Content-Length` headers, so handlers are
+//! regular [`JsonRpcRouter`](crate::JsonRpcRouter) methods. On top of that, this module has:
+//! * the error codes reserved by LSP, and [`error`] to build them
+//! * [`CancellationLayer`], answering `$/cancelRequest` by aborting the request in progress
+//!   with [`REQUEST_CANCELLED`]
+//! * [`serve_stdio`], serving a language server on the standard streams with both
+//! ```rust,no_run
+//! use axum_jrpc::{lsp, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//! use serde_json::json;
+//!
+//! async fn initialize(req: JsonRpcExtractor) -> JrpcResult {
+//!     let capabilities = json!({"capabilities": {"hoverProvider": true}});
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), capabilities))
+//! }
+//!
+//! async fn hover(req: JsonRpcExtractor) -> JrpcResult {
+//!     Err(JsonRpcResponse::error(
+//!         req.get_answer_id(),
+//!         lsp::error(lsp::CONTENT_MODIFIED, "document changed"),
+//!     ))
+//! }
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let rpc = JsonRpcRouter::new()
+//!     .method("initialize", initialize)
+//!     .method("textDocument/hover", hover);
+//! lsp::serve_stdio(rpc).await
+//! # }
+//! ```
+//! Other `$/` notifications, such as `$/setTrace`, are answered by the router like any
+//! unknown notification, with nothing.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tower::{Layer, Service};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{from_value, Id, JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// The server received a request before `initialize`
+pub const SERVER_NOT_INITIALIZED: i32 = -32002;
+pub const UNKNOWN_ERROR_CODE: i32 = -32001;
+/// A valid request failed, e.g. on a missing file
+pub const REQUEST_FAILED: i32 = -32803;
+/// The server cancelled a request, which the client may retry
+pub const SERVER_CANCELLED: i32 = -32802;
+/// The document changed while the request was handled, so its result is outdated
+pub const CONTENT_MODIFIED: i32 = -32801;
+/// The client cancelled the request with `$/cancelRequest`
+pub const REQUEST_CANCELLED: i32 = -32800;
+
+/// Method of the notification cancelling a request
+pub const CANCEL_REQUEST: &str = "$/cancelRequest";
+
+/// Params of `$/cancelRequest`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CancelParams {
+    /// Id of the request to cancel
+    pub id: Id,
+}
+
+/// An error with one of the codes of this module, or any other
+pub fn error(code: i32, message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError::new(
+        JsonRpcErrorReason::ApplicationError(code),
+        message.into(),
+        Value::default(),
+    )
+}
+
+/// Handles `$/cancelRequest`: the request in progress with the given id is dropped and
+/// answered with [`REQUEST_CANCELLED`]. Cancelling a finished or unknown request does
+/// nothing.
+/// ```rust
+/// use axum_jrpc::lsp::CancellationLayer;
+/// use axum_jrpc::JsonRpcRouter;
+/// use tower::Layer;
+///
+/// let rpc = CancellationLayer::new().layer(JsonRpcRouter::new());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationLayer {
+    _priv: (),
+}
+
+impl CancellationLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for CancellationLayer {
+    type Service = Cancellation<S>;
+
+    fn layer(&self, inner: S) -> Cancellation<S> {
+        Cancellation {
+            inner,
+            pending: Default::default(),
+        }
+    }
+}
+
+/// Aborts cancelled requests, see [`CancellationLayer`]
+#[derive(Debug, Clone)]
+pub struct Cancellation<S> {
+    inner: S,
+    pending: Arc<Pending>,
+}
+
+/// Requests in progress, by id
+#[derive(Debug, Default)]
+struct Pending {
+    requests: Mutex<HashMap<Id, (u64, oneshot::Sender<()>)>>,
+    /// Tells apart requests reusing an id
+    next: AtomicU64,
+}
+
+impl<S> Service<JsonRpcExtractor> for Cancellation<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let id = request.get_answer_id();
+        if &*request.method == CANCEL_REQUEST {
+            if let Ok(CancelParams { id: cancelled }) = from_value(request.parsed) {
+                let mut requests = self
+                    .pending
+                    .requests
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                if let Some((_, cancel)) = requests.remove(&cancelled) {
+                    let _ = cancel.send(());
+                }
+            }
+            return Box::pin(async move { Ok(JsonRpcResponse::success(id, Value::default())) });
+        }
+        if id.is_none() {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let (cancel, cancelled) = oneshot::channel();
+        let token = self.pending.next.fetch_add(1, Ordering::Relaxed);
+        let pending = self.pending.clone();
+        let mut requests = pending.requests.lock().unwrap_or_else(|e| e.into_inner());
+        requests.insert(id.clone(), (token, cancel));
+        drop(requests);
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = tokio::select! {
+                Ok(response) = response => response,
+                Ok(()) = cancelled => {
+                    let error = error(REQUEST_CANCELLED, "Request cancelled");
+                    return Ok(JsonRpcResponse::error(id, error));
+                }
+            };
+            let mut requests = pending.requests.lock().unwrap_or_else(|e| e.into_inner());
+            if requests
+                .get(&id)
+                .is_some_and(|(current, _)| *current == token)
+            {
+                requests.remove(&id);
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Serves a language server on stdin and stdout, with `Content-Length` framing and
+/// [`CancellationLayer`]
+pub async fn serve_stdio<S>(service: S) -> std::io::Result<()>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let stdio = tokio::io::join(tokio::io::stdin(), tokio::io::stdout());
+    let service = CancellationLayer::new().layer(service);
+    crate::stream::serve(service, stdio, crate::stream::ContentLengthCodec::new()).await
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tower::Layer;
+
+    use super::CancellationLayer;
+    use crate::stream::{serve, ContentLengthCodec};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn slow(req: JsonRpcExtractor) -> JrpcResult {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "done"))
+    }
+
+    fn frame(message: Value) -> String {
+        let message = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{message}", message.len())
+    }
+
+    #[tokio::test]
+    async fn cancels_requests() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let rpc = CancellationLayer::new().layer(JsonRpcRouter::new().method("slow", slow));
+        tokio::spawn(serve(rpc, server, ContentLengthCodec::new()));
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "slow"});
+        client.write_all(frame(request).as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let cancel = json!({"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": 1}});
+        client.write_all(frame(cancel).as_bytes()).await.unwrap();
+
+        let mut buffer = vec![0; 1024];
+        let read = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        let message = std::str::from_utf8(&buffer[..read]).unwrap();
+        let (_, body) = message.split_once("\r\n\r\n").unwrap();
+        let response: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], -32800);
+    }
+}
@@ -0,0 +1,102 @@
+//! Telemetry of messages that could not be turned into calls, so broken clients and probe
+//! traffic show up.
+//!
+//! With the `metrics` feature they are counted in `jrpc_malformed_requests_total`, by `kind`
+//! and, when it could be read, the `method`. Like all metrics, methods not registered with
+//! any router are labeled `unknown`. With the `tracing` feature each of them is logged at
+//! `DEBUG`, with the error and the peer address if axum provides
+//! [`ConnectInfo`](axum::extract::ConnectInfo).
+
+use http::Extensions;
+
+use crate::{Value, UNKNOWN_VERSION};
+
+/// Why a message was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Malformed {
+    /// Not valid json, or whichever encoding the content type announced
+    Syntax,
+    /// A jsonrpc version other than `2.0`
+    Version,
+    /// Valid json, but not a request object
+    Shape,
+    /// Larger than the body or batch element limits
+    Oversized,
+}
+
+impl Malformed {
+    /// Classifies the error of deserializing a request from a parsed message
+    pub(crate) fn of_request(error: &str) -> Self {
+        match error == UNKNOWN_VERSION {
+            true => Self::Version,
+            false => Self::Shape,
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "metrics", feature = "tracing")), allow(dead_code))]
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Syntax => "syntax",
+            Self::Version => "version",
+            Self::Shape => "shape",
+            Self::Oversized => "oversized",
+        }
+    }
+
+    /// Records the rejection of a message received with `extensions`
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    #[cfg_attr(
+        not(any(feature = "metrics", feature = "tracing")),
+        allow(clippy::unused_self)
+    )]
+    pub(crate) fn report(self, method: Option<&str>, error: &str, extensions: &Extensions) {
+        #[cfg(feature = "metrics")]
+        {
+            let method = method
+                .and_then(crate::intern::lookup)
+                .map_or(metrics::SharedString::const_str("unknown"), Into::into);
+            metrics::counter!(
+                "jrpc_malformed_requests_total",
+                "kind" => self.as_str(),
+                "method" => method,
+            )
+            .increment(1);
+        }
+        #[cfg(feature = "tracing")]
+        {
+            use std::net::SocketAddr;
+
+            #[cfg(not(target_arch = "wasm32"))]
+            let peer = extensions
+                .get::<axum::extract::ConnectInfo<SocketAddr>>()
+                .map(|info| info.0);
+            // axum has no connection info without tokio
+            #[cfg(target_arch = "wasm32")]
+            let peer = {
+                let _ = extensions;
+                None::<SocketAddr>
+            };
+            tracing::debug!(
+                kind = self.as_str(),
+                method,
+                peer = peer.map(tracing::field::display),
+                error,
+                "malformed request"
+            );
+        }
+    }
+}
+
+/// The method of a request object, read before it is deserialized so rejections can be
+/// labeled with it
+#[cfg_attr(not(any(feature = "metrics", feature = "tracing")), allow(dead_code))]
+pub(crate) fn method_of(value: &Value) -> Option<&str> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "serde_json")] {
+            value.get("method").and_then(Value::as_str)
+        } else {
+            use simd_json::prelude::*;
+            value.get_str("method")
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Serving an axum app over TLS with client certificates, for the `mtls` feature.
+//!
+//! [`serve_tls`] accepts TCP connections, runs the rustls handshake with the given
+//! [`ServerConfig`](rustls::ServerConfig), and gives every request received on a connection
+//! the [`ClientCert`] it presented, along with its
+//! [`ConnectInfo`](axum::extract::ConnectInfo). The calls of a request get both as extensions,
+//! so handlers read them with `req.extension::<ClientCert>()`, and since [`ClientCert`] is a
+//! [`Principal`], [`AuthorizeLayer`](crate::auth::AuthorizeLayer) can require a certificate
+//! naming a given host or user:
+//! ```rust,no_run
+//! use std::sync::Arc;
+//!
+//! use axum::Router;
+//! use axum_jrpc::auth::AuthorizeLayer;
+//! use axum_jrpc::mtls::{rustls, serve_tls, ClientCert};
+//! use axum_jrpc::{router, JsonRpcRouter};
+//! use tower::Layer;
+//!
+//! # async fn run(config: rustls::ServerConfig) -> std::io::Result<()> {
+//! let rpc = AuthorizeLayer::<ClientCert>::new()
+//!     .require("settle", ["settlement.internal"])
+//!     .layer(JsonRpcRouter::new());
+//! let app = Router::new().route("/", router::post(rpc));
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:8443").await?;
+//! serve_tls(listener, Arc::new(config), app).await;
+//! # Ok(())
+//! # }
+//! ```
+//! Whether a certificate is required, and which authorities are trusted, is up to the client
+//! certificate verifier of the config. Connections without one get no [`ClientCert`].
+
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{ConnectInfo, Request};
+use axum::response::Response;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::{Service, ServiceExt};
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+use crate::auth::Principal;
+
+pub use tokio_rustls::rustls;
+
+/// Certificate a client presented during the TLS handshake, an extension of the requests
+/// received by [`serve_tls`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCert {
+    /// Distinguished name of the subject, e.g. `CN=settlement, O=Example`
+    pub subject: String,
+    /// Common name of the subject, if it has one
+    pub common_name: Option<String>,
+    /// DNS names, emails, URIs and IP addresses of the subject alternative names
+    pub sans: Vec<String>,
+}
+
+impl ClientCert {
+    /// Parses a DER encoded certificate
+    pub fn from_der(der: &[u8]) -> Result<Self, String> {
+        let (_, cert) = X509Certificate::from_der(der).map_err(|e| e.to_string())?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_owned);
+        let sans = match cert.subject_alternative_name().map_err(|e| e.to_string())? {
+            Some(san) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name)
+                    | GeneralName::RFC822Name(name)
+                    | GeneralName::URI(name) => Some((*name).to_owned()),
+                    GeneralName::IPAddress(&[a, b, c, d]) => {
+                        Some(IpAddr::from([a, b, c, d]).to_string())
+                    }
+                    GeneralName::IPAddress(ip) => {
+                        let ip: [u8; 16] = (*ip).try_into().ok()?;
+                        Some(IpAddr::from(ip).to_string())
+                    }
+                    _ => None,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(Self {
+            subject: cert.subject().to_string(),
+            common_name,
+            sans,
+        })
+    }
+}
+
+/// A scope is held by certificates whose common name or one of whose alternative names is
+/// equal to it
+impl Principal for ClientCert {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.common_name.as_deref() == Some(scope) || self.sans.iter().any(|san| san == scope)
+    }
+}
+
+/// Serves `app` over TLS on every connection accepted by `listener`, until dropped.
+///
+/// HTTP/1 and HTTP/2 are both accepted, connection upgrades like WebSocket work too
+pub async fn serve_tls<S>(listener: TcpListener, config: Arc<rustls::ServerConfig>, app: S)
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    let acceptor = TlsAcceptor::from(config);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_e, "failed to accept connection");
+                // usually out of file descriptors, give the other connections time to close
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(%peer, error = %_e, "TLS handshake failed");
+                    return;
+                }
+            };
+            let cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .and_then(|der| ClientCert::from_der(der).ok());
+
+            let service = TowerToHyperService::new(app.map_request(move |req: Request<_>| {
+                let mut req = req.map(axum::body::Body::new);
+                req.extensions_mut().insert(ConnectInfo::<SocketAddr>(peer));
+                if let Some(cert) = &cert {
+                    req.extensions_mut().insert(cert.clone());
+                }
+                req
+            }));
+            let connection = Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await;
+            if let Err(_e) = connection {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(%peer, error = %_e, "connection failed");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use rcgen::{CertificateParams, DnType, Issuer, KeyPair};
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsConnector;
+    use tower::Layer;
+
+    use super::rustls::pki_types::{PrivateKeyDer, ServerName};
+    use super::rustls::server::WebPkiClientVerifier;
+    use super::rustls::{self, ClientConfig, RootCertStore, ServerConfig};
+    use super::{serve_tls, ClientCert};
+    use crate::auth::{AuthorizeLayer, FORBIDDEN};
+    use crate::{router, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn whoami(req: JsonRpcExtractor) -> JrpcResult {
+        let cert = req.extension::<ClientCert>().unwrap();
+        let answer = (cert.common_name.clone(), cert.sans.clone());
+        Ok(JsonRpcResponse::success(req.get_answer_id(), answer))
+    }
+
+    #[tokio::test]
+    async fn client_certs() {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca = CertificateParams::new(vec![]).unwrap();
+        ca.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        let ca_cert = ca.self_signed(&ca_key).unwrap();
+        let ca = Issuer::new(ca, ca_key);
+        let mut roots = RootCertStore::empty();
+        roots.add(ca_cert.der().clone()).unwrap();
+        let roots = Arc::new(roots);
+
+        let issue = |params: CertificateParams| {
+            let key = KeyPair::generate().unwrap();
+            let cert = params.signed_by(&key, &ca).unwrap();
+            let key = PrivateKeyDer::try_from(key.serialize_der()).unwrap();
+            (vec![cert.der().clone()], key)
+        };
+        let (chain, key) = issue(CertificateParams::new(vec!["localhost".to_owned()]).unwrap());
+        let verifier = WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone())
+            .build()
+            .unwrap();
+        let config = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(chain, key)
+            .unwrap();
+
+        let rpc = AuthorizeLayer::<ClientCert>::new()
+            .require("admin", ["ops.internal"])
+            .layer(
+                JsonRpcRouter::new()
+                    .method("whoami", whoami)
+                    .method("admin", whoami),
+            );
+        let app = Router::new().route("/", router::post(rpc));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_tls(listener, Arc::new(config), app));
+
+        let mut params = CertificateParams::new(vec!["settlement.internal".to_owned()]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "settlement");
+        let (chain, key) = issue(params);
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_client_auth_cert(chain, key)
+            .unwrap();
+        let connector = TlsConnector::from(Arc::new(config));
+        let call = |method: &str| {
+            let connector = connector.clone();
+            let body = json!({"jsonrpc": "2.0", "id": 1, "method": method}).to_string();
+            async move {
+                let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+                let name = ServerName::try_from("localhost").unwrap();
+                let mut stream = connector.connect(name, stream).await.unwrap();
+                let request = format!(
+                    "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                // the server closes the connection without a close_notify
+                let _ = stream.read_to_string(&mut response).await;
+                let (_, body) = response.split_once("\r\n\r\n").unwrap();
+                serde_json::from_str::<Value>(body).unwrap()
+            }
+        };
+
+        let res = call("whoami").await;
+        assert_eq!(
+            res["result"],
+            json!(["settlement", ["settlement.internal"]])
+        );
+        let res = call("admin").await;
+        assert_eq!(res["error"]["code"], FORBIDDEN);
+    }
+}
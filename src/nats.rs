@@ -0,0 +1,170 @@
+//! JSON-RPC over NATS, so the handlers serving HTTP also serve message-bus traffic.
+//!
+//! [`NatsServer`] answers requests published on a subject, replying on the reply subject of
+//! each message. Messages without a reply subject are handled as well, their responses are
+//! dropped. With a queue group, requests are spread over all replicas instead of being
+//! handled by each of them.
+//! ```rust,no_run
+//! use axum_jrpc::nats::NatsServer;
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! # async fn run() -> Result<(), axum_jrpc::nats::NatsError> {
+//! let client = async_nats::connect("nats://127.0.0.1:4222").await?;
+//! let rpc = JsonRpcRouter::new();
+//! NatsServer::new(client, "rpc.accounts")
+//!     .queue_group("accounts")
+//!     .serve(rpc)
+//!     .await
+//! # }
+//! ```
+//! Handlers can read the subject a request arrived on with
+//! `req.extension::<async_nats::Subject>()`.
+//!
+//! The other direction is covered by [`call`], which sends a request and waits for the
+//! response, and [`publish`], which sends notifications.
+
+use std::sync::Arc;
+
+use async_nats::subject::ToSubject;
+use async_nats::{Client, Message, Subscriber};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use http::Extensions;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+
+use crate::batch::MAX_IN_FLIGHT;
+use crate::router::{handle_slice, JsonRpcService};
+use crate::{from_slice, to_vec, JsonRpcRequest, JsonRpcResponse};
+
+/// Errors returned by the NATS adapter.
+#[derive(Debug, Error)]
+pub enum NatsError {
+    /// The NATS server could not be reached or refused the operation.
+    #[error("NATS error: {0}")]
+    Nats(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// A message could not be (de)serialized.
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
+
+impl<K> From<async_nats::error::Error<K>> for NatsError
+where
+    K: Clone + std::fmt::Debug + std::fmt::Display + PartialEq + Send + Sync + 'static,
+{
+    fn from(error: async_nats::error::Error<K>) -> Self {
+        Self::Nats(Box::new(error))
+    }
+}
+
+impl From<async_nats::SubscribeError> for NatsError {
+    fn from(error: async_nats::SubscribeError) -> Self {
+        Self::Nats(Box::new(error))
+    }
+}
+
+/// Subscribes to a subject and answers the requests published on it.
+#[derive(Debug, Clone)]
+pub struct NatsServer {
+    client: Client,
+    subject: String,
+    queue_group: Option<String>,
+}
+
+impl NatsServer {
+    /// `subject` may contain wildcards, e.g. `rpc.>`
+    pub fn new(client: Client, subject: impl Into<String>) -> Self {
+        Self {
+            client,
+            subject: subject.into(),
+            queue_group: None,
+        }
+    }
+
+    /// Joins a queue group, each request is then handled by a single member of the group
+    pub fn queue_group(mut self, queue_group: impl Into<String>) -> Self {
+        self.queue_group = Some(queue_group.into());
+        self
+    }
+
+    /// Subscribes to the subject
+    pub async fn subscribe(&self) -> Result<Subscriber, NatsError> {
+        let subject = self.subject.clone();
+        let subscriber = match &self.queue_group {
+            Some(group) => self.client.queue_subscribe(subject, group.clone()).await?,
+            None => self.client.subscribe(subject).await?,
+        };
+        Ok(subscriber)
+    }
+
+    /// Subscribes to the subject and answers requests until the connection is closed
+    pub async fn serve<S: JsonRpcService>(self, service: S) -> Result<(), NatsError> {
+        let subscriber = self.subscribe().await?;
+        serve_nats(self.client, subscriber, service).await;
+        Ok(())
+    }
+}
+
+/// Answers the requests received by `subscriber` until it ends.
+///
+/// Requests are handled concurrently, at most 64 of them at a time: the next message is only taken
+/// once one of them completes. Responses are published with `client`
+pub async fn serve_nats<S: JsonRpcService>(client: Client, mut subscriber: Subscriber, service: S) {
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+    loop {
+        let Ok(permit) = in_flight.clone().acquire_owned().await else {
+            break;
+        };
+        let Some(message) = subscriber.next().await else {
+            break;
+        };
+        let client = client.clone();
+        let service = service.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let Message {
+                subject,
+                reply,
+                payload,
+                ..
+            } = message;
+            let mut extensions = Extensions::new();
+            extensions.insert(subject);
+
+            let Some(response) = handle_slice(&service, &payload, extensions).await else {
+                return;
+            };
+            let (Some(subject), Ok(payload)) = (reply, to_vec(&response)) else {
+                return;
+            };
+            if let Err(_e) = client.publish(subject, Bytes::from(payload)).await {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %_e, "failed to publish response");
+            }
+        });
+    }
+}
+
+/// Sends `request` to `subject` and waits for the response.
+///
+/// Times out after the request timeout of `client`, 10 seconds by default
+pub async fn call(
+    client: &Client,
+    subject: impl ToSubject,
+    request: &JsonRpcRequest,
+) -> Result<JsonRpcResponse, NatsError> {
+    let payload = to_vec(request).map_err(NatsError::Serialization)?;
+    let response = client.request(subject, Bytes::from(payload)).await?;
+    from_slice(&mut Vec::from(response.payload)).map_err(NatsError::Serialization)
+}
+
+/// Publishes `notification` on `subject` without waiting for anything
+pub async fn publish(
+    client: &Client,
+    subject: impl ToSubject,
+    notification: &JsonRpcRequest,
+) -> Result<(), NatsError> {
+    let payload = to_vec(notification).map_err(NatsError::Serialization)?;
+    client.publish(subject, Bytes::from(payload)).await?;
+    Ok(())
+}
@@ -0,0 +1,271 @@
+//! [utoipa](https://docs.rs/utoipa) integration documenting a JSON-RPC endpoint next to REST
+//! routes in one OpenAPI document, for the `utoipa` feature.
+//!
+//! [`JsonRpcApi`] lists the methods of an endpoint with the [`ToSchema`] types of their params
+//! and results. Merged into an OpenAPI document, it contributes:
+//! * the envelope schemas `JsonRpcRequest`, `JsonRpcResponse`, `JsonRpcError` and `JsonRpcId`
+//! * a `POST` operation on the endpoint accepting any request
+//! * a `POST` operation per method, on the endpoint path with the method name as its fragment,
+//!   e.g. `/rpc#math.add`, whose bodies carry the params and result schemas of the method
+//!
+//! ```rust
+//! use axum_jrpc::openapi::JsonRpcApi;
+//! use serde::Deserialize;
+//! use utoipa::{OpenApi, ToSchema};
+//!
+//! #[derive(Deserialize, ToSchema)]
+//! struct AddParams {
+//!     a: i64,
+//!     b: i64,
+//! }
+//!
+//! #[derive(OpenApi)]
+//! #[openapi(info(title = "calculator"))]
+//! struct ApiDoc;
+//!
+//! let rpc = JsonRpcApi::new("/rpc").method::<AddParams, i64>("math.add", "Adds two numbers");
+//! let mut doc = ApiDoc::openapi();
+//! doc.merge(rpc.openapi());
+//! assert!(doc.paths.paths.contains_key("/rpc#math.add"));
+//! ```
+//! [`JsonRpcApi`] also implements [`Modify`], for `#[openapi(modifiers(..))]`.
+
+pub use utoipa;
+use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem, PathsBuilder};
+use utoipa::openapi::request_body::RequestBodyBuilder;
+use utoipa::openapi::schema::{ObjectBuilder, OneOfBuilder, Schema, Type};
+use utoipa::openapi::{
+    ComponentsBuilder, ContentBuilder, OpenApi, OpenApiBuilder, Ref, RefOr, Required,
+    ResponseBuilder,
+};
+use utoipa::{Modify, ToSchema};
+
+const JSON: &str = "application/json";
+
+/// Methods of a JSON-RPC endpoint, see the [module docs](self).
+#[derive(Clone)]
+pub struct JsonRpcApi {
+    path: String,
+    tag: Option<String>,
+    methods: Vec<Method>,
+    schemas: Vec<(String, RefOr<Schema>)>,
+}
+
+#[derive(Clone)]
+struct Method {
+    name: String,
+    summary: String,
+    params: RefOr<Schema>,
+    result: RefOr<Schema>,
+}
+
+impl std::fmt::Debug for JsonRpcApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let methods: Vec<_> = self.methods.iter().map(|method| &method.name).collect();
+        f.debug_struct("JsonRpcApi")
+            .field("path", &self.path)
+            .field("tag", &self.tag)
+            .field("methods", &methods)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JsonRpcApi {
+    /// Documents the endpoint served at `path`
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            tag: None,
+            methods: Vec::new(),
+            schemas: Vec::new(),
+        }
+    }
+
+    /// Groups the operations under `tag`
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Documents `method`, taking params of type `P` and returning an `R`
+    pub fn method<P: ToSchema, R: ToSchema>(
+        mut self,
+        name: impl Into<String>,
+        summary: impl Into<String>,
+    ) -> Self {
+        P::schemas(&mut self.schemas);
+        R::schemas(&mut self.schemas);
+        self.methods.push(Method {
+            name: name.into(),
+            summary: summary.into(),
+            params: P::schema(),
+            result: R::schema(),
+        });
+        self
+    }
+
+    /// The paths and schemas of the endpoint, to [`merge`](OpenApi::merge) into a document
+    pub fn openapi(&self) -> OpenApi {
+        let mut components = ComponentsBuilder::new()
+            .schema("JsonRpcId", id())
+            .schema("JsonRpcError", error())
+            .schema("JsonRpcRequest", request(string(None), any()))
+            .schema("JsonRpcResponse", response(any()));
+        for (name, schema) in &self.schemas {
+            components = components.schema(name, schema.clone());
+        }
+
+        let endpoint = self.operation(
+            "Any JSON-RPC call",
+            Ref::from_schema_name("JsonRpcRequest").into(),
+            Ref::from_schema_name("JsonRpcResponse").into(),
+        );
+        let mut paths =
+            PathsBuilder::new().path(&self.path, PathItem::new(HttpMethod::Post, endpoint));
+        for method in &self.methods {
+            let operation = self.operation(
+                &method.summary,
+                request(string(Some(&method.name)), method.params.clone()).into(),
+                response(method.result.clone()).into(),
+            );
+            let path = format!("{}#{}", self.path, method.name);
+            paths = paths.path(path, PathItem::new(HttpMethod::Post, operation));
+        }
+
+        OpenApiBuilder::new()
+            .paths(paths)
+            .components(Some(components.build()))
+            .build()
+    }
+
+    fn operation(
+        &self,
+        summary: &str,
+        request: RefOr<Schema>,
+        response: RefOr<Schema>,
+    ) -> utoipa::openapi::path::Operation {
+        let body = RequestBodyBuilder::new()
+            .required(Some(Required::True))
+            .content(JSON, ContentBuilder::new().schema(Some(request)).build())
+            .build();
+        let response = ResponseBuilder::new()
+            .description("JSON-RPC response, errors included")
+            .content(JSON, ContentBuilder::new().schema(Some(response)).build());
+        OperationBuilder::new()
+            .summary(Some(summary))
+            .tags(self.tag.as_ref().map(|tag| [tag.clone()]))
+            .request_body(Some(body))
+            .response("200", response)
+            .build()
+    }
+}
+
+impl Modify for JsonRpcApi {
+    fn modify(&self, openapi: &mut OpenApi) {
+        openapi.merge(self.openapi());
+    }
+}
+
+fn any() -> RefOr<Schema> {
+    utoipa::openapi::schema::empty().into()
+}
+
+/// A string, only `value` if set
+fn string(value: Option<&str>) -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(Type::String)
+        .enum_values(value.map(|value| [value]))
+        .into()
+}
+
+fn id() -> RefOr<Schema> {
+    OneOfBuilder::new()
+        .item(ObjectBuilder::new().schema_type(Type::String))
+        .item(ObjectBuilder::new().schema_type(Type::Integer))
+        .item(ObjectBuilder::new().schema_type(Type::Null))
+        .description(Some("Absent in notifications, which get no response"))
+        .into()
+}
+
+fn error() -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .property("code", ObjectBuilder::new().schema_type(Type::Integer))
+        .required("code")
+        .property("message", string(None))
+        .required("message")
+        .property("data", any())
+        .into()
+}
+
+fn request(method: RefOr<Schema>, params: RefOr<Schema>) -> Schema {
+    ObjectBuilder::new()
+        .property("jsonrpc", string(Some("2.0")))
+        .required("jsonrpc")
+        .property("id", Ref::from_schema_name("JsonRpcId"))
+        .property("method", method)
+        .required("method")
+        .property("params", params)
+        .into()
+}
+
+fn response(result: RefOr<Schema>) -> Schema {
+    ObjectBuilder::new()
+        .property("jsonrpc", string(Some("2.0")))
+        .required("jsonrpc")
+        .property("id", Ref::from_schema_name("JsonRpcId"))
+        .required("id")
+        .property("result", result)
+        .property("error", Ref::from_schema_name("JsonRpcError"))
+        .description(Some("Has either a `result` or an `error`"))
+        .into()
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::json;
+    use utoipa::ToSchema;
+
+    use super::JsonRpcApi;
+
+    #[allow(dead_code)]
+    #[derive(ToSchema)]
+    struct AddParams {
+        a: i64,
+        b: Operand,
+    }
+
+    #[allow(dead_code)]
+    #[derive(ToSchema)]
+    struct Operand {
+        value: i64,
+    }
+
+    #[test]
+    fn documents_methods() {
+        let api = JsonRpcApi::new("/rpc")
+            .tag("rpc")
+            .method::<AddParams, i64>("math.add", "Adds");
+        let doc = serde_json::to_value(api.openapi()).unwrap();
+
+        let add = &doc["paths"]["/rpc#math.add"]["post"];
+        assert_eq!(add["summary"], "Adds");
+        assert_eq!(add["tags"], json!(["rpc"]));
+        let request = &add["requestBody"]["content"]["application/json"]["schema"];
+        assert_eq!(request["properties"]["method"]["enum"], json!(["math.add"]));
+        assert_eq!(
+            request["properties"]["params"]["properties"]["b"]["$ref"],
+            "#/components/schemas/Operand"
+        );
+        let response = &add["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(response["properties"]["result"]["type"], "integer");
+        assert!(doc["paths"]["/rpc"]["post"].is_object());
+
+        let schemas = &doc["components"]["schemas"];
+        assert!(schemas["Operand"].is_object());
+        assert_eq!(
+            schemas["JsonRpcError"]["required"],
+            json!(["code", "message"])
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! [OpenRPC](https://spec.open-rpc.org) documents of the methods of a router, for the
+//! `openrpc` feature.
+//!
+//! Methods are described with [`MethodDoc`]s attached by
+//! [`JsonRpcRouter::describe`](crate::JsonRpcRouter::describe), their params and result
+//! schemas are derived with [`schemars`]. [`JsonRpcRouter::openrpc`](crate::JsonRpcRouter::openrpc)
+//! builds the document of every registered method, which can be written to disk or served
+//! with the `rpc.discover` method of [`JsonRpcRouter::discover`](crate::JsonRpcRouter::discover).
+//! ```rust
+//! use axum_jrpc::openrpc::{Info, MethodDoc};
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! struct AddParams {
+//!     a: i64,
+//!     b: i64,
+//! }
+//!
+//! async fn add(req: JsonRpcExtractor) -> JrpcResult {
+//!     let id = req.get_answer_id();
+//!     let params: AddParams = req.parse_params()?;
+//!     Ok(JsonRpcResponse::success(id, params.a + params.b))
+//! }
+//!
+//! let rpc = JsonRpcRouter::new().method("add", add).describe(
+//!     "add",
+//!     MethodDoc::new()
+//!         .summary("Adds two numbers")
+//!         .params::<AddParams>()
+//!         .result::<i64>()
+//!         .error(-32602, "Invalid params"),
+//! );
+//! let document = rpc.openrpc(Info::new("calculator", "1.0.0"));
+//! std::fs::write(
+//!     std::env::temp_dir().join("openrpc.json"),
+//!     serde_json::to_vec_pretty(&document).unwrap(),
+//! )
+//! .unwrap();
+//! ```
+
+pub use schemars;
+use schemars::generate::{SchemaGenerator, SchemaSettings};
+use schemars::{JsonSchema, Schema};
+use serde::Serialize;
+
+/// Version of the OpenRPC specification the documents follow
+pub const OPENRPC_VERSION: &str = "1.3.2";
+
+const DEFINITIONS: &str = "/components/schemas";
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+fn schema_of<T: JsonSchema>(generator: &mut SchemaGenerator) -> Schema {
+    generator.subschema_for::<T>()
+}
+
+/// Description of a method, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct MethodDoc {
+    summary: Option<String>,
+    description: Option<String>,
+    params: Params,
+    result: Option<SchemaFn>,
+    errors: Vec<ErrorDoc>,
+    deprecated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+enum Params {
+    #[default]
+    Unknown,
+    /// The params as a whole, its fields are the named params
+    Whole(SchemaFn),
+    /// Params accepted by position or by name
+    Each(Vec<(String, SchemaFn)>),
+}
+
+impl MethodDoc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Short summary of what the method does
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    /// Verbose explanation of the method
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Params parsed as a whole into `P`, e.g. with
+    /// [`parse_params`](crate::JsonRpcExtractor::parse_params). The fields of a struct become
+    /// the params, which are passed by name.
+    pub fn params<P: JsonSchema>(mut self) -> Self {
+        self.params = Params::Whole(schema_of::<P>);
+        self
+    }
+
+    /// Appends a param named `name`, passed by position or by name, the way methods generated
+    /// by [`jrpc_method`](crate::jrpc_method) accept their arguments
+    pub fn param<T: JsonSchema>(mut self, name: impl Into<String>) -> Self {
+        let mut params = match self.params {
+            Params::Each(params) => params,
+            _ => Vec::new(),
+        };
+        params.push((name.into(), schema_of::<T>));
+        self.params = Params::Each(params);
+        self
+    }
+
+    /// The method returns an `R`
+    pub fn result<R: JsonSchema>(mut self) -> Self {
+        self.result = Some(schema_of::<R>);
+        self
+    }
+
+    /// The method may fail with `code`
+    pub fn error(mut self, code: i32, message: impl Into<String>) -> Self {
+        self.errors.push(ErrorDoc {
+            code,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Marks the method as deprecated
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self
+    }
+
+    fn method(&self, name: &str, generator: &mut SchemaGenerator) -> Method {
+        let (params, param_structure) = match &self.params {
+            Params::Unknown => (Vec::new(), None),
+            Params::Whole(schema) => {
+                let schema = schema(generator);
+                (fields(&schema, generator), Some(ParamStructure::ByName))
+            }
+            Params::Each(params) => {
+                let params = params
+                    .iter()
+                    .map(|(name, schema)| ContentDescriptor {
+                        name: name.clone(),
+                        required: true,
+                        schema: schema(generator),
+                    })
+                    .collect();
+                (params, Some(ParamStructure::Either))
+            }
+        };
+        Method {
+            name: name.to_owned(),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            params,
+            param_structure,
+            result: ContentDescriptor {
+                name: "result".to_owned(),
+                required: true,
+                schema: self
+                    .result
+                    .map_or_else(Schema::default, |schema| schema(generator)),
+            },
+            errors: self.errors.clone(),
+            deprecated: self.deprecated,
+        }
+    }
+}
+
+/// Named params of the object `schema`, following its reference
+fn fields(schema: &Schema, generator: &SchemaGenerator) -> Vec<ContentDescriptor> {
+    let prefix = format!("#{DEFINITIONS}/");
+    let schema = match schema.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) => reference
+            .strip_prefix(&prefix)
+            .and_then(|name| generator.definitions().get(name)),
+        None => Some(schema.as_value()),
+    };
+    let Some(properties) = schema
+        .and_then(|schema| schema.get("properties"))
+        .and_then(|properties| properties.as_object())
+    else {
+        return Vec::new();
+    };
+    let required = schema
+        .and_then(|schema| schema.get("required"))
+        .and_then(|required| required.as_array());
+    properties
+        .iter()
+        .filter_map(|(name, schema)| {
+            Some(ContentDescriptor {
+                name: name.clone(),
+                required: required.is_some_and(|required| required.iter().any(|r| r == name)),
+                schema: Schema::try_from(schema.clone()).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Metadata of the api
+#[derive(Debug, Clone, Serialize)]
+pub struct Info {
+    pub title: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl Info {
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// An OpenRPC document
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenRpc {
+    pub openrpc: &'static str,
+    pub info: Info,
+    pub methods: Vec<Method>,
+    pub components: Components,
+}
+
+/// Schemas referenced by the methods
+#[derive(Debug, Clone, Serialize)]
+pub struct Components {
+    pub schemas: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Method {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub params: Vec<ContentDescriptor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub param_structure: Option<ParamStructure>,
+    pub result: ContentDescriptor,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ErrorDoc>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParamStructure {
+    ByName,
+    ByPosition,
+    Either,
+}
+
+/// A named param or result and its schema
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentDescriptor {
+    pub name: String,
+    pub required: bool,
+    pub schema: Schema,
+}
+
+/// An error a method may fail with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorDoc {
+    pub code: i32,
+    pub message: String,
+}
+
+/// Document of the `methods`, sorted by name, those without a [`MethodDoc`] are only named
+pub(crate) fn document<'a>(
+    info: Info,
+    methods: impl Iterator<Item = (&'a str, Option<&'a MethodDoc>)>,
+) -> OpenRpc {
+    let mut generator = SchemaSettings::draft2020_12()
+        .with(|settings| settings.definitions_path = DEFINITIONS.into())
+        .into_generator();
+    let mut methods: Vec<_> = methods
+        .map(|(name, doc)| match doc {
+            Some(doc) => doc.method(name, &mut generator),
+            None => MethodDoc::new().method(name, &mut generator),
+        })
+        .collect();
+    methods.sort_by(|a, b| a.name.cmp(&b.name));
+    OpenRpc {
+        openrpc: OPENRPC_VERSION,
+        info,
+        methods,
+        components: Components {
+            schemas: generator.take_definitions(true),
+        },
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    use super::{Info, MethodDoc};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    #[derive(Deserialize, JsonSchema)]
+    struct AddParams {
+        a: i64,
+        /// Defaults to zero
+        #[serde(default)]
+        b: i64,
+    }
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let params: AddParams = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, params.a + params.b))
+    }
+
+    #[tokio::test]
+    async fn documents() {
+        let rpc = JsonRpcRouter::new()
+            .method("add", add)
+            .method("ping", add)
+            .describe(
+                "add",
+                MethodDoc::new()
+                    .summary("Adds")
+                    .params::<AddParams>()
+                    .result::<i64>()
+                    .error(-32602, "Invalid params"),
+            )
+            .discover(Info::new("calculator", "1.0.0"));
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "rpc.discover"});
+        let reply = rpc.dispatch_bytes(request.to_string().into()).await;
+        let document: Value = serde_json::from_slice(&reply.unwrap()).unwrap();
+        let document = &document["result"];
+        assert_eq!(document["openrpc"], "1.3.2");
+        assert_eq!(document["info"]["title"], "calculator");
+
+        let methods = document["methods"].as_array().unwrap();
+        let names: Vec<_> = methods.iter().map(|method| &method["name"]).collect();
+        assert_eq!(names, ["add", "ping"]);
+        let add = &methods[0];
+        assert_eq!(add["summary"], "Adds");
+        assert_eq!(add["paramStructure"], "by-name");
+        assert_eq!(add["params"][0]["name"], "a");
+        assert_eq!(add["params"][0]["required"], true);
+        assert_eq!(add["params"][1]["required"], false);
+        assert_eq!(
+            add["params"][1]["schema"]["description"],
+            "Defaults to zero"
+        );
+        assert_eq!(add["result"]["schema"]["type"], "integer");
+        assert_eq!(
+            add["errors"],
+            json!([{"code": -32602, "message": "Invalid params"}])
+        );
+        assert_eq!(methods[1]["params"], json!([]));
+        assert_eq!(
+            document["components"]["schemas"]["AddParams"]["type"],
+            "object"
+        );
+    }
+}
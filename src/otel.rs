@@ -0,0 +1,55 @@
+use http::{HeaderMap, HeaderName, HeaderValue};
+#[cfg(feature = "server")]
+use opentelemetry::propagation::Extractor;
+use opentelemetry::propagation::Injector;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Writes the context of the current span into `headers` using the global propagator
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub(crate) fn inject_context(headers: &mut HeaderMap) {
+    let context = tracing::Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+#[cfg(feature = "server")]
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+#[cfg(feature = "server")]
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// Reads the context propagated in `headers`, e.g. `traceparent` and `tracestate`, using the
+/// global propagator and inserts it into `extensions` as an [`opentelemetry::Context`]
+#[cfg(feature = "server")]
+pub(crate) fn extract_context(headers: &HeaderMap, extensions: &mut http::Extensions) {
+    use opentelemetry::trace::TraceContextExt;
+
+    let context = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    if context.has_active_span() {
+        extensions.insert(context);
+    }
+}
@@ -0,0 +1,64 @@
+//! Interactive playground of an endpoint, for the `playground` feature.
+//!
+//! [`get`] serves a single HTML page which loads the [OpenRPC](crate::openrpc) document of
+//! the endpoint with `rpc.discover`, lists its methods with their schemas and sends calls
+//! edited in the browser. The endpoint must serve the document, see
+//! [`JsonRpcRouter::discover`](crate::JsonRpcRouter::discover).
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::openrpc::Info;
+//! use axum_jrpc::{playground, router, JsonRpcRouter};
+//!
+//! let rpc = JsonRpcRouter::new().discover(Info::new("calculator", "1.0.0"));
+//! let app: Router = Router::new()
+//!     .route("/rpc", router::post(rpc))
+//!     .route("/playground", playground::get("/rpc"));
+//! ```
+//! The page has no dependencies, but it lets anyone reaching it call every method: serve it
+//! in development, or behind the same authentication as the endpoint.
+
+use axum::response::Html;
+use axum::routing::MethodRouter;
+
+const PAGE: &str = include_str!("playground.html");
+
+/// Route serving the playground of the endpoint at `endpoint`, a path or a URL
+pub fn get(endpoint: &str) -> MethodRouter {
+    let page = Html(page(endpoint));
+    axum::routing::get(move || {
+        let page = page.clone();
+        async move { page }
+    })
+}
+
+fn page(endpoint: &str) -> String {
+    // a JS string literal which can't close the script tag
+    let endpoint = serde_json::to_string(endpoint)
+        .expect("strings serialize")
+        .replace('<', "\\u003c");
+    PAGE.replace("__ENDPOINT__", &endpoint)
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Router;
+    use axum_test::TestServer;
+
+    use super::get;
+
+    #[tokio::test]
+    async fn serves_page() {
+        let app = Router::new().route("/playground", get("/rpc</script>"));
+        let server = TestServer::new(app).unwrap();
+
+        let res = server.get("/playground").await;
+        res.assert_status_ok();
+        let page = res.text();
+        assert!(page.contains(r#"const ENDPOINT = "/rpc\u003c/script>";"#));
+        assert!(res
+            .header("content-type")
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+    }
+}
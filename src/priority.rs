@@ -0,0 +1,309 @@
+//! Priority scheduling of calls, for the `priority` feature.
+//!
+//! [`PriorityLayer`] wraps a service and lets a bounded number of calls run at once. Under
+//! load, the others wait in a lane per [`Priority`], and each call completing lets in the
+//! oldest call of the highest lane, so health checks and control calls are not stuck behind
+//! bulk queries. A call waiting longer than [`PriorityLayer::max_wait`] is let in before any
+//! other though, so the lower lanes still move while the higher ones are busy.
+//! ```rust
+//! use axum_jrpc::priority::{Priority, PriorityLayer};
+//! use axum_jrpc::api_key::ApiKey;
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let priority = PriorityLayer::new(64)
+//!     .method("health", Priority::High)
+//!     .method("export_all", Priority::Low)
+//!     .classify(|req| {
+//!         let key = req.extension::<ApiKey>()?;
+//!         (key.name == "ops").then_some(Priority::High)
+//!     });
+//! let rpc = priority.layer(JsonRpcRouter::new());
+//! ```
+//! Calls get the priority returned by the [`classify`](PriorityLayer::classify) closure, or
+//! else the one of their method, or else [`Priority::Normal`].
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use tokio::sync::oneshot;
+use tower::{Layer, Service};
+
+use crate::router::BoxFuture;
+use crate::{JsonRpcExtractor, JsonRpcResponse};
+
+/// Lane of a call, higher ones are let in first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+type ClassifyFn = dyn Fn(&JsonRpcExtractor) -> Option<Priority> + Send + Sync;
+
+/// Wraps services in [`Prioritized`], see the [module docs](self).
+#[derive(Clone)]
+pub struct PriorityLayer {
+    methods: Arc<HashMap<String, Priority>>,
+    classify: Option<Arc<ClassifyFn>>,
+    scheduler: Arc<Scheduler>,
+}
+
+impl std::fmt::Debug for PriorityLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PriorityLayer")
+            .field("methods", &self.methods)
+            .field("max_concurrent", &self.scheduler.max_concurrent)
+            .field("max_wait", &self.scheduler.max_wait)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PriorityLayer {
+    /// Longest a call waits behind higher lanes unless [`max_wait`](Self::max_wait) sets
+    /// another time
+    pub const MAX_WAIT: Duration = Duration::from_secs(1);
+
+    /// Lets `max_concurrent` calls run at once, the others wait in their lane
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            methods: Default::default(),
+            classify: None,
+            scheduler: Arc::new(Scheduler {
+                max_concurrent,
+                max_wait: Self::MAX_WAIT,
+                state: Default::default(),
+            }),
+        }
+    }
+
+    /// Sets the priority of `method`
+    pub fn method(mut self, method: impl Into<String>, priority: Priority) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into(), priority);
+        self
+    }
+
+    /// Gives calls the priority returned by `classify`, e.g. by their client, falling back
+    /// to the priority of their method when it returns `None`
+    pub fn classify<F>(mut self, classify: F) -> Self
+    where
+        F: Fn(&JsonRpcExtractor) -> Option<Priority> + Send + Sync + 'static,
+    {
+        self.classify = Some(Arc::new(classify));
+        self
+    }
+
+    /// Lets in calls which waited `max_wait` before those of higher lanes
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.scheduler = Arc::new(Scheduler {
+            max_concurrent: self.scheduler.max_concurrent,
+            max_wait,
+            state: Default::default(),
+        });
+        self
+    }
+
+    fn priority(&self, request: &JsonRpcExtractor) -> Priority {
+        self.classify
+            .as_ref()
+            .and_then(|classify| classify(request))
+            .or_else(|| self.methods.get(request.method()).copied())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+struct Scheduler {
+    max_concurrent: usize,
+    max_wait: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    running: usize,
+    /// Waiting calls by priority, oldest first
+    lanes: [VecDeque<Waiter>; 3],
+}
+
+#[derive(Debug)]
+struct Waiter {
+    since: Instant,
+    slot: oneshot::Sender<Slot>,
+}
+
+/// Permission to run a call, handed to the next one when dropped
+#[derive(Debug)]
+struct Slot(Arc<Scheduler>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let next = {
+            let mut state = self.0.state.lock().unwrap_or_else(|e| e.into_inner());
+            match self.0.next(&mut state) {
+                Some(waiter) => waiter,
+                None => {
+                    state.running -= 1;
+                    return;
+                }
+            }
+        };
+        // a cancelled call drops the slot right away, which hands it to the next one
+        let _ = next.slot.send(Slot(self.0.clone()));
+    }
+}
+
+impl Scheduler {
+    async fn acquire(self: &Arc<Self>, priority: Priority) -> Slot {
+        let receiver = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let queued = state.lanes.iter().any(|lane| !lane.is_empty());
+            if state.running < self.max_concurrent && !queued {
+                state.running += 1;
+                return Slot(self.clone());
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.lanes[priority as usize].push_back(Waiter {
+                since: Instant::now(),
+                slot: sender,
+            });
+            receiver
+        };
+        receiver
+            .await
+            .expect("waiters are only dropped once handed a slot")
+    }
+
+    /// The waiter to hand a slot to: the oldest one if it waited too long, otherwise the
+    /// oldest one of the highest lane
+    fn next(&self, state: &mut State) -> Option<Waiter> {
+        let oldest = state
+            .lanes
+            .iter()
+            .enumerate()
+            .filter_map(|(lane, waiters)| Some((lane, waiters.front()?.since)))
+            .min_by_key(|(_, since)| *since);
+        let lane = match oldest {
+            Some((lane, since)) if since.elapsed() >= self.max_wait => lane,
+            _ => state.lanes.iter().rposition(|lane| !lane.is_empty())?,
+        };
+        state.lanes[lane].pop_front()
+    }
+}
+
+impl<S> Layer<S> for PriorityLayer {
+    type Service = Prioritized<S>;
+
+    fn layer(&self, inner: S) -> Prioritized<S> {
+        Prioritized {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Schedules calls by priority, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Prioritized<S> {
+    inner: S,
+    layer: PriorityLayer,
+}
+
+impl<S> Service<JsonRpcExtractor> for Prioritized<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        // the ready service is taken, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let priority = self.layer.priority(&request);
+        let scheduler = self.layer.scheduler.clone();
+
+        Box::pin(async move {
+            let _slot = scheduler.acquire(priority).await;
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+    use serde_json::json;
+    use tower::Layer;
+
+    use super::{Priority, PriorityLayer};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    #[tokio::test]
+    async fn lanes() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let record = {
+            let order = order.clone();
+            move |req: JsonRpcExtractor| {
+                let order = order.clone();
+                async move {
+                    order.lock().unwrap().push(req.method().to_owned());
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    JrpcResult::Ok(JsonRpcResponse::success(req.get_answer_id(), ()))
+                }
+            }
+        };
+        let router = ["bulk", "health", "other"]
+            .into_iter()
+            .fold(JsonRpcRouter::new(), |router, method| {
+                router.method(method, record.clone())
+            });
+        let rpc = PriorityLayer::new(1)
+            .method("health", Priority::High)
+            .method("bulk", Priority::Low)
+            .layer(router);
+        let call = |method: &str| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": method}).to_string();
+            rpc.dispatch_bytes(request.into())
+        };
+
+        join_all([call("bulk"), call("bulk"), call("other"), call("health")]).await;
+        // the first call runs at once, the others by priority
+        assert_eq!(*order.lock().unwrap(), ["bulk", "health", "other", "bulk"]);
+
+        // calls waiting too long go first
+        let rpc = PriorityLayer::new(1)
+            .method("health", Priority::High)
+            .max_wait(Duration::ZERO)
+            .layer(
+                JsonRpcRouter::new()
+                    .method("bulk", record.clone())
+                    .method("health", record),
+            );
+        order.lock().unwrap().clear();
+        let call = |method: &str| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": method}).to_string();
+            rpc.dispatch_bytes(request.into())
+        };
+        join_all([call("bulk"), call("bulk"), call("health")]).await;
+        assert_eq!(*order.lock().unwrap(), ["bulk", "bulk", "health"]);
+    }
+}
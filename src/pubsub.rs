@@ -0,0 +1,706 @@
+//! Server-side subscriptions.
+//!
+//! A subscription method is registered with [`JsonRpcRouter::subscription`] together with
+//! the method name of its notifications and of the matching unsubscribe call. The handler
+//! receives a [`PendingSubscription`] and either rejects it or accepts it, getting a
+//! [`SubscriptionSink`] to push notifications with. The client gets the subscription id as
+//! the result of its call and receives notifications shaped like
+//! `{"method": "<notification>", "params": {"subscription": <id>, "result": <value>}}`.
+//!
+//! Subscriptions live as long as the connection they were made on, so they need a transport
+//! able to push messages, like [`ws`](crate::ws), [`sse`](crate::sse) or
+//! [`longpoll`](crate::longpoll).
+//!
+//! Each connection queues at most [`QUEUE_CAPACITY`] messages for its client. Once a slow
+//! client lets the queue fill up, [`SubscriptionSink::send`] waits for room, so producers
+//! are slowed down to the pace of the client rather than growing the queue.
+//! ```rust
+//! use std::time::Duration;
+//! use axum_jrpc::pubsub::PendingSubscription;
+//! use axum_jrpc::{JsonRpcExtractor, JsonRpcRouter};
+//!
+//! async fn ticks(_: JsonRpcExtractor, pending: PendingSubscription) {
+//!     let sink = pending.accept();
+//!     for tick in 0u64.. {
+//!         tokio::time::sleep(Duration::from_secs(1)).await;
+//!         if sink.send(tick).await.is_err() {
+//!             break; // unsubscribed or disconnected
+//!         }
+//!     }
+//! }
+//!
+//! let rpc = JsonRpcRouter::new().subscription("subscribe_ticks", "tick", "unsubscribe_ticks", ticks);
+//! ```
+//!
+//! [`JsonRpcRouter::subscription`]: crate::JsonRpcRouter::subscription
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::{Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::{handle_value, JsonRpcService, Reply};
+use crate::{
+    random_id, to_value, to_vec, Id, JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse,
+    Value,
+};
+
+/// Header tying a call to a session of a transport without persistent connections,
+/// like [`sse`](crate::sse)
+pub const SESSION_HEADER: &str = "jrpc-session";
+
+/// Number of messages queued for the client of a connection before senders have to wait
+pub const QUEUE_CAPACITY: usize = 1024;
+
+/// Identifies a subscription within its connection.
+pub type SubscriptionId = u64;
+
+/// Returned when pushing to a subscription which was cancelled or whose connection is gone.
+#[derive(Debug, Clone, Copy, Error)]
+#[error("Subscription closed")]
+pub struct SubscriptionClosed;
+
+/// Message queued for delivery to the client of a [`Session`].
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum Outgoing {
+    Request(JsonRpcRequest),
+    Response(Reply),
+}
+
+/// Per-connection state of a transport able to push messages to its client.
+///
+/// Responses and notifications share a single queue, bounded by [`QUEUE_CAPACITY`], so a
+/// subscription id always reaches the client before the first notification of that
+/// subscription.
+#[derive(Clone)]
+pub(crate) struct Session {
+    inner: Arc<SessionInner>,
+}
+
+struct SessionInner {
+    sender: mpsc::Sender<Outgoing>,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: SubscriptionId,
+    /// Dropping the sender closes the subscription, `true` once the client knows its id
+    subscriptions: HashMap<SubscriptionId, (Arc<str>, watch::Sender<bool>)>,
+    unacknowledged: HashMap<Id, SubscriptionId>,
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+// constructed by the transports
+#[cfg_attr(not(all(feature = "ws", feature = "sse")), allow(dead_code))]
+impl Session {
+    pub(crate) fn new() -> (Self, mpsc::Receiver<Outgoing>) {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let inner = SessionInner {
+            sender,
+            state: Mutex::default(),
+        };
+        let session = Self {
+            inner: Arc::new(inner),
+        };
+        (session, receiver)
+    }
+
+    /// Queues a request or notification, fails once the connection is gone or its queue is
+    /// full
+    pub(crate) fn request(&self, request: JsonRpcRequest) -> Result<(), SubscriptionClosed> {
+        self.inner
+            .sender
+            .try_send(Outgoing::Request(request))
+            .map_err(|_| SubscriptionClosed)
+    }
+
+    /// Queues a response, waiting for room, and starts the subscriptions it confirms, if any
+    pub(crate) async fn respond(&self, reply: Reply) {
+        let ids: Vec<_> = reply.responses().iter().map(|r| r.id.clone()).collect();
+        let _ = self.inner.sender.send(Outgoing::Response(reply)).await;
+        for id in &ids {
+            self.acknowledge(id);
+        }
+    }
+
+    /// Starts the subscription confirmed by the response to `id`, for transports delivering
+    /// responses outside of the session queue
+    pub(crate) fn acknowledge(&self, id: &Id) {
+        let mut state = self.inner.state.lock().unwrap();
+        if let Some(subscription) = state.unacknowledged.remove(id) {
+            if let Some((_, active)) = state.subscriptions.get(&subscription) {
+                active.send_replace(true);
+            }
+        }
+    }
+
+    /// Handles a message within this session and queues the answer
+    pub(crate) async fn dispatch<S: JsonRpcService>(
+        &self,
+        service: &S,
+        value: Result<Value, String>,
+        mut extensions: Extensions,
+    ) {
+        extensions.insert(self.clone());
+        let reply = match value {
+            Ok(value) => handle_value(service, value, extensions).await,
+            Err(e) => {
+                let error = JsonRpcError::new(JsonRpcErrorReason::ParseError, e, Value::default());
+                Some(Reply::Single(JsonRpcResponse::error(Id::None(()), error)))
+            }
+        };
+        if let Some(reply) = reply {
+            self.respond(reply).await;
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.inner.sender.is_closed()
+    }
+
+    /// Closes all subscriptions, called by the transport once the connection is gone
+    pub(crate) fn close(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.subscriptions.clear();
+        state.unacknowledged.clear();
+    }
+
+    fn subscribe(
+        &self,
+        method: Arc<str>,
+        request_id: Id,
+    ) -> (SubscriptionId, watch::Receiver<bool>) {
+        let mut state = self.inner.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        // nobody waits for the response of a notification
+        let (active, receiver) = watch::channel(request_id.is_none());
+        state.subscriptions.insert(id, (method, active));
+        if !request_id.is_none() {
+            state.unacknowledged.insert(request_id, id);
+        }
+        (id, receiver)
+    }
+
+    fn unsubscribe(&self, method: &str, id: SubscriptionId) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+        match state.subscriptions.get(&id) {
+            Some((subscribed, _)) if &**subscribed == method => {
+                state.subscriptions.remove(&id);
+                state
+                    .unacknowledged
+                    .retain(|_, subscription| *subscription != id);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Sessions outliving a single HTTP request, looked up by a random id.
+///
+/// Every message of a session gets a sequence number and the last `capacity` messages are
+/// kept, so clients can resume after reconnecting. Sessions without a reader for longer than
+/// `idle_timeout` are closed.
+#[derive(Clone)]
+pub(crate) struct SessionStore {
+    inner: Arc<StoreInner>,
+}
+
+struct StoreInner {
+    sessions: Mutex<HashMap<String, Arc<BufferedSession>>>,
+    capacity: usize,
+    idle_timeout: Duration,
+}
+
+/// Shortest period at which sessions are checked for readers
+const MIN_TICK: Duration = Duration::from_millis(10);
+
+/// Serialized message and its sequence number
+pub(crate) type Event = (u64, Arc<str>);
+
+pub(crate) struct BufferedSession {
+    id: String,
+    session: Session,
+    events: Mutex<VecDeque<Event>>,
+    live: broadcast::Sender<Event>,
+    last_seen: Mutex<Instant>,
+}
+
+#[cfg_attr(not(any(feature = "sse", feature = "long-poll")), allow(dead_code))]
+impl SessionStore {
+    pub(crate) fn new(capacity: usize, idle_timeout: Duration) -> Self {
+        let inner = StoreInner {
+            sessions: Mutex::default(),
+            capacity,
+            idle_timeout,
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Opens a new session, must be called within a tokio runtime
+    pub(crate) fn create(&self) -> Arc<BufferedSession> {
+        let (session, outgoing) = Session::new();
+        let (live, _) = broadcast::channel(self.inner.capacity.max(1));
+        let buffered = Arc::new(BufferedSession {
+            id: random_id(),
+            session,
+            events: Mutex::default(),
+            live,
+            last_seen: Mutex::new(Instant::now()),
+        });
+        self.inner
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(buffered.id.clone(), buffered.clone());
+
+        tokio::spawn(pump(self.clone(), buffered.clone(), outgoing));
+        buffered
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<Arc<BufferedSession>> {
+        let buffered = self.inner.sessions.lock().unwrap().get(id).cloned()?;
+        *buffered.last_seen.lock().unwrap() = Instant::now();
+        Some(buffered)
+    }
+
+    /// Returns the session named by the [`SESSION_HEADER`]
+    pub(crate) fn lookup(&self, headers: &HeaderMap) -> Option<Arc<BufferedSession>> {
+        let id = headers.get(SESSION_HEADER)?.to_str().ok()?;
+        self.get(id)
+    }
+
+    /// Dispatches `request` within the session named by the [`SESSION_HEADER`], if any.
+    /// Notifications are answered with `204 No Content`
+    pub(crate) async fn dispatch<S: JsonRpcService>(
+        &self,
+        service: &S,
+        headers: &HeaderMap,
+        mut request: JsonRpcExtractor,
+    ) -> Response {
+        let buffered = self.lookup(headers);
+        if let Some(buffered) = &buffered {
+            request.extensions.insert(buffered.session.clone());
+        }
+
+        let notification = request.extensions.get::<crate::NoId>().is_some();
+        let response = service.dispatch(request).await;
+        if let Some(buffered) = &buffered {
+            buffered.session.acknowledge(&response.id);
+        }
+
+        if notification {
+            StatusCode::NO_CONTENT.into_response()
+        } else {
+            response.into_response()
+        }
+    }
+}
+
+impl std::fmt::Debug for SessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionStore")
+            .field("sessions", &self.inner.sessions.lock().unwrap().len())
+            .field("capacity", &self.inner.capacity)
+            .field("idle_timeout", &self.inner.idle_timeout)
+            .finish()
+    }
+}
+
+#[cfg_attr(not(any(feature = "sse", feature = "long-poll")), allow(dead_code))]
+impl BufferedSession {
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the kept messages following `after` and a receiver of all later ones.
+    /// The receiver may yield some of the returned messages again
+    pub(crate) fn resume(&self, after: Option<u64>) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        let events = self.events.lock().unwrap();
+        let live = self.live.subscribe();
+        let missed = events
+            .iter()
+            .filter(|(seq, _)| after.is_none_or(|after| *seq > after))
+            .cloned()
+            .collect();
+        (missed, live)
+    }
+
+    /// Kept messages following `after` then all later ones, without duplicates. Ends when
+    /// the reader lags behind or the session closes, it should reconnect to resume then
+    #[cfg_attr(not(any(feature = "sse", feature = "ws")), allow(dead_code))]
+    pub(crate) fn replay(self: Arc<Self>, after: Option<u64>) -> impl Stream<Item = Event> {
+        let (missed, live) = self.resume(after);
+        let last = missed.last().map(|(seq, _)| *seq).or(after);
+        let live = futures_util::stream::unfold(
+            (self, live, last),
+            |(buffered, mut live, mut last)| async move {
+                loop {
+                    match live.recv().await {
+                        Ok(event) if last.is_some_and(|last| event.0 <= last) => {}
+                        Ok(event) => {
+                            last = Some(event.0);
+                            return Some((event, (buffered, live, last)));
+                        }
+                        // lagged or closed
+                        Err(_) => return None,
+                    }
+                }
+            },
+        );
+        futures_util::stream::iter(missed).chain(live)
+    }
+
+    /// Session to dispatch the calls of this session's client in
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
+    pub(crate) fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+async fn pump(
+    store: SessionStore,
+    buffered: Arc<BufferedSession>,
+    mut outgoing: mpsc::Receiver<Outgoing>,
+) {
+    let idle_timeout = store.inner.idle_timeout;
+    // `interval` panics on a zero period, such sessions are closed at the first tick without a
+    // reader
+    let mut ticker = tokio::time::interval(idle_timeout.max(MIN_TICK));
+    let mut seq = 0;
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => {
+                let Some(message) = message else {
+                    break;
+                };
+                let Ok(text) = to_vec(&message).map(String::from_utf8) else {
+                    continue;
+                };
+                seq += 1;
+                let event: Event = (seq, text.unwrap_or_default().into());
+
+                let mut events = buffered.events.lock().unwrap();
+                events.push_back(event.clone());
+                if events.len() > store.inner.capacity {
+                    events.pop_front();
+                }
+                drop(events);
+                let _ = buffered.live.send(event);
+            }
+            _ = ticker.tick() => {
+                let mut last_seen = buffered.last_seen.lock().unwrap();
+                if buffered.live.receiver_count() > 0 {
+                    // the idle timeout runs from the moment the last reader went away
+                    *last_seen = Instant::now();
+                } else if last_seen.elapsed() >= idle_timeout {
+                    break;
+                }
+            }
+        }
+    }
+
+    buffered.session.close();
+    store.inner.sessions.lock().unwrap().remove(&buffered.id);
+}
+
+/// A subscription call waiting to be accepted or rejected by its handler.
+///
+/// Dropping it without doing either answers the call with `Internal error`.
+#[derive(Debug)]
+pub struct PendingSubscription {
+    session: Session,
+    method: Arc<str>,
+    request_id: Id,
+    answer: oneshot::Sender<Result<SubscriptionId, JsonRpcError>>,
+}
+
+impl PendingSubscription {
+    /// Answers the call with a fresh subscription id
+    pub fn accept(self) -> SubscriptionSink {
+        let (id, active) = self.session.subscribe(self.method.clone(), self.request_id);
+        let _ = self.answer.send(Ok(id));
+        SubscriptionSink {
+            id,
+            method: self.method,
+            session: self.session.inner.sender.clone(),
+            active,
+        }
+    }
+
+    /// Answers the call with `error`
+    pub fn reject(self, error: JsonRpcError) {
+        let _ = self.answer.send(Err(error));
+    }
+}
+
+/// Pushes notifications of an accepted subscription to the client.
+#[derive(Debug)]
+pub struct SubscriptionSink {
+    id: SubscriptionId,
+    method: Arc<str>,
+    session: mpsc::Sender<Outgoing>,
+    active: watch::Receiver<bool>,
+}
+
+#[derive(Serialize)]
+struct Notification<T> {
+    subscription: SubscriptionId,
+    result: T,
+}
+
+impl SubscriptionSink {
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Sends `value` to the client, fails once the subscription is closed.
+    ///
+    /// Waits while the queue of the connection is full, see the [module docs](self)
+    pub async fn send<T: Serialize>(&self, value: T) -> Result<(), SubscriptionClosed> {
+        // the client must learn the subscription id before the first notification
+        let mut active = self.active.clone();
+        active
+            .wait_for(|active| *active)
+            .await
+            .map_err(|_| SubscriptionClosed)?;
+        if self.is_closed() {
+            return Err(SubscriptionClosed);
+        }
+
+        let params = Notification {
+            subscription: self.id,
+            result: value,
+        };
+        let request = JsonRpcRequest {
+            id: Id::None(()),
+            method: self.method.to_string(),
+            params: to_value(params).map_err(|_| SubscriptionClosed)?,
+        };
+        tokio::select! {
+            sent = self.session.send(Outgoing::Request(request)) => {
+                sent.map_err(|_| SubscriptionClosed)
+            }
+            () = self.closed() => Err(SubscriptionClosed),
+        }
+    }
+
+    /// Sends every item of `stream` until it ends or the subscription is closed
+    pub async fn pipe<S>(self, stream: S)
+    where
+        S: Stream,
+        S::Item: Serialize,
+    {
+        let mut stream = std::pin::pin!(stream);
+        loop {
+            tokio::select! {
+                item = stream.next() => {
+                    let Some(item) = item else {
+                        break;
+                    };
+                    if self.send(item).await.is_err() {
+                        break;
+                    }
+                }
+                _ = self.closed() => break,
+            }
+        }
+    }
+
+    /// Returns `true` once the client unsubscribed or disconnected
+    pub fn is_closed(&self) -> bool {
+        self.active.has_changed().is_err() || self.session.is_closed()
+    }
+
+    /// Waits until the client unsubscribes or disconnects
+    pub async fn closed(&self) {
+        let mut active = self.active.clone();
+        tokio::select! {
+            _ = async { while active.changed().await.is_ok() {} } => {}
+            _ = self.session.closed() => {}
+        }
+    }
+}
+
+pub(crate) fn subscribe_handler<H, F>(
+    notification: Arc<str>,
+    handler: Arc<H>,
+) -> impl Fn(JsonRpcExtractor) -> crate::router::BoxFuture<JrpcResult> + Send + Sync + 'static
+where
+    H: Fn(JsonRpcExtractor, PendingSubscription) -> F + Send + Sync + 'static,
+    F: Future<Output = ()> + Send + 'static,
+{
+    move |request: JsonRpcExtractor| {
+        let notification = notification.clone();
+        let handler = handler.clone();
+        Box::pin(async move {
+            let id = request.get_answer_id();
+            let Some(session) = request.extension::<Session>().cloned() else {
+                let error = JsonRpcError::new(
+                    JsonRpcErrorReason::InvalidRequest,
+                    "Subscriptions are not supported on this transport".to_owned(),
+                    Value::default(),
+                );
+                return Err(JsonRpcResponse::error(id, error));
+            };
+
+            let (answer, result) = oneshot::channel();
+            let pending = PendingSubscription {
+                session,
+                method: notification,
+                request_id: id.clone(),
+                answer,
+            };
+            tokio::spawn(handler(request, pending));
+
+            match result.await {
+                Ok(Ok(subscription)) => Ok(JsonRpcResponse::success(id, subscription)),
+                Ok(Err(error)) => Err(JsonRpcResponse::error(id, error)),
+                Err(_) => {
+                    let error = JsonRpcError::new(
+                        JsonRpcErrorReason::InternalError,
+                        "Subscription was neither accepted nor rejected".to_owned(),
+                        Value::default(),
+                    );
+                    Err(JsonRpcResponse::error(id, error))
+                }
+            }
+        })
+    }
+}
+
+pub(crate) fn unsubscribe_handler(
+    notification: Arc<str>,
+) -> impl Fn(JsonRpcExtractor) -> crate::router::BoxFuture<JrpcResult> + Send + Sync + 'static {
+    move |request: JsonRpcExtractor| {
+        let notification = notification.clone();
+        Box::pin(async move {
+            let id = request.get_answer_id();
+            let session = request.extension::<Session>().cloned();
+            let [subscription]: [SubscriptionId; 1] = request.parse_params()?;
+            let removed =
+                session.is_some_and(|session| session.unsubscribe(&notification, subscription));
+            Ok(JsonRpcResponse::success(id, removed))
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "ws", feature = "ws-client", feature = "serde_json"))]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum::routing::get;
+    use axum::Router;
+    use futures_util::StreamExt;
+    use tokio::sync::Notify;
+
+    use super::{PendingSubscription, Session, SessionStore, QUEUE_CAPACITY};
+    use crate::client::{JsonRpcClient, WsClient};
+    use crate::ws::JsonRpcWebSocket;
+    use crate::{Id, JsonRpcExtractor, JsonRpcRouter};
+
+    #[tokio::test]
+    async fn subscribe_and_unsubscribe() {
+        let closed = Arc::new((AtomicBool::new(false), Notify::new()));
+        let rpc = JsonRpcRouter::new().subscription("subscribe", "counter", "unsubscribe", {
+            let closed = closed.clone();
+            move |_: JsonRpcExtractor, pending: PendingSubscription| {
+                let closed = closed.clone();
+                async move {
+                    let sink = pending.accept();
+                    let mut counter = 0u64;
+                    while sink.send(counter).await.is_ok() {
+                        counter += 1;
+                        tokio::task::yield_now().await;
+                    }
+                    closed.0.store(true, Ordering::Relaxed);
+                    closed.1.notify_one();
+                }
+            }
+        });
+        let app = Router::new().route(
+            "/ws",
+            get(move |ws: JsonRpcWebSocket| async move { ws.serve(rpc) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (client, mut notifications) =
+            WsClient::connect(format!("ws://{addr}/ws")).await.unwrap();
+        let subscription: u64 = client.call("subscribe", ()).await.unwrap();
+
+        for expected in 0..3 {
+            let notification = notifications.next().await.unwrap();
+            assert_eq!(notification.method, "counter");
+            assert_eq!(
+                notification.params,
+                serde_json::json!({ "subscription": subscription, "result": expected })
+            );
+        }
+
+        let removed: bool = client.call("unsubscribe", [subscription]).await.unwrap();
+        assert!(removed);
+        closed.1.notified().await;
+        assert!(closed.0.load(Ordering::Relaxed));
+
+        let removed: bool = client.call("unsubscribe", [subscription]).await.unwrap();
+        assert!(!removed);
+    }
+
+    #[tokio::test]
+    async fn slow_client() {
+        let (session, mut outgoing) = Session::new();
+        let (answer, _) = tokio::sync::oneshot::channel();
+        let pending = PendingSubscription {
+            session: session.clone(),
+            method: "counter".into(),
+            request_id: Id::None(()),
+            answer,
+        };
+        let sink = pending.accept();
+        for counter in 0..QUEUE_CAPACITY {
+            sink.send(counter).await.unwrap();
+        }
+
+        // the queue is full, the next notification waits for the client
+        let send = sink.send(QUEUE_CAPACITY);
+        tokio::pin!(send);
+        let waiting = tokio::time::timeout(Duration::from_millis(50), &mut send).await;
+        assert!(waiting.is_err());
+        outgoing.recv().await.unwrap();
+        send.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn zero_idle_timeout() {
+        let store = SessionStore::new(8, Duration::ZERO);
+        let buffered = store.create();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(store.get(&buffered.id).is_none());
+    }
+}
@@ -0,0 +1,410 @@
+//! Daily or monthly quotas of calls and bytes per client.
+//!
+//! [`QuotaLayer`] wraps a service and adds up the calls each client makes, and the bytes of
+//! their params, in the current UTC day or month. Once either is over its [`Quota`], calls
+//! are answered with a [`QUOTA_EXCEEDED`] error until the period ends, its data holds
+//! `reset_at`, the unix time in seconds the counts start over.
+//!
+//! Counts are kept by a [`QuotaStore`], [`MemoryQuotaStore`] keeps them in memory, which
+//! suits single instances. Implementing the trait with an atomic increment, like Redis'
+//! `INCRBY` on a key per client and period, shares them between instances.
+//! ```rust
+//! use axum_jrpc::api_key::ApiKey;
+//! use axum_jrpc::quota::{MemoryQuotaStore, Quota, QuotaLayer};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let quota = QuotaLayer::new(MemoryQuotaStore::default(), Quota::monthly().calls(100_000))
+//!     .key_by(|req| req.extension::<ApiKey>().map(|key| key.name.clone()));
+//! let rpc = quota.layer(JsonRpcRouter::new());
+//! ```
+//! Clients are told apart like by [`RateLimitLayer`](crate::rate_limit::RateLimitLayer).
+//! Calls over the quota are counted too, and calls are let through if the store fails.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+#[cfg(not(target_arch = "wasm32"))]
+use axum::extract::ConnectInfo;
+use serde::Serialize;
+use tower::{Layer, Service};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{to_value, to_vec, JsonRpcExtractor, JsonRpcResponse};
+
+/// Error code of calls over their client's quota
+pub const QUOTA_EXCEEDED: i32 = -32007;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Length of the periods quotas apply to, in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Daily,
+    Monthly,
+}
+
+impl Period {
+    /// Start and end of the period containing `now`, in unix seconds
+    fn window(self, now: u64) -> (u64, u64) {
+        let day = now / DAY;
+        match self {
+            Self::Daily => (day * DAY, (day + 1) * DAY),
+            Self::Monthly => {
+                let (year, month) = civil_month(day);
+                let start = days_from_civil(year, month);
+                let end = match month {
+                    12 => days_from_civil(year + 1, 1),
+                    _ => days_from_civil(year, month + 1),
+                };
+                (start * DAY, end * DAY)
+            }
+        }
+    }
+}
+
+/// Year and month of the `days`th day since the unix epoch, after
+/// <https://howardhinnant.github.io/date_algorithms.html>
+fn civil_month(days: u64) -> (u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month)
+}
+
+/// Days since the unix epoch of the first day of `month`
+fn days_from_civil(year: u64, month: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year % 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Calls and bytes of params a client may send per period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    period: Period,
+    calls: Option<u64>,
+    bytes: Option<u64>,
+}
+
+impl Quota {
+    /// An unlimited quota per `period`, see [`calls`](Self::calls) and [`bytes`](Self::bytes)
+    pub fn new(period: Period) -> Self {
+        Self {
+            period,
+            calls: None,
+            bytes: None,
+        }
+    }
+
+    pub fn daily() -> Self {
+        Self::new(Period::Daily)
+    }
+
+    pub fn monthly() -> Self {
+        Self::new(Period::Monthly)
+    }
+
+    /// Allows `calls` calls per period
+    pub fn calls(mut self, calls: u64) -> Self {
+        self.calls = Some(calls);
+        self
+    }
+
+    /// Allows `bytes` bytes of json encoded params per period
+    pub fn bytes(mut self, bytes: u64) -> Self {
+        self.bytes = Some(bytes);
+        self
+    }
+}
+
+/// Calls and bytes counted in a period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub calls: u64,
+    pub bytes: u64,
+}
+
+/// Where usage is counted.
+#[async_trait::async_trait]
+pub trait QuotaStore: Send + Sync + 'static {
+    /// Adds `usage` to the count of `client` in the period starting at `window`, returning
+    /// the new count. Counts may be forgotten once `expires`, in unix seconds, has passed
+    async fn add(
+        &self,
+        client: &str,
+        window: u64,
+        usage: Usage,
+        expires: u64,
+    ) -> Result<Usage, String>;
+}
+
+/// Counts usage in memory, forgetting the periods which have ended.
+#[derive(Debug, Default)]
+pub struct MemoryQuotaStore {
+    counts: Mutex<HashMap<(String, u64), (Usage, u64)>>,
+}
+
+#[async_trait::async_trait]
+impl QuotaStore for MemoryQuotaStore {
+    async fn add(
+        &self,
+        client: &str,
+        window: u64,
+        usage: Usage,
+        expires: u64,
+    ) -> Result<Usage, String> {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        if counts.len() >= 1024 && counts.len().is_power_of_two() {
+            let now = unix_now();
+            counts.retain(|_, (_, expires)| *expires > now);
+        }
+        let (count, _) = counts
+            .entry((client.to_owned(), window))
+            .or_insert((Usage::default(), expires));
+        count.calls += usage.calls;
+        count.bytes += usage.bytes;
+        Ok(*count)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+type KeyFn = dyn Fn(&JsonRpcExtractor) -> Option<String> + Send + Sync;
+
+/// Wraps services in [`QuotaLimit`], see the [module docs](self).
+pub struct QuotaLayer<Q> {
+    store: Arc<Q>,
+    quota: Quota,
+    key: Option<Arc<KeyFn>>,
+}
+
+impl<Q> Clone for QuotaLayer<Q> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            quota: self.quota,
+            key: self.key.clone(),
+        }
+    }
+}
+
+impl<Q> std::fmt::Debug for QuotaLayer<Q> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuotaLayer")
+            .field("quota", &self.quota)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Serialize)]
+struct Exceeded {
+    period: Period,
+    reset_at: u64,
+}
+
+impl<Q: QuotaStore> QuotaLayer<Q> {
+    pub fn new(store: Q, quota: Quota) -> Self {
+        Self {
+            store: Arc::new(store),
+            quota,
+            key: None,
+        }
+    }
+
+    /// Identifies the client of a call with `key` instead of its IP address
+    pub fn key_by<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&JsonRpcExtractor) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Some(Arc::new(key));
+        self
+    }
+
+    /// Counts a call, returning the error answering it if it is over the quota
+    async fn check(&self, request: &JsonRpcExtractor, now: u64) -> Option<JsonRpcError> {
+        let client = match &self.key {
+            Some(key) => key(request),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => request
+                .extension::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(peer)| peer.ip().to_string()),
+            // axum has no connection info without tokio
+            #[cfg(target_arch = "wasm32")]
+            None => None,
+        };
+        let bytes = match self.quota.bytes {
+            Some(_) => to_vec(&request.parsed).map_or(0, |params| params.len() as u64),
+            None => 0,
+        };
+        let (window, reset_at) = self.quota.period.window(now);
+        let usage = Usage { calls: 1, bytes };
+        let client = client.unwrap_or_default();
+        let usage = match self.store.add(&client, window, usage, reset_at).await {
+            Ok(usage) => usage,
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(client, error = %_e, "failed to count quota usage");
+                return None;
+            }
+        };
+
+        let over = |limit: Option<u64>, used| limit.is_some_and(|limit| used > limit);
+        if !over(self.quota.calls, usage.calls) && !over(self.quota.bytes, usage.bytes) {
+            return None;
+        }
+        let data = Exceeded {
+            period: self.quota.period,
+            reset_at,
+        };
+        Some(JsonRpcError::new(
+            JsonRpcErrorReason::ServerError(QUOTA_EXCEEDED),
+            "Quota exceeded".to_owned(),
+            to_value(data).unwrap_or_default(),
+        ))
+    }
+}
+
+impl<Q, S> Layer<S> for QuotaLayer<Q> {
+    type Service = QuotaLimit<Q, S>;
+
+    fn layer(&self, inner: S) -> QuotaLimit<Q, S> {
+        QuotaLimit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Enforces the quota of each client, see the [module docs](self).
+pub struct QuotaLimit<Q, S> {
+    inner: S,
+    layer: QuotaLayer<Q>,
+}
+
+impl<Q, S: Clone> Clone for QuotaLimit<Q, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<Q, S: std::fmt::Debug> std::fmt::Debug for QuotaLimit<Q, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuotaLimit")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<Q, S> Service<JsonRpcExtractor> for QuotaLimit<Q, S>
+where
+    Q: QuotaStore,
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        // the ready service is taken, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            match layer.check(&request, unix_now()).await {
+                Some(error) => Ok(JsonRpcResponse::error(request.id, error)),
+                None => inner.call(request).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::json;
+
+    use super::{MemoryQuotaStore, Period, Quota, QuotaLayer, QUOTA_EXCEEDED};
+    use crate::{JsonRpcExtractor, JsonRpcRequest};
+
+    fn request(client: &str, params: serde_json::Value) -> JsonRpcExtractor {
+        let mut request: JsonRpcExtractor = JsonRpcRequest {
+            id: 1.into(),
+            method: "ping".to_owned(),
+            params,
+        }
+        .into();
+        request.extensions.insert(client.to_owned());
+        request
+    }
+
+    #[test]
+    fn periods() {
+        // 2024-02-29T12:00:00Z
+        let now = 1_709_208_000;
+        assert_eq!(Period::Daily.window(now), (1_709_164_800, 1_709_251_200));
+        // 2024-02-01 to 2024-03-01
+        assert_eq!(Period::Monthly.window(now), (1_706_745_600, 1_709_251_200));
+        // 2023-12-01 to 2024-01-01
+        assert_eq!(
+            Period::Monthly.window(1_702_000_000),
+            (1_701_388_800, 1_704_067_200)
+        );
+    }
+
+    #[tokio::test]
+    async fn quotas() {
+        let quota = Quota::daily().calls(2).bytes(12);
+        let layer = QuotaLayer::new(MemoryQuotaStore::default(), quota)
+            .key_by(|req| req.extension::<String>().cloned());
+        let now = 1_709_208_000;
+
+        let alice = request("alice", json!([1]));
+        assert!(layer.check(&alice, now).await.is_none());
+        assert!(layer.check(&alice, now).await.is_none());
+        let error = layer.check(&alice, now).await.unwrap();
+        assert_eq!(error.code(), QUOTA_EXCEEDED);
+        assert_eq!(
+            *error.data(),
+            json!({"period": "daily", "reset_at": 1_709_251_200})
+        );
+        // the next day starts over
+        assert!(layer.check(&alice, now + 86_400).await.is_none());
+
+        let bob = request("bob", json!(["0123456789"]));
+        assert!(layer.check(&bob, now).await.is_some());
+    }
+}
@@ -0,0 +1,285 @@
+//! Rate limiting per client and method.
+//!
+//! [`RateLimitLayer`] wraps a service and limits the calls each client makes to each method
+//! with [GCRA](https://en.wikipedia.org/wiki/Generic_cell_rate_algorithm), a token bucket
+//! tracking a single timestamp. Calls over the [`Quota`] are answered with a [`RATE_LIMITED`]
+//! error whose data holds `retry_after_ms`, the time until the call would be accepted.
+//!
+//! Clients are told apart by their IP address if axum provides
+//! [`ConnectInfo`](axum::extract::ConnectInfo), or by the key returned from
+//! [`RateLimitLayer::key_by`], e.g. the name of their [`ApiKey`](crate::api_key::ApiKey).
+//! Calls without a key share a limit.
+//! ```rust
+//! use axum_jrpc::api_key::ApiKey;
+//! use axum_jrpc::rate_limit::{Quota, RateLimitLayer};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let limit = RateLimitLayer::new(Quota::per_second(50))
+//!     .method("eth_getLogs", Quota::per_minute(10).burst(2))
+//!     .key_by(|req| req.extension::<ApiKey>().map(|key| key.name.clone()));
+//! let rpc = limit.layer(JsonRpcRouter::new());
+//! ```
+//! Methods not registered with any router share the `unknown` limit of each client.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use axum::extract::ConnectInfo;
+use serde::Serialize;
+use tower::{Layer, Service};
+use web_time::Instant;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::intern;
+use crate::router::BoxFuture;
+use crate::{to_value, Id, JsonRpcExtractor, JsonRpcResponse};
+
+/// Error code of calls over their rate limit
+pub const RATE_LIMITED: i32 = -32005;
+
+/// Calls allowed per period, and how many of them may be made at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    /// Time to regain one call
+    interval: Duration,
+    burst: u32,
+}
+
+impl Quota {
+    /// `calls` per `period`, all of which can be made at once
+    pub fn new(calls: u32, period: Duration) -> Self {
+        let calls = calls.max(1);
+        Self {
+            interval: period / calls,
+            burst: calls,
+        }
+    }
+
+    pub fn per_second(calls: u32) -> Self {
+        Self::new(calls, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(calls: u32) -> Self {
+        Self::new(calls, Duration::from_secs(60))
+    }
+
+    /// Lets at most `burst` calls be made at once, instead of the whole quota
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+type KeyFn = dyn Fn(&JsonRpcExtractor) -> Option<String> + Send + Sync;
+
+/// Wraps services in [`RateLimit`], see the [module docs](self).
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    default: Quota,
+    methods: Arc<HashMap<String, Quota>>,
+    key: Option<Arc<KeyFn>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl std::fmt::Debug for RateLimitLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitLayer")
+            .field("default", &self.default)
+            .field("methods", &self.methods)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Theoretical arrival times by client and method
+#[derive(Debug, Default)]
+struct State {
+    arrivals: HashMap<(String, Arc<str>), Instant>,
+    prune_at: usize,
+}
+
+impl RateLimitLayer {
+    /// Entries kept before ones of idle clients are removed
+    const MIN_PRUNE_AT: usize = 1024;
+
+    /// Limits the calls of every client to each method to `default`, unless the method has
+    /// its own quota
+    pub fn new(default: Quota) -> Self {
+        Self {
+            default,
+            methods: Default::default(),
+            key: None,
+            state: Arc::new(Mutex::new(State {
+                prune_at: Self::MIN_PRUNE_AT,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Sets the quota of `method`
+    pub fn method(mut self, method: impl Into<String>, quota: Quota) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into(), quota);
+        self
+    }
+
+    /// Identifies the client of a call with `key` instead of its IP address
+    pub fn key_by<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&JsonRpcExtractor) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Some(Arc::new(key));
+        self
+    }
+
+    /// Accounts for a call, returning how long to wait if it is over the quota
+    fn check(&self, request: &JsonRpcExtractor, now: Instant) -> Result<(), Duration> {
+        let client = match &self.key {
+            Some(key) => key(request),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => request
+                .extension::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(peer)| peer.ip().to_string()),
+            // axum has no connection info without tokio
+            #[cfg(target_arch = "wasm32")]
+            None => None,
+        };
+        let method = intern::lookup(request.method()).unwrap_or_else(|| Arc::from("unknown"));
+        let quota = self.methods.get(&*method).copied().unwrap_or(self.default);
+        let tolerance = quota.interval * (quota.burst - 1);
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.arrivals.len() >= state.prune_at {
+            // buckets whose arrival time has passed are full, like missing ones
+            state.arrivals.retain(|_, arrival| *arrival > now);
+            state.prune_at = (state.arrivals.len() * 2).max(Self::MIN_PRUNE_AT);
+        }
+        let arrival = state
+            .arrivals
+            .entry((client.unwrap_or_default(), method))
+            .or_insert(now);
+        let earliest = (*arrival).max(now);
+        match earliest.duration_since(now).checked_sub(tolerance) {
+            Some(wait) if !wait.is_zero() => Err(wait),
+            _ => {
+                *arrival = earliest + quota.interval;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> RateLimit<S> {
+        RateLimit {
+            inner,
+            limit: self.clone(),
+        }
+    }
+}
+
+/// Limits the calls of each client, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limit: RateLimitLayer,
+}
+
+#[derive(Serialize)]
+struct RetryAfter {
+    retry_after_ms: u64,
+}
+
+impl<S> Service<JsonRpcExtractor> for RateLimit<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let Err(wait) = self.limit.check(&request, Instant::now()) else {
+            return Box::pin(self.inner.call(request));
+        };
+        let response = limited(request.id, wait);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// Answer to a call allowed again after `wait`
+pub(crate) fn limited(id: Id, wait: Duration) -> JsonRpcResponse {
+    let data = RetryAfter {
+        // rounded up, retrying any earlier would fail
+        retry_after_ms: wait.as_micros().div_ceil(1000) as u64,
+    };
+    let error = JsonRpcError::new(
+        JsonRpcErrorReason::ServerError(RATE_LIMITED),
+        "Rate limit exceeded".to_owned(),
+        to_value(data).unwrap_or_default(),
+    );
+    JsonRpcResponse::error(id, error)
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use super::{Quota, RateLimitLayer};
+    use crate::{JsonRpcExtractor, JsonRpcRequest};
+
+    fn request(method: &str, client: &str) -> JsonRpcExtractor {
+        let mut request: JsonRpcExtractor = JsonRpcRequest {
+            id: 1.into(),
+            method: method.to_owned(),
+            params: json!([]),
+        }
+        .into();
+        request.extensions.insert(client.to_owned());
+        request
+    }
+
+    #[test]
+    fn gcra() {
+        crate::intern::intern("limited_ping");
+        crate::intern::intern("limited_pong");
+        let limit = RateLimitLayer::new(Quota::per_second(100))
+            .method("limited_ping", Quota::per_second(2))
+            .key_by(|req| req.extension::<String>().cloned());
+        let now = Instant::now();
+
+        let ping = request("limited_ping", "alice");
+        assert_eq!(limit.check(&ping, now), Ok(()));
+        assert_eq!(limit.check(&ping, now), Ok(()));
+        assert_eq!(limit.check(&ping, now), Err(Duration::from_millis(500)));
+        assert_eq!(
+            limit.check(&ping, now + Duration::from_millis(200)),
+            Err(Duration::from_millis(300))
+        );
+        assert_eq!(limit.check(&ping, now + Duration::from_millis(500)), Ok(()));
+
+        // other clients and methods have their own limits
+        assert_eq!(limit.check(&request("limited_ping", "bob"), now), Ok(()));
+        assert_eq!(limit.check(&request("limited_pong", "alice"), now), Ok(()));
+
+        let limit = RateLimitLayer::new(Quota::per_minute(60).burst(1));
+        let unknown = request("limited_unknown", "alice");
+        assert_eq!(limit.check(&unknown, now), Ok(()));
+        assert_eq!(limit.check(&unknown, now), Err(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,305 @@
+//! Requests whose params stay unparsed until the handler asks for them.
+//!
+//! [`JsonRpcExtractor`](crate::JsonRpcExtractor) parses params into a [`Value`] tree which
+//! [`parse_params`](crate::JsonRpcExtractor::parse_params) then converts into the target
+//! type. [`JsonRpcRawExtractor`] keeps the params as raw json and deserializes them straight
+//! into the target type, skipping the intermediate tree. Only available with `serde_json`.
+//!
+//! [`JsonRpcRequestRef`] goes further and borrows everything but the id from the message,
+//! for proxies and loggers which only look at the method or id.
+//! ```rust
+//! use axum_jrpc::raw::JsonRpcRawExtractor;
+//! use axum_jrpc::{JrpcResult, JsonRpcResponse};
+//!
+//! async fn len(req: JsonRpcRawExtractor) -> JrpcResult {
+//!     // borrows the string from the request body
+//!     let [text]: [&str; 1] = req.parse_params()?;
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), text.len()))
+//! }
+//! ```
+
+use std::borrow::Cow;
+
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{json_content_type, Id, JsonRpcRequest, JsonRpcResponse, Value, JSONRPC};
+
+/// A JSON-RPC request with raw params, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct JsonRpcRawExtractor {
+    pub id: Id,
+    pub method: String,
+    /// Params as sent, `None` if omitted
+    pub params: Option<Box<RawValue>>,
+    /// Extensions of the underlying HTTP request
+    pub extensions: http::Extensions,
+}
+
+impl JsonRpcRawExtractor {
+    pub fn get_answer_id(&self) -> Id {
+        self.id.clone()
+    }
+
+    /// Borrows the id, for looking at it without cloning
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Deserializes the params, which may borrow from the request. Omitted params are
+    /// deserialized from `null`
+    pub fn parse_params<'a, T: Deserialize<'a>>(&'a self) -> Result<T, JsonRpcResponse> {
+        let raw = self.params.as_deref().map_or("null", RawValue::get);
+        serde_json::from_str(raw).map_err(|e| {
+            let error = JsonRpcError::new(
+                JsonRpcErrorReason::InvalidParams,
+                e.to_string(),
+                Value::default(),
+            );
+            JsonRpcResponse::error(self.id.clone(), error)
+        })
+    }
+
+    /// Returns a value inserted by middleware
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
+    pub fn method_not_found(&self, method: &str) -> JsonRpcResponse {
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::MethodNotFound,
+            format!("Method `{}` not found", method),
+            Value::default(),
+        );
+        JsonRpcResponse::error(self.id.clone(), error)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonRpcRawExtractor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        struct Helper<'a> {
+            #[serde(borrow)]
+            jsonrpc: Cow<'a, str>,
+            // notifications have no id
+            #[serde(default)]
+            id: Id,
+            method: String,
+            #[serde(default)]
+            params: Option<Box<RawValue>>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+        if helper.jsonrpc != JSONRPC {
+            return Err(D::Error::custom("Unknown jsonrpc version"));
+        }
+        Ok(Self {
+            id: helper.id,
+            method: helper.method,
+            params: helper.params,
+            extensions: Default::default(),
+        })
+    }
+}
+
+/// A JSON-RPC request borrowing its method and params from the message it was parsed from.
+/// ```rust
+/// use axum_jrpc::raw::JsonRpcRequestRef;
+///
+/// let body = br#"{"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]}"#;
+/// let request = JsonRpcRequestRef::parse(body).unwrap();
+/// assert_eq!(request.method, "add");
+/// assert_eq!(request.params.unwrap().get(), "[1, 2]");
+///
+/// let owned = request.into_owned();
+/// assert_eq!(owned.params, serde_json::json!([1, 2]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonRpcRequestRef<'a> {
+    pub id: Id,
+    /// Only owned when the method name contains escape sequences
+    pub method: Cow<'a, str>,
+    /// Params as sent, `None` if omitted
+    pub params: Option<&'a RawValue>,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for JsonRpcRequestRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        struct Helper<'a> {
+            #[serde(borrow)]
+            jsonrpc: Cow<'a, str>,
+            // notifications have no id
+            #[serde(default)]
+            id: Id,
+            #[serde(borrow)]
+            method: Cow<'a, str>,
+            #[serde(borrow, default)]
+            params: Option<&'a RawValue>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+        if helper.jsonrpc != JSONRPC {
+            return Err(D::Error::custom("Unknown jsonrpc version"));
+        }
+        Ok(Self {
+            id: helper.id,
+            method: helper.method,
+            params: helper.params,
+        })
+    }
+}
+
+impl<'a> JsonRpcRequestRef<'a> {
+    /// Parses a single request from `bytes`, borrowing from them
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, String> {
+        serde_json::from_slice(bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+
+    /// Parses the params into a [`Value`], giving an owned request
+    pub fn into_owned(self) -> JsonRpcRequest {
+        let params = self
+            .params
+            .and_then(|raw| serde_json::from_str(raw.get()).ok())
+            .unwrap_or_default();
+        JsonRpcRequest {
+            id: self.id,
+            method: self.method.into_owned(),
+            params,
+        }
+    }
+}
+
+impl Serialize for JsonRpcRequestRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct Helper<'a> {
+            jsonrpc: &'static str,
+            #[serde(skip_serializing_if = "Id::is_none")]
+            id: &'a Id,
+            method: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            params: Option<&'a RawValue>,
+        }
+
+        Helper {
+            jsonrpc: JSONRPC,
+            id: &self.id,
+            method: &self.method,
+            params: self.params,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[async_trait::async_trait]
+impl<S> FromRequest<S> for JsonRpcRawExtractor
+where
+    Bytes: FromRequest<S>,
+    S: Send + Sync,
+{
+    type Rejection = JsonRpcResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let invalid = |message: String| {
+            let error = JsonRpcError::new(
+                JsonRpcErrorReason::InvalidRequest,
+                message,
+                Value::default(),
+            );
+            JsonRpcResponse::error(Id::None(()), error)
+        };
+
+        if !json_content_type(req.headers()) {
+            return Err(invalid("Invalid content type".to_owned()));
+        }
+        let extensions = req.extensions().clone();
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|_| invalid("Invalid request".to_owned()))?;
+
+        let mut request: Self =
+            serde_json::from_slice(&bytes).map_err(|e| invalid(e.to_string()))?;
+        request.extensions = extensions;
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::routing::post;
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+
+    use super::{JsonRpcRawExtractor, JsonRpcRequestRef};
+    use crate::{JrpcResult, JsonRpcResponse};
+
+    async fn concat(req: JsonRpcRawExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let parts: Vec<&str> = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, parts.concat()))
+    }
+
+    #[tokio::test]
+    async fn raw_params() {
+        let server = TestServer::new(Router::new().route("/", post(concat))).unwrap();
+
+        let res = server
+            .post("/")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "concat", "params": ["a", "b"]}))
+            .await;
+        assert_eq!(res.json::<Value>()["result"], "ab");
+
+        let res = server
+            .post("/")
+            .json(&json!({"jsonrpc": "2.0", "id": 2, "method": "concat", "params": [1]}))
+            .await;
+        assert_eq!(res.json::<Value>()["error"]["code"], -32602);
+
+        let res = server
+            .post("/")
+            .json(&json!({"jsonrpc": "1.0", "id": 3, "method": "concat"}))
+            .await;
+        assert_eq!(res.json::<Value>()["error"]["code"], -32600);
+    }
+
+    #[test]
+    fn request_ref() {
+        let body = br#"{"jsonrpc":"2.0","method":"log\u0073","params":{"level":"info"}}"#;
+        let request = JsonRpcRequestRef::parse(body).unwrap();
+        assert!(request.is_notification());
+        assert_eq!(request.method, "logs");
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"jsonrpc":"2.0","method":"logs","params":{"level":"info"}}"#
+        );
+        assert_eq!(request.into_owned().params, json!({"level": "info"}));
+
+        assert!(JsonRpcRequestRef::parse(br#"{"jsonrpc":"1.0","method":"a"}"#).is_err());
+    }
+}
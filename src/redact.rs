@@ -0,0 +1,180 @@
+//! Redaction of secrets from internal errors, see
+//! [`JsonRpcRouter::redact`](crate::JsonRpcRouter::redact), for the `redact` feature.
+//!
+//! Handlers turning errors into [`InternalError`](JsonRpcErrorReason::InternalError)s with
+//! `e.to_string()` easily leak connection strings, tokens or file paths to clients. A
+//! [`Redaction`] replaces every match of its patterns in the message and the strings of the
+//! data of such errors with `[redacted]`, before they are serialized.
+//! ```rust
+//! use axum_jrpc::redact::{Redaction, Regex};
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! let redaction = Redaction::new()
+//!     .credentials()
+//!     .paths()
+//!     .deny("hunter2")
+//!     .pattern(Regex::new(r"\bacct_[0-9a-f]{16}\b").unwrap());
+//! let rpc = JsonRpcRouter::new().redact(redaction);
+//! ```
+//! The error observers of the router still see the original errors, see
+//! [`JsonRpcRouter::on_error`](crate::JsonRpcRouter::on_error).
+
+use std::borrow::Cow;
+
+pub use regex::Regex;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{JsonRpcAnswer, JsonRpcResponse, Value};
+
+/// Text replacing the secrets
+const REDACTED: &str = "[redacted]";
+
+/// Patterns of the secrets removed from internal errors, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Redaction {
+    /// Patterns and their replacement
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redaction {
+    /// Redacts nothing until patterns are added
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redacts the credentials of URLs, `password=`, `token=` and similar pairs, and bearer
+    /// tokens
+    pub fn credentials(self) -> Self {
+        self.rule(r"(?i)\b([a-z][a-z0-9+.-]*://)[^\s/@]+@", "${1}[redacted]@")
+            .rule(
+                r#"(?i)(password|passwd|pwd|secret|token|api[_-]?key)(\s*[=:]\s*)[^\s&;,'"]+"#,
+                "${1}${2}[redacted]",
+            )
+            .rule(r"(?i)\b(bearer\s+)[a-z0-9._~+/-]+=*", "${1}[redacted]")
+    }
+
+    /// Redacts absolute file paths
+    pub fn paths(self) -> Self {
+        self.rule(
+            r#"(^|[\s'"(=])(/[\w.@-]+)+/?|\b[A-Za-z]:\\[^\s'"]*"#,
+            "${1}[redacted]",
+        )
+    }
+
+    /// Redacts every occurrence of `secret`, e.g. a password read from the environment
+    pub fn deny(self, secret: &str) -> Self {
+        self.rule(&regex::escape(secret), REDACTED)
+    }
+
+    /// Redacts every match of `pattern`
+    pub fn pattern(mut self, pattern: Regex) -> Self {
+        self.rules.push((pattern, REDACTED.to_owned()));
+        self
+    }
+
+    fn rule(mut self, pattern: &str, replacement: &str) -> Self {
+        let pattern = Regex::new(pattern).expect("valid pattern");
+        self.rules.push((pattern, replacement.to_owned()));
+        self
+    }
+
+    /// `text` with every match replaced
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for (pattern, replacement) in &self.rules {
+            if let Cow::Owned(redacted) = pattern.replace_all(&text, replacement.as_str()) {
+                text = Cow::Owned(redacted);
+            }
+        }
+        text
+    }
+
+    /// `response` with its error redacted if it is internal
+    pub(crate) fn response(&self, response: JsonRpcResponse) -> JsonRpcResponse {
+        match response.result {
+            JsonRpcAnswer::Error(error)
+                if matches!(error.error_reason(), JsonRpcErrorReason::InternalError) =>
+            {
+                let mut data = error.data().clone();
+                self.value(&mut data);
+                let message = self.redact(error.message()).into_owned();
+                let error = JsonRpcError::new(error.error_reason(), message, data);
+                JsonRpcResponse::error(response.id, error)
+            }
+            result => JsonRpcResponse {
+                id: response.id,
+                result,
+            },
+        }
+    }
+
+    #[cfg_attr(
+        not(feature = "serde_json"),
+        allow(clippy::match_wildcard_for_single_variants)
+    )]
+    fn value(&self, value: &mut Value) {
+        match value {
+            Value::String(string) => {
+                if let Cow::Owned(redacted) = self.redact(string) {
+                    *string = redacted;
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.value(value)),
+            Value::Object(map) => map.values_mut().for_each(|value| self.value(value)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::Redaction;
+    use crate::error::{JsonRpcError, JsonRpcErrorReason};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    async fn connect(req: JsonRpcExtractor) -> JrpcResult {
+        let reason = match req.method() {
+            "connect" => JsonRpcErrorReason::InternalError,
+            _ => JsonRpcErrorReason::InvalidParams,
+        };
+        let error = JsonRpcError::new(
+            reason,
+            "postgres://app:hunter2@db/prod: can't read /etc/app/tls.key".to_owned(),
+            json!({"env": ["PGPASSWORD=hunter2", "api_key: 12ab"]}),
+        );
+        Err(JsonRpcResponse::error(req.get_answer_id(), error))
+    }
+
+    #[tokio::test]
+    async fn redacts() {
+        let redaction = Redaction::new().credentials().paths().deny("12ab");
+        let rpc = JsonRpcRouter::new()
+            .method("connect", connect)
+            .method("other", connect)
+            .redact(redaction);
+        let call = |method: &str| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": method}).to_string();
+            let rpc = rpc.clone();
+            async move {
+                let reply = rpc.dispatch_bytes(request.into()).await.unwrap();
+                serde_json::from_slice::<Value>(&reply).unwrap()["error"].clone()
+            }
+        };
+
+        let error = call("connect").await;
+        assert_eq!(
+            error["message"],
+            "postgres://[redacted]@db/prod: can't read [redacted]"
+        );
+        assert_eq!(
+            error["data"]["env"],
+            json!(["PGPASSWORD=[redacted]", "api_key: [redacted]"])
+        );
+        // other errors are left alone
+        let error = call("other").await;
+        assert_eq!(error["data"]["env"][1], "api_key: 12ab");
+    }
+}
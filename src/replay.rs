@@ -0,0 +1,346 @@
+//! Replay protection with nonces and timestamps, for endpoints where a request must not take
+//! effect twice.
+//!
+//! [`ReplayLayer`] wraps a service and requires every call to carry a unique nonce and the
+//! unix time in seconds it was made at, either in the `X-Nonce` and `X-Timestamp` headers or
+//! in a `_meta` object of the params:
+//! ```json
+//! {"jsonrpc": "2.0", "id": 1, "method": "transfer",
+//!     "params": {"to": "bob", "amount": 5, "_meta": {"nonce": "f81d4fae", "timestamp": 1700000000}}}
+//! ```
+//! Calls older or newer than [`ReplayLayer::max_age`], or with a nonce seen before, are
+//! answered with a [`REPLAY_DETECTED`] error. Nonces are remembered by a [`NonceStore`] until
+//! their calls are stale anyway, [`MemoryNonceStore`] keeps them in memory, which suits
+//! single instances. The calls of a batch share the nonce of its headers.
+//! ```rust
+//! use axum_jrpc::replay::{MemoryNonceStore, ReplayLayer};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let rpc = ReplayLayer::new(MemoryNonceStore::default()).layer(JsonRpcRouter::new());
+//! ```
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tower::{Layer, Service};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::{BatchIndex, BoxFuture, RequestHeaders};
+use crate::{JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Error code of calls without a fresh timestamp and unused nonce
+pub const REPLAY_DETECTED: i32 = -32006;
+
+/// Remembers the nonces used so far.
+#[async_trait::async_trait]
+pub trait NonceStore: Send + Sync + 'static {
+    /// Records `nonce`, which can be forgotten at `expires`. Returns `false` if it was
+    /// recorded before
+    async fn insert(&self, nonce: &str, expires: SystemTime) -> bool;
+}
+
+/// Keeps nonces in memory until they expire. Expired nonces are dropped every
+/// [`PRUNE_INTERVAL`](Self::PRUNE_INTERVAL).
+#[derive(Debug, Default)]
+pub struct MemoryNonceStore {
+    nonces: Mutex<Nonces>,
+}
+
+#[derive(Debug, Default)]
+struct Nonces {
+    expiries: HashMap<String, SystemTime>,
+    next_prune: Option<SystemTime>,
+}
+
+impl MemoryNonceStore {
+    /// Time between two sweeps over the nonces for expired ones
+    pub const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+}
+
+#[async_trait::async_trait]
+impl NonceStore for MemoryNonceStore {
+    async fn insert(&self, nonce: &str, expires: SystemTime) -> bool {
+        let now = SystemTime::now();
+        let mut nonces = self.nonces.lock().unwrap_or_else(|e| e.into_inner());
+        let Nonces {
+            expiries,
+            next_prune,
+        } = &mut *nonces;
+        if next_prune.is_none_or(|next| next <= now) {
+            expiries.retain(|_, expires| *expires > now);
+            *next_prune = now.checked_add(Self::PRUNE_INTERVAL);
+        }
+        match expiries.get(nonce) {
+            Some(expires) if *expires > now => false,
+            _ => {
+                expiries.insert(nonce.to_owned(), expires);
+                true
+            }
+        }
+    }
+}
+
+/// Wraps services in [`Replay`], see the [module docs](self).
+pub struct ReplayLayer<N> {
+    store: Arc<N>,
+    max_age: Duration,
+}
+
+impl<N> Clone for ReplayLayer<N> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            max_age: self.max_age,
+        }
+    }
+}
+
+impl<N> std::fmt::Debug for ReplayLayer<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayLayer")
+            .field("max_age", &self.max_age)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<N: NonceStore> ReplayLayer<N> {
+    /// Age of the oldest calls accepted unless [`max_age`](Self::max_age) sets another one
+    pub const MAX_AGE: Duration = Duration::from_secs(300);
+
+    pub fn new(store: N) -> Self {
+        Self {
+            store: Arc::new(store),
+            max_age: Self::MAX_AGE,
+        }
+    }
+
+    /// Rejects calls made more than `max_age` before or after they were received
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// The error answering `request`, if it is a replay
+    async fn check(&self, request: &JsonRpcExtractor) -> Option<JsonRpcError> {
+        let replay = |message: &str| {
+            JsonRpcError::new(
+                JsonRpcErrorReason::ServerError(REPLAY_DETECTED),
+                message.to_owned(),
+                Value::default(),
+            )
+        };
+        let (mut nonce, timestamp, shared) = match from_headers(request) {
+            Some((nonce, timestamp)) => (nonce, timestamp, true),
+            None => match from_meta(request) {
+                Some((nonce, timestamp)) => (nonce, timestamp, false),
+                None => return Some(replay("Missing nonce or timestamp")),
+            },
+        };
+
+        // timestamps too far out for the clock are as stale as any other
+        let Some(made) = UNIX_EPOCH.checked_add(Duration::from_secs(timestamp)) else {
+            return Some(replay("Stale timestamp"));
+        };
+        let now = SystemTime::now();
+        let age = now.duration_since(made).unwrap_or_else(|e| e.duration());
+        if age > self.max_age {
+            return Some(replay("Stale timestamp"));
+        }
+        let Some(expires) = made.checked_add(self.max_age) else {
+            return Some(replay("Stale timestamp"));
+        };
+        // the first call of a batch uses the nonce itself, so no call can be replayed alone
+        match request.extension() {
+            Some(BatchIndex(index)) if shared && *index > 0 => nonce = format!("{nonce}#{index}"),
+            _ => {}
+        }
+        match self.store.insert(&nonce, expires).await {
+            true => None,
+            false => Some(replay("Nonce already used")),
+        }
+    }
+}
+
+/// Nonce and timestamp of the headers, if there are both
+fn from_headers(request: &JsonRpcExtractor) -> Option<(String, u64)> {
+    let headers = &request.extension::<RequestHeaders>()?.0;
+    let nonce = headers.get("x-nonce")?.to_str().ok()?;
+    let timestamp = headers.get("x-timestamp")?.to_str().ok()?.parse().ok()?;
+    Some((nonce.to_owned(), timestamp))
+}
+
+/// Nonce and timestamp of the `_meta` object of the params, if there are both
+fn from_meta(request: &JsonRpcExtractor) -> Option<(String, u64)> {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "serde_json")] {
+            let meta = request.parsed.get("_meta")?;
+            let nonce = meta.get("nonce")?.as_str()?;
+            let timestamp = meta.get("timestamp")?.as_u64()?;
+        } else {
+            use simd_json::prelude::*;
+            let meta = request.parsed.get("_meta")?;
+            let nonce = meta.get_str("nonce")?;
+            let timestamp = meta.get_u64("timestamp")?;
+        }
+    }
+    Some((nonce.to_owned(), timestamp))
+}
+
+impl<N, S> Layer<S> for ReplayLayer<N> {
+    type Service = Replay<N, S>;
+
+    fn layer(&self, inner: S) -> Replay<N, S> {
+        Replay {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Rejects replayed calls, see the [module docs](self).
+pub struct Replay<N, S> {
+    inner: S,
+    layer: ReplayLayer<N>,
+}
+
+impl<N, S: Clone> Clone for Replay<N, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<N, S: std::fmt::Debug> std::fmt::Debug for Replay<N, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Replay")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<N, S> Service<JsonRpcExtractor> for Replay<N, S>
+where
+    N: NonceStore,
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        // the ready service is taken, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            match layer.check(&request).await {
+                Some(error) => Ok(JsonRpcResponse::error(request.id, error)),
+                None => inner.call(request).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{MemoryNonceStore, ReplayLayer, REPLAY_DETECTED};
+    use crate::router;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn ping(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "pong"))
+    }
+
+    #[tokio::test]
+    async fn replays() {
+        let rpc = ReplayLayer::new(MemoryNonceStore::default())
+            .layer(JsonRpcRouter::new().method("ping", ping));
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let call = |nonce: &str, timestamp: u64| {
+            let meta = json!({"nonce": nonce, "timestamp": timestamp});
+            let request =
+                json!({"jsonrpc": "2.0", "id": 1, "method": "ping", "params": {"_meta": meta}});
+            server.post("/").json(&request)
+        };
+
+        let res: Value = call("a", now).await.json();
+        assert_eq!(res["result"], "pong");
+        let res: Value = call("a", now).await.json();
+        assert_eq!(res["error"]["code"], REPLAY_DETECTED);
+        assert_eq!(res["error"]["message"], "Nonce already used");
+        let res: Value = call("b", now - 600).await.json();
+        assert_eq!(res["error"]["message"], "Stale timestamp");
+        let res: Value = call("b", u64::MAX).await.json();
+        assert_eq!(res["error"]["message"], "Stale timestamp");
+        let res: Value = server
+            .post("/")
+            .add_header("x-nonce", "b")
+            .add_header("x-timestamp", u64::MAX.to_string())
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "ping"}))
+            .await
+            .json();
+        assert_eq!(res["error"]["message"], "Stale timestamp");
+        let res: Value = server
+            .post("/")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "ping"}))
+            .await
+            .json();
+        assert_eq!(res["error"]["message"], "Missing nonce or timestamp");
+
+        // the calls of a batch share the nonce of the headers
+        let batch = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+            {"jsonrpc": "2.0", "id": 2, "method": "ping"},
+        ]);
+        let send = || {
+            server
+                .post("/")
+                .add_header("x-nonce", "c")
+                .add_header("x-timestamp", now.to_string())
+                .json(&batch)
+        };
+        let res: Vec<Value> = send().await.json();
+        assert!(res.iter().all(|res| res["result"] == "pong"), "{res:?}");
+        let res: Vec<Value> = send().await.json();
+        assert!(res
+            .iter()
+            .all(|res| res["error"]["code"] == REPLAY_DETECTED));
+        let res: Value = server
+            .post("/")
+            .add_header("x-nonce", "c")
+            .add_header("x-timestamp", now.to_string())
+            .json(&batch[1])
+            .await
+            .json();
+        assert_eq!(res["error"]["code"], REPLAY_DETECTED);
+    }
+}
@@ -0,0 +1,194 @@
+//! REST routes for the methods of a router, for clients which can't speak JSON-RPC.
+//!
+//! [`routes`] answers `POST /<method>` for every method registered with a
+//! [`JsonRpcRouter`], taking the params as the request body, so nested under `/rpc` the
+//! `math.add` method is `POST /rpc/math.add`:
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::{rest, router};
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct AddParams {
+//!     a: i64,
+//!     b: i64,
+//! }
+//!
+//! async fn add(req: JsonRpcExtractor) -> JrpcResult {
+//!     let id = req.get_answer_id();
+//!     let AddParams { a, b } = req.parse_params()?;
+//!     Ok(JsonRpcResponse::success(id, a + b))
+//! }
+//!
+//! let rpc = JsonRpcRouter::new().method("math.add", add);
+//! let app: Router = Router::new()
+//!     .route("/", router::post(rpc.clone()))
+//!     .nest("/rpc", rest::routes(&rpc));
+//! ```
+//! A result is the body of a `200 OK` response. An error is sent as the JSON-RPC error
+//! object, with `400 Bad Request` for malformed or invalid params, `404 Not Found` for
+//! methods which aren't registered and `500 Internal Server Error` for any other code. An
+//! empty body means no params.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Path, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+
+use crate::buffer::json_response;
+use crate::error::{
+    JsonRpcError, JsonRpcErrorReason, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND,
+    PARSE_ERROR,
+};
+use crate::intern;
+use crate::router::{JsonRpcService, RequestHeaders};
+use crate::{from_bytes, Id, JsonRpcAnswer, JsonRpcExtractor, JsonRpcRouter, Value};
+
+/// Routes `POST /<method>` to every method of `router`, see the [module docs](self).
+pub fn routes(router: &JsonRpcRouter) -> Router {
+    routes_for(router.methods(), router.clone())
+}
+
+/// Routes `POST /<method>` to `service` for each of `methods`, e.g. for a router wrapped in
+/// middleware:
+/// ```rust
+/// use axum_jrpc::auth::{AuthLayer, Unauthorized};
+/// use axum_jrpc::{rest, JsonRpcExtractor, JsonRpcRouter};
+/// use tower::Layer;
+///
+/// let rpc = JsonRpcRouter::new();
+/// let auth = AuthLayer::new(|_: &JsonRpcExtractor| Err::<(), _>(Unauthorized::new("no")));
+/// let routes = rest::routes_for(rpc.methods(), auth.layer(rpc.clone()));
+/// ```
+pub fn routes_for<S: JsonRpcService>(
+    methods: impl IntoIterator<Item = impl AsRef<str>>,
+    service: S,
+) -> Router {
+    let methods: Arc<HashSet<Arc<str>>> = Arc::new(
+        methods
+            .into_iter()
+            .map(|method| intern::intern(method.as_ref()))
+            .collect(),
+    );
+    let handler = move |Path(method): Path<String>, request: Request| {
+        let method = methods.get(method.as_str()).cloned();
+        call(service.clone(), method, request)
+    };
+    Router::new().route("/*method", axum::routing::post(handler))
+}
+
+async fn call<S: JsonRpcService>(
+    service: S,
+    method: Option<Arc<str>>,
+    request: Request,
+) -> Response {
+    let Some(method) = method else {
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::MethodNotFound,
+            "Method not found".to_owned(),
+            Value::default(),
+        );
+        return error_response(error);
+    };
+    let mut extensions = request.extensions().clone();
+    extensions.insert(RequestHeaders(Arc::new(request.headers().clone())));
+    let bytes = match Bytes::from_request(request, &()).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let params = if bytes.iter().all(u8::is_ascii_whitespace) {
+        Value::default()
+    } else {
+        match from_bytes(&bytes) {
+            Ok(params) => params,
+            Err(e) => {
+                let error = JsonRpcError::new(JsonRpcErrorReason::ParseError, e, Value::default());
+                return error_response(error);
+            }
+        }
+    };
+
+    let request = JsonRpcExtractor {
+        parsed: params,
+        method,
+        // any id but none, which would make the call a notification
+        id: Id::Num(0),
+        extensions,
+    };
+    match service.dispatch(request).await.result {
+        JsonRpcAnswer::Result(result) => json_response(&result),
+        JsonRpcAnswer::Error(error) => error_response(error),
+    }
+}
+
+fn error_response(error: JsonRpcError) -> Response {
+    let status = match error.code() {
+        PARSE_ERROR | INVALID_REQUEST | INVALID_PARAMS => StatusCode::BAD_REQUEST,
+        METHOD_NOT_FOUND => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, json_response(&error)).into_response()
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::http::StatusCode;
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    #[derive(Deserialize)]
+    struct AddParams {
+        a: i64,
+        b: i64,
+    }
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let AddParams { a, b } = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    async fn ping(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "pong"))
+    }
+
+    #[tokio::test]
+    async fn serves_methods() {
+        let rpc = JsonRpcRouter::new()
+            .method("math.add", add)
+            .method("health/ping", ping);
+        let app = Router::new().nest("/rpc", super::routes(&rpc));
+        let server = TestServer::new(app).unwrap();
+
+        let response = server
+            .post("/rpc/math.add")
+            .json(&json!({"a": 1, "b": 2}))
+            .await;
+        response.assert_status_ok();
+        response.assert_json(&json!(3));
+        server
+            .post("/rpc/health/ping")
+            .await
+            .assert_json(&json!("pong"));
+
+        let response = server.post("/rpc/math.add").json(&json!({"a": 1})).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(response.json::<Value>()["code"], -32602);
+        let response = server.post("/rpc/math.add").text("{").await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(response.json::<Value>()["code"], -32700);
+        let response = server.post("/rpc/math.sub").json(&json!({})).await;
+        response.assert_status_not_found();
+        assert_eq!(response.json::<Value>()["code"], -32601);
+    }
+}
@@ -0,0 +1,1217 @@
+//! Method registry shared by all server transports.
+//!
+//! [`JsonRpcRouter`] maps method names to handlers and is a tower [`Service`], so it can be
+//! wrapped in middleware and served over HTTP with [`post`], over WebSocket, or any other
+//! transport accepting a [`JsonRpcService`].
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::router::{self, JsonRpcRouter};
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse};
+//!
+//! async fn add(req: JsonRpcExtractor) -> JrpcResult {
+//!     let id = req.get_answer_id();
+//!     let params: [i32; 2] = req.parse_params()?;
+//!     Ok(JsonRpcResponse::success(id, params[0] + params[1]))
+//! }
+//!
+//! let rpc = JsonRpcRouter::new().method("add", add);
+//! let app: Router = Router::new().route("/", router::post(rpc));
+//! ```
+//! The router and [`JsonRpcService::dispatch_bytes`] also build for `wasm32-unknown-unknown`
+//! with the default features, so edge runtimes such as Cloudflare Workers can serve the same
+//! handlers by passing request bodies in and the returned bytes out. Transports and layers
+//! relying on tokio stay behind their features. std has no clock on that target, so the
+//! router and layers measuring time read the JavaScript clock there, through
+//! [web-time](https://docs.rs/web-time).
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, Extensions, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::MethodRouter;
+use axum::RequestExt;
+use bytes::BytesMut;
+use futures_util::future::join_all;
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+use tower::{Service, ServiceExt};
+use web_time::Instant;
+
+use crate::cache::CacheStore;
+#[cfg(feature = "cbor")]
+use crate::codec::Cbor;
+#[cfg(feature = "msgpack")]
+use crate::codec::MessagePack;
+use crate::codec::{Codec, JsonBackend};
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::intern::{self, Interned};
+use crate::malformed::Malformed;
+use crate::{batch, buffer};
+use crate::{
+    from_bytes, from_value, json_content_type, to_vec, Id, JrpcResult, JsonRpcAnswer,
+    JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, RequestFields, Value,
+};
+
+pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+pub(crate) type BoxHandler = Arc<dyn Fn(JsonRpcExtractor) -> BoxFuture<JrpcResult> + Send + Sync>;
+
+/// Anything able to answer JSON-RPC requests: a [`JsonRpcRouter`], possibly wrapped in tower
+/// middleware.
+pub trait JsonRpcService: Clone + Send + Sync + 'static {
+    fn dispatch(&self, request: JsonRpcExtractor) -> BoxFuture<JsonRpcResponse>;
+
+    /// Parses a request or a batch of requests, dispatches it and serializes the response.
+    /// Returns `None` when there is nothing to answer, i.e. for notifications.
+    ///
+    /// This is the whole pipeline of a transport, new ones only have to move bytes around
+    fn dispatch_bytes(&self, bytes: Bytes) -> BoxFuture<Option<Bytes>> {
+        let service = self.clone();
+        Box::pin(async move {
+            let reply = handle_slice(&service, &bytes, Extensions::new()).await?;
+            let reply = to_vec(&reply).ok()?;
+            #[cfg(feature = "metrics")]
+            crate::instrument::message_size("response", reply.len());
+            Some(Bytes::from(reply))
+        })
+    }
+
+    /// Dispatches an already built request, without any transport, e.g. to unit test
+    /// handlers. Returns `None` for notifications, like a transport would.
+    /// ```rust
+    /// use axum_jrpc::router::JsonRpcService;
+    /// use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+    ///
+    /// async fn add(req: JsonRpcExtractor) -> JrpcResult {
+    ///     let id = req.get_answer_id();
+    ///     let [a, b]: [i64; 2] = req.parse_params()?;
+    ///     Ok(JsonRpcResponse::success(id, a + b))
+    /// }
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), axum_jrpc::error::JsonRpcError> {
+    /// let rpc = JsonRpcRouter::new().method("add", add);
+    /// let request = JsonRpcRequest::builder("add").positional((1, 2))?.id(1).build();
+    /// let response = rpc.dispatch_request(request).await.unwrap();
+    /// assert_eq!(response.into_result::<i64>()?, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn dispatch_request(&self, request: JsonRpcRequest) -> BoxFuture<Option<JsonRpcResponse>> {
+        let service = self.clone();
+        Box::pin(async move {
+            let notification = request.id.is_none();
+            let response = service.dispatch(request.into()).await;
+            (!notification).then_some(response)
+        })
+    }
+}
+
+impl<S> JsonRpcService for S
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    fn dispatch(&self, request: JsonRpcExtractor) -> BoxFuture<JsonRpcResponse> {
+        let service = self.clone();
+        Box::pin(async move {
+            match service.oneshot(request).await {
+                Ok(response) => response,
+                Err(infallible) => match infallible {},
+            }
+        })
+    }
+}
+
+/// Routes requests to handlers by method name.
+///
+/// A handler is any `async fn(JsonRpcExtractor) -> JrpcResult`, the same signature used
+/// for plain axum handlers.
+#[derive(Clone, Default)]
+pub struct JsonRpcRouter {
+    methods: Arc<HashMap<Arc<str>, BoxHandler>>,
+    fallback: Option<BoxHandler>,
+    on_success: Option<Arc<dyn Fn(&CallInfo, &Value) + Send + Sync>>,
+    on_error: Option<Arc<dyn Fn(&CallInfo, &JsonRpcError) + Send + Sync>>,
+    #[cfg(feature = "redact")]
+    redaction: Option<Arc<crate::redact::Redaction>>,
+    #[cfg(feature = "openrpc")]
+    docs: Arc<HashMap<Arc<str>, crate::openrpc::MethodDoc>>,
+}
+
+/// A completed call, as seen by [`JsonRpcRouter::on_success`] and [`JsonRpcRouter::on_error`]
+#[derive(Debug, Clone, Copy)]
+pub struct CallInfo<'a> {
+    pub method: &'a str,
+    pub id: &'a Id,
+    /// Time spent in the handler
+    pub duration: Duration,
+}
+
+impl std::fmt::Debug for JsonRpcRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonRpcRouter")
+            .field("methods", &self.methods.keys().collect::<Vec<_>>())
+            .field("fallback", &self.fallback.is_some())
+            .field("on_success", &self.on_success.is_some())
+            .field("on_error", &self.on_error.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl JsonRpcRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for `method`, replacing any previous handler
+    pub fn method<H, F>(mut self, method: impl Into<String>, handler: H) -> Self
+    where
+        H: Fn(JsonRpcExtractor) -> F + Send + Sync + 'static,
+        F: Future<Output = JrpcResult> + Send + 'static,
+    {
+        Arc::make_mut(&mut self.methods)
+            .insert(intern::intern(&method.into()), box_handler(handler));
+        self
+    }
+
+    /// Registers a method generated by [`jrpc_method`](crate::jrpc_method)
+    #[cfg(feature = "macros")]
+    pub fn register<M: crate::JsonRpcMethod>(self) -> Self {
+        self.method(M::NAME, M::call)
+    }
+
+    /// Registers every method generated by [`jrpc_method`](crate::jrpc_method) in the binary
+    #[cfg(feature = "inventory")]
+    pub fn collect(self) -> Self {
+        crate::__private::inventory::iter::<crate::__private::MethodEntry>
+            .into_iter()
+            .fold(self, |rpc, entry| rpc.method(entry.name, entry.handler))
+    }
+
+    /// Registers `handler` for an idempotent `method`, its results are reused for `ttl` by
+    /// requests with the same params instead of calling it again
+    pub fn cached_method<H, F>(
+        mut self,
+        method: impl Into<String>,
+        ttl: Duration,
+        handler: H,
+    ) -> Self
+    where
+        H: Fn(JsonRpcExtractor) -> F + Send + Sync + 'static,
+        F: Future<Output = JrpcResult> + Send + 'static,
+    {
+        let method = method.into();
+        let handler = crate::cache::cached(&method, ttl, None, box_handler(handler));
+        Arc::make_mut(&mut self.methods).insert(intern::intern(&method), Arc::new(handler));
+        self
+    }
+
+    /// Like [`cached_method`](Self::cached_method), keeping results in `store`, which may be
+    /// shared with other methods and replicas, see [`cache`](crate::cache)
+    pub fn cached_method_in<H, F>(
+        mut self,
+        method: impl Into<String>,
+        ttl: Duration,
+        store: impl CacheStore,
+        handler: H,
+    ) -> Self
+    where
+        H: Fn(JsonRpcExtractor) -> F + Send + Sync + 'static,
+        F: Future<Output = JrpcResult> + Send + 'static,
+    {
+        let method = method.into();
+        let store = Arc::new(store);
+        let handler = crate::cache::cached(&method, ttl, Some(store), box_handler(handler));
+        Arc::make_mut(&mut self.methods).insert(intern::intern(&method), Arc::new(handler));
+        self
+    }
+
+    /// Caps the calls of `method` running at once, see [`bulkhead`](crate::bulkhead).
+    ///
+    /// # Panics
+    ///
+    /// If no handler is registered for `method` yet
+    #[cfg(feature = "bulkhead")]
+    pub fn bulkhead(mut self, method: &str, bulkhead: crate::bulkhead::Bulkhead) -> Self {
+        let methods = Arc::make_mut(&mut self.methods);
+        let Some((method, handler)) = methods.remove_entry(method) else {
+            panic!("no handler registered for `{method}`");
+        };
+        let handler = crate::bulkhead::bulkhead(bulkhead, handler);
+        methods.insert(method, Arc::new(handler));
+        self
+    }
+
+    /// Validates the params of `method` against `schema` before calling its handler, see
+    /// [`schema`](crate::schema).
+    ///
+    /// # Panics
+    ///
+    /// If no handler is registered for `method` yet
+    #[cfg(feature = "jsonschema")]
+    pub fn validate(mut self, method: &str, schema: crate::schema::ParamsSchema) -> Self {
+        let methods = Arc::make_mut(&mut self.methods);
+        let Some((method, handler)) = methods.remove_entry(method) else {
+            panic!("no handler registered for `{method}`");
+        };
+        let handler = crate::schema::validate(schema, handler);
+        methods.insert(method, Arc::new(handler));
+        self
+    }
+
+    /// Registers a subscription: `subscribe` calls are handed to `handler` which pushes
+    /// `notification`s until the client calls `unsubscribe` or disconnects, see [`pubsub`]
+    ///
+    /// [`pubsub`]: crate::pubsub
+    #[cfg(feature = "pubsub")]
+    pub fn subscription<H, F>(
+        mut self,
+        subscribe: impl Into<String>,
+        notification: impl Into<String>,
+        unsubscribe: impl Into<String>,
+        handler: H,
+    ) -> Self
+    where
+        H: Fn(JsonRpcExtractor, crate::pubsub::PendingSubscription) -> F + Send + Sync + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let notification: Arc<str> = notification.into().into();
+        let methods = Arc::make_mut(&mut self.methods);
+        methods.insert(
+            intern::intern(&subscribe.into()),
+            Arc::new(crate::pubsub::subscribe_handler(
+                notification.clone(),
+                Arc::new(handler),
+            )),
+        );
+        methods.insert(
+            intern::intern(&unsubscribe.into()),
+            Arc::new(crate::pubsub::unsubscribe_handler(notification)),
+        );
+        self
+    }
+
+    /// Handles every method without its own handler, e.g. a dispatcher generated by
+    /// [`rpc`](crate::rpc). Unknown methods are answered with `Method not found` by default
+    pub fn fallback<H, F>(mut self, handler: H) -> Self
+    where
+        H: Fn(JsonRpcExtractor) -> F + Send + Sync + 'static,
+        F: Future<Output = JrpcResult> + Send + 'static,
+    {
+        self.fallback = Some(box_handler(handler));
+        self
+    }
+
+    /// Calls `observer` after every call answered with a result, e.g. to feed custom
+    /// alerting. It runs on the request's task, so it should be quick
+    pub fn on_success<O>(mut self, observer: O) -> Self
+    where
+        O: Fn(&CallInfo, &Value) + Send + Sync + 'static,
+    {
+        self.on_success = Some(Arc::new(observer));
+        self
+    }
+
+    /// Calls `observer` after every call answered with an error, including unknown methods,
+    /// e.g. to report them to an error tracker. It runs on the request's task, so it should
+    /// be quick
+    pub fn on_error<O>(mut self, observer: O) -> Self
+    where
+        O: Fn(&CallInfo, &JsonRpcError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(observer));
+        self
+    }
+
+    /// Removes secrets from the internal errors of all methods before they are serialized,
+    /// see [`redact`](crate::redact)
+    #[cfg(feature = "redact")]
+    pub fn redact(mut self, redaction: crate::redact::Redaction) -> Self {
+        self.redaction = Some(Arc::new(redaction));
+        self
+    }
+
+    /// Names of all registered methods
+    pub fn methods(&self) -> impl Iterator<Item = &str> {
+        self.methods.keys().map(|method| &**method)
+    }
+
+    /// Describes `method` in the [OpenRPC](crate::openrpc) document of the router
+    #[cfg(feature = "openrpc")]
+    pub fn describe(mut self, method: &str, doc: crate::openrpc::MethodDoc) -> Self {
+        Arc::make_mut(&mut self.docs).insert(intern::intern(method), doc);
+        self
+    }
+
+    /// [OpenRPC](crate::openrpc) document of all registered methods
+    #[cfg(feature = "openrpc")]
+    pub fn openrpc(&self, info: crate::openrpc::Info) -> crate::openrpc::OpenRpc {
+        let methods = self
+            .methods
+            .keys()
+            .map(|method| (&**method, self.docs.get(method)));
+        crate::openrpc::document(info, methods)
+    }
+
+    /// Serves the [OpenRPC](crate::openrpc) document of the methods registered so far with
+    /// the `rpc.discover` method, so it should be called last
+    #[cfg(feature = "openrpc")]
+    pub fn discover(self, info: crate::openrpc::Info) -> Self {
+        let document = Arc::new(self.openrpc(info));
+        self.method("rpc.discover", move |request: JsonRpcExtractor| {
+            let document = document.clone();
+            async move {
+                Ok(JsonRpcResponse::success(
+                    request.get_answer_id(),
+                    &*document,
+                ))
+            }
+        })
+    }
+}
+
+fn box_handler<H, F>(handler: H) -> BoxHandler
+where
+    H: Fn(JsonRpcExtractor) -> F + Send + Sync + 'static,
+    F: Future<Output = JrpcResult> + Send + 'static,
+{
+    Arc::new(move |request| Box::pin(handler(request)))
+}
+
+impl Service<JsonRpcExtractor> for JsonRpcRouter {
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let handler = self
+            .methods
+            .get(request.method())
+            .or(self.fallback.as_ref())
+            .cloned();
+
+        let observed = (self.on_success.is_some() || self.on_error.is_some())
+            .then(|| (request.method.clone(), Instant::now()));
+        let (on_success, on_error) = (self.on_success.clone(), self.on_error.clone());
+        #[cfg(feature = "redact")]
+        let redaction = self.redaction.clone();
+
+        Box::pin(async move {
+            let response = match handler {
+                Some(handler) => handler(request).await,
+                None => Ok(request.method_not_found(request.method())),
+            };
+            let response = response.unwrap_or_else(|e| e);
+
+            if let Some((method, started)) = observed {
+                let call = CallInfo {
+                    method: &method,
+                    id: &response.id,
+                    duration: started.elapsed(),
+                };
+                match (&response.result, on_success, on_error) {
+                    (JsonRpcAnswer::Result(value), Some(observer), _) => observer(&call, value),
+                    (JsonRpcAnswer::Error(error), _, Some(observer)) => observer(&call, error),
+                    _ => {}
+                }
+            }
+            // after the observers, which get to see the original error
+            #[cfg(feature = "redact")]
+            let response = match redaction {
+                Some(redaction) => redaction.response(response),
+                None => response,
+            };
+            Ok(response)
+        })
+    }
+}
+
+/// Serves `service` on HTTP `POST`, for use with [`axum::Router::route`].
+/// Batches are supported, notifications are answered with `204 No Content`.
+///
+/// Clients accepting [`JSON_LINES`] get the responses of a batch streamed one per line as
+/// soon as each completes, instead of a single array once all of them are done. Such
+/// batches are also parsed incrementally: each request is dispatched as soon as it has been
+/// received, and only the one being received is buffered. The body as a whole is still
+/// held to the [`DefaultBodyLimit`](axum::extract::DefaultBodyLimit), exceeding it ends the
+/// batch with a parse error.
+///
+/// With the `msgpack` feature, requests may be sent as [MessagePack](https://msgpack.org/) with
+/// the `application/msgpack` content type. Likewise with the `cbor` feature for
+/// [CBOR](https://cbor.io/) and `application/cbor`. Responses use the encoding preferred by
+/// the `Accept` header, or the encoding of the request if it lists none of them
+pub fn post<S: JsonRpcService>(service: S) -> MethodRouter {
+    axum::routing::post(move |request: Request| handle_post(service, request, None))
+}
+
+/// Like [`post`], but bodies of at least `threshold` bytes are parsed, and their responses
+/// serialized, on tokio's blocking pool, so multi-megabyte batches don't stall the other
+/// connections served by the same worker thread
+#[cfg(feature = "blocking")]
+pub fn post_blocking<S: JsonRpcService>(service: S, threshold: usize) -> MethodRouter {
+    axum::routing::post(move |request: Request| handle_post(service, request, Some(threshold)))
+}
+
+pub(crate) async fn handle_post<S: JsonRpcService>(
+    service: S,
+    request: Request,
+    blocking_threshold: Option<usize>,
+) -> Response {
+    let Some(format) = Format::from_headers(request.headers()) else {
+        let response = error_response(JsonRpcErrorReason::InvalidRequest, "Invalid content type");
+        return response.into_response();
+    };
+
+    let streaming = accepts_json_lines(request.headers());
+    let response_format = Format::negotiate(request.headers()).unwrap_or(format);
+    let mut extensions = request.extensions().clone();
+    extensions.insert(RequestHeaders(Arc::new(request.headers().clone())));
+    #[cfg(feature = "opentelemetry")]
+    crate::otel::extract_context(request.headers(), &mut extensions);
+    let streaming = streaming && format == Format::Json;
+    // streamed batches are held to the same `DefaultBodyLimit` as buffered bodies
+    let request = match streaming {
+        true => request.with_limited_body(),
+        false => request,
+    };
+    let (parts, mut body) = request.into_parts();
+    if streaming {
+        // peek at the first token, batches are handled while they are received
+        let mut data = body.into_data_stream();
+        let mut prefix = BytesMut::new();
+        let first = loop {
+            match data.next().await {
+                Some(Ok(chunk)) => prefix.extend_from_slice(&chunk),
+                Some(Err(e)) if exceeds_limit(&e) => {
+                    let rejection = "Failed to buffer the request body";
+                    Malformed::Oversized.report(None, rejection, &extensions);
+                    return (StatusCode::PAYLOAD_TOO_LARGE, rejection).into_response();
+                }
+                Some(Err(e)) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+                None => break None,
+            }
+            if let Some(&byte) = prefix.iter().find(|byte| !byte.is_ascii_whitespace()) {
+                break Some(byte);
+            }
+            // bodies of whitespace are not worth streaming
+            if prefix.len() >= MAX_PEEK {
+                break None;
+            }
+        };
+        let prefix = prefix.freeze();
+        if first == Some(b'[') {
+            return batch::stream_batch(service, prefix, data, extensions);
+        }
+        body = Body::from_stream(stream::iter([Ok(prefix)]).chain(data));
+    }
+
+    let request = Request::from_parts(parts, body);
+    let bytes = match Bytes::from_request(request, &()).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => {
+            if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+                Malformed::Oversized.report(None, &rejection.body_text(), &extensions);
+            }
+            return rejection.into_response();
+        }
+    };
+    let size = bytes.len();
+    #[cfg(feature = "metrics")]
+    crate::instrument::message_size("request", size);
+    let value = match offload(size, blocking_threshold, move || format.decode(&bytes)).await {
+        Ok(value) => value,
+        Err(e) => {
+            Malformed::Syntax.report(None, &e, &extensions);
+            let response = error_response(JsonRpcErrorReason::ParseError, e);
+            return response_format.respond(Reply::Single(response));
+        }
+    };
+    match handle_value(&service, value, extensions).await {
+        Some(reply) => {
+            let response = offload(size, blocking_threshold, move || {
+                response_format.respond(reply)
+            })
+            .await;
+            #[cfg(feature = "metrics")]
+            if let Some(size) = axum::body::HttpBody::size_hint(response.body()).exact() {
+                crate::instrument::message_size("response", size as usize);
+            }
+            response
+        }
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Runs `f` on the blocking pool if `size` reaches `threshold`, otherwise in place
+#[cfg_attr(not(feature = "blocking"), allow(unused_variables))]
+async fn offload<T, F>(size: usize, threshold: Option<usize>, f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    #[cfg(feature = "blocking")]
+    if threshold.is_some_and(|threshold| size >= threshold) {
+        return match tokio::task::spawn_blocking(f).await {
+            Ok(value) => value,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        };
+    }
+    f()
+}
+
+/// Media type of MessagePack encoded requests and responses
+#[cfg(feature = "msgpack")]
+pub const MSGPACK: &str = MessagePack::CONTENT_TYPE;
+
+/// Media type of CBOR encoded requests and responses
+#[cfg(feature = "cbor")]
+pub const CBOR: &str = Cbor::CONTENT_TYPE;
+
+/// Codec of a request body, selected by its content type. Responses use the same one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl Format {
+    /// Codec of the request body, from its content type
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        if json_content_type(headers) {
+            return Some(Self::Json);
+        }
+        let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        Self::from_media_type(content_type.split(';').next().unwrap_or_default().trim())
+    }
+
+    /// Codec preferred by the `Accept` header, if any of the enabled ones is listed
+    fn negotiate(headers: &HeaderMap) -> Option<Self> {
+        let mut best = None;
+        for (media_type, quality) in accepted(headers) {
+            let Some(format) = Self::from_media_type(media_type) else {
+                continue;
+            };
+            if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+                best = Some((format, quality));
+            }
+        }
+        best.map(|(format, _)| format)
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        if media_type.eq_ignore_ascii_case(JsonBackend::CONTENT_TYPE) {
+            return Some(Self::Json);
+        }
+        #[cfg(feature = "msgpack")]
+        {
+            let msgpack = [MSGPACK, "application/x-msgpack", "application/vnd.msgpack"];
+            if msgpack.iter().any(|t| media_type.eq_ignore_ascii_case(t)) {
+                return Some(Self::MessagePack);
+            }
+        }
+        #[cfg(feature = "cbor")]
+        if media_type.eq_ignore_ascii_case(CBOR) {
+            return Some(Self::Cbor);
+        }
+        None
+    }
+
+    fn decode(self, bytes: &[u8]) -> Result<Value, String> {
+        match self {
+            Self::Json => JsonBackend::decode_slice(bytes),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => MessagePack::decode_slice(bytes),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => Cbor::decode_slice(bytes),
+        }
+    }
+
+    fn respond(self, reply: Reply) -> Response {
+        match self {
+            Self::Json => buffer::json_response(&reply),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => encoded::<MessagePack>(&reply),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => encoded::<Cbor>(&reply),
+        }
+    }
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+fn encoded<C: Codec>(reply: &Reply) -> Response {
+    match C::encode(reply) {
+        Ok(body) => ([(header::CONTENT_TYPE, C::CONTENT_TYPE)], body).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Whether reading a body failed because it is longer than the body limit
+pub(crate) fn exceeds_limit(error: &axum::Error) -> bool {
+    let error: &(dyn std::error::Error + 'static) = error;
+    std::iter::successors(Some(error), |e| e.source())
+        .any(|e| e.is::<http_body_util::LengthLimitError>())
+}
+
+/// Bytes of leading whitespace read to find out if a body is a batch to stream, bodies with
+/// more are buffered as usual
+const MAX_PEEK: usize = 4096;
+
+/// Media type of batch responses streamed as [JSON Lines](https://jsonlines.org/)
+pub const JSON_LINES: &str = "application/x-ndjson";
+
+fn accepts_json_lines(headers: &HeaderMap) -> bool {
+    accepted(headers).any(|(media_type, quality)| {
+        quality > 0.0
+            && (media_type.eq_ignore_ascii_case(JSON_LINES)
+                || media_type.eq_ignore_ascii_case("application/jsonl"))
+    })
+}
+
+/// Media types listed in the `Accept` header with their quality, `1` when unspecified
+fn accepted(headers: &HeaderMap) -> impl Iterator<Item = (&str, f32)> {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|media_range| {
+            let mut parts = media_range.split(';');
+            let media_type = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| name.trim() == "q")
+                .and_then(|(_, q)| q.trim().parse().ok())
+                .unwrap_or(1.0);
+            (media_type, quality)
+        })
+}
+
+/// Position of a request in its batch, an extension of every request received in one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchIndex(pub usize);
+
+/// Headers of the HTTP request a call was received in, an extension of the calls received
+/// by [`post`] and of [`JsonRpcExtractor`]
+#[derive(Debug, Clone)]
+pub struct RequestHeaders(pub Arc<HeaderMap>);
+
+/// Answer to a single message, which may be a batch of requests.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub(crate) enum Reply {
+    Single(JsonRpcResponse),
+    Batch(Vec<JsonRpcResponse>),
+}
+
+impl Reply {
+    #[cfg_attr(not(feature = "pubsub"), allow(dead_code))]
+    pub(crate) fn responses(&self) -> &[JsonRpcResponse] {
+        match self {
+            Self::Single(response) => std::slice::from_ref(response),
+            Self::Batch(responses) => responses,
+        }
+    }
+}
+
+pub(crate) fn error_response(
+    reason: JsonRpcErrorReason,
+    message: impl Into<String>,
+) -> JsonRpcResponse {
+    let error = JsonRpcError::new(reason, message.into(), Value::default());
+    JsonRpcResponse::error(Id::None(()), error)
+}
+
+/// Parses and handles a single message, see [`JsonRpcService::dispatch_bytes`]. Only
+/// simd-json copies `bytes`, every request gets its own copy of `extensions`
+pub(crate) async fn handle_slice<S: JsonRpcService>(
+    service: &S,
+    bytes: &[u8],
+    extensions: Extensions,
+) -> Option<Reply> {
+    #[cfg(feature = "metrics")]
+    crate::instrument::message_size("request", bytes.len());
+    match from_bytes(bytes) {
+        Ok(value) => handle_value(service, value, extensions).await,
+        Err(e) => {
+            Malformed::Syntax.report(None, &e, &extensions);
+            Some(Reply::Single(error_response(
+                JsonRpcErrorReason::ParseError,
+                e,
+            )))
+        }
+    }
+}
+
+/// Handles an already parsed message, see [`handle_slice`]
+pub(crate) async fn handle_value<S: JsonRpcService>(
+    service: &S,
+    value: Value,
+    extensions: Extensions,
+) -> Option<Reply> {
+    match value {
+        Value::Array(values) if values.is_empty() => Some(Reply::Single(error_response(
+            JsonRpcErrorReason::InvalidRequest,
+            "Empty batch",
+        ))),
+        Value::Array(values) => {
+            let requests = values.into_iter().enumerate().map(|(index, value)| {
+                let mut extensions = extensions.clone();
+                extensions.insert(BatchIndex(index));
+                handle_request(service, value, extensions)
+            });
+            let responses: Vec<_> = join_all(requests).await.into_iter().flatten().collect();
+            // a batch of notifications is not answered at all
+            (!responses.is_empty()).then_some(Reply::Batch(responses))
+        }
+        value => handle_request(service, value, extensions)
+            .await
+            .map(Reply::Single),
+    }
+}
+
+pub(crate) async fn handle_request<S: JsonRpcService>(
+    service: &S,
+    value: Value,
+    extensions: Extensions,
+) -> Option<JsonRpcResponse> {
+    // the value is gone once deserialized, rejections are labeled with what it was
+    #[cfg(any(feature = "metrics", feature = "tracing"))]
+    let method = crate::malformed::method_of(&value).and_then(intern::lookup);
+    #[cfg(not(any(feature = "metrics", feature = "tracing")))]
+    let method: Option<Arc<str>> = None;
+    let request: RequestFields<Interned> = match from_value(value) {
+        Ok(request) => request,
+        Err(e) => {
+            Malformed::of_request(&e).report(method.as_deref(), &e, &extensions);
+            return Some(error_response(JsonRpcErrorReason::InvalidRequest, e));
+        }
+    };
+
+    let notification = request.id.is_none();
+    #[cfg(feature = "metrics")]
+    let call = crate::instrument::Call::start(&request.method.0);
+    let response = service.dispatch(request.into_extractor(extensions)).await;
+    #[cfg(feature = "metrics")]
+    call.finish(&response);
+    (!notification).then_some(response)
+}
+
+impl From<JsonRpcRequest> for JsonRpcExtractor {
+    fn from(request: JsonRpcRequest) -> Self {
+        Self {
+            parsed: request.params,
+            method: intern::name(&request.method),
+            id: request.id,
+            extensions: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::body::{Body, Bytes};
+    use axum::extract::{DefaultBodyLimit, Request};
+    use axum::http::{header, HeaderValue, StatusCode};
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::{post, JsonRpcRouter, JsonRpcService, JSON_LINES};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse};
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i32; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    async fn call(rpc: &JsonRpcRouter, message: &str) -> Option<Value> {
+        let reply = rpc.dispatch_bytes(Bytes::from(message.to_owned())).await?;
+        Some(serde_json::from_slice(&reply).unwrap())
+    }
+
+    #[tokio::test]
+    async fn dispatch_bytes() {
+        let rpc = JsonRpcRouter::new().method("add", add);
+
+        let reply = call(
+            &rpc,
+            r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}"#,
+        )
+        .await;
+        assert_eq!(reply.unwrap()["result"], 3);
+
+        let reply = call(&rpc, r#"{"jsonrpc":"2.0","method":"add","params":[1,2]}"#).await;
+        assert!(reply.is_none());
+
+        let reply = call(&rpc, r#"{"jsonrpc":"2.0","#).await.unwrap();
+        assert_eq!(reply["error"]["code"], -32700);
+        assert_eq!(reply["id"], Value::Null);
+
+        let reply = call(&rpc, "[]").await.unwrap();
+        assert_eq!(reply["error"]["code"], -32600);
+
+        let batch = r#"[
+            {"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]},
+            {"jsonrpc":"2.0","method":"add","params":[1,2]},
+            {"jsonrpc":"2.0","id":2,"method":"missing"},
+            1
+        ]"#;
+        let reply = call(&rpc, batch).await.unwrap();
+        let codes: Vec<_> = reply
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| (r["id"].clone(), r["error"]["code"].clone()))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (json!(1), Value::Null),
+                (json!(2), json!(-32601)),
+                (Value::Null, json!(-32600))
+            ]
+        );
+
+        let notifications = r#"[{"jsonrpc":"2.0","method":"add","params":[1,2]}]"#;
+        assert!(call(&rpc, notifications).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn null_id_is_answered() {
+        let rpc = JsonRpcRouter::new().method("add", add);
+
+        let message = r#"{"jsonrpc":"2.0","id":null,"method":"add","params":[1,2]}"#;
+        let reply = call(&rpc, message).await.unwrap();
+        assert_eq!(reply["id"], Value::Null);
+        assert_eq!(reply["result"], 3);
+
+        let batch = format!("[{message}]");
+        let reply = call(&rpc, &batch).await.unwrap();
+        assert_eq!(reply[0]["result"], 3);
+    }
+
+    #[tokio::test]
+    async fn dispatch_request() {
+        let rpc = JsonRpcRouter::new().method("add", add);
+
+        let request = JsonRpcRequest::builder("add").positional((1, 2)).unwrap();
+        let response = rpc.dispatch_request(request.id("a").build()).await.unwrap();
+        assert_eq!(response.id, "a".into());
+        assert_eq!(response.into_result::<i32>().unwrap(), 3);
+
+        let request = JsonRpcRequest::builder("add").positional((1, 2)).unwrap();
+        assert!(rpc.dispatch_request(request.notification()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn http_batch() {
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let server = TestServer::new(Router::new().route("/", post(rpc))).unwrap();
+
+        let res = server
+            .post("/")
+            .json(&json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]},
+                {"jsonrpc": "2.0", "id": 2, "method": "add", "params": [3, 4]},
+            ]))
+            .await;
+        let results: Vec<_> = res
+            .json::<Vec<Value>>()
+            .into_iter()
+            .map(|r| r["result"].clone())
+            .collect();
+        assert_eq!(results, [json!(3), json!(7)]);
+
+        let res = server
+            .post("/")
+            .json(&json!({"jsonrpc": "2.0", "method": "add", "params": [1, 2]}))
+            .await;
+        assert_eq!(res.status_code(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn json_lines() {
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let server = TestServer::new(Router::new().route("/", post(rpc))).unwrap();
+
+        let res = server
+            .post("/")
+            .add_header(
+                header::ACCEPT,
+                HeaderValue::from_static("application/x-ndjson"),
+            )
+            .json(&json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]},
+                {"jsonrpc": "2.0", "method": "add", "params": [1, 2]},
+                {"jsonrpc": "2.0", "id": 2, "method": "add", "params": [3, 4]},
+            ]))
+            .await;
+        assert_eq!(res.header(header::CONTENT_TYPE), JSON_LINES);
+        let mut results: Vec<_> = res
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["result"].clone())
+            .collect();
+        results.sort_by_key(|result| result.as_i64());
+        assert_eq!(results, [json!(3), json!(7)]);
+
+        // requests before a defect are still answered
+        let res = server
+            .post("/")
+            .add_header(
+                header::ACCEPT,
+                HeaderValue::from_static("application/x-ndjson"),
+            )
+            .text(r#"[{"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]}, {"#)
+            .content_type("application/json")
+            .await;
+        let codes: Vec<_> = res
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["error"]["code"].clone())
+            .collect();
+        assert!(codes.contains(&json!(-32700)) && codes.contains(&Value::Null));
+
+        // single requests are not streamed
+        let res = server
+            .post("/")
+            .add_header(
+                header::ACCEPT,
+                HeaderValue::from_static("application/x-ndjson"),
+            )
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]}))
+            .await;
+        assert_eq!(res.json::<Value>()["result"], 3);
+
+        // streamed bodies are held to the body limit
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let app = Router::new()
+            .route("/", post(rpc))
+            .layer(DefaultBodyLimit::max(1024));
+        let server = TestServer::new(app.clone()).unwrap();
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]});
+        let res = server
+            .post("/")
+            .add_header(
+                header::ACCEPT,
+                HeaderValue::from_static("application/x-ndjson"),
+            )
+            .json(&vec![call.clone(); 100])
+            .await;
+        res.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        // also once the batch is being handled
+        let chunks = std::iter::once("[".to_owned())
+            .chain(std::iter::repeat_n(format!("{call},"), 100))
+            .map(Ok::<_, std::io::Error>);
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, JSON_LINES)
+            .body(Body::from_stream(futures_util::stream::iter(chunks)))
+            .unwrap();
+        let res = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let lines: Vec<Value> = serde_json::Deserializer::from_slice(&body)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert!(lines.len() < 100);
+        assert_eq!(lines.last().unwrap()["error"]["code"], -32700);
+
+        // long leading whitespace is not streamed
+        let chunks = [" ".repeat(4096), " ".repeat(4096), format!("[{call}]")];
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, JSON_LINES)
+            .body(Body::from_stream(futures_util::stream::iter(
+                chunks.map(Ok::<_, std::io::Error>),
+            )))
+            .unwrap();
+        let app = Router::new()
+            .route("/", post(JsonRpcRouter::new().method("add", add)))
+            .layer(DefaultBodyLimit::disable());
+        let res = app.oneshot(request).await.unwrap();
+        assert_ne!(res.headers()[header::CONTENT_TYPE], JSON_LINES);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let res: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(res[0]["result"], 3);
+    }
+
+    #[tokio::test]
+    async fn observers() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (successes, errors) = (seen.clone(), seen.clone());
+        let rpc = JsonRpcRouter::new()
+            .method("add", add)
+            .on_success(move |call, value| {
+                let entry = format!("{} {:?} {value}", call.method, call.id);
+                successes.lock().unwrap().push(entry);
+            })
+            .on_error(move |call, error| {
+                let entry = format!("{} {:?} {}", call.method, call.id, error.code());
+                errors.lock().unwrap().push(entry);
+            });
+
+        call(
+            &rpc,
+            r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}"#,
+        )
+        .await;
+        call(
+            &rpc,
+            r#"{"jsonrpc":"2.0","id":2,"method":"add","params":[1]}"#,
+        )
+        .await;
+        call(
+            &rpc,
+            r#"{"jsonrpc":"2.0","id":3,"method":"sub","params":[1,2]}"#,
+        )
+        .await;
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["add Num(1) 3", "add Num(2) -32602", "sub Num(3) -32601"]
+        );
+    }
+
+    #[cfg(feature = "blocking")]
+    #[tokio::test]
+    async fn blocking() {
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let app = Router::new().route("/", super::post_blocking(rpc, 0));
+        let server = TestServer::new(app).unwrap();
+
+        let res = server
+            .post("/")
+            .json(&json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]},
+                {"jsonrpc": "2.0", "id": 2, "method": "add", "params": [3, 4]},
+            ]))
+            .await;
+        let results: Vec<Value> = res.json();
+        assert_eq!(results[0]["result"], 3);
+        assert_eq!(results[1]["result"], 7);
+
+        let res = server
+            .post("/")
+            .text("{")
+            .content_type("application/json")
+            .await;
+        assert_eq!(res.json::<Value>()["error"]["code"], -32700);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn msgpack() {
+        use super::MSGPACK;
+
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let server = TestServer::new(Router::new().route("/", post(rpc))).unwrap();
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]});
+        let res = server
+            .post("/")
+            .content_type(MSGPACK)
+            .bytes(rmp_serde::to_vec_named(&request).unwrap().into())
+            .await;
+        assert_eq!(res.header(header::CONTENT_TYPE), MSGPACK);
+        let response: Value = rmp_serde::from_slice(&res.as_bytes()[..]).unwrap();
+        assert_eq!(response["result"], 3);
+        assert_eq!(response["id"], 1);
+
+        let res = server
+            .post("/")
+            .content_type(MSGPACK)
+            .bytes(Bytes::from_static(b"\xc1"))
+            .await;
+        let response: Value = rmp_serde::from_slice(&res.as_bytes()[..]).unwrap();
+        assert_eq!(response["error"]["code"], -32700);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[tokio::test]
+    async fn cbor() {
+        use super::CBOR;
+
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let server = TestServer::new(Router::new().route("/", post(rpc))).unwrap();
+
+        let request = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]},
+            {"jsonrpc": "2.0", "id": 2, "method": "add", "params": [3, 4]},
+        ]);
+        let mut body = Vec::new();
+        ciborium::into_writer(&request, &mut body).unwrap();
+        let res = server.post("/").content_type(CBOR).bytes(body.into()).await;
+        assert_eq!(res.header(header::CONTENT_TYPE), CBOR);
+        let response: Value = ciborium::from_reader(&res.as_bytes()[..]).unwrap();
+        assert_eq!(response[0]["result"], 3);
+        assert_eq!(response[1]["result"], 7);
+    }
+
+    #[cfg(all(feature = "msgpack", feature = "cbor"))]
+    #[tokio::test]
+    async fn negotiation() {
+        use super::{CBOR, MSGPACK};
+
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let server = TestServer::new(Router::new().route("/", post(rpc))).unwrap();
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "add", "params": [1, 2]});
+
+        let res = server
+            .post("/")
+            .add_header(header::ACCEPT, HeaderValue::from_static(MSGPACK))
+            .json(&request)
+            .await;
+        assert_eq!(res.header(header::CONTENT_TYPE), MSGPACK);
+        let response: Value = rmp_serde::from_slice(&res.as_bytes()[..]).unwrap();
+        assert_eq!(response["result"], 3);
+
+        let accept = "application/cbor;q=0.5, application/json, text/html";
+        let res = server
+            .post("/")
+            .add_header(header::ACCEPT, HeaderValue::from_static(accept))
+            .content_type(MSGPACK)
+            .bytes(rmp_serde::to_vec_named(&request).unwrap().into())
+            .await;
+        assert_eq!(res.json::<Value>()["result"], 3);
+
+        let res = server
+            .post("/")
+            .add_header(header::ACCEPT, HeaderValue::from_static("*/*"))
+            .content_type(CBOR)
+            .bytes(Bytes::from_static(b"\xff"))
+            .await;
+        assert_eq!(res.header(header::CONTENT_TYPE), CBOR);
+        let response: Value = ciborium::from_reader(&res.as_bytes()[..]).unwrap();
+        assert_eq!(response["error"]["code"], -32700);
+    }
+}
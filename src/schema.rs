@@ -0,0 +1,169 @@
+//! Validation of params against JSON Schemas, for the `jsonschema` feature.
+//!
+//! A [`ParamsSchema`] attached to a method with
+//! [`JsonRpcRouter::validate`](crate::JsonRpcRouter::validate) checks the params of its calls
+//! before the handler runs. Calls with invalid params are answered with an
+//! [`InvalidParams`](crate::error::JsonRpcErrorReason::InvalidParams) error listing every
+//! violation in its data:
+//! ```json
+//! {"errors": [{"instancePath": "/b", "schemaPath": "/properties/b/type", "message": "\"2\" is not of type \"integer\""}]}
+//! ```
+//! ```rust
+//! use axum_jrpc::schema::ParamsSchema;
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//! use serde_json::json;
+//!
+//! async fn add(req: JsonRpcExtractor) -> JrpcResult {
+//!     let id = req.get_answer_id();
+//!     let (a, b): (i64, i64) = req.parse_params()?;
+//!     Ok(JsonRpcResponse::success(id, a + b))
+//! }
+//!
+//! let schema = ParamsSchema::new(&json!({
+//!     "type": "array",
+//!     "prefixItems": [{"type": "integer"}, {"type": "integer"}],
+//!     "minItems": 2,
+//!     "maxItems": 2,
+//! }))
+//! .unwrap();
+//! let rpc = JsonRpcRouter::new().method("add", add).validate("add", schema);
+//! ```
+
+use std::sync::Arc;
+
+pub use jsonschema;
+use serde::Serialize;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::{BoxFuture, BoxHandler};
+use crate::{to_value, JrpcResult, JsonRpcExtractor, JsonRpcResponse};
+
+/// Compiled schema of the params of a method, see the [module docs](self).
+#[derive(Clone)]
+pub struct ParamsSchema {
+    validator: Arc<jsonschema::Validator>,
+}
+
+impl std::fmt::Debug for ParamsSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParamsSchema").finish_non_exhaustive()
+    }
+}
+
+impl ParamsSchema {
+    /// Compiles `schema`, its draft is detected from `$schema` and defaults to 2020-12
+    pub fn new(schema: &serde_json::Value) -> Result<Self, jsonschema::ValidationError<'static>> {
+        Ok(Self {
+            validator: Arc::new(jsonschema::validator_for(schema)?),
+        })
+    }
+
+    /// Schema of the params parsed as a whole into `P`
+    #[cfg(feature = "openrpc")]
+    pub fn of<P: schemars::JsonSchema>() -> Self {
+        let schema = schemars::schema_for!(P);
+        Self::new(schema.as_value()).expect("schemars generates valid schemas")
+    }
+
+    /// Every violation of the schema by `params`
+    pub fn violations(&self, params: &serde_json::Value) -> Vec<Violation> {
+        self.validator
+            .iter_errors(params)
+            .map(|error| Violation {
+                instance_path: error.instance_path().to_string(),
+                schema_path: error.schema_path().to_string(),
+                message: error.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// A violation of a [`ParamsSchema`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    /// JSON pointer to the invalid part of the params
+    pub instance_path: String,
+    /// JSON pointer to the keyword of the schema which failed
+    pub schema_path: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+struct Violations {
+    errors: Vec<Violation>,
+}
+
+pub(crate) fn validate(
+    schema: ParamsSchema,
+    handler: BoxHandler,
+) -> impl Fn(JsonRpcExtractor) -> BoxFuture<JrpcResult> + Send + Sync + 'static {
+    move |request: JsonRpcExtractor| {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "serde_json")] {
+                let errors = schema.violations(&request.parsed);
+            } else {
+                let params = serde_json::to_value(&request.parsed).unwrap_or_default();
+                let errors = schema.violations(&params);
+            }
+        }
+        if errors.is_empty() {
+            return handler(request);
+        }
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::InvalidParams,
+            "Invalid params".to_owned(),
+            to_value(Violations { errors }).unwrap_or_default(),
+        );
+        let response = JsonRpcResponse::error(request.id, error);
+        Box::pin(async move { Err(response) })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::ParamsSchema;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let params: Value = req.parse_params()?;
+        Ok(JsonRpcResponse::success(
+            id,
+            params["a"].as_i64().unwrap() + 1,
+        ))
+    }
+
+    #[tokio::test]
+    async fn validates_params() {
+        let schema = ParamsSchema::new(&json!({
+            "type": "object",
+            "properties": {"a": {"type": "integer"}},
+            "required": ["a"],
+        }))
+        .unwrap();
+        let rpc = JsonRpcRouter::new()
+            .method("add", add)
+            .validate("add", schema);
+        let call = |params: Value| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": "add", "params": params});
+            let rpc = rpc.clone();
+            async move {
+                let reply = rpc.dispatch_bytes(request.to_string().into()).await;
+                serde_json::from_slice::<Value>(&reply.unwrap()).unwrap()
+            }
+        };
+
+        assert_eq!(call(json!({"a": 1})).await["result"], 2);
+        let res = call(json!({"a": "1"})).await;
+        assert_eq!(res["error"]["code"], -32602);
+        let errors = &res["error"]["data"]["errors"];
+        assert_eq!(errors[0]["instancePath"], "/a");
+        assert_eq!(errors[0]["schemaPath"], "/properties/a/type");
+        let res = call(json!({})).await;
+        assert_eq!(res["error"]["data"]["errors"][0]["instancePath"], "");
+    }
+}
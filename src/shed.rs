@@ -0,0 +1,184 @@
+//! Load shedding, so an overloaded server answers quickly instead of slowly.
+//!
+//! [`LoadShedLayer`] wraps a service and counts the calls it is handling. Calls arriving while
+//! the limit is reached are answered at once with a [`SERVER_OVERLOADED`] error whose data
+//! holds `retry_after_ms`, a hint of when to retry, rather than queueing up behind the others
+//! until every call times out. Each call of a batch is counted on its own, so a batch larger
+//! than the headroom gets some of its calls handled and the others shed.
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use axum_jrpc::shed::LoadShedLayer;
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let shed = LoadShedLayer::new(512).retry_after(Duration::from_millis(200));
+//! let rpc = shed.layer(JsonRpcRouter::new());
+//! ```
+//! Clones of a layer share its count, so a limit can span several routes.
+
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use serde::Serialize;
+use tower::{Layer, Service};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{to_value, JsonRpcExtractor, JsonRpcResponse};
+
+/// Error code of calls shed while the server is overloaded
+pub const SERVER_OVERLOADED: i32 = -32000;
+
+/// Wraps services in [`LoadShed`], see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct LoadShedLayer {
+    max_in_flight: usize,
+    retry_after: Duration,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl LoadShedLayer {
+    /// Retry hint of shed calls unless [`retry_after`](Self::retry_after) sets another one
+    pub const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+    /// Sheds the calls arriving while `max_in_flight` others are being handled
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight,
+            retry_after: Self::RETRY_AFTER,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// Tells the clients of shed calls to retry after `retry_after`
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Calls being handled
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Counts a call in, unless the limit is reached
+    fn admit(&self) -> Option<InFlight> {
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < self.max_in_flight).then_some(n + 1)
+            })
+            .ok()?;
+        Some(InFlight(self.in_flight.clone()))
+    }
+}
+
+/// Counts a call out once it completes or is cancelled
+struct InFlight(Arc<AtomicUsize>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl<S> Layer<S> for LoadShedLayer {
+    type Service = LoadShed<S>;
+
+    fn layer(&self, inner: S) -> LoadShed<S> {
+        LoadShed {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Sheds calls over the limit, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct LoadShed<S> {
+    inner: S,
+    layer: LoadShedLayer,
+}
+
+#[derive(Serialize)]
+struct RetryAfter {
+    retry_after_ms: u64,
+}
+
+impl<S> Service<JsonRpcExtractor> for LoadShed<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        if let Some(in_flight) = self.layer.admit() {
+            let future = self.inner.call(request);
+            return Box::pin(async move {
+                let response = future.await;
+                drop(in_flight);
+                response
+            });
+        }
+        let data = RetryAfter {
+            retry_after_ms: self.layer.retry_after.as_millis() as u64,
+        };
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::ServerError(SERVER_OVERLOADED),
+            "Server overloaded".to_owned(),
+            to_value(data).unwrap_or_default(),
+        );
+        let response = JsonRpcResponse::error(request.id, error);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::Duration;
+
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{LoadShedLayer, SERVER_OVERLOADED};
+    use crate::{router, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn slow(req: JsonRpcExtractor) -> JrpcResult {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "done"))
+    }
+
+    #[tokio::test]
+    async fn shedding() {
+        let shed = LoadShedLayer::new(2).retry_after(Duration::from_millis(250));
+        let rpc = shed.layer(JsonRpcRouter::new().method("slow", slow));
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+        let batch: Vec<_> = (0..3)
+            .map(|id| json!({"jsonrpc": "2.0", "id": id, "method": "slow"}))
+            .collect();
+
+        let res: Vec<Value> = server.post("/").json(&batch).await.json();
+        let done = res.iter().filter(|res| res["result"] == "done").count();
+        assert_eq!(done, 2, "{res:?}");
+        let shed = res.iter().find(|res| res["error"].is_object()).unwrap();
+        assert_eq!(shed["error"]["code"], SERVER_OVERLOADED);
+        assert_eq!(shed["error"]["data"]["retry_after_ms"], 250);
+
+        // the slots are free again
+        let res: Value = server.post("/").json(&batch[0]).await.json();
+        assert_eq!(res["result"], "done");
+    }
+}
@@ -0,0 +1,283 @@
+//! Graceful shutdown draining the calls in flight, for the `shutdown` feature.
+//!
+//! A [`Shutdown`] handle is shared by the services wrapped in [`ShutdownLayer`] and the
+//! transports. Once [`Shutdown::shutdown`] is called:
+//! * new calls, including each call of a batch, are answered with a [`SHUTTING_DOWN`] error
+//! * calls in flight keep running until they complete or the deadline passes
+//! * WebSocket clients get a [`SHUTDOWN_METHOD`](crate::ws::SHUTDOWN_METHOD) notification,
+//!   and their connections are closed once the calls are drained, see
+//!   [`JsonRpcWebSocket::serve_until`](crate::ws::JsonRpcWebSocket::serve_until)
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use axum::Router;
+//! use axum_jrpc::router::post;
+//! use axum_jrpc::shutdown::{Shutdown, ShutdownLayer};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let shutdown = Shutdown::new();
+//! let rpc = ShutdownLayer::new(shutdown.clone()).layer(JsonRpcRouter::new());
+//! let app: Router = Router::new().route("/", post(rpc));
+//!
+//! // e.g. on SIGTERM
+//! async fn stop(shutdown: Shutdown) {
+//!     if !shutdown.shutdown(Duration::from_secs(30)).await {
+//!         eprintln!("calls still running after 30 seconds");
+//!     }
+//! }
+//! ```
+//! [`Shutdown::closed`] resolves once the calls are drained, which suits
+//! `axum::serve(..).with_graceful_shutdown(..)`: connections are accepted, and answered with
+//! errors, until then.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use tower::{Layer, Service};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{to_value, JsonRpcExtractor, JsonRpcResponse};
+
+/// Error code of calls arriving once the server is shutting down
+pub const SHUTTING_DOWN: i32 = -32008;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Running,
+    Draining { deadline: Instant },
+    Closed,
+}
+
+/// Handle starting and following a graceful shutdown, see the [module docs](self).
+#[derive(Clone)]
+pub struct Shutdown {
+    phase: Arc<watch::Sender<Phase>>,
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+impl std::fmt::Debug for Shutdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shutdown")
+            .field("phase", &*self.phase.borrow())
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            phase: Arc::new(watch::Sender::new(Phase::Running)),
+            in_flight: Arc::new(watch::Sender::new(0)),
+        }
+    }
+
+    /// Stops admitting calls and waits up to `deadline` for those in flight, then closes the
+    /// connections. Returns whether all calls completed in time.
+    pub async fn shutdown(&self, deadline: Duration) -> bool {
+        let deadline = Instant::now() + deadline;
+        self.phase.send_if_modified(|phase| {
+            let running = *phase == Phase::Running;
+            if running {
+                *phase = Phase::Draining { deadline };
+            }
+            running
+        });
+        let mut in_flight = self.in_flight.subscribe();
+        let drained = tokio::time::timeout_at(deadline, in_flight.wait_for(|n| *n == 0))
+            .await
+            .is_ok();
+        self.phase.send_replace(Phase::Closed);
+        drained
+    }
+
+    /// Whether [`shutdown`](Self::shutdown) was called
+    pub fn is_shutting_down(&self) -> bool {
+        *self.phase.borrow() != Phase::Running
+    }
+
+    /// Calls being handled by the services sharing this handle
+    pub fn in_flight(&self) -> usize {
+        *self.in_flight.borrow()
+    }
+
+    /// Resolves once [`shutdown`](Self::shutdown) is called
+    pub async fn signal(&self) {
+        let mut phase = self.phase.subscribe();
+        // the sender lives as long as `self`
+        let _ = phase.wait_for(|phase| *phase != Phase::Running).await;
+    }
+
+    /// Resolves once the calls are drained, or the deadline passed
+    pub async fn closed(&self) {
+        let mut phase = self.phase.subscribe();
+        let _ = phase.wait_for(|phase| *phase == Phase::Closed).await;
+    }
+
+    /// Time left until the deadline, zero while running or once closed
+    pub(crate) fn remaining(&self) -> Duration {
+        match *self.phase.borrow() {
+            Phase::Draining { deadline } => deadline.saturating_duration_since(Instant::now()),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Counts a call in, unless shutting down
+    fn admit(&self) -> Option<InFlight> {
+        // counted first, so a shutdown starting meanwhile waits for the call
+        self.in_flight.send_modify(|n| *n += 1);
+        let in_flight = InFlight(self.in_flight.clone());
+        (!self.is_shutting_down()).then_some(in_flight)
+    }
+}
+
+/// Counts a call out once it completes or is cancelled
+struct InFlight(Arc<watch::Sender<usize>>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.send_modify(|n| *n -= 1);
+    }
+}
+
+/// Wraps services in [`GracefulShutdown`], see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct ShutdownLayer {
+    shutdown: Shutdown,
+}
+
+impl ShutdownLayer {
+    pub fn new(shutdown: Shutdown) -> Self {
+        Self { shutdown }
+    }
+}
+
+impl<S> Layer<S> for ShutdownLayer {
+    type Service = GracefulShutdown<S>;
+
+    fn layer(&self, inner: S) -> GracefulShutdown<S> {
+        GracefulShutdown {
+            inner,
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+/// Turns calls away once shutting down, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct GracefulShutdown<S> {
+    inner: S,
+    shutdown: Shutdown,
+}
+
+#[derive(Serialize)]
+struct Draining {
+    deadline_ms: u64,
+}
+
+impl<S> Service<JsonRpcExtractor> for GracefulShutdown<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        if let Some(in_flight) = self.shutdown.admit() {
+            let future = self.inner.call(request);
+            return Box::pin(async move {
+                let response = future.await;
+                drop(in_flight);
+                response
+            });
+        }
+        let data = Draining {
+            deadline_ms: self.shutdown.remaining().as_millis() as u64,
+        };
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::ServerError(SHUTTING_DOWN),
+            "Shutting down".to_owned(),
+            to_value(data).unwrap_or_default(),
+        );
+        let response = JsonRpcResponse::error(request.id, error);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use super::{Shutdown, ShutdownLayer, SHUTTING_DOWN};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter, JsonRpcService};
+
+    async fn sleep(req: JsonRpcExtractor) -> JrpcResult {
+        let millis: u64 = req.clone().parse_params()?;
+        tokio::time::sleep(Duration::from_millis(millis)).await;
+        Ok(JsonRpcResponse::success(req.get_answer_id(), millis))
+    }
+
+    #[tokio::test]
+    async fn drains() {
+        let shutdown = Shutdown::new();
+        let rpc =
+            ShutdownLayer::new(shutdown.clone()).layer(JsonRpcRouter::new().method("sleep", sleep));
+        let call = |millis: u64| {
+            let request = json!({"jsonrpc": "2.0", "id": 1, "method": "sleep", "params": millis});
+            let rpc = rpc.clone();
+            async move {
+                let reply = rpc.dispatch_bytes(request.to_string().into()).await;
+                serde_json::from_slice::<Value>(&reply.unwrap()).unwrap()
+            }
+        };
+
+        let slow = tokio::spawn(call(50));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(shutdown.in_flight(), 1);
+        let draining = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.shutdown(Duration::from_secs(1)).await }
+        });
+        shutdown.signal().await;
+        let res = call(0).await;
+        assert_eq!(res["error"]["code"], SHUTTING_DOWN);
+        // the call in flight completes before the shutdown does
+        assert_eq!(slow.await.unwrap()["result"], 50);
+        assert!(draining.await.unwrap());
+        shutdown.closed().await;
+
+        // calls outliving the deadline are left behind
+        let shutdown = Shutdown::new();
+        let rpc =
+            ShutdownLayer::new(shutdown.clone()).layer(JsonRpcRouter::new().method("sleep", sleep));
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "sleep", "params": 1000});
+        let slow =
+            tokio::spawn(async move { rpc.dispatch_bytes(request.to_string().into()).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!shutdown.shutdown(Duration::from_millis(20)).await);
+        slow.abort();
+    }
+}
@@ -0,0 +1,284 @@
+//! HMAC signatures of request bodies, for machine-to-machine APIs and the `hmac` feature.
+//!
+//! [`SignatureLayer`] wraps the axum route of an endpoint and only lets requests through if
+//! their body is signed with the secret of the client named by the `X-Client-Id` header. The
+//! `X-Signature` header holds the hex encoded HMAC-SHA256 of the raw body, optionally
+//! prefixed with `sha256=`. Requests are checked before they are parsed, so tampered ones are
+//! answered with an [`UNAUTHORIZED`] error without an id, and the others get the
+//! [`SignedBy`] extension.
+//! ```rust
+//! use std::collections::HashMap;
+//!
+//! use axum::Router;
+//! use axum_jrpc::router;
+//! use axum_jrpc::signature::SignatureLayer;
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! let secrets = HashMap::from([("settlement".to_owned(), b"s3cret".to_vec())]);
+//! let app: Router = Router::new()
+//!     .route("/", router::post(JsonRpcRouter::new()))
+//!     .layer(SignatureLayer::new(secrets));
+//! ```
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tower::{Layer, Service};
+
+use crate::auth::Unauthorized;
+#[cfg(doc)]
+use crate::auth::UNAUTHORIZED;
+use crate::router::BoxFuture;
+use crate::{Id, JsonRpcResponse};
+
+/// Client whose signature a request carried, an extension of verified requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedBy(pub String);
+
+/// Where the secrets shared with clients are looked up.
+#[async_trait::async_trait]
+pub trait SecretStore: Send + Sync + 'static {
+    /// The secret of `client`, `None` if it is unknown
+    async fn secret(&self, client: &str) -> Option<Vec<u8>>;
+}
+
+#[async_trait::async_trait]
+impl SecretStore for HashMap<String, Vec<u8>> {
+    async fn secret(&self, client: &str) -> Option<Vec<u8>> {
+        self.get(client).cloned()
+    }
+}
+
+#[async_trait::async_trait]
+impl<F> SecretStore for F
+where
+    F: Fn(&str) -> Option<Vec<u8>> + Send + Sync + 'static,
+{
+    async fn secret(&self, client: &str) -> Option<Vec<u8>> {
+        self(client)
+    }
+}
+
+/// Wraps axum services in [`Signature`], see the [module docs](self).
+pub struct SignatureLayer<K> {
+    secrets: Arc<K>,
+    body_limit: usize,
+}
+
+impl<K> Clone for SignatureLayer<K> {
+    fn clone(&self) -> Self {
+        Self {
+            secrets: self.secrets.clone(),
+            body_limit: self.body_limit,
+        }
+    }
+}
+
+impl<K> std::fmt::Debug for SignatureLayer<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignatureLayer")
+            .field("body_limit", &self.body_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K: SecretStore> SignatureLayer<K> {
+    /// Header naming the client
+    pub const CLIENT_HEADER: HeaderName = HeaderName::from_static("x-client-id");
+
+    /// Header carrying the signature
+    pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-signature");
+
+    /// Longest body read to verify it, the same as axum's default limit
+    pub const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+    pub fn new(secrets: K) -> Self {
+        Self {
+            secrets: Arc::new(secrets),
+            body_limit: Self::BODY_LIMIT,
+        }
+    }
+
+    /// Reads bodies of up to `limit` bytes, longer ones are rejected
+    pub fn body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
+
+    /// Verifies `request`, returning the client that signed it
+    async fn verify(&self, request: Request) -> Result<(Request, SignedBy), Response> {
+        let unauthorized = |message: &str| {
+            let error = Unauthorized::new(message).into();
+            JsonRpcResponse::error(Id::None(()), error).into_response()
+        };
+        let (parts, body) = request.into_parts();
+        let header = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let client = header(Self::CLIENT_HEADER)
+            .ok_or_else(|| unauthorized("Missing client id"))?
+            .to_owned();
+        let signature =
+            header(Self::SIGNATURE_HEADER).ok_or_else(|| unauthorized("Missing signature"))?;
+        let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+        let signature = hex::decode(signature).map_err(|_| unauthorized("Invalid signature"))?;
+        let secret = self
+            .secrets
+            .secret(&client)
+            .await
+            .ok_or_else(|| unauthorized("Unknown client"))?;
+
+        let body = axum::body::to_bytes(body, self.body_limit)
+            .await
+            .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&secret).expect("any key length works");
+        mac.update(&body);
+        // compares in constant time
+        mac.verify_slice(&signature)
+            .map_err(|_| unauthorized("Invalid signature"))?;
+        Ok((
+            Request::from_parts(parts, Body::from(body)),
+            SignedBy(client),
+        ))
+    }
+}
+
+impl<K, S> Layer<S> for SignatureLayer<K> {
+    type Service = Signature<K, S>;
+
+    fn layer(&self, inner: S) -> Signature<K, S> {
+        Signature {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Verifies the signature of every request, see the [module docs](self).
+pub struct Signature<K, S> {
+    inner: S,
+    layer: SignatureLayer<K>,
+}
+
+impl<K, S: Clone> Clone for Signature<K, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<K, S: std::fmt::Debug> std::fmt::Debug for Signature<K, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Signature")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<K, S> Service<Request> for Signature<K, S>
+where
+    K: SecretStore,
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // the ready service is taken, a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            match layer.verify(request).await {
+                Ok((mut request, signed_by)) => {
+                    request.extensions_mut().insert(signed_by);
+                    inner.call(request).await
+                }
+                Err(response) => Ok(response),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::collections::HashMap;
+
+    use axum::Router;
+    use axum_test::TestServer;
+    use hmac::{Hmac, Mac};
+    use serde_json::Value;
+    use sha2::Sha256;
+
+    use super::{SignatureLayer, SignedBy};
+    use crate::auth::UNAUTHORIZED;
+    use crate::router;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn signer(req: JsonRpcExtractor) -> JrpcResult {
+        let client = req.extension::<SignedBy>().map(|signed| signed.0.clone());
+        Ok(JsonRpcResponse::success(req.get_answer_id(), client))
+    }
+
+    fn sign(body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body.as_bytes());
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[tokio::test]
+    async fn signatures() {
+        let secrets = HashMap::from([("settlement".to_owned(), b"s3cret".to_vec())]);
+        let rpc = JsonRpcRouter::new().method("signer", signer);
+        let app = Router::new()
+            .route("/", router::post(rpc))
+            .layer(SignatureLayer::new(secrets));
+        let server = TestServer::new(app).unwrap();
+        let body = r#"{"jsonrpc": "2.0", "id": 1, "method": "signer"}"#;
+        let call = |client: &str, signature: &str, body: &str| {
+            server
+                .post("/")
+                .text(body.to_owned())
+                .content_type("application/json")
+                .add_header("x-client-id", client.to_owned())
+                .add_header("x-signature", signature.to_owned())
+        };
+
+        let res: Value = call("settlement", &sign(body), body).await.json();
+        assert_eq!(res["result"], "settlement");
+
+        let tampered = body.replace("signer", "signer ");
+        let res: Value = call("settlement", &sign(body), &tampered).await.json();
+        assert_eq!(res["error"]["code"], UNAUTHORIZED);
+        assert_eq!(res["error"]["message"], "Invalid signature");
+        assert_eq!(res["id"], Value::Null);
+
+        let res: Value = call("other", &sign(body), body).await.json();
+        assert_eq!(res["error"]["message"], "Unknown client");
+
+        let res: Value = server.post("/").text(body).await.json();
+        assert_eq!(res["error"]["message"], "Missing client id");
+    }
+}
@@ -0,0 +1,304 @@
+//! Signatures of response bodies, for the `hmac` and `ed25519` features.
+//!
+//! [`SigningLayer`] wraps the axum route of an endpoint and signs the serialized body of
+//! every response, so consumers reached through proxies they don't trust can tell it was
+//! not altered on the way. The signature is sent in the `X-Signature` header and is also the
+//! [`ResponseSignature`] extension of the response, for the layers around this one. It is
+//! prefixed with the algorithm: `sha256=` and the hex encoded HMAC-SHA256 of the body with
+//! [`HmacSigner`], the format [`SignatureLayer`](crate::signature::SignatureLayer) expects of
+//! requests, or `ed25519=` and the hex encoded signature with [`Ed25519Signer`].
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::router;
+//! use axum_jrpc::signing::{HmacSigner, SigningLayer};
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! let app: Router = Router::new()
+//!     .route("/", router::post(JsonRpcRouter::new()))
+//!     .layer(SigningLayer::new(HmacSigner::new(b"s3cret")));
+//! ```
+//! Responses are buffered to be signed, so batches streamed as
+//! [JSON Lines](crate::router::JSON_LINES) arrive at once.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+#[cfg(feature = "ed25519")]
+pub use ed25519_dalek;
+#[cfg(feature = "hmac")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "hmac")]
+use sha2::Sha256;
+use tower::{Layer, Service};
+
+use crate::router::BoxFuture;
+
+/// Signature of a response body, an extension of signed responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseSignature(pub String);
+
+/// Algorithm and key signing response bodies.
+pub trait ResponseSigner: Send + Sync + 'static {
+    /// Signature of `body`, prefixed with the name of the algorithm. Responses whose signature
+    /// isn't a valid header value are replaced with a `500 Internal Server Error`
+    fn sign(&self, body: &[u8]) -> String;
+}
+
+/// Signs with HMAC-SHA256 and a secret shared with the consumers.
+#[cfg(feature = "hmac")]
+#[derive(Clone)]
+pub struct HmacSigner(Hmac<Sha256>);
+
+#[cfg(feature = "hmac")]
+impl std::fmt::Debug for HmacSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacSigner").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl HmacSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self(Hmac::new_from_slice(secret).expect("any key length works"))
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl ResponseSigner for HmacSigner {
+    fn sign(&self, body: &[u8]) -> String {
+        let mut mac = self.0.clone();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+/// Signs with an Ed25519 key, consumers only need its public half to verify.
+#[cfg(feature = "ed25519")]
+#[derive(Clone)]
+pub struct Ed25519Signer(ed25519_dalek::SigningKey);
+
+#[cfg(feature = "ed25519")]
+impl std::fmt::Debug for Ed25519Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ed25519Signer")
+            .field("verifying_key", &self.0.verifying_key())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "ed25519")]
+impl Ed25519Signer {
+    pub fn new(key: ed25519_dalek::SigningKey) -> Self {
+        Self(key)
+    }
+
+    /// Public key verifying the signatures, to hand to the consumers
+    pub fn verifying_key(&self) -> ed25519_dalek::VerifyingKey {
+        self.0.verifying_key()
+    }
+}
+
+#[cfg(feature = "ed25519")]
+impl ResponseSigner for Ed25519Signer {
+    fn sign(&self, body: &[u8]) -> String {
+        use ed25519_dalek::Signer;
+
+        format!("ed25519={}", hex::encode(self.0.sign(body).to_bytes()))
+    }
+}
+
+/// Wraps axum services in [`Signing`], see the [module docs](self).
+pub struct SigningLayer<G> {
+    signer: Arc<G>,
+    header: HeaderName,
+}
+
+impl<G> Clone for SigningLayer<G> {
+    fn clone(&self) -> Self {
+        Self {
+            signer: self.signer.clone(),
+            header: self.header.clone(),
+        }
+    }
+}
+
+impl<G> std::fmt::Debug for SigningLayer<G> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningLayer")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<G: ResponseSigner> SigningLayer<G> {
+    /// Header carrying the signature unless [`header`](Self::header) sets another one
+    pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-signature");
+
+    pub fn new(signer: G) -> Self {
+        Self {
+            signer: Arc::new(signer),
+            header: Self::SIGNATURE_HEADER,
+        }
+    }
+
+    /// Sends the signature in the `header` header
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    async fn sign(&self, response: Response) -> Response {
+        let (mut parts, body) = response.into_parts();
+        let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        };
+        let signature = self.signer.sign(&body);
+        let Ok(value) = HeaderValue::try_from(&signature) else {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        };
+        parts.headers.insert(self.header.clone(), value);
+        parts.extensions.insert(ResponseSignature(signature));
+        Response::from_parts(parts, Body::from(body))
+    }
+}
+
+impl<G, S> Layer<S> for SigningLayer<G> {
+    type Service = Signing<G, S>;
+
+    fn layer(&self, inner: S) -> Signing<G, S> {
+        Signing {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Signs the body of every response, see the [module docs](self).
+pub struct Signing<G, S> {
+    inner: S,
+    layer: SigningLayer<G>,
+}
+
+impl<G, S: Clone> Clone for Signing<G, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<G, S: std::fmt::Debug> std::fmt::Debug for Signing<G, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Signing")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<G, S> Service<Request> for Signing<G, S>
+where
+    G: ResponseSigner,
+    S: Service<Request, Response = Response, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let future = self.inner.call(request);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            let response = match future.await {
+                Ok(response) => response,
+                Err(infallible) => match infallible {},
+            };
+            Ok(layer.sign(response).await)
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "serde_json", feature = "hmac"))]
+mod test {
+    use axum::http::StatusCode;
+    use axum::Router;
+    use axum_test::TestServer;
+
+    use super::{HmacSigner, ResponseSigner, SigningLayer};
+    use crate::router;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn hello(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "hello"))
+    }
+
+    #[tokio::test]
+    async fn signs_responses() {
+        let rpc = JsonRpcRouter::new().method("hello", hello);
+        let app = Router::new()
+            .route("/", router::post(rpc))
+            .layer(SigningLayer::new(HmacSigner::new(b"s3cret")));
+        let server = TestServer::new(app).unwrap();
+
+        let res = server
+            .post("/")
+            .json(&serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "hello"}))
+            .await;
+        let signature = res.header("x-signature");
+        let expected = HmacSigner::new(b"s3cret").sign(res.as_bytes());
+        assert_eq!(signature.to_str().unwrap(), expected);
+        assert!(expected.starts_with("sha256="));
+
+        #[cfg(feature = "ed25519")]
+        {
+            use ed25519_dalek::{Signature, SigningKey, Verifier};
+
+            use super::Ed25519Signer;
+
+            let signer = Ed25519Signer::new(SigningKey::from_bytes(&[7; 32]));
+            let signature = signer.sign(res.as_bytes());
+            let signature = hex::decode(signature.strip_prefix("ed25519=").unwrap()).unwrap();
+            let signature = Signature::from_slice(&signature).unwrap();
+            let verified = signer.verifying_key().verify(res.as_bytes(), &signature);
+            assert!(verified.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_signatures() {
+        struct Multiline;
+
+        impl ResponseSigner for Multiline {
+            fn sign(&self, _body: &[u8]) -> String {
+                "plain=\n".to_owned()
+            }
+        }
+
+        let rpc = JsonRpcRouter::new().method("hello", hello);
+        let app = Router::new()
+            .route("/", router::post(rpc))
+            .layer(SigningLayer::new(Multiline));
+        let server = TestServer::new(app).unwrap();
+
+        let res = server
+            .post("/")
+            .json(&serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "hello"}))
+            .expect_failure()
+            .await;
+        res.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(res.maybe_header("x-signature").is_none());
+    }
+}
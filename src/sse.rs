@@ -0,0 +1,210 @@
+//! JSON-RPC notifications over Server-Sent Events, for clients unable to use WebSockets.
+//!
+//! Calls are regular HTTP `POST`s, while subscription notifications are streamed to a `GET`
+//! on the same route:
+//! 1. `GET` opens a session, its first event is named `session` and carries the session id.
+//! 2. Calls sent with the [`SESSION_HEADER`] belong to that session, so subscriptions made by
+//!    them push their notifications to its stream.
+//! 3. Every event id has the form `<session>:<sequence>`. Reconnecting with the
+//!    `Last-Event-ID` header, as `EventSource` does automatically, resumes the session and
+//!    replays the notifications missed in between, as long as they are still buffered.
+//!
+//! Responses travel over HTTP and notifications over the stream, so the first notification
+//! of a subscription may arrive before the subscription id.
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::sse::SseTransport;
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! let rpc = JsonRpcRouter::new();
+//! let app: Router = Router::new().route("/rpc", SseTransport::new().serve(rpc));
+//! ```
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{self, MethodRouter};
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::pubsub::{BufferedSession, SessionStore};
+use crate::router::JsonRpcService;
+use crate::JsonRpcExtractor;
+
+pub use crate::pubsub::SESSION_HEADER;
+
+const LAST_EVENT_ID: &str = "last-event-id";
+
+/// Serves calls on `POST` and notifications on `GET`, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct SseTransport {
+    capacity: usize,
+    idle_timeout: Duration,
+}
+
+impl Default for SseTransport {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            idle_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl SseTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of notifications kept per session for resuming, 1024 by default
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// How long a session survives without a connected stream, 60 seconds by default. A zero
+    /// timeout closes it as soon as it has no reader
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Builds the route, for use with [`axum::Router::route`]
+    pub fn serve<S: JsonRpcService>(self, service: S) -> MethodRouter {
+        let store = SessionStore::new(self.capacity, self.idle_timeout);
+        let events = {
+            let store = store.clone();
+            move |headers: HeaderMap| async move { events(&store, &headers) }
+        };
+        let call = move |headers: HeaderMap, request: JsonRpcExtractor| async move {
+            store.dispatch(&service, &headers, request).await
+        };
+        routing::get(events).post(call)
+    }
+}
+
+fn events(store: &SessionStore, headers: &HeaderMap) -> Response {
+    let last_event_id = headers.get(LAST_EVENT_ID).and_then(|v| v.to_str().ok());
+    let (buffered, after) = match last_event_id.and_then(|id| id.split_once(':')) {
+        Some((session, seq)) => match store.get(session) {
+            Some(buffered) => (buffered, seq.parse().ok()),
+            None => return StatusCode::NOT_FOUND.into_response(),
+        },
+        None => (store.create(), None),
+    };
+
+    Sse::new(stream(buffered, after))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn stream(
+    buffered: std::sync::Arc<BufferedSession>,
+    after: Option<u64>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let session = buffered.id().to_owned();
+    let hello = Event::default()
+        .event("session")
+        .id(format!("{session}:{}", after.unwrap_or_default()))
+        .data(&session);
+
+    stream::once(async move { hello })
+        .chain(
+            buffered.replay(after).map(move |(seq, data)| {
+                Event::default().id(format!("{session}:{seq}")).data(&*data)
+            }),
+        )
+        .map(Ok)
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "client", feature = "serde_json"))]
+mod test {
+    use axum::Router;
+    use serde_json::{json, Value};
+
+    use super::{SseTransport, SESSION_HEADER};
+    use crate::pubsub::PendingSubscription;
+    use crate::{JsonRpcExtractor, JsonRpcRouter};
+
+    async fn numbers(_: JsonRpcExtractor, pending: PendingSubscription) {
+        let sink = pending.accept();
+        for number in 0..3 {
+            sink.send(number).await.unwrap();
+        }
+        sink.closed().await;
+    }
+
+    /// Reads events until `count` were received, returns their ids and data
+    async fn read(response: &mut reqwest::Response, count: usize) -> Vec<(String, String)> {
+        let mut buffer = String::new();
+        let mut events = Vec::new();
+        while events.len() < count {
+            let chunk = response.chunk().await.unwrap().unwrap();
+            buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+            while let Some((event, rest)) = buffer.split_once("\n\n") {
+                let field = |name: &str| {
+                    event
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .map(|v| v.trim().to_owned())
+                };
+                if let (Some(id), Some(data)) = (field("id:"), field("data:")) {
+                    events.push((id, data));
+                }
+                buffer = rest.to_owned();
+            }
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn notifications_and_resume() {
+        let rpc = JsonRpcRouter::new().subscription("subscribe", "number", "unsubscribe", numbers);
+        let app = Router::new().route("/rpc", SseTransport::new().serve(rpc));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/rpc", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        let mut events = client.get(&url).send().await.unwrap();
+        let session = read(&mut events, 1).await.remove(0).1;
+
+        let response: Value = client
+            .post(&url)
+            .header(SESSION_HEADER, &session)
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "subscribe", "params": []}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let subscription = response["result"].clone();
+
+        let received = read(&mut events, 2).await;
+        assert_eq!(received[0].0, format!("{session}:1"));
+        let notification: Value = serde_json::from_str(&received[1].1).unwrap();
+        assert_eq!(notification["method"], "number");
+        assert_eq!(
+            notification["params"],
+            json!({"subscription": subscription, "result": 1})
+        );
+        drop(events);
+
+        // the third notification is replayed after reconnecting
+        let mut events = client
+            .get(&url)
+            .header("last-event-id", format!("{session}:2"))
+            .send()
+            .await
+            .unwrap();
+        let received = read(&mut events, 2).await;
+        assert_eq!(received[0].1, session);
+        assert_eq!(received[1].0, format!("{session}:3"));
+        let notification: Value = serde_json::from_str(&received[1].1).unwrap();
+        assert_eq!(notification["params"]["result"], 2);
+    }
+}
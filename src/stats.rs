@@ -0,0 +1,308 @@
+//! Live per-method statistics, served by an `rpc.stats` method for quick diagnostics without
+//! a metrics stack.
+//!
+//! [`Stats::layer`] wraps a service and gathers call counts, errors, calls in flight and
+//! latencies of every method. [`Stats::handler`] answers with a snapshot of them. Expose it
+//! to administrators only, e.g. on a separate route, or behind an authorization layer.
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::router;
+//! use axum_jrpc::stats::{Stats, STATS_METHOD};
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let stats = Stats::new();
+//! let rpc = stats.layer().layer(JsonRpcRouter::new());
+//! let admin = JsonRpcRouter::new().method(STATS_METHOD, stats.handler());
+//! let app: Router = Router::new()
+//!     .route("/", router::post(rpc))
+//!     .route("/admin", router::post(admin));
+//! ```
+//! A snapshot looks like
+//! ```json
+//! {"methods": {"add": {"calls": 12, "errors": 1, "error_rate": 0.083, "in_flight": 0,
+//!     "latency_ms": {"p50": 0.256, "p90": 1.024, "p99": 1.024}}}}
+//! ```
+//! Latencies are tracked in power of two buckets of microseconds, percentiles are the upper
+//! bound of their bucket. Methods not registered with any router are counted as `unknown`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use serde::Serialize;
+use tower::{Layer, Service};
+use web_time::Instant;
+
+use crate::intern;
+use crate::router::BoxFuture;
+use crate::{JrpcResult, JsonRpcAnswer, JsonRpcExtractor, JsonRpcResponse};
+
+/// Conventional name of the method serving [`Stats::handler`]
+pub const STATS_METHOD: &str = "rpc.stats";
+
+/// Latencies up to 2^(BUCKETS - 1) microseconds, about 35 minutes, are told apart
+const BUCKETS: usize = 32;
+
+#[derive(Debug)]
+struct MethodStats {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    in_flight: AtomicU64,
+    latency: [AtomicU64; BUCKETS],
+}
+
+impl MethodStats {
+    fn new() -> Self {
+        Self {
+            calls: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            in_flight: AtomicU64::new(0),
+            latency: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    fn record_latency(&self, micros: u64) {
+        let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+        self.latency[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> MethodSnapshot {
+        let counts: Vec<u64> = self
+            .latency
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        let calls = self.calls.load(Ordering::Relaxed);
+        let errors = self.errors.load(Ordering::Relaxed);
+        let latency_ms = match counts.iter().any(|count| *count > 0) {
+            true => Some(Percentiles {
+                p50: percentile(&counts, 0.5),
+                p90: percentile(&counts, 0.9),
+                p99: percentile(&counts, 0.99),
+            }),
+            false => None,
+        };
+        MethodSnapshot {
+            calls,
+            errors,
+            error_rate: match calls {
+                0 => 0.0,
+                calls => errors as f64 / calls as f64,
+            },
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            latency_ms,
+        }
+    }
+}
+
+/// Upper bound of the bucket holding the `p` quantile, in milliseconds
+fn percentile(counts: &[u64], p: f64) -> f64 {
+    let total: u64 = counts.iter().sum();
+    let rank = (total as f64 * p).ceil() as u64;
+    let mut seen = 0;
+    for (bucket, count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank.max(1) {
+            return (1u64 << bucket) as f64 / 1000.0;
+        }
+    }
+    0.0
+}
+
+#[derive(Debug, Serialize)]
+struct Percentiles {
+    p50: f64,
+    p90: f64,
+    p99: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct MethodSnapshot {
+    calls: u64,
+    errors: u64,
+    error_rate: f64,
+    in_flight: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<Percentiles>,
+}
+
+/// Statistics shared by a [`StatsLayer`] and the handler serving them.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    methods: Arc<Mutex<HashMap<Arc<str>, Arc<MethodStats>>>>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gathers statistics of the calls to the wrapped service
+    pub fn layer(&self) -> StatsLayer {
+        StatsLayer {
+            stats: self.clone(),
+        }
+    }
+
+    /// A handler answering with a snapshot of the statistics, for
+    /// [`JsonRpcRouter::method`](crate::JsonRpcRouter::method)
+    pub fn handler(
+        &self,
+    ) -> impl Fn(JsonRpcExtractor) -> BoxFuture<JrpcResult> + Clone + Send + Sync + 'static {
+        let stats = self.clone();
+        move |req: JsonRpcExtractor| {
+            let snapshot = stats.snapshot();
+            Box::pin(async move {
+                #[derive(Serialize)]
+                struct Snapshot {
+                    methods: BTreeMap<String, MethodSnapshot>,
+                }
+
+                Ok(JsonRpcResponse::success(
+                    req.get_answer_id(),
+                    Snapshot { methods: snapshot },
+                ))
+            })
+        }
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, MethodSnapshot> {
+        let methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        methods
+            .iter()
+            .map(|(method, stats)| (method.to_string(), stats.snapshot()))
+            .collect()
+    }
+
+    fn method(&self, method: &str) -> Arc<MethodStats> {
+        let method = intern::lookup(method).unwrap_or_else(|| Arc::from("unknown"));
+        let mut methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        methods
+            .entry(method)
+            .or_insert_with(|| Arc::new(MethodStats::new()))
+            .clone()
+    }
+}
+
+/// Wraps services in [`StatsService`], see [`Stats::layer`].
+#[derive(Debug, Clone)]
+pub struct StatsLayer {
+    stats: Stats,
+}
+
+impl<S> Layer<S> for StatsLayer {
+    type Service = StatsService<S>;
+
+    fn layer(&self, inner: S) -> StatsService<S> {
+        StatsService {
+            inner,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// Gathers statistics of every call, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct StatsService<S> {
+    inner: S,
+    stats: Stats,
+}
+
+/// Counts a call as in flight until dropped, also when it is cancelled
+struct InFlight(Arc<MethodStats>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S> Service<JsonRpcExtractor> for StatsService<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let stats = self.stats.method(request.method());
+        stats.calls.fetch_add(1, Ordering::Relaxed);
+        stats.in_flight.fetch_add(1, Ordering::Relaxed);
+        let in_flight = InFlight(stats);
+        let started = Instant::now();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let Ok(response) = response.await;
+            let stats = &in_flight.0;
+            stats.record_latency(started.elapsed().as_micros() as u64);
+            if let JsonRpcAnswer::Error(_) = response.result {
+                stats.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::json;
+    use tower::Layer;
+
+    use super::{Stats, STATS_METHOD};
+    use crate::router::JsonRpcService;
+    use crate::{
+        JrpcResult, JsonRpcAnswer, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter,
+    };
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i32; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    #[tokio::test]
+    async fn stats() {
+        let stats = Stats::new();
+        let rpc = JsonRpcRouter::new()
+            .method("stats_add", add)
+            .method(STATS_METHOD, stats.handler());
+        let rpc = stats.layer().layer(rpc);
+        let call = |method: &str, params| {
+            let request = JsonRpcRequest {
+                id: 1.into(),
+                method: method.to_owned(),
+                params,
+            };
+            rpc.dispatch(request.into())
+        };
+
+        call("stats_add", json!([1, 2])).await;
+        call("stats_add", json!([1])).await;
+        call("stats_missing", json!([])).await;
+        let snapshot = match call(STATS_METHOD, json!([])).await.result {
+            JsonRpcAnswer::Result(value) => value,
+            JsonRpcAnswer::Error(error) => panic!("{error}"),
+        };
+
+        let add = &snapshot["methods"]["stats_add"];
+        assert_eq!(add["calls"], 2);
+        assert_eq!(add["errors"], 1);
+        assert_eq!(add["error_rate"], 0.5);
+        assert_eq!(add["in_flight"], 0);
+        assert!(add["latency_ms"]["p99"].as_f64().unwrap() > 0.0);
+        assert_eq!(snapshot["methods"]["unknown"]["errors"], 1);
+        // the call being answered is in flight
+        assert_eq!(snapshot["methods"][STATS_METHOD]["in_flight"], 1);
+    }
+}
@@ -0,0 +1,484 @@
+//! JSON-RPC over any byte stream: pipes, sockets or the standard streams of a process.
+//!
+//! [`serve`] reads requests from a stream split into messages by a [`tokio_util::codec`]
+//! codec and writes responses back as soon as they are ready. Subscriptions are supported
+//! and live until the stream ends. [`LineCodec`] frames one message per line,
+//! [`ContentLengthCodec`] uses the `Content-Length` headers of the Language Server Protocol.
+//!
+//! With the `stdio` feature [`serve_stdio`] answers requests on stdin and stdout, which is
+//! enough to power editor plugins and other tools spawned as child processes:
+//! ```rust,no_run
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let rpc = JsonRpcRouter::new();
+//! axum_jrpc::stream::serve_stdio(rpc).await
+//! # }
+//! ```
+//! With the `tcp` feature [`serve_tcp`] accepts newline-delimited JSON-RPC connections on a
+//! TCP listener.
+//!
+//! Language servers talk the same way, but with LSP framing:
+//! ```rust,no_run
+//! use axum_jrpc::stream::{self, ContentLengthCodec};
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let rpc = JsonRpcRouter::new();
+//! let stdio = tokio::io::join(tokio::io::stdin(), tokio::io::stdout());
+//! stream::serve(rpc, stdio, ContentLengthCodec::new()).await
+//! # }
+//! ```
+
+use std::io;
+use std::sync::Arc;
+
+use bytes::{BufMut, BytesMut};
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use http::Extensions;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+use crate::batch::MAX_IN_FLIGHT;
+use crate::pubsub::Session;
+use crate::router::JsonRpcService;
+use crate::{from_slice, to_vec};
+
+/// Splits a byte stream into messages and frames outgoing ones.
+pub trait JsonRpcCodec:
+    Decoder<Item = BytesMut, Error = io::Error> + Encoder<Vec<u8>, Error = io::Error>
+{
+}
+
+impl<C> JsonRpcCodec for C where
+    C: Decoder<Item = BytesMut, Error = io::Error> + Encoder<Vec<u8>, Error = io::Error>
+{
+}
+
+/// One message per line, empty lines are skipped.
+#[derive(Debug, Clone)]
+pub struct LineCodec {
+    max_length: usize,
+    /// Bytes already searched for a newline
+    scanned: usize,
+}
+
+impl Default for LineCodec {
+    fn default() -> Self {
+        Self {
+            max_length: 16 * 1024 * 1024,
+            scanned: 0,
+        }
+    }
+}
+
+impl LineCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Longest accepted line, 16 MiB by default
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+}
+
+impl Decoder for LineCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, io::Error> {
+        loop {
+            let Some(end) = src[self.scanned..].iter().position(|b| *b == b'\n') else {
+                self.scanned = src.len();
+                if src.len() > self.max_length {
+                    return Err(invalid_data("Line too long"));
+                }
+                return Ok(None);
+            };
+
+            let mut line = src.split_to(self.scanned + end + 1);
+            self.scanned = 0;
+            line.truncate(line.len() - 1);
+            if line.last() == Some(&b'\r') {
+                line.truncate(line.len() - 1);
+            }
+            if line.len() > self.max_length {
+                return Err(invalid_data("Line too long"));
+            }
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                return Ok(Some(line));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, io::Error> {
+        match self.decode(src)? {
+            Some(line) => Ok(Some(line)),
+            None if src.iter().all(u8::is_ascii_whitespace) => {
+                src.clear();
+                Ok(None)
+            }
+            None => {
+                self.scanned = 0;
+                Ok(Some(src.split()))
+            }
+        }
+    }
+}
+
+impl Encoder<Vec<u8>> for LineCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: Vec<u8>, dst: &mut BytesMut) -> Result<(), io::Error> {
+        dst.reserve(message.len() + 1);
+        dst.put_slice(&message);
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
+
+/// Frames of the [LSP base protocol]: a `Content-Length` header, optionally other headers,
+/// an empty line and the message.
+///
+/// [LSP base protocol]: https://microsoft.github.io/language-server-protocol/specifications/base/0.9/specification/
+#[derive(Debug, Clone)]
+pub struct ContentLengthCodec {
+    max_length: usize,
+    /// Length of the message whose headers were already consumed
+    content_length: Option<usize>,
+}
+
+impl Default for ContentLengthCodec {
+    fn default() -> Self {
+        Self {
+            max_length: 16 * 1024 * 1024,
+            content_length: None,
+        }
+    }
+}
+
+impl ContentLengthCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Largest accepted message, 16 MiB by default
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    fn decode_headers(&self, src: &mut BytesMut) -> Result<Option<usize>, io::Error> {
+        let Some(end) = src.windows(4).position(|w| w == b"\r\n\r\n") else {
+            // headers are short, anything longer is garbage
+            if src.len() > 8 * 1024 {
+                return Err(invalid_data("Header section too long"));
+            }
+            return Ok(None);
+        };
+
+        let headers = src.split_to(end + 4);
+        let headers = std::str::from_utf8(&headers[..end])
+            .map_err(|_| invalid_data("Header section is not valid utf-8"))?;
+        let length = headers
+            .split("\r\n")
+            .filter_map(|header| header.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .ok_or_else(|| invalid_data("Missing Content-Length header"))?
+            .1
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| invalid_data("Invalid Content-Length header"))?;
+        if length > self.max_length {
+            return Err(invalid_data("Message too long"));
+        }
+        Ok(Some(length))
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Decoder for ContentLengthCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, io::Error> {
+        let length = match self.content_length {
+            Some(length) => length,
+            None => match self.decode_headers(src)? {
+                Some(length) => length,
+                None => return Ok(None),
+            },
+        };
+
+        if src.len() < length {
+            self.content_length = Some(length);
+            src.reserve(length - src.len());
+            return Ok(None);
+        }
+        self.content_length = None;
+        Ok(Some(src.split_to(length)))
+    }
+}
+
+impl Encoder<Vec<u8>> for ContentLengthCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: Vec<u8>, dst: &mut BytesMut) -> Result<(), io::Error> {
+        let header = format!("Content-Length: {}\r\n\r\n", message.len());
+        dst.reserve(header.len() + message.len());
+        dst.put_slice(header.as_bytes());
+        dst.put_slice(&message);
+        Ok(())
+    }
+}
+
+/// Answers requests read from `io` with `service` until the stream ends.
+///
+/// Requests and batches are handled concurrently, at most 64 of them at a time: the stream is not
+/// read further until one of them completes. Once the stream ends, the responses of requests still
+/// in progress are written before returning.
+pub async fn serve<S, T, C>(service: S, io: T, codec: C) -> io::Result<()>
+where
+    S: JsonRpcService,
+    T: AsyncRead + AsyncWrite,
+    C: JsonRpcCodec,
+{
+    let (session, mut outgoing) = Session::new();
+    let (mut sink, mut frames) = Framed::new(io, codec).split();
+    let mut tasks = JoinSet::new();
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+
+    let read = async {
+        loop {
+            let Ok(permit) = in_flight.clone().acquire_owned().await else {
+                break;
+            };
+            let Some(frame) = frames.next().await else {
+                break;
+            };
+            let mut frame = frame?;
+            let service = service.clone();
+            let session = session.clone();
+            tasks.spawn(async move {
+                let value = from_slice(&mut frame);
+                session.dispatch(&service, value, Extensions::new()).await;
+                drop(permit);
+            });
+            // reap the finished ones, the set would otherwise grow with every frame
+            while let Some(Some(_)) = tasks.join_next().now_or_never() {}
+        }
+        while tasks.join_next().await.is_some() {}
+        Ok::<_, io::Error>(())
+    };
+    let write = async {
+        while let Some(message) = outgoing.recv().await {
+            if let Ok(message) = to_vec(&message) {
+                sink.send(message).await?;
+            }
+        }
+        Ok::<_, io::Error>(())
+    };
+
+    // the writer only stops on errors, the session keeps the queue open
+    tokio::select! {
+        res = read => res?,
+        res = write => return res,
+    }
+
+    session.close();
+    outgoing.close();
+    while let Some(message) = outgoing.recv().await {
+        if let Ok(message) = to_vec(&message) {
+            sink.feed(message).await?;
+        }
+    }
+    sink.close().await
+}
+
+/// Answers newline-delimited requests on stdin, writing responses to stdout
+#[cfg(feature = "stdio")]
+pub async fn serve_stdio<S: JsonRpcService>(service: S) -> io::Result<()> {
+    let stdio = tokio::io::join(tokio::io::stdin(), tokio::io::stdout());
+    serve(service, stdio, LineCodec::new()).await
+}
+
+/// Answers newline-delimited requests on every connection accepted by `listener`.
+///
+/// Runs until dropped, failed connections are closed without affecting the others
+#[cfg(feature = "tcp")]
+pub async fn serve_tcp<S: JsonRpcService>(listener: tokio::net::TcpListener, service: S) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_e, "failed to accept connection");
+                // usually out of file descriptors, give the other connections time to close
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let _ = stream.set_nodelay(true);
+        let service = service.clone();
+        tokio::spawn(async move {
+            if let Err(_e) = serve(service, stream, LineCodec::new()).await {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %_e, "connection failed");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::{serve, ContentLengthCodec, LineCodec};
+    use crate::batch::MAX_IN_FLIGHT;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i32; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    #[tokio::test]
+    async fn lines() {
+        let (client, server) = tokio::io::duplex(1024);
+        let rpc = JsonRpcRouter::new().method("add", add);
+        let server = tokio::spawn(serve(rpc, server, LineCodec::new()));
+
+        let (reader, mut writer) = tokio::io::split(client);
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}"#,
+            "\r\n\n",
+            r#"{"jsonrpc":"2.0","method":"add","params":[1,2]}"#,
+            "\n",
+            "garbage\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"add","params":[3,4]}"#,
+        );
+        writer.write_all(input.as_bytes()).await.unwrap();
+        writer.shutdown().await.unwrap();
+
+        let mut lines = BufReader::new(reader).lines();
+        let mut responses = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            responses.push(serde_json::from_str::<Value>(&line).unwrap());
+        }
+        server.await.unwrap().unwrap();
+
+        responses.sort_by_key(|response| response["id"].as_i64());
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["error"]["code"], -32700);
+        assert_eq!(responses[1]["result"], json!(3));
+        assert_eq!(responses[2]["result"], json!(7));
+    }
+
+    #[tokio::test]
+    async fn bounds_in_flight() {
+        static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+        static MAX: AtomicUsize = AtomicUsize::new(0);
+
+        async fn count(req: JsonRpcExtractor) -> JrpcResult {
+            let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+            Ok(JsonRpcResponse::success(req.get_answer_id(), ()))
+        }
+
+        let (client, server) = tokio::io::duplex(1024);
+        let rpc = JsonRpcRouter::new().method("count", count);
+        let server = tokio::spawn(serve(rpc, server, LineCodec::new()));
+
+        let (reader, mut writer) = tokio::io::split(client);
+        tokio::spawn(async move {
+            for id in 0..MAX_IN_FLIGHT * 2 {
+                let request = json!({"jsonrpc": "2.0", "id": id, "method": "count"});
+                writer
+                    .write_all(format!("{request}\n").as_bytes())
+                    .await
+                    .unwrap();
+            }
+            writer.shutdown().await.unwrap();
+        });
+
+        let mut lines = BufReader::new(reader).lines();
+        let mut responses = 0;
+        while lines.next_line().await.unwrap().is_some() {
+            responses += 1;
+        }
+        server.await.unwrap().unwrap();
+
+        assert_eq!(responses, MAX_IN_FLIGHT * 2);
+        assert!(MAX.load(Ordering::SeqCst) <= MAX_IN_FLIGHT);
+    }
+
+    #[test]
+    fn content_length() {
+        let mut codec = ContentLengthCodec::new();
+        let mut buffer = BytesMut::new();
+        codec.encode(b"{}".to_vec(), &mut buffer).unwrap();
+        assert_eq!(&buffer[..], b"Content-Length: 2\r\n\r\n{}");
+
+        let message = r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}"#;
+        let framed = format!(
+            "content-length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{message}",
+            message.len()
+        );
+        let mut buffer = BytesMut::new();
+        // split in the middle of the header and of the body
+        for chunk in [&framed[..10], &framed[10..90], &framed[90..]] {
+            assert!(buffer.is_empty() || codec.decode(&mut buffer).unwrap().is_none());
+            buffer.extend_from_slice(chunk.as_bytes());
+        }
+        assert_eq!(
+            &codec.decode(&mut buffer).unwrap().unwrap()[..],
+            message.as_bytes()
+        );
+        assert!(buffer.is_empty());
+
+        let mut buffer = BytesMut::from("Content-Type: json\r\n\r\n{}");
+        assert!(codec.decode(&mut buffer).is_err());
+    }
+
+    #[cfg(feature = "tcp")]
+    #[tokio::test]
+    async fn tcp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let rpc = JsonRpcRouter::new().method("add", add);
+        tokio::spawn(super::serve_tcp(listener, rpc));
+
+        for _ in 0..2 {
+            let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let request = r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[1,2]}"#;
+            writer
+                .write_all(format!("{request}\n").as_bytes())
+                .await
+                .unwrap();
+
+            let line = BufReader::new(reader).lines().next_line().await.unwrap();
+            let response: Value = serde_json::from_str(&line.unwrap()).unwrap();
+            assert_eq!(response["result"], json!(3));
+        }
+    }
+}
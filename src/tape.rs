@@ -0,0 +1,191 @@
+//! Requests deserialized straight from simd-json's tape, for the `simd` feature.
+//!
+//! With `simd`, [`JsonRpcExtractor`](crate::JsonRpcExtractor) builds an owned value tree
+//! of the params, allocating every string and object on the way, before
+//! [`parse_params`](crate::JsonRpcExtractor::parse_params) converts it into the target type.
+//! [`JsonRpcTapeExtractor`] keeps the message instead and deserializes the params from the
+//! tape simd-json parses it into, so only the target type allocates.
+//! ```rust
+//! use axum_jrpc::tape::JsonRpcTapeExtractor;
+//! use axum_jrpc::{JrpcResult, JsonRpcResponse};
+//!
+//! async fn len(req: JsonRpcTapeExtractor) -> JrpcResult {
+//!     let [text]: [String; 1] = req.parse_params()?;
+//!     Ok(JsonRpcResponse::success(req.get_answer_id(), text.len()))
+//! }
+//! ```
+
+use std::borrow::Cow;
+
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Request};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Deserialize;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{json_content_type, Id, JsonRpcResponse, Value, JSONRPC};
+
+/// A JSON-RPC request whose params are parsed on demand, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct JsonRpcTapeExtractor {
+    pub id: Id,
+    pub method: String,
+    /// The whole message as received
+    pub message: Bytes,
+    /// Extensions of the underlying HTTP request
+    pub extensions: http::Extensions,
+}
+
+impl JsonRpcTapeExtractor {
+    /// Parses everything but the params of `message`
+    pub fn parse(message: Bytes) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        struct Helper<'a> {
+            #[serde(borrow)]
+            jsonrpc: Cow<'a, str>,
+            // notifications have no id
+            #[serde(default)]
+            id: Id,
+            method: String,
+            #[serde(default, rename = "params")]
+            _params: IgnoredAny,
+        }
+
+        // simd-json uses the input as scratch space
+        let mut scratch = message.to_vec();
+        let helper: Helper = simd_json::from_slice(&mut scratch).map_err(|e| e.to_string())?;
+        if helper.jsonrpc != JSONRPC {
+            return Err("Unknown jsonrpc version".to_owned());
+        }
+        Ok(Self {
+            id: helper.id,
+            method: helper.method,
+            message,
+            extensions: Default::default(),
+        })
+    }
+
+    pub fn get_answer_id(&self) -> Id {
+        self.id.clone()
+    }
+
+    /// Borrows the id, for looking at it without cloning
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Deserializes the params from a fresh parse of the message. Omitted params are
+    /// deserialized from `null`
+    pub fn parse_params<T: DeserializeOwned>(&self) -> Result<T, JsonRpcResponse> {
+        #[derive(Deserialize)]
+        struct Helper<T> {
+            #[serde(default = "Option::default")]
+            params: Option<T>,
+        }
+
+        let mut scratch = self.message.to_vec();
+        simd_json::from_slice::<Helper<T>>(&mut scratch)
+            .map_err(|e| e.to_string())
+            .and_then(|helper| match helper.params {
+                Some(params) => Ok(params),
+                None => simd_json::from_slice(&mut b"null".to_vec()).map_err(|e| e.to_string()),
+            })
+            .map_err(|e| {
+                let error =
+                    JsonRpcError::new(JsonRpcErrorReason::InvalidParams, e, Value::default());
+                JsonRpcResponse::error(self.id.clone(), error)
+            })
+    }
+
+    /// Returns a value inserted by middleware
+    pub fn extension<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
+    pub fn method_not_found(&self, method: &str) -> JsonRpcResponse {
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::MethodNotFound,
+            format!("Method `{}` not found", method),
+            Value::default(),
+        );
+        JsonRpcResponse::error(self.id.clone(), error)
+    }
+}
+
+#[async_trait::async_trait]
+impl<S> FromRequest<S> for JsonRpcTapeExtractor
+where
+    Bytes: FromRequest<S>,
+    S: Send + Sync,
+{
+    type Rejection = JsonRpcResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let invalid = |message: String| {
+            let error = JsonRpcError::new(
+                JsonRpcErrorReason::InvalidRequest,
+                message,
+                Value::default(),
+            );
+            JsonRpcResponse::error(Id::None(()), error)
+        };
+
+        if !json_content_type(req.headers()) {
+            return Err(invalid("Invalid content type".to_owned()));
+        }
+        let extensions = req.extensions().clone();
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|_| invalid("Invalid request".to_owned()))?;
+
+        let mut request = Self::parse(bytes).map_err(invalid)?;
+        request.extensions = extensions;
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::body::Bytes;
+    use serde::Deserialize;
+
+    use super::JsonRpcTapeExtractor;
+    use crate::Id;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Transfer {
+        to: String,
+        amount: u64,
+    }
+
+    #[test]
+    fn tape_params() {
+        let message = br#"{"jsonrpc": "2.0", "id": 1, "method": "transfer", "params": {"to": "b\"ob", "amount": 5}}"#;
+        let request = JsonRpcTapeExtractor::parse(Bytes::from_static(message)).unwrap();
+        assert_eq!(request.method(), "transfer");
+        assert_eq!(request.id(), &Id::Num(1));
+        let transfer: Transfer = request.parse_params().unwrap();
+        assert_eq!(
+            transfer,
+            Transfer {
+                to: "b\"ob".to_owned(),
+                amount: 5
+            }
+        );
+        // parsing again works, the message is left untouched
+        assert!(request.parse_params::<Transfer>().is_ok());
+        assert!(request.parse_params::<[u64; 2]>().is_err());
+
+        let message = br#"{"jsonrpc": "2.0", "method": "ping"}"#;
+        let request = JsonRpcTapeExtractor::parse(Bytes::from_static(message)).unwrap();
+        assert!(request.id().is_none());
+        request.parse_params::<()>().unwrap();
+
+        let message = br#"{"jsonrpc": "1.0", "id": 1, "method": "ping"}"#;
+        assert!(JsonRpcTapeExtractor::parse(Bytes::from_static(message)).is_err());
+    }
+}
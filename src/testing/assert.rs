@@ -0,0 +1,187 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{JsonRpcAnswer, JsonRpcResponse};
+
+/// Asserts that a [`JsonRpcResponse`] succeeded with a result equal, as JSON, to the expected
+/// value. On failure the message lists the paths that differ.
+/// ```rust
+/// use axum_jrpc::{assert_rpc_success, JsonRpcResponse};
+/// use serde_json::json;
+///
+/// let response = JsonRpcResponse::success(1, json!({"sum": 3, "terms": [1, 2]}));
+/// assert_rpc_success!(response, json!({"sum": 3, "terms": [1, 2]}));
+/// ```
+#[macro_export]
+macro_rules! assert_rpc_success {
+    ($response:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_success(&$response, &$expected)
+    };
+}
+
+/// Asserts that a [`JsonRpcResponse`] failed with the expected code, given as an `i32` or a
+/// [`JsonRpcErrorReason`](crate::error::JsonRpcErrorReason), and optionally a message
+/// containing the given text.
+/// ```rust
+/// use axum_jrpc::error::{JsonRpcError, JsonRpcErrorReason};
+/// use axum_jrpc::{assert_rpc_error, JsonRpcResponse, Value};
+///
+/// let error = JsonRpcError::new(JsonRpcErrorReason::InvalidParams, "missing b".to_owned(), Value::Null);
+/// let response = JsonRpcResponse::error(1, error);
+/// assert_rpc_error!(response, -32602);
+/// assert_rpc_error!(response, JsonRpcErrorReason::InvalidParams, "missing");
+/// ```
+#[macro_export]
+macro_rules! assert_rpc_error {
+    ($response:expr, $code:expr $(,)?) => {
+        $crate::testing::assert_error(&$response, $code, ::core::option::Option::None)
+    };
+    ($response:expr, $code:expr, $message:expr $(,)?) => {
+        $crate::testing::assert_error(&$response, $code, ::core::option::Option::Some($message))
+    };
+}
+
+/// See [`assert_rpc_success!`]
+#[track_caller]
+pub fn assert_success<T: Serialize + ?Sized>(response: &JsonRpcResponse, expected: &T) {
+    let expected = serde_json::to_value(expected).expect("failed to encode the expected result");
+    let result = match &response.result {
+        JsonRpcAnswer::Result(result) => serde_json::to_value(result).unwrap_or_default(),
+        JsonRpcAnswer::Error(error) => panic!(
+            "expected a result, got error {} {:?}\nresponse: {}",
+            error.code(),
+            error.message(),
+            pretty(response)
+        ),
+    };
+    let mut diff = String::new();
+    compare("result", &expected, &result, &mut diff);
+    if !diff.is_empty() {
+        panic!(
+            "result differs from the expected one:\n{diff}expected: {}\nresponse: {}",
+            pretty(&expected),
+            pretty(response)
+        );
+    }
+}
+
+/// See [`assert_rpc_error!`]
+#[track_caller]
+pub fn assert_error(response: &JsonRpcResponse, code: impl Into<i32>, message: Option<&str>) {
+    let code = code.into();
+    let error = match &response.result {
+        JsonRpcAnswer::Error(error) => error,
+        JsonRpcAnswer::Result(_) => {
+            panic!(
+                "expected error {code}, got a result\nresponse: {}",
+                pretty(response)
+            )
+        }
+    };
+    if error.code() != code {
+        panic!(
+            "expected error {code}, got {} {:?}\nresponse: {}",
+            error.code(),
+            error.message(),
+            pretty(response)
+        );
+    }
+    if let Some(message) = message {
+        if !error.message().contains(message) {
+            panic!(
+                "expected an error message containing {message:?}, got {:?}\nresponse: {}",
+                error.message(),
+                pretty(response)
+            );
+        }
+    }
+}
+
+/// Appends a line per difference between `expected` and `actual` to `diff`
+fn compare(path: &str, expected: &Value, actual: &Value, diff: &mut String) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected) in expected {
+                let path = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(actual) => compare(&path, expected, actual, diff),
+                    None => _ = writeln!(diff, "  {path}: missing, expected {expected}"),
+                }
+            }
+            for (key, actual) in actual {
+                if !expected.contains_key(key) {
+                    _ = writeln!(diff, "  {path}.{key}: unexpected {actual}");
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                compare(&format!("{path}[{i}]"), expected, actual, diff);
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            _ = writeln!(
+                diff,
+                "  {path}: expected {} items, got {}",
+                expected.len(),
+                actual.len()
+            )
+        }
+        _ if expected != actual => {
+            _ = writeln!(diff, "  {path}: expected {expected}, got {actual}")
+        }
+        _ => {}
+    }
+}
+
+fn pretty<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::panic::catch_unwind;
+
+    use serde_json::json;
+
+    use crate::error::{JsonRpcError, JsonRpcErrorReason};
+    use crate::{JsonRpcResponse, Value};
+
+    fn message(f: impl FnOnce() + std::panic::UnwindSafe) -> String {
+        let panic = catch_unwind(f).unwrap_err();
+        panic.downcast::<String>().map(|s| *s).unwrap()
+    }
+
+    #[test]
+    fn asserts() {
+        let response = JsonRpcResponse::success(1, json!({"sum": 3, "terms": [1, 2]}));
+        assert_rpc_success!(response, json!({"terms": [1, 2], "sum": 3}));
+        let diff = message(|| assert_rpc_success!(response, json!({"sum": 4, "terms": [1]})));
+        assert!(diff.contains("result.sum: expected 4, got 3"), "{diff}");
+        assert!(
+            diff.contains("result.terms: expected 1 items, got 2"),
+            "{diff}"
+        );
+
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::MethodNotFound,
+            "Method not found".to_owned(),
+            Value::Null,
+        );
+        let response = JsonRpcResponse::error(1, error);
+        assert_rpc_error!(response, JsonRpcErrorReason::MethodNotFound, "not found");
+        let diff = message(|| assert_rpc_error!(response, -32602));
+        assert!(
+            diff.starts_with("expected error -32602, got -32601"),
+            "{diff}"
+        );
+        let diff = message(|| assert_rpc_success!(response, 1));
+        assert!(
+            diff.starts_with("expected a result, got error -32601"),
+            "{diff}"
+        );
+    }
+}
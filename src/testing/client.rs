@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use axum::Router;
+use axum_test::TestServer;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::JsonRpcError;
+use crate::{JsonRpcRequest, JsonRpcResponse};
+
+/// Calls a router through an [`axum_test::TestServer`], see the [module docs](super).
+///
+/// Calls get increasing numeric ids. Failing to encode the params or to decode a response
+/// panics, like other test assertions.
+#[derive(Debug)]
+pub struct JsonRpcTestClient {
+    server: TestServer,
+    path: String,
+    next_id: AtomicI64,
+}
+
+impl JsonRpcTestClient {
+    /// Serves `app` on a [`TestServer`], calling its root path
+    pub fn new(app: Router) -> Self {
+        let server = TestServer::new(app).expect("failed to start the test server");
+        Self::from(server)
+    }
+
+    /// Posts the requests to `path` instead of `/`
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// The underlying server, e.g. to set headers
+    pub fn server(&mut self) -> &mut TestServer {
+        &mut self.server
+    }
+
+    /// Calls `method` and returns its response
+    pub async fn call<P: Serialize>(&self, method: &str, params: P) -> JsonRpcResponse {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = request(method, params).id(id).build();
+        let response = self.send(&request).await;
+        response.expect("no response to a call")
+    }
+
+    /// Calls `method` and decodes its result into `T`
+    pub async fn call_typed<T: DeserializeOwned, P: Serialize>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> Result<T, JsonRpcError> {
+        self.call(method, params).await.into_result()
+    }
+
+    /// Sends a notification, panicking if the server answers it
+    pub async fn notify<P: Serialize>(&self, method: &str, params: P) {
+        let request = request(method, params).notification();
+        if let Some(response) = self.send::<_, JsonRpcResponse>(&request).await {
+            panic!("notification answered with {response:?}");
+        }
+    }
+
+    /// Sends `requests` as a batch and returns the responses, empty when the batch holds
+    /// notifications only
+    pub async fn batch(
+        &self,
+        requests: impl IntoIterator<Item = JsonRpcRequest>,
+    ) -> Vec<JsonRpcResponse> {
+        let requests: Vec<_> = requests.into_iter().collect();
+        self.send(&requests).await.unwrap_or_default()
+    }
+
+    async fn send<B: Serialize, R: DeserializeOwned>(&self, body: &B) -> Option<R> {
+        let response = self.server.post(&self.path).json(body).await;
+        match response.as_bytes().is_empty() {
+            true => None,
+            false => Some(response.json()),
+        }
+    }
+}
+
+impl From<TestServer> for JsonRpcTestClient {
+    fn from(server: TestServer) -> Self {
+        Self {
+            server,
+            path: "/".to_owned(),
+            next_id: AtomicI64::new(1),
+        }
+    }
+}
+
+fn request<P: Serialize>(method: &str, params: P) -> crate::JsonRpcRequestBuilder {
+    JsonRpcRequest::builder(method)
+        .params(params)
+        .expect("failed to encode the params")
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::Router;
+    use serde_json::json;
+
+    use super::JsonRpcTestClient;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i64; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    fn client() -> JsonRpcTestClient {
+        let rpc = JsonRpcRouter::new().method("add", add);
+        JsonRpcTestClient::new(Router::new().route("/rpc", crate::router::post(rpc)))
+            .with_path("/rpc")
+    }
+
+    #[tokio::test]
+    async fn calls() {
+        let client = client();
+        let sum: i64 = client.call_typed("add", [1, 2]).await.unwrap();
+        assert_eq!(sum, 3);
+
+        let error = client.call_typed::<i64, _>("sub", [1, 2]).await;
+        assert_eq!(error.unwrap_err().code(), -32601);
+        let response = client.call("add", json!({"a": 1})).await;
+        assert_eq!(response.id, 3.into());
+        assert_eq!(response.into_result::<i64>().unwrap_err().code(), -32602);
+
+        client.notify("add", [1, 2]).await;
+
+        let responses = client
+            .batch([
+                JsonRpcRequest::builder("add")
+                    .params([2, 2])
+                    .unwrap()
+                    .id(7)
+                    .build(),
+                JsonRpcRequest::builder("add")
+                    .params([2, 2])
+                    .unwrap()
+                    .notification(),
+            ])
+            .await;
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].id, 7.into());
+        assert_eq!(responses[0].clone().into_result::<i64>().unwrap(), 4);
+    }
+}
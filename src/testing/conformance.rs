@@ -0,0 +1,319 @@
+use std::fmt;
+
+use axum::Router;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+/// A method no service is expected to have
+const MISSING: &str = "conformance.missing";
+
+/// Runs a [JSON-RPC 2.0 specification](https://www.jsonrpc.org/specification) conformance
+/// suite against a router serving JSON-RPC.
+///
+/// The suite holds the examples of the specification which need no particular method, and
+/// edge cases around batches, notifications, versions and ids. Calls are made to the
+/// `conformance.missing` method, which the service must not have.
+/// ```rust
+/// use axum::Router;
+/// use axum_jrpc::testing::Conformance;
+/// use axum_jrpc::JsonRpcRouter;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let app = Router::new().route("/rpc", axum_jrpc::router::post(JsonRpcRouter::new()));
+/// let report = Conformance::new(app).path("/rpc").run().await;
+/// report.assert_conformant();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Conformance {
+    app: Router,
+    path: String,
+}
+
+impl Conformance {
+    pub fn new(app: Router) -> Self {
+        Self {
+            app,
+            path: "/".to_owned(),
+        }
+    }
+
+    /// Posts the requests to `path` instead of `/`
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Sends every case of the suite and reports the deviations
+    pub async fn run(self) -> Report {
+        let server = TestServer::new(self.app).expect("failed to start the test server");
+        let mut checks = Vec::new();
+        for case in cases() {
+            let response = server
+                .post(&self.path)
+                .content_type("application/json")
+                .bytes(case.request.clone().into_bytes().into())
+                .await;
+            let body = response.as_bytes();
+            let deviation = match body.is_empty() {
+                true => case.expected.check(None),
+                false => match serde_json::from_slice(body) {
+                    Ok(reply) => case.expected.check(Some(&reply)),
+                    Err(_) => Err(format!("answered with {}", response.text())),
+                },
+            };
+            checks.push(Check {
+                name: case.name,
+                request: case.request,
+                deviation: deviation.err(),
+            });
+        }
+        Report { checks }
+    }
+}
+
+/// Outcome of a [`Conformance`] run
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+/// A case of the suite
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub request: String,
+    /// How the service deviated from the specification, if it did
+    pub deviation: Option<String>,
+}
+
+impl Report {
+    /// Checks the service failed
+    pub fn deviations(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| check.deviation.is_some())
+    }
+
+    pub fn is_conformant(&self) -> bool {
+        self.deviations().next().is_none()
+    }
+
+    /// Panics with the report if the service deviated from the specification
+    #[track_caller]
+    pub fn assert_conformant(&self) {
+        if !self.is_conformant() {
+            panic!("{self}");
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let deviations = self.deviations().count();
+        writeln!(f, "{deviations} of {} checks deviate", self.checks.len())?;
+        for check in self.deviations() {
+            let deviation = check.deviation.as_deref().unwrap_or_default();
+            writeln!(f, "  {}: {deviation}", check.name)?;
+            writeln!(f, "    request: {}", check.request)?;
+        }
+        Ok(())
+    }
+}
+
+struct Case {
+    name: &'static str,
+    request: String,
+    expected: Expected,
+}
+
+enum Expected {
+    /// No response at all
+    Nothing,
+    /// An error with the id and code
+    Error(Value, i32),
+    /// A batch of errors with the ids and codes, in any order
+    Errors(Vec<(Value, i32)>),
+}
+
+impl Expected {
+    fn check(&self, reply: Option<&Value>) -> Result<(), String> {
+        match (self, reply) {
+            (Expected::Nothing, None) => Ok(()),
+            (Expected::Nothing, Some(reply)) => Err(format!("answered with {reply}")),
+            (_, None) => Err("no response".to_owned()),
+            (Expected::Error(id, code), Some(reply)) => error(reply, id, *code),
+            (Expected::Errors(expected), Some(reply)) => {
+                let Some(replies) = reply.as_array() else {
+                    return Err(format!("expected a batch, got {reply}"));
+                };
+                if replies.len() != expected.len() {
+                    return Err(format!(
+                        "expected {} responses, got {reply}",
+                        expected.len()
+                    ));
+                }
+                let mut expected: Vec<_> = expected.iter().collect();
+                for reply in replies {
+                    let matching = expected
+                        .iter()
+                        .position(|(id, code)| error(reply, id, *code).is_ok());
+                    match matching {
+                        Some(i) => _ = expected.remove(i),
+                        None => return Err(format!("unexpected response {reply}")),
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Checks that `reply` is a well formed error response with `id` and `code`
+fn error(reply: &Value, id: &Value, code: i32) -> Result<(), String> {
+    if reply["jsonrpc"] != "2.0" {
+        return Err(format!("missing \"jsonrpc\": \"2.0\" in {reply}"));
+    }
+    if reply.get("result").is_some() {
+        return Err(format!("expected error {code}, got {reply}"));
+    }
+    if reply["id"] != *id {
+        return Err(format!("expected id {id}, got {reply}"));
+    }
+    if reply["error"]["code"] != code {
+        return Err(format!("expected error {code}, got {reply}"));
+    }
+    if !reply["error"]["message"].is_string() {
+        return Err(format!("error without a message in {reply}"));
+    }
+    Ok(())
+}
+
+fn cases() -> Vec<Case> {
+    let call = |id: Value| json!({"jsonrpc": "2.0", "method": MISSING, "params": [1], "id": id});
+    let notification = json!({"jsonrpc": "2.0", "method": MISSING, "params": [1]});
+    let case = |name, request: Value, expected| Case {
+        name,
+        request: request.to_string(),
+        expected,
+    };
+    let raw = |name, request: &str, expected| Case {
+        name,
+        request: request.to_owned(),
+        expected,
+    };
+
+    vec![
+        case(
+            "call of a non-existent method",
+            call(json!("1")),
+            Expected::Error(json!("1"), -32601),
+        ),
+        case(
+            "numeric id",
+            call(json!(7)),
+            Expected::Error(json!(7), -32601),
+        ),
+        // a null id is discouraged but still a call, unlike a missing one
+        case(
+            "null id",
+            call(Value::Null),
+            Expected::Error(Value::Null, -32601),
+        ),
+        case("notification", notification.clone(), Expected::Nothing),
+        raw(
+            "invalid JSON",
+            r#"{"jsonrpc": "2.0", "method": "foobar, "params": "bar", "baz]"#,
+            Expected::Error(Value::Null, -32700),
+        ),
+        case(
+            "invalid request object",
+            json!({"jsonrpc": "2.0", "method": 1, "params": "bar"}),
+            Expected::Error(Value::Null, -32600),
+        ),
+        case(
+            "wrong version",
+            json!({"jsonrpc": "1.0", "method": MISSING, "id": 1}),
+            Expected::Error(Value::Null, -32600),
+        ),
+        case(
+            "missing version",
+            json!({"method": MISSING, "id": 1}),
+            Expected::Error(Value::Null, -32600),
+        ),
+        case(
+            "object id",
+            json!({"jsonrpc": "2.0", "method": MISSING, "id": {}}),
+            Expected::Error(Value::Null, -32600),
+        ),
+        raw(
+            "batch with invalid JSON",
+            r#"[{"jsonrpc": "2.0", "method": "sum", "params": [1,2,4], "id": "1"}, {"jsonrpc": "2.0", "method"]"#,
+            Expected::Error(Value::Null, -32700),
+        ),
+        case(
+            "empty batch",
+            json!([]),
+            Expected::Error(Value::Null, -32600),
+        ),
+        case(
+            "invalid batch",
+            json!([1]),
+            Expected::Errors(vec![(Value::Null, -32600)]),
+        ),
+        case(
+            "invalid batch entries",
+            json!([1, 2, 3]),
+            Expected::Errors(vec![(Value::Null, -32600); 3]),
+        ),
+        case(
+            "batch of notifications",
+            json!([notification, notification]),
+            Expected::Nothing,
+        ),
+        case(
+            "mixed batch",
+            json!([call(json!(1)), notification, {"foo": "boo"}, call(json!("5"))]),
+            Expected::Errors(vec![
+                (json!(1), -32601),
+                (Value::Null, -32600),
+                (json!("5"), -32601),
+            ]),
+        ),
+    ]
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::routing::post;
+    use axum::Router;
+    use serde_json::json;
+
+    use super::Conformance;
+    use crate::JsonRpcRouter;
+
+    #[tokio::test]
+    async fn reports_deviations() {
+        let app = Router::new().route("/", crate::router::post(JsonRpcRouter::new()));
+        let report = Conformance::new(app).run().await;
+        report.assert_conformant();
+
+        // answers everything, notifications and invalid requests included
+        let app = Router::new().route(
+            "/",
+            post(|| async { axum::Json(json!({"jsonrpc": "2.0", "id": null, "result": 1})) }),
+        );
+        let report = Conformance::new(app).run().await;
+        assert!(!report.is_conformant());
+        let notification = report
+            .checks
+            .iter()
+            .find(|c| c.name == "notification")
+            .unwrap();
+        assert_eq!(
+            notification.deviation.as_deref(),
+            Some(r#"answered with {"id":null,"jsonrpc":"2.0","result":1}"#)
+        );
+        assert!(report.to_string().starts_with("15 of 15 checks deviate"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Pathological payloads, for testing how a service or a parser copes with them.
+//!
+//! Payloads are raw bytes, most of them aren't valid JSON-RPC or even JSON. [`all`] lists
+//! every payload of the corpus with a name, e.g. to feed a service in a loop or to seed a
+//! fuzzer.
+//! ```rust
+//! use axum_jrpc::router::JsonRpcService;
+//! use axum_jrpc::testing::corpus;
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let rpc = JsonRpcRouter::new();
+//! for (name, payload) in corpus::all() {
+//!     if let Some(reply) = rpc.dispatch_bytes(payload.into()).await {
+//!         assert!(serde_json::from_slice::<serde_json::Value>(&reply).is_ok(), "{name}");
+//!     }
+//! }
+//! # }
+//! ```
+//! Calls in the corpus are made to the `corpus.echo` method.
+
+/// Method called by the payloads of the corpus
+pub const METHOD: &str = "corpus.echo";
+
+/// Ids which are valid json but unusual or invalid as JSON-RPC ids, as json text
+pub const IDS: &[&str] = &[
+    "0",
+    "-1",
+    "9007199254740993",
+    "9223372036854775807",
+    "9223372036854775808",
+    "-9223372036854775809",
+    "1.5",
+    "1e3",
+    "-0",
+    r#""""#,
+    r#"" ""#,
+    r#""\u0000""#,
+    r#""😀""#,
+    "true",
+    "[]",
+    "{}",
+    "null",
+];
+
+/// Not JSON at all
+pub const INVALID_JSON: &[&[u8]] = &[
+    b"",
+    b" ",
+    b"{",
+    b"[",
+    b"}",
+    b"nul",
+    b"{\"jsonrpc\":\"2.0\",\"method\":\"corpus.echo\",\"id\":1",
+    b"{\"jsonrpc\":\"2.0\",\"method\":\"corpus.echo\",\"id\":1}}",
+    b"{\"jsonrpc\":\"2.0\",\"method\":\"corpus.echo\",\"id\":1} garbage",
+    b"{'jsonrpc':'2.0','method':'corpus.echo','id':1}",
+    b"{\"jsonrpc\":\"2.0\",\"method\":\"corpus.echo\",\"id\":01}",
+    b"{\"jsonrpc\":\"2.0\",\"method\":\"corpus.echo\",\"id\":NaN}",
+    b"\xff\xfe{}",
+    b"{\"jsonrpc\":\"2.0\",\"method\":\"\xc3\x28\",\"id\":1}",
+    // a lone surrogate, which most parsers reject
+    b"{\"jsonrpc\":\"2.0\",\"method\":\"corpus.echo\",\"id\":\"\\ud800\"}",
+];
+
+/// JSON, but odd as a JSON-RPC message
+pub const ODD_MESSAGES: &[&str] = &[
+    "\u{feff}{\"jsonrpc\":\"2.0\",\"method\":\"corpus.echo\",\"id\":1}",
+    r#"{"jsonrpc":"2.0","method":"corpus.echo","id":1,"id":2}"#,
+    r#"{"jsonrpc":"2.0","jsonrpc":"1.0","method":"corpus.echo","id":1}"#,
+    r#"{"jsonrpc":"2.0","method":"corpus.echo","params":null,"id":1}"#,
+    r#"{"jsonrpc":"2.0","method":"corpus.echo","params":1,"id":1}"#,
+    r#"{"jsonrpc":"2.0","method":"corpus.echo","params":"text","id":1}"#,
+    r#"{"jsonrpc":"2.0","method":"","id":1}"#,
+    r#"{"jsonrpc":"2.0","method":"rpc.discover","id":1}"#,
+    r#"{"jsonrpc":"2.0","method":"corpus.\u0000","id":1}"#,
+    r#"{"jsonrpc":"2.0","method":null,"id":1}"#,
+    r#"{"jsonrpc":2.0,"method":"corpus.echo","id":1}"#,
+    r#"{"jsonrpc":"2.0","method":"corpus.echo","id":1,"extra":{"nested":[1,2,3]}}"#,
+    r#"{"JSONRPC":"2.0","METHOD":"corpus.echo","ID":1}"#,
+    r#"{}"#,
+    r#"[]"#,
+    r#"[[]]"#,
+    r#"[{}]"#,
+    r#""2.0""#,
+    "null",
+    "42",
+];
+
+/// A call with `id`, given as json text, see [`IDS`]
+pub fn with_id(id: &str) -> String {
+    format!(r#"{{"jsonrpc":"2.0","method":"{METHOD}","params":[],"id":{id}}}"#)
+}
+
+/// A call whose params are arrays nested `depth` levels deep
+pub fn deeply_nested(depth: usize) -> String {
+    let params = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    format!(r#"{{"jsonrpc":"2.0","method":"{METHOD}","params":{params},"id":1}}"#)
+}
+
+/// A call with a single string param of `len` bytes, made of escapes every other byte
+pub fn huge_string(len: usize) -> String {
+    let text: String = "a\\n".repeat(len / 3 + 1);
+    format!(r#"{{"jsonrpc":"2.0","method":"{METHOD}","params":["{text}"],"id":1}}"#)
+}
+
+/// A call with `count` params
+pub fn many_params(count: usize) -> String {
+    let params = vec!["0"; count].join(",");
+    format!(r#"{{"jsonrpc":"2.0","method":"{METHOD}","params":[{params}],"id":1}}"#)
+}
+
+/// A batch of `len` calls
+pub fn huge_batch(len: usize) -> String {
+    let calls: Vec<_> = (0..len).map(|id| with_id(&id.to_string())).collect();
+    format!("[{}]", calls.join(","))
+}
+
+/// A batch mixing calls, notifications, duplicate ids, invalid requests and non-objects
+pub fn mixed_batch() -> String {
+    let notification = format!(r#"{{"jsonrpc":"2.0","method":"{METHOD}","params":[]}}"#);
+    let entries = [
+        with_id("1"),
+        notification.clone(),
+        with_id("1"),
+        with_id(r#""1""#),
+        r#"{"jsonrpc":"1.0","method":"corpus.echo","id":2}"#.to_owned(),
+        r#"{"method":"corpus.echo","id":3}"#.to_owned(),
+        "1".to_owned(),
+        "null".to_owned(),
+        "[]".to_owned(),
+        notification,
+        with_id("{}"),
+    ];
+    format!("[{}]", entries.join(","))
+}
+
+/// Every payload of the corpus, with a name. Builders are used with sizes large enough to
+/// hit common limits, e.g. the recursion limit of serde_json, without being slow to handle
+pub fn all() -> Vec<(String, Vec<u8>)> {
+    let mut payloads = Vec::new();
+    for (i, payload) in INVALID_JSON.iter().enumerate() {
+        payloads.push((format!("invalid json #{i}"), payload.to_vec()));
+    }
+    for (i, payload) in ODD_MESSAGES.iter().enumerate() {
+        payloads.push((format!("odd message #{i}"), payload.as_bytes().to_vec()));
+    }
+    for id in IDS {
+        payloads.push((format!("id {id}"), with_id(id).into_bytes()));
+    }
+    let built = [
+        ("nested 64 deep", deeply_nested(64)),
+        ("nested 100000 deep", deeply_nested(100_000)),
+        ("1 MiB string", huge_string(1 << 20)),
+        ("100000 params", many_params(100_000)),
+        ("batch of 10000", huge_batch(10_000)),
+        ("mixed batch", mixed_batch()),
+    ];
+    for (name, payload) in built {
+        payloads.push((name.to_owned(), payload.into_bytes()));
+    }
+    payloads
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::{all, deeply_nested, huge_string, mixed_batch, with_id, IDS, ODD_MESSAGES};
+    use crate::router::JsonRpcService;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn echo(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        Ok(JsonRpcResponse::success(id, req.parsed))
+    }
+
+    #[test]
+    fn builds_json() {
+        for payload in [deeply_nested(8), huge_string(100), mixed_batch()] {
+            serde_json::from_str::<Value>(&payload).unwrap();
+        }
+        for message in ODD_MESSAGES {
+            serde_json::from_str::<Value>(message.trim_start_matches('\u{feff}')).unwrap();
+        }
+        for id in IDS {
+            serde_json::from_str::<Value>(&with_id(id)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn router_copes() {
+        let rpc = JsonRpcRouter::new().method(super::METHOD, echo);
+        for (name, payload) in all() {
+            if let Some(reply) = rpc.dispatch_bytes(payload.into()).await {
+                let reply: Value = serde_json::from_slice(&reply).unwrap();
+                assert!(reply.is_object() || reply.is_array(), "{name}");
+            }
+        }
+
+        let reply = rpc.dispatch_bytes(mixed_batch().into()).await.unwrap();
+        let reply: Vec<Value> = serde_json::from_slice(&reply).unwrap();
+        let ids: Vec<_> = reply.iter().map(|r| r["id"].clone()).collect();
+        assert_eq!(ids.iter().filter(|id| **id == json!(1)).count(), 2);
+        assert_eq!(reply.len(), 9);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tower::{Layer, Service};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::BoxFuture;
+use crate::{JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Faults injected into the calls of a method, each with its own probability.
+///
+/// A call is first delayed, then possibly dropped, then possibly failed with an error.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    latency: Option<(f64, Duration)>,
+    drop: f64,
+    error: Option<(f64, JsonRpcErrorReason)>,
+}
+
+impl Faults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delays calls by `latency` with the given probability
+    pub fn latency(mut self, probability: f64, latency: Duration) -> Self {
+        self.latency = Some((probability, latency));
+        self
+    }
+
+    /// Never answers calls with the given probability, so clients run into their timeout
+    pub fn drop_responses(mut self, probability: f64) -> Self {
+        self.drop = probability;
+        self
+    }
+
+    /// Answers calls with an error of `reason` with the given probability, without calling
+    /// the handler
+    pub fn error(mut self, probability: f64, reason: JsonRpcErrorReason) -> Self {
+        self.error = Some((probability, reason));
+        self
+    }
+}
+
+/// Injects [`Faults`] into calls, to exercise the retries and backoff of clients.
+/// ```rust
+/// use std::time::Duration;
+///
+/// use axum_jrpc::error::JsonRpcErrorReason;
+/// use axum_jrpc::testing::{FaultLayer, Faults};
+/// use axum_jrpc::JsonRpcRouter;
+/// use tower::Layer;
+///
+/// let faults = FaultLayer::new()
+///     .seed(7)
+///     .method("getBlock", Faults::new().error(0.2, JsonRpcErrorReason::ServerError(-32005)))
+///     .others(Faults::new().latency(0.5, Duration::from_millis(300)).drop_responses(0.01));
+/// let rpc = faults.layer(JsonRpcRouter::new());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FaultLayer {
+    methods: Arc<HashMap<String, Faults>>,
+    others: Option<Arc<Faults>>,
+    rng: Arc<Mutex<fastrand::Rng>>,
+}
+
+impl FaultLayer {
+    /// Injects nothing until faults are configured
+    pub fn new() -> Self {
+        Self {
+            methods: Default::default(),
+            others: None,
+            rng: Arc::new(Mutex::new(fastrand::Rng::new())),
+        }
+    }
+
+    /// Makes the injected faults reproducible
+    pub fn seed(self, seed: u64) -> Self {
+        *self.rng.lock().unwrap_or_else(|e| e.into_inner()) = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Injects `faults` into the calls of `method`
+    pub fn method(mut self, method: impl Into<String>, faults: Faults) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into(), faults);
+        self
+    }
+
+    /// Injects `faults` into the calls of methods without faults of their own
+    pub fn others(mut self, faults: Faults) -> Self {
+        self.others = Some(Arc::new(faults));
+        self
+    }
+}
+
+impl Default for FaultLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for FaultLayer {
+    type Service = Fault<S>;
+
+    fn layer(&self, inner: S) -> Fault<S> {
+        Fault {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Injects faults into calls, see [`FaultLayer`]
+#[derive(Debug, Clone)]
+pub struct Fault<S> {
+    inner: S,
+    layer: FaultLayer,
+}
+
+impl<S> Service<JsonRpcExtractor> for Fault<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let faults = match self.layer.methods.get(&*request.method) {
+            Some(faults) => faults,
+            None => match &self.layer.others {
+                Some(faults) => faults,
+                None => return Box::pin(self.inner.call(request)),
+            },
+        };
+
+        // roll every die now, so the outcome only depends on the order of the calls
+        let mut rng = self.layer.rng.lock().unwrap_or_else(|e| e.into_inner());
+        let mut roll = |probability: f64| rng.f64() < probability;
+        let latency = faults
+            .latency
+            .and_then(|(probability, latency)| roll(probability).then_some(latency));
+        let dropped = roll(faults.drop);
+        let error = faults
+            .error
+            .and_then(|(probability, reason)| roll(probability).then_some(reason));
+        drop(rng);
+
+        // the handler runs only if its response is sent, after the delay
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut inner, &mut self.inner);
+        Box::pin(async move {
+            if let Some(latency) = latency {
+                tokio::time::sleep(latency).await;
+            }
+            if dropped {
+                std::future::pending::<()>().await;
+            }
+            match error {
+                Some(reason) => {
+                    let error = JsonRpcError::new(reason, reason.to_string(), Value::default());
+                    Ok(JsonRpcResponse::error(request.id, error))
+                }
+                None => inner.call(request).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+    use tower::Layer;
+
+    use super::{FaultLayer, Faults};
+    use crate::error::JsonRpcErrorReason;
+    use crate::router::JsonRpcService;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i64; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    fn request(method: &str) -> JsonRpcExtractor {
+        JsonRpcRequest {
+            id: 1.into(),
+            method: method.to_owned(),
+            params: json!([1, 2]),
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn injects_faults() {
+        let router = JsonRpcRouter::new().method("add", add).method("sub", add);
+        let rpc = FaultLayer::new()
+            .seed(1)
+            .method(
+                "add",
+                Faults::new().error(0.5, JsonRpcErrorReason::ServerError(-32005)),
+            )
+            .others(Faults::new().latency(1.0, Duration::from_millis(20)))
+            .layer(router.clone());
+
+        let mut errors = 0;
+        for _ in 0..1000 {
+            if let Err(error) = rpc.dispatch(request("add")).await.into_result::<i64>() {
+                assert_eq!(error.code(), -32005);
+                errors += 1;
+            }
+        }
+        assert!((400..600).contains(&errors), "{errors}");
+
+        let started = Instant::now();
+        let response = rpc.dispatch(request("sub")).await;
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        assert_eq!(response.into_result::<i64>().unwrap(), 3);
+
+        let rpc = FaultLayer::new()
+            .method("add", Faults::new().drop_responses(1.0))
+            .layer(router);
+        let response =
+            tokio::time::timeout(Duration::from_millis(20), rpc.dispatch(request("add")));
+        assert!(response.await.is_err());
+        assert!(rpc
+            .dispatch(request("sub"))
+            .await
+            .into_result::<i64>()
+            .is_ok());
+    }
+}
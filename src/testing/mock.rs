@@ -0,0 +1,267 @@
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::Router;
+use axum_test::{TestServer, TestServerConfig};
+use serde::Serialize;
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::router::{post, JsonRpcRouter};
+use crate::{to_value, JrpcResult, JsonRpcExtractor, JsonRpcResponse, Value};
+
+/// Answers calls as told by expectations, for testing JSON-RPC clients without the real
+/// service.
+///
+/// Every call is answered by the first expectation matching its method and params that isn't
+/// used up. Calls matching none are answered with `Method not found`. Dropping the server
+/// panics if a call matched nothing or an expectation wasn't called as often as expected,
+/// unless the test is already panicking.
+/// ```rust
+/// use axum_jrpc::client::{HttpClient, JsonRpcClient};
+/// use axum_jrpc::testing::MockJsonRpcServer;
+/// use serde_json::json;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let mock = MockJsonRpcServer::new();
+/// mock.expect("getBlock")
+///     .with_params(json!([7]))
+///     .returning(json!({"height": 7}))
+///     .times(2);
+///
+/// let client = HttpClient::new(mock.url());
+/// for _ in 0..2 {
+///     let block: serde_json::Value = client.call("getBlock", [7]).await.unwrap();
+///     assert_eq!(block["height"], 7);
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockJsonRpcServer {
+    state: Arc<Mutex<State>>,
+    server: TestServer,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    expectations: Vec<Expected>,
+    unexpected: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Expected {
+    method: String,
+    params: Option<serde_json::Value>,
+    answer: Result<Value, JsonRpcError>,
+    times: Option<usize>,
+    calls: usize,
+}
+
+impl MockJsonRpcServer {
+    /// Starts the server on a random local port. Must be called within a tokio runtime
+    pub fn new() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let server = TestServerConfig::builder()
+            .http_transport()
+            .build_server(Router::new().route("/", post(service(state.clone()))))
+            .expect("failed to start the mock server");
+        Self { state, server }
+    }
+
+    /// Expects calls of `method`, answered with `null` by default
+    pub fn expect(&self, method: impl Into<String>) -> Expectation {
+        let mut state = lock(&self.state);
+        state.expectations.push(Expected {
+            method: method.into(),
+            params: None,
+            answer: Ok(Value::default()),
+            times: None,
+            calls: 0,
+        });
+        Expectation {
+            state: self.state.clone(),
+            index: state.expectations.len() - 1,
+        }
+    }
+
+    /// URL to point clients at
+    pub fn url(&self) -> String {
+        let address = self.server.server_address();
+        address.expect("the mock server has an address").to_string()
+    }
+
+    /// A router serving the same expectations, e.g. to mount in another app
+    pub fn router(&self) -> Router {
+        Router::new().route("/", post(self.service()))
+    }
+
+    /// A service answering from the same expectations, e.g. for a
+    /// [`LocalClient`](crate::client::LocalClient)
+    pub fn service(&self) -> JsonRpcRouter {
+        service(self.state.clone())
+    }
+
+    /// Panics if a call matched no expectation or an expectation wasn't called as often as
+    /// expected
+    #[track_caller]
+    pub fn verify(&self) {
+        let state = lock(&self.state);
+        let mut failures = String::new();
+        for call in &state.unexpected {
+            _ = writeln!(failures, "  unexpected call {call}");
+        }
+        for expected in &state.expectations {
+            let calls = expected.calls;
+            match expected.times {
+                Some(times) if calls != times => {}
+                None if calls == 0 => {}
+                _ => continue,
+            }
+            let times = expected
+                .times
+                .map_or("at least 1".to_owned(), |t| t.to_string());
+            _ = writeln!(
+                failures,
+                "  {} expected {times} calls, got {calls}",
+                expected.method
+            );
+        }
+        if !failures.is_empty() {
+            panic!("mock server expectations failed:\n{failures}");
+        }
+    }
+}
+
+impl Default for MockJsonRpcServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for MockJsonRpcServer {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.verify();
+        }
+    }
+}
+
+/// An expectation of a [`MockJsonRpcServer`], configured in place
+#[derive(Debug)]
+pub struct Expectation {
+    state: Arc<Mutex<State>>,
+    index: usize,
+}
+
+impl Expectation {
+    /// Only matches calls with params equal, as JSON, to `params`
+    pub fn with_params<P: Serialize>(self, params: P) -> Self {
+        let params = serde_json::to_value(params).expect("failed to encode the params");
+        self.update(|expected| expected.params = Some(params))
+    }
+
+    /// Answers with `result`
+    pub fn returning<R: Serialize>(self, result: R) -> Self {
+        let result = to_value(result).expect("failed to encode the result");
+        self.update(|expected| expected.answer = Ok(result))
+    }
+
+    /// Answers with `error`
+    pub fn returning_error(self, error: JsonRpcError) -> Self {
+        self.update(|expected| expected.answer = Err(error))
+    }
+
+    /// Expects exactly `times` calls, later ones are left to other expectations
+    pub fn times(self, times: usize) -> Self {
+        self.update(|expected| expected.times = Some(times))
+    }
+
+    fn update(self, f: impl FnOnce(&mut Expected)) -> Self {
+        f(&mut lock(&self.state).expectations[self.index]);
+        self
+    }
+}
+
+fn service(state: Arc<Mutex<State>>) -> JsonRpcRouter {
+    JsonRpcRouter::new().fallback(move |req| {
+        let answer = answer(&state, req);
+        async move { answer }
+    })
+}
+
+fn answer(state: &Mutex<State>, req: JsonRpcExtractor) -> JrpcResult {
+    let params = serde_json::to_value(&req.parsed).unwrap_or_default();
+    let mut state = lock(state);
+    let expected = state.expectations.iter_mut().find(|expected| {
+        expected.method == *req.method
+            && expected.params.as_ref().is_none_or(|p| *p == params)
+            && expected.times.is_none_or(|times| expected.calls < times)
+    });
+    match expected {
+        Some(expected) => {
+            expected.calls += 1;
+            match &expected.answer {
+                Ok(result) => Ok(JsonRpcResponse::success(req.id, result)),
+                Err(error) => Err(JsonRpcResponse::error(req.id, error.clone())),
+            }
+        }
+        None => {
+            state.unexpected.push(format!("{}({params})", req.method));
+            let error = JsonRpcError::new(
+                JsonRpcErrorReason::MethodNotFound,
+                format!("unexpected call of {}", req.method),
+                Value::default(),
+            );
+            Err(JsonRpcResponse::error(req.id, error))
+        }
+    }
+}
+
+/// Locks the state, even if a panicking test poisoned it
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "serde_json", feature = "client"))]
+mod test {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use serde_json::{json, Value};
+
+    use super::MockJsonRpcServer;
+    use crate::client::{ClientError, HttpClient, JsonRpcClient};
+    use crate::error::{JsonRpcError, JsonRpcErrorReason};
+
+    #[tokio::test]
+    async fn answers_expected_calls() {
+        let mock = MockJsonRpcServer::new();
+        mock.expect("getBlock")
+            .with_params(json!([1]))
+            .returning(json!({"height": 1}))
+            .times(1);
+        let error = JsonRpcError::new(
+            JsonRpcErrorReason::ServerError(-32000),
+            "pruned".to_owned(),
+            Value::Null,
+        );
+        mock.expect("getBlock").returning_error(error);
+
+        let client = HttpClient::new(mock.url());
+        let block: Value = client.call("getBlock", [1]).await.unwrap();
+        assert_eq!(block, json!({"height": 1}));
+        let error = client.call::<_, Value>("getBlock", [1]).await.unwrap_err();
+        assert!(matches!(error, ClientError::Rpc(e) if e.code() == -32000));
+        mock.verify();
+
+        let error = client.call::<_, Value>("getTx", [1]).await.unwrap_err();
+        assert!(matches!(error, ClientError::Rpc(e) if e.code() == -32601));
+        mock.expect("getTx").times(2);
+        let panic = catch_unwind(AssertUnwindSafe(|| drop(mock))).unwrap_err();
+        let panic = panic.downcast::<String>().unwrap();
+        assert!(panic.contains("unexpected call getTx([1])"), "{panic}");
+        assert!(panic.contains("getTx expected 2 calls, got 0"), "{panic}");
+    }
+}
@@ -0,0 +1,61 @@
+//! Helpers for testing JSON-RPC services, for the `testing` feature.
+//!
+//! [`JsonRpcTestClient`] calls a router through [`axum_test::TestServer`], building the
+//! request envelopes and decoding the responses.
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::testing::JsonRpcTestClient;
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+//!
+//! async fn add(req: JsonRpcExtractor) -> JrpcResult {
+//!     let id = req.get_answer_id();
+//!     let [a, b]: [i64; 2] = req.parse_params()?;
+//!     Ok(JsonRpcResponse::success(id, a + b))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let rpc = JsonRpcRouter::new().method("add", add);
+//! let app = Router::new().route("/", axum_jrpc::router::post(rpc));
+//! let client = JsonRpcTestClient::new(app);
+//! let sum: i64 = client.call_typed("add", [1, 2]).await.unwrap();
+//! assert_eq!(sum, 3);
+//! # }
+//! ```
+//!
+//! [`assert_rpc_success!`](crate::assert_rpc_success) and
+//! [`assert_rpc_error!`](crate::assert_rpc_error) check the outcome of a response.
+//!
+//! [`MockJsonRpcServer`] stands in for a remote service when testing code using a client.
+//!
+//! [`Conformance`] reports how a service deviates from the specification.
+//!
+//! [`Recorder`] captures real traffic into fixture files, which [`replay`] feeds back through
+//! a service to catch changed responses.
+//!
+//! [`FaultLayer`] injects latency, dropped responses and errors, to exercise the resilience
+//! of clients.
+//!
+//! [`snapshot`] renders messages in a canonical form for snapshot tests.
+//!
+//! [`corpus`] holds pathological payloads to feed a service or a fuzzer with.
+
+pub use axum_test;
+
+pub mod corpus;
+
+pub use assert::{assert_error, assert_success};
+pub use client::JsonRpcTestClient;
+pub use conformance::{Check, Conformance, Report};
+pub use fault::{Fault, FaultLayer, Faults};
+pub use mock::{Expectation, MockJsonRpcServer};
+pub use record::{load, replay, Exchange, Mismatch, Record, RecordLayer, Recorder};
+pub use snapshot::{canonical, snapshot};
+
+mod assert;
+mod client;
+mod conformance;
+mod fault;
+mod mock;
+mod record;
+mod snapshot;
@@ -0,0 +1,245 @@
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use serde::{Deserialize, Serialize};
+use tower::{Layer, Service};
+
+use crate::router::{BoxFuture, JsonRpcService};
+use crate::{JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse};
+
+/// A call and the response it got, the unit of fixture files
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    pub request: JsonRpcRequest,
+    pub response: JsonRpcResponse,
+}
+
+/// Collects the calls passing through its [`RecordLayer`]s, to save them as a fixture file
+/// for [`replay`].
+/// ```rust
+/// use axum_jrpc::testing::{load, replay, Recorder};
+/// use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+/// use tower::Layer;
+///
+/// async fn add(req: JsonRpcExtractor) -> JrpcResult {
+///     let id = req.get_answer_id();
+///     let [a, b]: [i64; 2] = req.parse_params()?;
+///     Ok(JsonRpcResponse::success(id, a + b))
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let recorder = Recorder::new();
+/// let rpc = recorder.layer().layer(JsonRpcRouter::new().method("add", add));
+/// // serve `rpc`, then
+/// # let path = std::env::temp_dir().join("jrpc-fixture-doc.json");
+/// recorder.save(&path)?;
+///
+/// // in the regression suite
+/// let mismatches = replay(&JsonRpcRouter::new().method("add", add), load(&path)?).await;
+/// assert!(mismatches.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    exchanges: Arc<Mutex<Vec<Exchange>>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A layer recording into this recorder
+    pub fn layer(&self) -> RecordLayer {
+        RecordLayer {
+            recorder: self.clone(),
+        }
+    }
+
+    /// Number of calls recorded so far
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Takes the calls recorded so far
+    pub fn take(&self) -> Vec<Exchange> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Writes the calls recorded so far to `path` as a pretty-printed json array
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(&*self.lock())?;
+        std::fs::write(path, json)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Exchange>> {
+        self.exchanges
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Wraps services in [`Record`]
+#[derive(Debug, Clone)]
+pub struct RecordLayer {
+    recorder: Recorder,
+}
+
+impl<S> Layer<S> for RecordLayer {
+    type Service = Record<S>;
+
+    fn layer(&self, inner: S) -> Record<S> {
+        Record {
+            inner,
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+/// Records every call into a [`Recorder`]
+#[derive(Debug, Clone)]
+pub struct Record<S> {
+    inner: S,
+    recorder: Recorder,
+}
+
+impl<S> Service<JsonRpcExtractor> for Record<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let recorded = JsonRpcRequest {
+            id: request.id.clone(),
+            method: request.method.to_string(),
+            params: request.parsed.clone(),
+        };
+        let recorder = self.recorder.clone();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let Ok(response) = response.await;
+            recorder.lock().push(Exchange {
+                request: recorded,
+                response: response.clone(),
+            });
+            Ok(response)
+        })
+    }
+}
+
+/// Reads a fixture file written by [`Recorder::save`]
+pub fn load(path: impl AsRef<Path>) -> std::io::Result<Vec<Exchange>> {
+    let json = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// A replayed call answered differently than when it was recorded
+#[derive(Debug)]
+pub struct Mismatch {
+    pub exchange: Exchange,
+    pub response: JsonRpcResponse,
+}
+
+/// Sends the recorded calls through `service`, one after another, and returns those
+/// answered differently
+pub async fn replay<S: JsonRpcService>(
+    service: &S,
+    exchanges: impl IntoIterator<Item = Exchange>,
+) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    for exchange in exchanges {
+        let request = JsonRpcRequest {
+            id: exchange.request.id.clone(),
+            method: exchange.request.method.clone(),
+            params: exchange.request.params.clone(),
+        };
+        let response = service.dispatch(request.into()).await;
+        if response != exchange.response {
+            mismatches.push(Mismatch { exchange, response });
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::json;
+    use tower::Layer;
+
+    use super::{load, replay, Recorder};
+    use crate::router::JsonRpcService;
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    async fn add(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i64; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a + b))
+    }
+
+    async fn sub(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [a, b]: [i64; 2] = req.parse_params()?;
+        Ok(JsonRpcResponse::success(id, a - b))
+    }
+
+    #[tokio::test]
+    async fn replays() {
+        let recorder = Recorder::new();
+        let rpc = recorder
+            .layer()
+            .layer(JsonRpcRouter::new().method("add", add));
+        for (id, params) in [(1, json!([1, 2])), (2, json!([1]))] {
+            let request = JsonRpcRequest {
+                id: id.into(),
+                method: "add".to_owned(),
+                params,
+            };
+            rpc.dispatch(request.into()).await;
+        }
+        assert_eq!(recorder.len(), 2);
+
+        let path = std::env::temp_dir().join(format!("jrpc-fixture-{}.json", std::process::id()));
+        recorder.save(&path).unwrap();
+        let exchanges = load(&path).unwrap();
+        assert_eq!(exchanges[0].request.params, json!([1, 2]));
+
+        let rpc = JsonRpcRouter::new().method("add", add);
+        assert!(replay(&rpc, exchanges).await.is_empty());
+
+        let rpc = JsonRpcRouter::new().method("add", sub);
+        let mismatches = replay(&rpc, load(&path).unwrap()).await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(
+            mismatches[0]
+                .exchange
+                .response
+                .clone()
+                .into_result::<i64>()
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            mismatches[0].response.clone().into_result::<i64>().unwrap(),
+            -1
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Canonical form of a message or a batch, for snapshot tests.
+///
+/// Object keys are sorted, whatever the map ordering of the json backend, and the ids of the
+/// messages are replaced by their order of appearance, so snapshots don't churn when ids are
+/// generated. Repeated ids get the same number, `null` ids are kept.
+/// ```rust
+/// use axum_jrpc::testing::{canonical, snapshot};
+/// use axum_jrpc::JsonRpcResponse;
+/// use serde_json::json;
+///
+/// let response = JsonRpcResponse::success("0b6f1c", json!({"b": 1, "a": 2}));
+/// assert_eq!(
+///     canonical(&response),
+///     json!({"id": 1, "jsonrpc": "2.0", "result": {"a": 2, "b": 1}})
+/// );
+/// // e.g. insta::assert_snapshot!(snapshot(&response))
+/// assert!(snapshot(&response).starts_with("{\n  \"id\": 1,"));
+/// ```
+pub fn canonical<T: Serialize + ?Sized>(message: &T) -> Value {
+    let mut message = serde_json::to_value(message).expect("failed to encode the message");
+    let mut ids = Vec::new();
+    match &mut message {
+        Value::Array(batch) => batch.iter_mut().for_each(|m| normalize_id(m, &mut ids)),
+        message => normalize_id(message, &mut ids),
+    }
+    sorted(message)
+}
+
+/// [`canonical`] form of a message, pretty-printed
+pub fn snapshot<T: Serialize + ?Sized>(message: &T) -> String {
+    serde_json::to_string_pretty(&canonical(message)).unwrap_or_default()
+}
+
+fn normalize_id(message: &mut Value, ids: &mut Vec<Value>) {
+    let Some(id) = message.get_mut("id").filter(|id| !id.is_null()) else {
+        return;
+    };
+    let position = match ids.iter().position(|seen| seen == id) {
+        Some(position) => position,
+        None => {
+            ids.push(id.take());
+            ids.len() - 1
+        }
+    };
+    *id = Value::from(position + 1);
+}
+
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let object: Map<_, _> = entries.into_iter().map(|(k, v)| (k, sorted(v))).collect();
+            Value::Object(object)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sorted).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde_json::json;
+
+    use super::snapshot;
+
+    #[test]
+    fn normalizes_batches() {
+        let batch = json!([
+            {"jsonrpc": "2.0", "result": {"z": [{"b": 1, "a": 2}], "y": null}, "id": "f00"},
+            {"jsonrpc": "2.0", "error": {"message": "Parse error", "code": -32700}, "id": null},
+            {"jsonrpc": "2.0", "result": 1, "id": 99},
+            {"jsonrpc": "2.0", "result": 2, "id": "f00"},
+        ]);
+        let expected = r#"[
+  {
+    "id": 1,
+    "jsonrpc": "2.0",
+    "result": {
+      "y": null,
+      "z": [
+        {
+          "a": 2,
+          "b": 1
+        }
+      ]
+    }
+  },
+  {
+    "error": {
+      "code": -32700,
+      "message": "Parse error"
+    },
+    "id": null,
+    "jsonrpc": "2.0"
+  },
+  {
+    "id": 2,
+    "jsonrpc": "2.0",
+    "result": 1
+  },
+  {
+    "id": 1,
+    "jsonrpc": "2.0",
+    "result": 2
+  }
+]"#;
+        assert_eq!(snapshot(&batch), expected);
+    }
+}
@@ -0,0 +1,304 @@
+//! Per-call spans for JSON-RPC services.
+//!
+//! [`TraceLayer`] wraps a [`JsonRpcRouter`](crate::JsonRpcRouter) or any other service and
+//! opens a `jrpc_request` span for each call, single or part of a batch, with the method, the
+//! id and the [`BatchIndex`]. Once the call completes the duration and, for errors, the code
+//! are recorded on it.
+//!
+//! With the `opentelemetry` feature, the trace context propagated by the caller in the
+//! `traceparent` and `tracestate` headers is read with the global propagator and becomes the
+//! parent of these spans, so calls show up in the caller's distributed trace. Handlers can
+//! read it with `req.extension::<opentelemetry::Context>()`.
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::router;
+//! use axum_jrpc::trace::TraceLayer;
+//! use axum_jrpc::JsonRpcRouter;
+//! use tower::Layer;
+//!
+//! let rpc = TraceLayer.layer(JsonRpcRouter::new());
+//! let app: Router = Router::new().route("/", router::post(rpc));
+//! ```
+//!
+//! [`SlowRequestLayer`] only logs calls taking longer than a threshold, at `WARN`, so the
+//! slowest calls are visible without logging every request.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tower::{Layer, Service};
+use tracing::Instrument;
+use web_time::Instant;
+
+use crate::codec::{Codec, JsonBackend};
+use crate::router::{BatchIndex, BoxFuture};
+use crate::{JsonRpcAnswer, JsonRpcExtractor, JsonRpcResponse};
+
+/// Wraps services in [`Trace`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceLayer;
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = Trace<S>;
+
+    fn layer(&self, inner: S) -> Trace<S> {
+        Trace { inner }
+    }
+}
+
+/// Opens a span per call, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Trace<S> {
+    inner: S,
+}
+
+impl<S> Service<JsonRpcExtractor> for Trace<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let span = tracing::debug_span!(
+            "jrpc_request",
+            method = %request.method(),
+            id = ?request.id(),
+            batch_index = tracing::field::Empty,
+            code = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        if let Some(BatchIndex(index)) = request.extension() {
+            span.record("batch_index", index);
+        }
+        #[cfg(feature = "opentelemetry")]
+        if let Some(context) = request.extension::<opentelemetry::Context>() {
+            use tracing_opentelemetry::OpenTelemetrySpanExt;
+            // fails only without an opentelemetry layer, then there is nothing to link
+            let _ = span.set_parent(context.clone());
+        }
+        let started = Instant::now();
+        let response = span.in_scope(|| self.inner.call(request));
+
+        Box::pin(async move {
+            let Ok(response) = response.instrument(span.clone()).await;
+            span.record("duration_ms", started.elapsed().as_millis() as u64);
+            match &response.result {
+                JsonRpcAnswer::Result(_) => tracing::debug!(parent: &span, "request succeeded"),
+                JsonRpcAnswer::Error(e) => {
+                    span.record("code", e.code());
+                    tracing::debug!(parent: &span, error = %e, "request failed");
+                }
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Wraps services in [`SlowRequest`], which logs calls exceeding a latency threshold.
+/// ```rust
+/// use std::time::Duration;
+///
+/// use axum_jrpc::trace::SlowRequestLayer;
+/// use axum_jrpc::JsonRpcRouter;
+/// use tower::Layer;
+///
+/// let slow = SlowRequestLayer::new(Duration::from_millis(500))
+///     .method("eth_getLogs", Duration::from_secs(5));
+/// let rpc = slow.layer(JsonRpcRouter::new());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SlowRequestLayer {
+    default: Duration,
+    methods: Arc<HashMap<String, Duration>>,
+}
+
+impl SlowRequestLayer {
+    /// Logs calls taking longer than `default`, unless their method has its own threshold
+    pub fn new(default: Duration) -> Self {
+        Self {
+            default,
+            methods: Default::default(),
+        }
+    }
+
+    /// Sets the threshold of `method`
+    pub fn method(mut self, method: impl Into<String>, threshold: Duration) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into(), threshold);
+        self
+    }
+}
+
+impl<S> Layer<S> for SlowRequestLayer {
+    type Service = SlowRequest<S>;
+
+    fn layer(&self, inner: S) -> SlowRequest<S> {
+        SlowRequest {
+            inner,
+            thresholds: self.clone(),
+        }
+    }
+}
+
+/// Logs calls exceeding a latency threshold, see [`SlowRequestLayer`].
+#[derive(Debug, Clone)]
+pub struct SlowRequest<S> {
+    inner: S,
+    thresholds: SlowRequestLayer,
+}
+
+impl<S> Service<JsonRpcExtractor> for SlowRequest<S>
+where
+    S: Service<JsonRpcExtractor, Response = JsonRpcResponse, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = JsonRpcResponse;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<JsonRpcResponse, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: JsonRpcExtractor) -> Self::Future {
+        let threshold = self
+            .thresholds
+            .methods
+            .get(request.method())
+            .copied()
+            .unwrap_or(self.thresholds.default);
+        let method = request.method.clone();
+        // measured up front, the handler takes the params
+        let params_size = JsonBackend::encoded_len(&request.parsed).unwrap_or_default();
+        let started = Instant::now();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = response.await;
+            let elapsed = started.elapsed();
+            if elapsed > threshold {
+                tracing::warn!(
+                    method = %method,
+                    params_size,
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    threshold_ms = threshold.as_millis() as u64,
+                    "slow request"
+                );
+            }
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use axum::Router;
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use tower::Layer;
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::{SlowRequestLayer, TraceLayer};
+    use crate::router::{self, BatchIndex, JsonRpcService};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcRequest, JsonRpcResponse, JsonRpcRouter};
+
+    async fn index(req: JsonRpcExtractor) -> JrpcResult {
+        let index = req.extension::<BatchIndex>().map(|index| index.0);
+        Ok(JsonRpcResponse::success(req.get_answer_id(), index))
+    }
+
+    #[tokio::test]
+    async fn traced() {
+        let rpc = TraceLayer.layer(JsonRpcRouter::new().method("index", index));
+        let server = TestServer::new(Router::new().route("/", router::post(rpc))).unwrap();
+
+        let res = server
+            .post("/")
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": "index"}))
+            .await;
+        assert_eq!(res.json::<Value>()["result"], Value::Null);
+
+        let res = server
+            .post("/")
+            .json(&json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "index"},
+                {"jsonrpc": "2.0", "id": 2, "method": "missing"},
+                {"jsonrpc": "2.0", "id": 3, "method": "index"},
+            ]))
+            .await;
+        let responses: Vec<Value> = res.json();
+        assert_eq!(responses[0]["result"], 0);
+        assert_eq!(responses[1]["error"]["code"], -32601);
+        assert_eq!(responses[2]["result"], 2);
+    }
+
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn sleep(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let [ms]: [u64; 1] = req.parse_params()?;
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+        Ok(JsonRpcResponse::success(id, ms))
+    }
+
+    #[tokio::test]
+    async fn slow_requests() {
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let rpc = JsonRpcRouter::new()
+            .method("sleep", sleep)
+            .method("nap", sleep);
+        let rpc = SlowRequestLayer::new(Duration::from_millis(50))
+            .method("nap", Duration::from_secs(10))
+            .layer(rpc);
+        let call = |method: &str, ms: u64| {
+            let request = JsonRpcRequest {
+                id: 1.into(),
+                method: method.to_owned(),
+                params: json!([ms]),
+            };
+            rpc.dispatch(request.into())
+        };
+
+        call("sleep", 0).await;
+        call("nap", 60).await;
+        assert!(logs.0.lock().unwrap().is_empty());
+        call("sleep", 60).await;
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("WARN"), "{logs}");
+        assert!(
+            logs.contains("slow request method=sleep params_size=4"),
+            "{logs}"
+        );
+    }
+}
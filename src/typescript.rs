@@ -0,0 +1,390 @@
+//! TypeScript clients generated from [OpenRPC](crate::openrpc) documents, for the
+//! `typescript` feature.
+//!
+//! [`client`] turns the document of a router into a module with a type per schema and a
+//! fetch-based `Client` class with a typed function per method. Functions are named after the
+//! methods in camel case, `math.add` becomes `mathAdd`. Methods described with
+//! [`MethodDoc::params`](crate::openrpc::MethodDoc::params) take their params as an object,
+//! those described with [`MethodDoc::param`](crate::openrpc::MethodDoc::param) take them as
+//! arguments.
+//!
+//! The module can be written by a test or a small binary sharing the router with the server,
+//! with [`write`] which leaves the file alone when it is up to date, or served with [`get`]:
+//! ```rust
+//! use axum::Router;
+//! use axum_jrpc::openrpc::Info;
+//! use axum_jrpc::{router, typescript, JsonRpcRouter};
+//!
+//! let rpc = JsonRpcRouter::new();
+//! let document = rpc.openrpc(Info::new("calculator", "1.0.0"));
+//! typescript::write(&document, std::env::temp_dir().join("client.ts")).unwrap();
+//!
+//! let app: Router = Router::new()
+//!     .route("/", router::post(rpc))
+//!     .route("/client.ts", typescript::get(&document));
+//! ```
+
+use std::fmt::Write;
+use std::path::Path;
+
+use axum::http::header;
+use axum::routing::MethodRouter;
+use serde_json::Value;
+
+use crate::openrpc::{ContentDescriptor, Method, OpenRpc, ParamStructure};
+
+const PRELUDE: &str = r#"export class JsonRpcError extends Error {
+  constructor(readonly code: number, message: string, readonly data?: unknown) {
+    super(message);
+  }
+}
+
+export class Client {
+  private nextId = 1;
+
+  constructor(readonly url: string, readonly init: RequestInit = {}) {}
+
+  async call<T>(method: string, params?: unknown): Promise<T> {
+    const body: Record<string, unknown> = { jsonrpc: "2.0", id: this.nextId++, method };
+    if (params !== undefined) body.params = params;
+    const res = await fetch(this.url, {
+      ...this.init,
+      method: "POST",
+      headers: { "Content-Type": "application/json", ...this.init.headers },
+      body: JSON.stringify(body),
+    });
+    const reply = await res.json();
+    if (reply.error) throw new JsonRpcError(reply.error.code, reply.error.message, reply.error.data);
+    return reply.result as T;
+  }
+"#;
+
+/// Source of the TypeScript client of `document`
+pub fn client(document: &OpenRpc) -> String {
+    let mut out = format!(
+        "// Generated from the OpenRPC document of {} {}, do not edit.\n\n",
+        document.info.title, document.info.version
+    );
+    for (name, schema) in &document.components.schemas {
+        comment(
+            &mut out,
+            "",
+            schema.get("description").and_then(Value::as_str),
+        );
+        let name = identifier(name);
+        match object(schema) {
+            Some(body) => writeln!(out, "export interface {name} {body}\n"),
+            None => writeln!(out, "export type {name} = {};\n", ty(schema)),
+        }
+        .expect("writing to a String");
+    }
+    out.push_str(PRELUDE);
+    for method in &document.methods {
+        out.push('\n');
+        function(&mut out, method);
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Writes the client of `document` to `path` unless it is up to date, so build scripts don't
+/// trigger rebuilds
+pub fn write(document: &OpenRpc, path: impl AsRef<Path>) -> std::io::Result<()> {
+    let source = client(document);
+    match std::fs::read_to_string(&path) {
+        Ok(current) if current == source => Ok(()),
+        _ => std::fs::write(path, source),
+    }
+}
+
+/// Route serving the client of `document`
+pub fn get(document: &OpenRpc) -> MethodRouter {
+    let source = client(document);
+    axum::routing::get(move || {
+        let source = source.clone();
+        async move { ([(header::CONTENT_TYPE, "text/typescript")], source) }
+    })
+}
+
+fn function(out: &mut String, method: &Method) {
+    let description = match (&method.summary, &method.description) {
+        (Some(summary), Some(description)) => Some(format!("{summary}\n\n{description}")),
+        (summary, description) => summary.clone().or_else(|| description.clone()),
+    };
+    comment(out, "  ", description.as_deref());
+    if method.deprecated {
+        out.push_str("  /** @deprecated */\n");
+    }
+    let result = ty(method.result.schema.as_value());
+    let name = camel_case(&method.name);
+    let method_name = serde_json::to_string(&method.name).expect("strings serialize");
+    let (args, params) = match method.param_structure {
+        None => ("params?: unknown".to_owned(), "params".to_owned()),
+        Some(ParamStructure::ByName) => {
+            let fields = method.params.iter().map(field).collect::<String>();
+            (format!("params: {{{fields} }}"), "params".to_owned())
+        }
+        Some(_) => {
+            let args: Vec<_> = method
+                .params
+                .iter()
+                .map(|param| {
+                    format!(
+                        "{}: {}",
+                        identifier(&param.name),
+                        ty(param.schema.as_value())
+                    )
+                })
+                .collect();
+            let names: Vec<_> = method.params.iter().map(|p| identifier(&p.name)).collect();
+            (args.join(", "), format!("[{}]", names.join(", ")))
+        }
+    };
+    writeln!(
+        out,
+        "  {name}({args}): Promise<{result}> {{\n    return this.call({method_name}, {params});\n  }}"
+    )
+    .expect("writing to a String");
+}
+
+fn field(param: &ContentDescriptor) -> String {
+    let optional = if param.required { "" } else { "?" };
+    let name = property(&param.name);
+    format!(" {name}{optional}: {};", ty(param.schema.as_value()))
+}
+
+fn comment(out: &mut String, indent: &str, text: Option<&str>) {
+    let Some(text) = text else { return };
+    out.push_str(indent);
+    out.push_str("/**\n");
+    for line in text.lines() {
+        let line = line.replace("*/", "*\\/");
+        writeln!(out, "{indent} * {line}").expect("writing to a String");
+    }
+    out.push_str(indent);
+    out.push_str(" */\n");
+}
+
+/// TypeScript type of `schema`
+fn ty(schema: &Value) -> String {
+    let Some(map) = schema.as_object() else {
+        return match schema {
+            Value::Bool(false) => "never".to_owned(),
+            _ => "unknown".to_owned(),
+        };
+    };
+    if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+        return identifier(reference.rsplit('/').next().unwrap_or(reference));
+    }
+    if let Some(value) = map.get("const") {
+        return serde_json::to_string(value).expect("values serialize");
+    }
+    if let Some(values) = map.get("enum").and_then(Value::as_array) {
+        return union(
+            values
+                .iter()
+                .map(|value| serde_json::to_string(value).expect("values serialize")),
+        );
+    }
+    for keyword in ["oneOf", "anyOf"] {
+        if let Some(schemas) = map.get(keyword).and_then(Value::as_array) {
+            return union(schemas.iter().map(ty));
+        }
+    }
+    if let Some(schemas) = map.get("allOf").and_then(Value::as_array) {
+        let types: Vec<_> = schemas
+            .iter()
+            .map(|schema| format!("({})", ty(schema)))
+            .collect();
+        return types.join(" & ");
+    }
+    match map.get("type") {
+        Some(Value::String(name)) => typed(name, schema),
+        Some(Value::Array(names)) => union(
+            names
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|name| typed(name, schema)),
+        ),
+        _ => object(schema).unwrap_or_else(|| "unknown".to_owned()),
+    }
+}
+
+fn typed(name: &str, schema: &Value) -> String {
+    match name {
+        "string" => "string".to_owned(),
+        "integer" | "number" => "number".to_owned(),
+        "boolean" => "boolean".to_owned(),
+        "null" => "null".to_owned(),
+        "array" => {
+            if let Some(items) = schema.get("prefixItems").and_then(Value::as_array) {
+                let items: Vec<_> = items.iter().map(ty).collect();
+                format!("[{}]", items.join(", "))
+            } else {
+                let item = schema.get("items").map_or_else(|| "unknown".to_owned(), ty);
+                format!("({item})[]")
+            }
+        }
+        "object" => object(schema).unwrap_or_else(|| {
+            let value = match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => return "Record<string, never>".to_owned(),
+                Some(schema @ Value::Object(_)) => ty(schema),
+                _ => "unknown".to_owned(),
+            };
+            format!("Record<string, {value}>")
+        }),
+        _ => "unknown".to_owned(),
+    }
+}
+
+/// Body of the interface of an object `schema` with properties
+fn object(schema: &Value) -> Option<String> {
+    let properties = schema.get("properties")?.as_object()?;
+    let required: Vec<_> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let mut out = "{\n".to_owned();
+    for (name, property_schema) in properties {
+        let description = property_schema.get("description").and_then(Value::as_str);
+        comment(&mut out, "  ", description);
+        let optional = if required.contains(&name.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+        writeln!(
+            out,
+            "  {}{optional}: {};",
+            property(name),
+            ty(property_schema)
+        )
+        .expect("writing to a String");
+    }
+    out.push('}');
+    Some(out)
+}
+
+fn union(types: impl Iterator<Item = String>) -> String {
+    let mut types: Vec<_> = types.collect();
+    types.dedup();
+    match types.len() {
+        0 => "never".to_owned(),
+        _ => types.join(" | "),
+    }
+}
+
+/// A valid identifier, invalid characters replaced with `_`
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if identifier.starts_with(|c: char| c.is_ascii_digit()) || identifier.is_empty() {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+/// A property name, quoted unless it is an identifier
+fn property(name: &str) -> String {
+    if identifier(name) == name {
+        name.to_owned()
+    } else {
+        serde_json::to_string(name).expect("strings serialize")
+    }
+}
+
+fn camel_case(method: &str) -> String {
+    let mut name = String::with_capacity(method.len());
+    let mut upper = false;
+    for c in method.chars() {
+        if c.is_ascii_alphanumeric() {
+            if upper && !name.is_empty() {
+                name.push(c.to_ascii_uppercase());
+            } else {
+                name.push(c);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    identifier(&name)
+}
+
+#[cfg(test)]
+mod test {
+    use schemars::JsonSchema;
+
+    use super::client;
+    use crate::openrpc::{Info, MethodDoc};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    /// Operands
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct AddParams {
+        a: i64,
+        b: Option<i64>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum Rounding {
+        Up,
+        Down,
+    }
+
+    async fn nothing(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), ()))
+    }
+
+    #[test]
+    fn generates_client() {
+        let rpc = JsonRpcRouter::new()
+            .method("math.add", nothing)
+            .method("math.div", nothing)
+            .method("ping", nothing)
+            .describe(
+                "math.add",
+                MethodDoc::new()
+                    .summary("Adds")
+                    .params::<AddParams>()
+                    .result::<i64>(),
+            )
+            .describe(
+                "math.div",
+                MethodDoc::new()
+                    .param::<f64>("a")
+                    .param::<f64>("b")
+                    .param::<Rounding>("rounding")
+                    .result::<Vec<f64>>(),
+            );
+        let source = client(&rpc.openrpc(Info::new("calculator", "1.0.0")));
+
+        assert!(source.contains("/**\n * Operands\n */\nexport interface AddParams {\n  a: number;\n  b?: number | null;\n}"), "{source}");
+        assert!(
+            source.contains(r#"export type Rounding = "up" | "down";"#),
+            "{source}"
+        );
+        assert!(source.contains(
+            "  /**\n   * Adds\n   */\n  mathAdd(params: { a: number; b?: number | null; }): Promise<number> {\n    return this.call(\"math.add\", params);\n  }"
+        ), "{source}");
+        assert!(source.contains(
+            "  mathDiv(a: number, b: number, rounding: Rounding): Promise<(number)[]> {\n    return this.call(\"math.div\", [a, b, rounding]);\n  }"
+        ), "{source}");
+        assert!(
+            source.contains("  ping(params?: unknown): Promise<unknown> {"),
+            "{source}"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Serving an axum app on a Unix domain socket, e.g. the local control socket of a daemon.
+//! ```rust,no_run
+//! use axum::Router;
+//! use axum_jrpc::router;
+//! use axum_jrpc::unix::UnixServer;
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let rpc = JsonRpcRouter::new();
+//! let app = Router::new().route("/", router::post(rpc));
+//! UnixServer::new("/run/mydaemon/control.sock")
+//!     .mode(0o660)
+//!     .serve(app)
+//!     .await
+//! # }
+//! ```
+//! Sockets created by a service manager, like systemd socket activation, are served with
+//! [`serve_unix`].
+
+use std::convert::Infallible;
+use std::fs::Permissions;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use axum::extract::Request;
+use axum::response::Response;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::UnixListener;
+use tower::{Service, ServiceExt};
+
+/// Binds a Unix domain socket and serves an axum app on it.
+#[derive(Debug, Clone)]
+pub struct UnixServer {
+    path: PathBuf,
+    mode: Option<u32>,
+    replace: bool,
+}
+
+impl UnixServer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            mode: None,
+            replace: true,
+        }
+    }
+
+    /// Permissions of the socket file, e.g. `0o660` to allow only the owner and its group.
+    /// Defaults to the process umask
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Whether a socket left behind by a previous run is removed before binding, `true` by
+    /// default. Other files at the path are never removed
+    pub fn replace_stale(mut self, replace: bool) -> Self {
+        self.replace = replace;
+        self
+    }
+
+    /// Binds the socket, applying the configured permissions
+    pub fn bind(&self) -> io::Result<UnixListener> {
+        if self.replace {
+            match std::fs::symlink_metadata(&self.path) {
+                Ok(metadata) if metadata.file_type().is_socket() => {
+                    std::fs::remove_file(&self.path)?
+                }
+                _ => {}
+            }
+        }
+
+        let listener = UnixListener::bind(&self.path)?;
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(&self.path, Permissions::from_mode(mode))?;
+        }
+        Ok(listener)
+    }
+
+    /// Binds the socket and serves `app` on it until dropped
+    pub async fn serve<S>(self, app: S) -> io::Result<()>
+    where
+        S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+        S::Future: Send,
+    {
+        let listener = self.bind()?;
+        serve_unix(listener, app).await;
+        Ok(())
+    }
+}
+
+/// Serves `app` on every connection accepted by `listener`, until dropped.
+///
+/// HTTP/1 and HTTP/2 are both accepted, connection upgrades like WebSocket work too
+pub async fn serve_unix<S>(listener: UnixListener, app: S)
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_e, "failed to accept connection");
+                // usually out of file descriptors, give the other connections time to close
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let service = TowerToHyperService::new(
+            app.clone()
+                .map_request(|req: Request<_>| req.map(axum::body::Body::new)),
+        );
+        tokio::spawn(async move {
+            let connection = Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await;
+            if let Err(_e) = connection {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %_e, "connection failed");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "client", feature = "serde_json"))]
+mod test {
+    use std::os::unix::fs::PermissionsExt;
+
+    use axum::Router;
+
+    use super::UnixServer;
+    use crate::client::{HttpClient, JsonRpcClient};
+    use crate::{router, JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn ping(req: JsonRpcExtractor) -> JrpcResult {
+        Ok(JsonRpcResponse::success(req.get_answer_id(), "pong"))
+    }
+
+    #[tokio::test]
+    async fn serve() {
+        let path = std::env::temp_dir().join(format!("axum-jrpc-{}.sock", std::process::id()));
+        let app = Router::new().route("/", router::post(JsonRpcRouter::new().method("ping", ping)));
+        let server = UnixServer::new(&path).mode(0o600);
+        let listener = server.bind().unwrap();
+        tokio::spawn(super::serve_unix(listener, app.clone()));
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let client = HttpClient::unix(&path, "http://localhost/").unwrap();
+        let res: String = client.call("ping", ()).await.unwrap();
+        assert_eq!(res, "pong");
+
+        // a second server replaces the socket left behind
+        let listener = server.bind().unwrap();
+        tokio::spawn(super::serve_unix(listener, app));
+        let client = HttpClient::unix(&path, "http://localhost/").unwrap();
+        let res: String = client.call("ping", ()).await.unwrap();
+        assert_eq!(res, "pong");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
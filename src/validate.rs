@@ -0,0 +1,141 @@
+//! Validation of params with [validator](https://docs.rs/validator), for the `validator`
+//! feature.
+//!
+//! [`JsonRpcExtractor::parse_valid_params`] deserializes the params, then runs their
+//! [`Validate`] implementation. Violations are answered with an
+//! [`InvalidParams`](JsonRpcErrorReason::InvalidParams) error whose data maps every invalid
+//! field, nested ones as `address.city` or `items[0].name`, to its messages:
+//! ```json
+//! {"fields": {"email": ["must be an email"], "items[0].name": ["length"]}}
+//! ```
+//! A violation without a message is reported by its code.
+//! ```rust
+//! use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse};
+//! use serde::Deserialize;
+//! use validator::Validate;
+//!
+//! #[derive(Deserialize, Validate)]
+//! struct SignUp {
+//!     #[validate(email(message = "must be an email"))]
+//!     email: String,
+//!     #[validate(length(min = 8))]
+//!     password: String,
+//! }
+//!
+//! async fn sign_up(req: JsonRpcExtractor) -> JrpcResult {
+//!     let id = req.get_answer_id();
+//!     let params: SignUp = req.parse_valid_params()?;
+//!     Ok(JsonRpcResponse::success(id, params.email))
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+pub use validator;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::{to_value, JsonRpcExtractor, JsonRpcResponse};
+
+#[derive(Serialize)]
+struct Violations {
+    fields: BTreeMap<String, Vec<String>>,
+}
+
+impl JsonRpcExtractor {
+    /// [`parse_params`](Self::parse_params), then validates them, see the
+    /// [module docs](crate::validate)
+    pub fn parse_valid_params<T: DeserializeOwned + Validate>(self) -> Result<T, JsonRpcResponse> {
+        let id = self.get_answer_id();
+        let params: T = self.parse_params()?;
+        match params.validate() {
+            Ok(()) => Ok(params),
+            Err(errors) => {
+                let mut fields = BTreeMap::new();
+                flatten(&errors, "", &mut fields);
+                let error = JsonRpcError::new(
+                    JsonRpcErrorReason::InvalidParams,
+                    "Invalid params".to_owned(),
+                    to_value(Violations { fields }).unwrap_or_default(),
+                );
+                Err(JsonRpcResponse::error(id, error))
+            }
+        }
+    }
+}
+
+/// Messages of the invalid fields of `errors`, by path
+fn flatten(errors: &ValidationErrors, prefix: &str, fields: &mut BTreeMap<String, Vec<String>>) {
+    for (field, kind) in errors.errors() {
+        let path = match prefix {
+            "" => field.to_string(),
+            _ => format!("{prefix}.{field}"),
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                let messages = errors.iter().map(|error| match &error.message {
+                    Some(message) => message.to_string(),
+                    None => error.code.to_string(),
+                });
+                fields.entry(path).or_default().extend(messages);
+            }
+            ValidationErrorsKind::Struct(errors) => flatten(errors, &path, fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    flatten(errors, &format!("{path}[{index}]"), fields);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde_json")]
+mod test {
+    use serde::Deserialize;
+    use serde_json::json;
+    use validator::Validate;
+
+    use crate::{JsonRpcExtractor, JsonRpcRequest};
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Order {
+        #[validate(email(message = "must be an email"))]
+        email: String,
+        #[validate(nested)]
+        items: Vec<Item>,
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Item {
+        #[validate(length(min = 1))]
+        name: String,
+    }
+
+    fn extractor(params: serde_json::Value) -> JsonRpcExtractor {
+        JsonRpcRequest {
+            id: 1.into(),
+            method: "order".to_owned(),
+            params,
+        }
+        .into()
+    }
+
+    #[test]
+    fn reports_fields() {
+        let order = json!({"email": "a@b.c", "items": [{"name": "tea"}]});
+        let order: Order = extractor(order).parse_valid_params().unwrap();
+        assert_eq!(order.items[0].name, "tea");
+
+        let order = json!({"email": "nope", "items": [{"name": "tea"}, {"name": ""}]});
+        let response = extractor(order).parse_valid_params::<Order>().unwrap_err();
+        let response = serde_json::to_value(response).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        assert_eq!(
+            response["error"]["data"],
+            json!({"fields": {"email": ["must be an email"], "items[1].name": ["length"]}})
+        );
+    }
+}
@@ -0,0 +1,685 @@
+//! JSON-RPC over WebSocket.
+//!
+//! Every text or binary message carries one request or a batch, responses are written back on the same
+//! socket as soon as they are ready, so slow calls don't hold back the rest. Up to 64 messages
+//! of a connection are handled at the same time, further ones are read once one completes.
+//!
+//! The connection is bidirectional: handlers can call methods on the client through the
+//! [`WsPeer`] found in [`JsonRpcExtractor::extensions`](crate::JsonRpcExtractor::extensions).
+//! Subscriptions registered with [`JsonRpcRouter::subscription`] are served too and closed
+//! when the socket goes away, or with [`ResumableWebSocket`] once the client failed to
+//! reconnect within a grace period.
+//! With a [`Shutdown`] handle, clients are told when the server is shutting down, and the
+//! sockets are closed once the calls in flight are drained.
+//!
+//! [`JsonRpcRouter::subscription`]: crate::JsonRpcRouter::subscription
+//! ```rust
+//! use axum::routing::get;
+//! use axum::Router;
+//! use axum_jrpc::ws::JsonRpcWebSocket;
+//! use axum_jrpc::JsonRpcRouter;
+//!
+//! let rpc = JsonRpcRouter::new();
+//! let app: Router = Router::new().route(
+//!     "/ws",
+//!     get(move |ws: JsonRpcWebSocket| async move { ws.serve(rpc) }),
+//! );
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::async_trait;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{FromRequestParts, Query};
+use axum::http::request::Parts;
+use axum::http::Extensions;
+use axum::response::Response;
+use axum::routing::{self, MethodRouter};
+use futures_util::stream::SplitStream;
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, Semaphore};
+
+use crate::batch::MAX_IN_FLIGHT;
+use crate::error::{JsonRpcError, JsonRpcErrorReason};
+use crate::pubsub::{Session, SessionStore};
+use crate::router::JsonRpcService;
+use crate::shutdown::Shutdown;
+use crate::{from_slice, from_value, to_value, to_vec, Id, JsonRpcRequest, JsonRpcResponse, Value};
+
+type Pending = Arc<Mutex<HashMap<Id, oneshot::Sender<JsonRpcResponse>>>>;
+
+/// Extractor upgrading the connection to a JSON-RPC WebSocket.
+#[derive(Debug)]
+pub struct JsonRpcWebSocket(pub WebSocketUpgrade);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for JsonRpcWebSocket
+where
+    S: Send + Sync,
+{
+    type Rejection = <WebSocketUpgrade as FromRequestParts<S>>::Rejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        WebSocketUpgrade::from_request_parts(parts, state)
+            .await
+            .map(Self)
+    }
+}
+
+impl JsonRpcWebSocket {
+    /// Finishes the upgrade and answers requests on the socket with `service`
+    pub fn serve<S: JsonRpcService>(self, service: S) -> Response {
+        self.0.on_upgrade(move |socket| run(socket, service, None))
+    }
+
+    /// Like [`serve`](Self::serve), and once `shutdown` begins tells the client with a
+    /// [`SHUTDOWN_METHOD`] notification, then closes the socket once the calls are drained
+    pub fn serve_until<S: JsonRpcService>(self, service: S, shutdown: Shutdown) -> Response {
+        self.0
+            .on_upgrade(move |socket| run(socket, service, Some(shutdown)))
+    }
+}
+
+/// Handle for calling methods on the client of a WebSocket connection.
+///
+/// Available to handlers as an extension of every request received on the socket:
+/// ```rust
+/// use axum_jrpc::ws::WsPeer;
+/// use axum_jrpc::{JrpcResult, JsonRpcExtractor, JsonRpcResponse};
+///
+/// async fn transfer(req: JsonRpcExtractor) -> JrpcResult {
+///     let id = req.get_answer_id();
+///     let peer = req.extension::<WsPeer>().cloned();
+///     let confirmed: bool = match peer {
+///         Some(peer) => peer
+///             .call("confirm", ["transfer"])
+///             .await
+///             .map_err(|e| JsonRpcResponse::error(id.clone(), e))?,
+///         None => false,
+///     };
+///     Ok(JsonRpcResponse::success(id, confirmed))
+/// }
+/// ```
+#[derive(Clone)]
+pub struct WsPeer {
+    session: Session,
+    pending: Pending,
+    next_id: Arc<AtomicI64>,
+}
+
+impl fmt::Debug for WsPeer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WsPeer")
+            .field("closed", &self.session.is_closed())
+            .finish()
+    }
+}
+
+impl WsPeer {
+    /// Calls `method` on the client and waits for its response
+    pub async fn call<P, R>(&self, method: &str, params: P) -> Result<R, JsonRpcError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let id = Id::Num(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let request = JsonRpcRequest {
+            id: id.clone(),
+            method: method.to_owned(),
+            params: to_value(params).map_err(internal_error)?,
+        };
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), tx);
+        if let Err(e) = self.send(request) {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        let response = rx.await.map_err(|_| internal_error("Connection closed"))?;
+        response.into_result()
+    }
+
+    /// Sends a notification to the client, fails if the queue of the connection is full, see
+    /// [`QUEUE_CAPACITY`](crate::pubsub::QUEUE_CAPACITY)
+    pub fn notify<P: Serialize>(&self, method: &str, params: P) -> Result<(), JsonRpcError> {
+        let request = JsonRpcRequest {
+            id: Id::None(()),
+            method: method.to_owned(),
+            params: to_value(params).map_err(internal_error)?,
+        };
+        self.send(request)
+    }
+
+    /// Returns `true` once the connection is closed
+    pub fn is_closed(&self) -> bool {
+        self.session.is_closed()
+    }
+
+    fn send(&self, request: JsonRpcRequest) -> Result<(), JsonRpcError> {
+        self.session
+            .request(request)
+            .map_err(|_| internal_error("Connection closed or its queue is full"))
+    }
+}
+
+fn internal_error(message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError::new(
+        JsonRpcErrorReason::InternalError,
+        message.into(),
+        Value::default(),
+    )
+}
+
+fn to_text<T: Serialize>(message: &T) -> Result<Message, String> {
+    let bytes = to_vec(message)?;
+    String::from_utf8(bytes)
+        .map(Message::Text)
+        .map_err(|e| e.to_string())
+}
+
+/// Method of the notification telling clients the server is shutting down, its params hold
+/// `deadline_ms`, the time left before the connection closes
+pub const SHUTDOWN_METHOD: &str = "rpc.shutdown";
+
+#[derive(Serialize)]
+struct ShuttingDown {
+    deadline_ms: u64,
+}
+
+/// Resolves once the connection should close, telling the client when `shutdown` begins
+async fn closing(shutdown: Option<Shutdown>, session: Session) {
+    let Some(shutdown) = shutdown else {
+        return std::future::pending().await;
+    };
+    shutdown.signal().await;
+    let params = ShuttingDown {
+        deadline_ms: shutdown.remaining().as_millis() as u64,
+    };
+    let notification = JsonRpcRequest {
+        id: Id::None(()),
+        method: SHUTDOWN_METHOD.to_owned(),
+        params: to_value(params).unwrap_or_default(),
+    };
+    let _ = session.request(notification);
+    shutdown.closed().await;
+}
+
+async fn run<S: JsonRpcService>(socket: WebSocket, service: S, shutdown: Option<Shutdown>) {
+    let (mut sink, stream) = socket.split();
+    let (session, mut outgoing) = Session::new();
+    let peer = WsPeer {
+        session: session.clone(),
+        pending: Pending::default(),
+        next_id: Arc::default(),
+    };
+    let pending = peer.pending.clone();
+    let closing = closing(shutdown, session.clone());
+
+    let writer = async move {
+        futures_util::pin_mut!(closing);
+        loop {
+            let message = tokio::select! {
+                // queued answers are sent before closing
+                biased;
+                message = outgoing.recv() => message,
+                () = &mut closing => None,
+            };
+            let Some(message) = message else {
+                break;
+            };
+            let Ok(message) = to_text(&message) else {
+                continue;
+            };
+            if sink.send(message).await.is_err() {
+                return;
+            }
+        }
+        let _ = sink.send(Message::Close(None)).await;
+    };
+
+    tokio::select! {
+        _ = writer => {}
+        _ = read(stream, service, peer) => {}
+    }
+
+    // Dropping the senders fails calls still waiting for the client
+    pending.lock().unwrap().clear();
+    session.close();
+}
+
+/// Dispatches the requests received on `stream` within the session of `peer`, until the
+/// socket closes. At most [`MAX_IN_FLIGHT`] messages are handled at the same time, the socket
+/// is not read further until one of them completes
+async fn read<S: JsonRpcService>(mut stream: SplitStream<WebSocket>, service: S, peer: WsPeer) {
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+    while let Some(Ok(message)) = stream.next().await {
+        let mut bytes = match message {
+            Message::Text(text) => text.into_bytes(),
+            Message::Binary(bytes) => bytes,
+            Message::Close(_) => break,
+            Message::Ping(_) | Message::Pong(_) => continue,
+        };
+
+        let value: Result<Value, _> = from_slice(&mut bytes);
+        if let Ok(Value::Object(object)) = &value {
+            let answer = object.contains_key("result") || object.contains_key("error");
+            if answer && !object.contains_key("method") {
+                // an answer to a call made with `WsPeer`, anything else is an invalid request
+                if let Ok(response) = from_value::<JsonRpcResponse>(value.unwrap()) {
+                    let waiter = peer.pending.lock().unwrap().remove(&response.id);
+                    if let Some(waiter) = waiter {
+                        let _ = waiter.send(response);
+                    }
+                }
+                continue;
+            }
+        }
+
+        let Ok(permit) = in_flight.clone().acquire_owned().await else {
+            break;
+        };
+        let service = service.clone();
+        let session = peer.session.clone();
+        let mut extensions = Extensions::new();
+        extensions.insert(peer.clone());
+        tokio::spawn(async move {
+            session.dispatch(&service, value, extensions).await;
+            drop(permit);
+        });
+    }
+}
+
+/// Method of the notification opening every connection of a [`ResumableWebSocket`]
+pub const SESSION_METHOD: &str = "rpc.session";
+
+/// WebSocket transport whose sessions outlive their connection.
+///
+/// The first message on every connection is a `rpc.session` notification carrying the
+/// session id, and whether an existing session was resumed:
+/// ```json
+/// {"jsonrpc": "2.0", "method": "rpc.session", "params": {"session": "...", "resumed": false}}
+/// ```
+/// All following messages are numbered from 1 within the session. A client reconnecting to
+/// `?session=<id>&after=<n>`, with `n` the number of messages it received, gets the messages
+/// sent in between replayed and keeps its subscriptions, as long as it comes back within the
+/// grace period. Otherwise it gets a new session and has to subscribe again.
+/// ```rust
+/// use axum::Router;
+/// use axum_jrpc::ws::ResumableWebSocket;
+/// use axum_jrpc::JsonRpcRouter;
+///
+/// let rpc = JsonRpcRouter::new();
+/// let app: Router = Router::new().route("/ws", ResumableWebSocket::new().serve(rpc));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResumableWebSocket {
+    capacity: usize,
+    grace_period: Duration,
+    shutdown: Option<Shutdown>,
+}
+
+impl Default for ResumableWebSocket {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            grace_period: Duration::from_secs(60),
+            shutdown: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Resume {
+    session: Option<String>,
+    after: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct SessionOpened<'a> {
+    session: &'a str,
+    resumed: bool,
+}
+
+impl ResumableWebSocket {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of messages kept per session for resuming, 1024 by default
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// How long a session survives without a connection, 60 seconds by default
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Tells clients with a [`SHUTDOWN_METHOD`] notification once `shutdown` begins, and
+    /// closes the connections once the calls are drained
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Builds the route, for use with [`axum::Router::route`]
+    pub fn serve<S: JsonRpcService>(self, service: S) -> MethodRouter {
+        let store = SessionStore::new(self.capacity, self.grace_period);
+        let shutdown = self.shutdown;
+        routing::get(
+            move |ws: WebSocketUpgrade, Query(resume): Query<Resume>| async move {
+                ws.on_upgrade(move |socket| run_resumable(socket, service, store, resume, shutdown))
+            },
+        )
+    }
+}
+
+async fn run_resumable<S: JsonRpcService>(
+    socket: WebSocket,
+    service: S,
+    store: SessionStore,
+    resume: Resume,
+    shutdown: Option<Shutdown>,
+) {
+    let resumed = resume.session.as_deref().and_then(|id| store.get(id));
+    let (buffered, after, resumed) = match resumed {
+        Some(buffered) => (buffered, resume.after, true),
+        None => (store.create(), None, false),
+    };
+
+    let (mut sink, stream) = socket.split();
+    let opened = JsonRpcRequest {
+        id: Id::None(()),
+        method: SESSION_METHOD.to_owned(),
+        params: to_value(SessionOpened {
+            session: buffered.id(),
+            resumed,
+        })
+        .unwrap_or_default(),
+    };
+    let Ok(opened) = to_text(&opened) else {
+        return;
+    };
+    if sink.send(opened).await.is_err() {
+        return;
+    }
+
+    let peer = WsPeer {
+        session: buffered.session().clone(),
+        pending: Pending::default(),
+        next_id: Arc::default(),
+    };
+    let pending = peer.pending.clone();
+
+    let events = buffered.clone().replay(after);
+    let closing = closing(shutdown, buffered.session().clone());
+    let writer = async move {
+        futures_util::pin_mut!(events, closing);
+        loop {
+            let event = tokio::select! {
+                biased;
+                event = events.next() => event,
+                () = &mut closing => None,
+            };
+            let Some((_, text)) = event else {
+                break;
+            };
+            if sink.send(Message::Text(text.to_string())).await.is_err() {
+                return;
+            }
+        }
+        let _ = sink.send(Message::Close(None)).await;
+    };
+
+    tokio::select! {
+        _ = writer => {}
+        _ = read(stream, service, peer) => {}
+    }
+
+    // the session and its subscriptions stay until the grace period ends
+    pending.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "ws-client", feature = "serde_json"))]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use axum::routing::get;
+    use axum::Router;
+
+    use futures_util::StreamExt;
+
+    use super::{JsonRpcWebSocket, WsPeer};
+    use crate::batch::MAX_IN_FLIGHT;
+    use crate::client::{JsonRpcClient, WsClient};
+    use crate::{JrpcResult, JsonRpcExtractor, JsonRpcResponse, JsonRpcRouter};
+
+    async fn sleep(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let millis: u64 = req.parse_params()?;
+        tokio::time::sleep(Duration::from_millis(millis)).await;
+        Ok(JsonRpcResponse::success(id, millis))
+    }
+
+    async fn confirm(req: JsonRpcExtractor) -> JrpcResult {
+        let id = req.get_answer_id();
+        let peer = req.extension::<WsPeer>().cloned().unwrap();
+        let question: String = req.parse_params()?;
+        let answer: bool = peer
+            .call("confirm", [question])
+            .await
+            .map_err(|e| JsonRpcResponse::error(id.clone(), e))?;
+        Ok(JsonRpcResponse::success(id, answer))
+    }
+
+    async fn serve() -> String {
+        let rpc = JsonRpcRouter::new()
+            .method("sleep", sleep)
+            .method("confirm", confirm);
+        let app = Router::new().route(
+            "/ws",
+            get(move |ws: JsonRpcWebSocket| async move { ws.serve(rpc) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{addr}/ws")
+    }
+
+    #[tokio::test]
+    async fn dispatch() {
+        let (client, _) = WsClient::connect(serve().await).await.unwrap();
+        let client = client.with_timeout(Duration::from_millis(150));
+        // both calls run concurrently on the server, so neither times out
+        let (a, b) = tokio::join!(
+            client.call::<_, u64>("sleep", 100),
+            client.call::<_, u64>("sleep", 100)
+        );
+        assert_eq!((a.unwrap(), b.unwrap()), (100, 100));
+
+        let res = client.call::<_, ()>("missing", ()).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn bounds_in_flight() {
+        static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+        static MAX: AtomicUsize = AtomicUsize::new(0);
+
+        async fn count(req: JsonRpcExtractor) -> JrpcResult {
+            let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+            MAX.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+            Ok(JsonRpcResponse::success(req.get_answer_id(), ()))
+        }
+
+        let rpc = JsonRpcRouter::new().method("count", count);
+        let app = Router::new().route(
+            "/ws",
+            get(move |ws: JsonRpcWebSocket| async move { ws.serve(rpc) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (client, _) = WsClient::connect(format!("ws://{addr}/ws")).await.unwrap();
+        let calls = (0..MAX_IN_FLIGHT * 2).map(|_| client.call::<_, ()>("count", ()));
+        for res in futures_util::future::join_all(calls).await {
+            res.unwrap();
+        }
+        assert!(MAX.load(Ordering::SeqCst) <= MAX_IN_FLIGHT);
+    }
+
+    #[tokio::test]
+    async fn server_calls_client() {
+        let (client, mut requests) = WsClient::connect(serve().await).await.unwrap();
+
+        let answer = async {
+            let request = requests.next().await.unwrap();
+            assert_eq!(request.method, "confirm");
+            assert_eq!(request.params, serde_json::json!(["proceed?"]));
+            client
+                .respond(JsonRpcResponse::success(request.id, true))
+                .unwrap();
+        };
+        let (res, ()) = tokio::join!(client.call::<_, bool>("confirm", "proceed?"), answer);
+        assert!(res.unwrap());
+    }
+
+    #[tokio::test]
+    async fn answers_objects_without_method() {
+        use futures_util::SinkExt;
+        use serde_json::{json, Value};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(serve().await)
+            .await
+            .unwrap();
+        // an unsolicited response is dropped, an object with neither method nor result is not one
+        let response = json!({"jsonrpc": "2.0", "id": 1, "result": true});
+        let invalid = json!({"jsonrpc": "2.0", "id": 2});
+        for message in [response, invalid] {
+            socket
+                .send(Message::Text(message.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let message = socket.next().await.unwrap().unwrap();
+        let reply: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(reply["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn shutdown() {
+        use serde_json::Value;
+        use tokio_tungstenite::tungstenite::Message;
+        use tower::Layer;
+
+        use crate::shutdown::{Shutdown, ShutdownLayer};
+
+        let shutdown = Shutdown::new();
+        let rpc =
+            ShutdownLayer::new(shutdown.clone()).layer(JsonRpcRouter::new().method("sleep", sleep));
+        let app = Router::new().route(
+            "/ws",
+            get({
+                let shutdown = shutdown.clone();
+                move |ws: JsonRpcWebSocket| async move { ws.serve_until(rpc, shutdown) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let call = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "sleep", "params": 50});
+        futures_util::SinkExt::send(&mut socket, Message::Text(call.to_string()))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let drained = tokio::spawn(async move { shutdown.shutdown(Duration::from_secs(1)).await });
+
+        let mut messages = Vec::new();
+        while let Some(Ok(Message::Text(text))) = socket.next().await {
+            messages.push(serde_json::from_str::<Value>(&text).unwrap());
+        }
+        // the client is told first, and gets its answer before the socket closes
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["method"], super::SHUTDOWN_METHOD);
+        assert_eq!(messages[1]["result"], 50);
+        assert!(drained.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn resume() {
+        use futures_util::SinkExt;
+        use serde_json::{json, Value};
+        use tokio_tungstenite::tungstenite::Message;
+
+        use super::ResumableWebSocket;
+        use crate::pubsub::PendingSubscription;
+
+        async fn receive<S>(socket: &mut S) -> Value
+        where
+            S: futures_util::Stream<Item = tokio_tungstenite::tungstenite::Result<Message>> + Unpin,
+        {
+            let message = socket.next().await.unwrap().unwrap();
+            serde_json::from_str(message.to_text().unwrap()).unwrap()
+        }
+
+        async fn numbers(_: JsonRpcExtractor, pending: PendingSubscription) {
+            let sink = pending.accept();
+            for number in 0..3 {
+                sink.send(number).await.unwrap();
+            }
+            sink.closed().await;
+        }
+
+        let rpc = JsonRpcRouter::new().subscription("subscribe", "number", "unsubscribe", numbers);
+        let app = Router::new().route("/ws", ResumableWebSocket::new().serve(rpc));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let opened = receive(&mut socket).await;
+        assert_eq!(opened["params"]["resumed"], false);
+        let session = opened["params"]["session"].as_str().unwrap().to_owned();
+
+        let subscribe = json!({"jsonrpc": "2.0", "id": 1, "method": "subscribe"});
+        socket
+            .send(Message::Text(subscribe.to_string()))
+            .await
+            .unwrap();
+        let response = receive(&mut socket).await;
+        assert_eq!(response["result"], 0);
+        drop(socket);
+
+        // the response was the first message of the session
+        let url = format!("{url}?session={session}&after=1");
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let mut messages = Vec::new();
+        while messages.len() < 4 {
+            messages.push(receive(&mut socket).await);
+        }
+        assert_eq!(messages[0]["params"]["resumed"], true);
+        let results: Vec<_> = messages[1..]
+            .iter()
+            .map(|message| message["params"]["result"].clone())
+            .collect();
+        assert_eq!(results, [json!(0), json!(1), json!(2)]);
+    }
+}